@@ -0,0 +1,322 @@
+//* The Boyer benchmark, a rewrite-rule theorem prover: it rewrites a term
+//* with about a hundred lemmas until no lemma applies, then checks that the
+//* result is a tautology. Terms, lemmas and substitutions are lists and
+//* association lists of symbols, so it is all pair and symbol churn, the
+//* canonical workload for data representation decisions in Scheme.
+//*
+//* Ported directly to Rust over `Scm`, in two versions:
+//*
+//*    nboyer   rewriting conses a fresh copy of every term it visits
+//*    sboyer   rewriting shares every subterm that it left unchanged
+//*
+//* As in nboyer, lemmas are looked up by the symbol at the head of their
+//* left side (here in a HashMap instead of on property lists), numbers in
+//* patterns only match equal numbers, and the test term can be scaled up by
+//* wrapping it in (or term (f)) a few times.
+
+#[macro_use]
+extern crate criterion;
+
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::order::equal;
+use scm_repr::reader::{read_all, read_str};
+use scm_repr::{car, cdr, cons, is_number, is_pair, Scm};
+
+const LEMMAS: &str = "
+(equal (compile form) (reverse (codegen (optimize form) (nil))))
+(equal (eqp x y) (equal (fix x) (fix y)))
+(equal (greaterp x y) (lessp y x))
+(equal (lesseqp x y) (not (lessp y x)))
+(equal (greatereqp x y) (not (lessp x y)))
+(equal (boolean x) (or (equal x (t)) (equal x (f))))
+(equal (iff x y) (and (implies x y) (implies y x)))
+(equal (even1 x) (if (zerop x) (t) (odd (_1- x))))
+(equal (countps- l pred) (countps-loop l pred (zero)))
+(equal (fact- i) (fact-loop i 1))
+(equal (reverse- x) (reverse-loop x (nil)))
+(equal (divides x y) (zerop (remainder y x)))
+(equal (assume-true var alist) (cons (cons var (t)) alist))
+(equal (assume-false var alist) (cons (cons var (f)) alist))
+(equal (tautology-checker x) (tautologyp (normalize x) (nil)))
+(equal (falsify x) (falsify1 (normalize x) (nil)))
+(equal (prime x) (and (not (zerop x)) (not (equal x (add1 (zero)))) (prime1 x (_1- x))))
+(equal (and p q) (if p (if q (t) (f)) (f)))
+(equal (or p q) (if p (t) (if q (t) (f)) (f)))
+(equal (not p) (if p (f) (t)))
+(equal (implies p q) (if p (if q (t) (f)) (t)))
+(equal (fix x) (if (numberp x) x (zero)))
+(equal (if (if a b c) d e) (if a (if b d e) (if c d e)))
+(equal (zerop x) (or (equal x (zero)) (not (numberp x))))
+(equal (plus (plus x y) z) (plus x (plus y z)))
+(equal (equal (plus a b) (zero)) (and (zerop a) (zerop b)))
+(equal (difference x x) (zero))
+(equal (equal (plus a b) (plus a c)) (equal (fix b) (fix c)))
+(equal (equal (zero) (difference x y)) (not (lessp y x)))
+(equal (equal x (difference x y)) (and (numberp x) (or (equal x (zero)) (zerop y))))
+(equal (meaning (plus-tree (append x y)) a) (plus (meaning (plus-tree x) a) (meaning (plus-tree y) a)))
+(equal (meaning (plus-tree (plus-fringe x)) a) (fix (meaning x a)))
+(equal (append (append x y) z) (append x (append y z)))
+(equal (reverse (append a b)) (append (reverse b) (reverse a)))
+(equal (times x (plus y z)) (plus (times x y) (times x z)))
+(equal (times (times x y) z) (times x (times y z)))
+(equal (equal (times x y) (zero)) (or (zerop x) (zerop y)))
+(equal (exec (append x y) pds envrn) (exec y (exec x pds envrn) envrn))
+(equal (mc-flatten x y) (append (flatten x) y))
+(equal (member x (append a b)) (or (member x a) (member x b)))
+(equal (member x (reverse y)) (member x y))
+(equal (length (reverse x)) (length x))
+(equal (member a (intersect b c)) (and (member a b) (member a c)))
+(equal (nth (zero) i) (zero))
+(equal (exp i (plus j k)) (times (exp i j) (exp i k)))
+(equal (exp i (times j k)) (exp (exp i j) k))
+(equal (reverse-loop x y) (append (reverse x) y))
+(equal (reverse-loop x (nil)) (reverse x))
+(equal (count-list z (sort-lp x y)) (plus (count-list z x) (count-list z y)))
+(equal (equal (append a b) (append a c)) (equal b c))
+(equal (plus (remainder x y) (times y (quotient x y))) (fix x))
+(equal (power-eval (big-plus1 l i base) base) (plus (power-eval l base) i))
+(equal (power-eval (big-plus x y i base) base) (plus i (plus (power-eval x base) (power-eval y base))))
+(equal (remainder y 1) (zero))
+(equal (lessp (remainder x y) y) (not (zerop y)))
+(equal (remainder x x) (zero))
+(equal (lessp (quotient i j) i) (and (not (zerop i)) (or (zerop j) (not (equal j 1)))))
+(equal (lessp (remainder x y) x) (and (not (zerop y)) (not (zerop x)) (not (lessp x y))))
+(equal (power-eval (power-rep i base) base) (fix i))
+(equal (power-eval (big-plus (power-rep i base) (power-rep j base) (zero) base) base) (plus i j))
+(equal (gcd x y) (gcd y x))
+(equal (nth (append a b) i) (append (nth a i) (nth b (difference i (length a)))))
+(equal (difference (plus x y) x) (fix y))
+(equal (difference (plus y x) x) (fix y))
+(equal (difference (plus x y) (plus x z)) (difference y z))
+(equal (times x (difference c w)) (difference (times c x) (times w x)))
+(equal (remainder (times x z) z) (zero))
+(equal (difference (plus b (plus a c)) a) (plus b c))
+(equal (difference (add1 (plus y z)) z) (add1 y))
+(equal (lessp (plus x y) (plus x z)) (lessp y z))
+(equal (lessp (times x z) (times y z)) (and (not (zerop z)) (lessp x y)))
+(equal (lessp y (plus x y)) (not (zerop x)))
+(equal (gcd (times x z) (times y z)) (times z (gcd x y)))
+(equal (value (normalize x) a) (value x a))
+(equal (equal (flatten x) (cons y (nil))) (and (nlistp x) (equal x y)))
+(equal (listp (gopher x)) (listp x))
+(equal (samefringe x y) (equal (flatten x) (flatten y)))
+(equal (equal (greatest-factor x y) (zero)) (and (or (zerop y) (equal y 1)) (equal x (zero))))
+(equal (equal (greatest-factor x y) 1) (equal x 1))
+(equal (numberp (greatest-factor x y)) (not (and (or (zerop y) (equal y 1)) (not (numberp x)))))
+(equal (times-list (append x y)) (times (times-list x) (times-list y)))
+(equal (prime-list (append x y)) (and (prime-list x) (prime-list y)))
+(equal (equal z (times w z)) (and (numberp z) (or (equal z (zero)) (equal w 1))))
+(equal (greatereqpr x y) (not (lessp x y)))
+(equal (equal x (times x y)) (or (equal x (zero)) (and (numberp x) (equal y 1))))
+(equal (remainder (times y x) y) (zero))
+(equal (equal (times a b) 1) (and (not (equal a (zero))) (not (equal b (zero))) (numberp a) (numberp b) (equal (_1- a) (zero)) (equal (_1- b) (zero))))
+(equal (lessp (length (delete x l)) (length l)) (member x l))
+(equal (sort2 (delete x l)) (delete x (sort2 l)))
+(equal (dsort x) (sort2 x))
+(equal (length (cons x1 (cons x2 (cons x3 (cons x4 (cons x5 (cons x6 x7))))))) (plus 6 (length x7)))
+(equal (difference (add1 (add1 x)) 2) (fix x))
+(equal (quotient (plus x (plus x y)) 2) (plus x (quotient y 2)))
+(equal (sigma (zero) i) (quotient (times i (add1 i)) 2))
+(equal (plus x (add1 y)) (if (numberp y) (add1 (plus x y)) (add1 x)))
+(equal (equal (difference x y) (difference z y)) (if (lessp x y) (not (lessp y z)) (if (lessp z y) (not (lessp y x)) (equal (fix x) (fix z)))))
+(equal (meaning (plus-tree (delete x y)) a) (if (member x y) (difference (meaning (plus-tree y) a) (meaning x a)) (meaning (plus-tree y) a)))
+(equal (times x (add1 y)) (if (numberp y) (plus x (times x y)) (fix x)))
+(equal (nth (nil) i) (if (zerop i) (nil) (zero)))
+(equal (last (append a b)) (if (listp b) (last b) (if (listp a) (cons (car (last a)) b) b)))
+(equal (equal (lessp x y) z) (if (lessp x y) (equal t z) (equal f z)))
+(equal (assignment x (append a b)) (if (assignedp x a) (assignment x a) (assignment x b)))
+(equal (car (gopher x)) (if (listp x) (car (flatten x)) (zero)))
+(equal (flatten (cdr (gopher x))) (if (listp x) (cdr (flatten x)) (cons (zero) (nil))))
+(equal (quotient (times y x) y) (if (zerop y) (zero) (fix x)))
+(equal (get j (set i val mem)) (if (eqp j i) val (get j mem)))
+";
+
+const SUBSTITUTION: &str = "
+((x f (plus (plus a b) (plus c (zero))))
+ (y f (times (times a b) (plus c d)))
+ (z f (reverse (append (append a b) (nil))))
+ (u equal (plus a b) (difference x y))
+ (w lessp (remainder a b) (member a (length b))))";
+
+const TERM: &str = "(implies (and (implies x y) (and (implies y z) (and (implies z u) (implies u w)))) (implies x w))";
+
+fn first(x: Scm) -> Scm {
+    car(x).expect("pair")
+}
+
+fn rest(x: Scm) -> Scm {
+    cdr(x).expect("pair")
+}
+
+// The entry for `key` in an association list, compared with `eq?`.
+fn assq(key: Scm, mut alist: Scm) -> Option<Scm> {
+    while is_pair(alist) {
+        if first(first(alist)) == key {
+            return Some(first(alist))
+        }
+        alist = rest(alist);
+    }
+    None
+}
+
+fn member(x: Scm, mut list: Scm) -> bool {
+    while is_pair(list) {
+        if equal(x, first(list)) {
+            return true
+        }
+        list = rest(list);
+    }
+    false
+}
+
+fn apply_subst(alist: Scm, term: Scm) -> Scm {
+    if !is_pair(term) {
+        return assq(term, alist).map_or(term, rest)
+    }
+    cons(first(term), apply_subst_list(alist, rest(term)))
+}
+
+fn apply_subst_list(alist: Scm, terms: Scm) -> Scm {
+    if !is_pair(terms) {
+        return terms
+    }
+    cons(apply_subst(alist, first(terms)), apply_subst_list(alist, rest(terms)))
+}
+
+// Binds the variables of `pattern` so that it matches `term`, extending the
+// substitution in `subst`.
+fn unify(term: Scm, pattern: Scm, subst: &mut Scm) -> bool {
+    if !is_pair(pattern) {
+        if let Some(binding) = assq(pattern, *subst) {
+            return equal(term, rest(binding))
+        }
+        if is_number(pattern) {
+            return equal(term, pattern)
+        }
+        *subst = cons(cons(pattern, term), *subst);
+        true
+    } else if !is_pair(term) || first(term) != first(pattern) {
+        false
+    } else {
+        let (mut terms, mut patterns) = (rest(term), rest(pattern));
+        while is_pair(terms) && is_pair(patterns) {
+            if !unify(first(terms), first(patterns), subst) {
+                return false
+            }
+            terms = rest(terms);
+            patterns = rest(patterns);
+        }
+        terms.is_nil() && patterns.is_nil()
+    }
+}
+
+struct Boyer {
+    // by the head symbol of their left side, the last one added first
+    lemmas: HashMap<Scm, Vec<Scm>>,
+    sharing: bool,
+    rewrites: Cell<usize>,
+}
+
+impl Boyer {
+    fn new(sharing: bool) -> Self {
+        let mut lemmas: HashMap<Scm, Vec<Scm>> = HashMap::new();
+        for lemma in read_all(LEMMAS).unwrap() {
+            let head = first(first(rest(lemma)));
+            lemmas.entry(head).or_default().insert(0, lemma);
+        }
+        Boyer { lemmas, sharing, rewrites: Cell::new(0) }
+    }
+
+    fn rewrite(&self, term: Scm) -> Scm {
+        self.rewrites.set(self.rewrites.get() + 1);
+        if !is_pair(term) {
+            return term
+        }
+        let args = self.rewrite_args(rest(term));
+        let term = if self.sharing && args == rest(term) { term } else { cons(first(term), args) };
+        self.rewrite_with_lemmas(term)
+    }
+
+    fn rewrite_args(&self, args: Scm) -> Scm {
+        if !is_pair(args) {
+            return args
+        }
+        let (head, tail) = (self.rewrite(first(args)), self.rewrite_args(rest(args)));
+        if self.sharing && head == first(args) && tail == rest(args) {
+            args
+        } else {
+            cons(head, tail)
+        }
+    }
+
+    fn rewrite_with_lemmas(&self, term: Scm) -> Scm {
+        for &lemma in self.lemmas.get(&first(term)).into_iter().flatten() {
+            let mut subst = Scm::NIL;
+            if unify(term, first(rest(lemma)), &mut subst) {
+                return self.rewrite(apply_subst(subst, first(rest(rest(lemma)))))
+            }
+        }
+        term
+    }
+
+    fn tautology(&self, term: Scm) -> bool {
+        tautologyp(self.rewrite(term), Scm::NIL, Scm::NIL)
+    }
+}
+
+fn tautologyp(x: Scm, true_list: Scm, false_list: Scm) -> bool {
+    let (t, f) = (read_str("(t)").unwrap(), read_str("(f)").unwrap());
+    if equal(x, t) || member(x, true_list) {
+        true
+    } else if equal(x, f) || member(x, false_list) || !is_pair(x) || first(x) != Scm::symbol("if") {
+        false
+    } else {
+        let test = first(rest(x));
+        let (consequent, alternative) = (first(rest(rest(x))), first(rest(rest(rest(x)))));
+        if equal(test, t) || member(test, true_list) {
+            tautologyp(consequent, true_list, false_list)
+        } else if equal(test, f) || member(test, false_list) {
+            tautologyp(alternative, true_list, false_list)
+        } else {
+            tautologyp(consequent, cons(test, true_list), false_list)
+                && tautologyp(alternative, true_list, cons(test, false_list))
+        }
+    }
+}
+
+// The test term with the substitution applied, wrapped `n` times.
+fn test_term(n: usize) -> Scm {
+    let mut term = read_str(TERM).unwrap();
+    for _ in 0..n {
+        term = cons(Scm::symbol("or"), cons(term, cons(read_str("(f)").unwrap(), Scm::NIL)));
+    }
+    apply_subst(read_str(SUBSTITUTION).unwrap(), term)
+}
+
+fn boyer_performance(c: &mut Criterion) {
+    let (nboyer, sboyer) = (Boyer::new(false), Boyer::new(true));
+    let term = test_term(0);
+    assert!(nboyer.tautology(term) && sboyer.tautology(term));
+    let mut group = c.benchmark_group("boyer");
+    group.bench_function("nboyer", |b| b.iter(|| nboyer.tautology(black_box(term))));
+    group.bench_function("sboyer", |b| b.iter(|| sboyer.tautology(black_box(term))));
+    group.finish();
+}
+
+#[test]
+fn both_versions_prove_the_term() {
+    let (nboyer, sboyer) = (Boyer::new(false), Boyer::new(true));
+    let term = test_term(1);
+    assert!(nboyer.tautology(term));
+    assert!(sboyer.tautology(term));
+    assert_eq!(nboyer.rewrites.get(), sboyer.rewrites.get());
+    assert!(!nboyer.tautology(read_str("(implies x y)").unwrap()));
+}
+
+criterion_group!(benches, boyer_performance);
+criterion_main!(benches);
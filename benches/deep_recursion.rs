@@ -0,0 +1,156 @@
+//* Recursion that does little but make and test small integers: Ackermann's
+//* function and the mutually recursive even?/odd?. Per call they do even
+//* less arithmetic than fib, so the cost of the Option-returning accessors
+//* and of retagging stands out more. Each is written four ways:
+//*
+//*    untag/retag   with `as_integer` and `from_int`
+//*    generic       with the generic numeric tower
+//*    checked       with the fixnum fast paths, which check both tags at
+//*                  once and return None on anything else
+//*    tagged        with the unchecked fast paths on the tagged words
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::num::{self, fixnum_add, fixnum_add_unchecked, fixnum_sub, fixnum_sub_unchecked};
+use scm_repr::Scm;
+
+// ack(3, 7) takes almost 700,000 calls, but recurses only about 1000 deep.
+const ACK_M: i64 = 3;
+const ACK_N: i64 = 7;
+// even?/odd? recurses all the way down, so this is also the stack depth.
+const PARITY_N: i64 = 10_000;
+
+const ZERO: Scm = Scm::from_int(0);
+const ONE: Scm = Scm::from_int(1);
+
+#[inline(never)]
+fn ack_untagged(m: Scm, n: Scm) -> Scm {
+    let (i, j) = (m.as_integer().expect("int"), n.as_integer().expect("int"));
+    if i == 0 {
+        Scm::from_int(j + 1)
+    } else if j == 0 {
+        ack_untagged(Scm::from_int(i - 1), ONE)
+    } else {
+        ack_untagged(Scm::from_int(i - 1), ack_untagged(m, Scm::from_int(j - 1)))
+    }
+}
+
+#[inline(never)]
+fn ack_generic(m: Scm, n: Scm) -> Scm {
+    if num::eq(m, ZERO).unwrap() {
+        num::add(n, ONE).unwrap()
+    } else if num::eq(n, ZERO).unwrap() {
+        ack_generic(num::sub(m, ONE).unwrap(), ONE)
+    } else {
+        ack_generic(num::sub(m, ONE).unwrap(), ack_generic(m, num::sub(n, ONE).unwrap()))
+    }
+}
+
+// Fixnums are equal exactly when their words are.
+#[inline(never)]
+fn ack_checked(m: Scm, n: Scm) -> Scm {
+    if m == ZERO {
+        fixnum_add(n, ONE).expect("fixnum")
+    } else if n == ZERO {
+        ack_checked(fixnum_sub(m, ONE).expect("fixnum"), ONE)
+    } else {
+        ack_checked(fixnum_sub(m, ONE).expect("fixnum"), ack_checked(m, fixnum_sub(n, ONE).expect("fixnum")))
+    }
+}
+
+#[inline(never)]
+fn ack_tagged(m: Scm, n: Scm) -> Scm {
+    // m and n stay small non-negative fixnums throughout
+    unsafe {
+        if m == ZERO {
+            fixnum_add_unchecked(n, ONE)
+        } else if n == ZERO {
+            ack_tagged(fixnum_sub_unchecked(m, ONE), ONE)
+        } else {
+            ack_tagged(fixnum_sub_unchecked(m, ONE), ack_tagged(m, fixnum_sub_unchecked(n, ONE)))
+        }
+    }
+}
+
+#[inline(never)]
+fn even_untagged(n: Scm) -> bool {
+    let i = n.as_integer().expect("int");
+    i == 0 || odd_untagged(Scm::from_int(i - 1))
+}
+
+#[inline(never)]
+fn odd_untagged(n: Scm) -> bool {
+    let i = n.as_integer().expect("int");
+    i != 0 && even_untagged(Scm::from_int(i - 1))
+}
+
+#[inline(never)]
+fn even_generic(n: Scm) -> bool {
+    num::eq(n, ZERO).unwrap() || odd_generic(num::sub(n, ONE).unwrap())
+}
+
+#[inline(never)]
+fn odd_generic(n: Scm) -> bool {
+    !num::eq(n, ZERO).unwrap() && even_generic(num::sub(n, ONE).unwrap())
+}
+
+#[inline(never)]
+fn even_checked(n: Scm) -> bool {
+    n == ZERO || odd_checked(fixnum_sub(n, ONE).expect("fixnum"))
+}
+
+#[inline(never)]
+fn odd_checked(n: Scm) -> bool {
+    n != ZERO && even_checked(fixnum_sub(n, ONE).expect("fixnum"))
+}
+
+#[inline(never)]
+fn even_tagged(n: Scm) -> bool {
+    // n counts down from a non-negative fixnum to zero
+    n == ZERO || odd_tagged(unsafe { fixnum_sub_unchecked(n, ONE) })
+}
+
+#[inline(never)]
+fn odd_tagged(n: Scm) -> bool {
+    n != ZERO && even_tagged(unsafe { fixnum_sub_unchecked(n, ONE) })
+}
+
+fn recursion_performance(c: &mut Criterion) {
+    let (m, n) = (Scm::from_int(ACK_M), Scm::from_int(ACK_N));
+    let mut group = c.benchmark_group(format!("ackermann {} {}", ACK_M, ACK_N));
+    group.bench_function("untag/retag", |b| b.iter(|| ack_untagged(black_box(m), black_box(n))));
+    group.bench_function("generic", |b| b.iter(|| ack_generic(black_box(m), black_box(n))));
+    group.bench_function("checked", |b| b.iter(|| ack_checked(black_box(m), black_box(n))));
+    group.bench_function("tagged", |b| b.iter(|| ack_tagged(black_box(m), black_box(n))));
+    group.finish();
+
+    let n = Scm::from_int(PARITY_N);
+    let mut group = c.benchmark_group(format!("even? {}", PARITY_N));
+    group.bench_function("untag/retag", |b| b.iter(|| even_untagged(black_box(n))));
+    group.bench_function("generic", |b| b.iter(|| even_generic(black_box(n))));
+    group.bench_function("checked", |b| b.iter(|| even_checked(black_box(n))));
+    group.bench_function("tagged", |b| b.iter(|| even_tagged(black_box(n))));
+    group.finish();
+}
+
+#[test]
+fn all_variants_agree() {
+    let (m, n) = (Scm::from_int(2), Scm::from_int(3));
+    assert_eq!(ack_untagged(m, n), Scm::from_int(9));
+    assert_eq!(ack_generic(m, n), Scm::from_int(9));
+    assert_eq!(ack_checked(m, n), Scm::from_int(9));
+    assert_eq!(ack_tagged(m, n), Scm::from_int(9));
+    for &i in &[0, 1, 10, 11] {
+        let n = Scm::from_int(i);
+        let expected = i % 2 == 0;
+        assert_eq!([even_untagged(n), even_generic(n), even_checked(n), even_tagged(n)], [expected; 4]);
+        assert_eq!([odd_untagged(n), odd_generic(n), odd_checked(n), odd_tagged(n)], [!expected; 4]);
+    }
+}
+
+criterion_group!(benches, recursion_performance);
+criterion_main!(benches);
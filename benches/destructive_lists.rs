@@ -0,0 +1,92 @@
+//* The destructive list operations against their copying counterparts, on
+//* freshly built lists of a few lengths:
+//*
+//*    append    `append` copies every list but the last, `append_destructive`
+//*              relinks the last cdr of each
+//*    reverse   `append_reverse` onto the empty list conses a new list,
+//*              `reverse_destructive` turns the cdrs around
+//*
+//* Building the inputs is left out of the measurements. Before timing, the
+//* number of pairs each operation allocates is printed, which is where the
+//* difference comes from: none for the destructive versions.
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::black_box;
+use criterion::{BatchSize, BenchmarkId, Criterion};
+
+use scm_repr::heap::{AllocationCount, Kind};
+use scm_repr::lists::{append, append_destructive, append_reverse, reverse_destructive, vec_to_list};
+use scm_repr::Scm;
+
+const LENGTHS: [usize; 3] = [10, 1_000, 100_000];
+
+fn ints(n: usize) -> Scm {
+    vec_to_list(&(0..n as i64).map(Scm::from_int).collect::<Vec<_>>())
+}
+
+fn two_lists(n: usize) -> [Scm; 2] {
+    [ints(n / 2), ints(n - n / 2)]
+}
+
+// The number of pairs that `f` allocates.
+fn pairs_allocated(f: impl FnOnce() -> Scm) -> isize {
+    let count = AllocationCount::start();
+    black_box(f());
+    count.outstanding().iter().filter(|(kind, _)| *kind == Kind::Pair).map(|(_, n)| n).sum()
+}
+
+fn report_allocations() {
+    for &n in &LENGTHS {
+        let lists = two_lists(n);
+        let copying = pairs_allocated(|| append(&lists).unwrap());
+        let destructive = pairs_allocated(|| unsafe { append_destructive(&lists) }.unwrap());
+        println!("append of {} elements: {} pairs copying, {} destructive", n, copying, destructive);
+
+        let list = ints(n);
+        let copying = pairs_allocated(|| append_reverse(list, Scm::NIL).unwrap());
+        let destructive = pairs_allocated(|| unsafe { reverse_destructive(list) }.unwrap());
+        println!("reverse of {} elements: {} pairs copying, {} destructive", n, copying, destructive);
+    }
+}
+
+fn destructive_lists(c: &mut Criterion) {
+    report_allocations();
+
+    let mut group = c.benchmark_group("append");
+    for &n in &LENGTHS {
+        group.bench_with_input(BenchmarkId::new("copying", n), &n, |b, &n| {
+            b.iter_batched(|| two_lists(n), |lists| append(&lists).unwrap(), BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("destructive", n), &n, |b, &n| {
+            b.iter_batched(|| two_lists(n), |lists| unsafe { append_destructive(&lists) }.unwrap(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("reverse");
+    for &n in &LENGTHS {
+        group.bench_with_input(BenchmarkId::new("copying", n), &n, |b, &n| {
+            b.iter_batched(|| ints(n), |list| append_reverse(list, Scm::NIL).unwrap(), BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("destructive", n), &n, |b, &n| {
+            b.iter_batched(|| ints(n), |list| unsafe { reverse_destructive(list) }.unwrap(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+#[test]
+fn destructive_versions_allocate_no_pairs() {
+    let lists = two_lists(100);
+    assert_eq!(pairs_allocated(|| append(&lists).unwrap()), 50);
+    assert_eq!(pairs_allocated(|| unsafe { append_destructive(&lists) }.unwrap()), 0);
+    let list = ints(100);
+    assert_eq!(pairs_allocated(|| append_reverse(list, Scm::NIL).unwrap()), 100);
+    let reversed = unsafe { reverse_destructive(list) }.unwrap();
+    assert_eq!(scm_repr::car(reversed), Some(Scm::from_int(99)));
+}
+
+criterion_group!(benches, destructive_lists);
+criterion_main!(benches);
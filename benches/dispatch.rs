@@ -0,0 +1,101 @@
+//* The cost of type dispatch alone: the same classification of a shuffled
+//* stream of integers, pairs, empty lists, booleans and characters on every
+//* representation. How each one tells the types apart:
+//*
+//*    scm_repr   masks the tag bits of the word
+//*    simple     loads the enum behind the reference and matches its
+//*               discriminant
+//*    fastint    tests the integer bit, then matches the enum of anything
+//*               else
+//*    cheapair   masks the tag bits; only the remaining heap values need a
+//*               match
+//*
+//* The stream is built once, so only the dispatch is measured. It is in a
+//* random order, so that the branch predictor can't learn it. The tag_bits
+//* bench compares tags with header loads within scm_repr.
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "representations/cheaper_pairs.rs"]
+mod cheaper_pairs;
+#[allow(dead_code)]
+#[path = "representations/faster_integers.rs"]
+mod faster_integers;
+#[allow(dead_code)]
+#[path = "representations/simple.rs"]
+mod simple;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::repr::Representation;
+use scm_repr::Scm;
+
+const N: usize = 10_000;
+
+fn mixed_values<R: Representation>(n: usize) -> Vec<R> {
+    let mut state: u32 = 0x9e37_79b9;
+    (0..n as i64)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            match state % 5 {
+                0 => R::from_int(i),
+                1 => R::cons(R::from_int(i), R::nil()),
+                2 => R::nil(),
+                3 => R::from_bool(i % 2 == 0),
+                _ => R::from_char('x'),
+            }
+        })
+        .collect()
+}
+
+// Integers, pairs, empty lists and anything else, as a Scheme procedure
+// that dispatches on the type of its argument would tell them apart.
+fn classify<R: Representation>(values: &[R]) -> [usize; 4] {
+    let mut counts = [0; 4];
+    for &x in values {
+        let class = if x.as_integer().is_some() {
+            0
+        } else if x.car().is_some() {
+            1
+        } else if x.is_null() {
+            2
+        } else {
+            3
+        };
+        counts[class] += 1;
+    }
+    counts
+}
+
+fn dispatch_performance(c: &mut Criterion) {
+    let scm = mixed_values::<Scm>(N);
+    let simple = mixed_values::<simple::Scm>(N);
+    let fastint = mixed_values::<faster_integers::Scm>(N);
+    let cheapair = mixed_values::<cheaper_pairs::Scm>(N);
+    let expected = classify(&scm);
+    assert!(classify(&simple) == expected && classify(&fastint) == expected && classify(&cheapair) == expected);
+
+    let mut group = c.benchmark_group("dispatch");
+    group.bench_function("scm_repr", |b| b.iter(|| classify(black_box(&scm))));
+    group.bench_function("simple", |b| b.iter(|| classify(black_box(&simple))));
+    group.bench_function("fastint", |b| b.iter(|| classify(black_box(&fastint))));
+    group.bench_function("cheapair", |b| b.iter(|| classify(black_box(&cheapair))));
+    group.finish();
+}
+
+#[test]
+fn every_representation_classifies_alike() {
+    let expected = classify(&mixed_values::<Scm>(100));
+    assert_eq!(classify(&mixed_values::<simple::Scm>(100)), expected);
+    assert_eq!(classify(&mixed_values::<faster_integers::Scm>(100)), expected);
+    assert_eq!(classify(&mixed_values::<cheaper_pairs::Scm>(100)), expected);
+    assert_eq!(expected.iter().sum::<usize>(), 100);
+}
+
+criterion_group!(benches, dispatch_performance);
+criterion_main!(benches);
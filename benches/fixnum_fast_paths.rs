@@ -0,0 +1,72 @@
+//* The fibonacci benchmark spends most of its time untagging and retagging
+//* integers. Here the same function is written three ways: with the plain
+//* accessors, with the generic numeric tower, and with the tagged fixnum fast
+//* paths that add the tagged words directly.
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::num::{self, fixnum_add_unchecked, fixnum_lt_unchecked, fixnum_sub_unchecked};
+use scm_repr::Scm;
+
+
+#[inline(never)]
+fn fib_untagged(n: Scm) -> Scm {
+    if n.as_integer().expect("int") < 2 {
+        Scm::from_int(1)
+    } else {
+        let a = (fib_untagged(Scm::from_int(n.as_integer().unwrap() - 1))).as_integer().unwrap();
+        let b = (fib_untagged(Scm::from_int(n.as_integer().unwrap() - 2))).as_integer().unwrap();
+        Scm::from_int(a + b)
+    }
+}
+
+#[inline(never)]
+fn fib_generic(n: Scm) -> Scm {
+    if n.expect_integer().unwrap() < 2 {
+        Scm::from_int(1)
+    } else {
+        let a = fib_generic(num::sub(n, Scm::from_int(1)).unwrap());
+        let b = fib_generic(num::sub(n, Scm::from_int(2)).unwrap());
+        num::add(a, b).unwrap()
+    }
+}
+
+#[inline(never)]
+fn fib_tagged(n: Scm) -> Scm {
+    const ONE: Scm = Scm::from_int(1);
+    const TWO: Scm = Scm::from_int(2);
+    unsafe {
+        if fixnum_lt_unchecked(n, TWO) {
+            ONE
+        } else {
+            let a = fib_tagged(fixnum_sub_unchecked(n, ONE));
+            let b = fib_tagged(fixnum_sub_unchecked(n, TWO));
+            fixnum_add_unchecked(a, b)
+        }
+    }
+}
+
+fn fixnum_performance(c: &mut Criterion) {
+    let n = params::fib_depth();
+    c.bench_function(&format!("fixnum fib {} untag/retag", n), |b| b.iter(|| fib_untagged(black_box(Scm::from_int(n)))));
+    c.bench_function(&format!("fixnum fib {} generic", n), |b| b.iter(|| fib_generic(black_box(Scm::from_int(n)))));
+    c.bench_function(&format!("fixnum fib {} tagged", n), |b| b.iter(|| fib_tagged(black_box(Scm::from_int(n)))));
+}
+
+#[test]
+fn all_variants_agree() {
+    let n = Scm::from_int(15);
+    assert_eq!(fib_untagged(n), fib_tagged(n));
+    assert_eq!(fib_generic(n), fib_tagged(n));
+}
+
+criterion_group!(benches, fixnum_performance);
+criterion_main!(benches);
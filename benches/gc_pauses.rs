@@ -0,0 +1,114 @@
+//* Pause times: allocates at a steady rate while keeping a sliding window of
+//* lists alive, times every single operation, and prints the distribution
+//* of those latencies for each way of managing the heap:
+//*
+//*    stop-the-world   the Boehm GC as it is set up by default
+//*    incremental      the Boehm GC, collecting a little on every allocation
+//*    leak             the Boehm GC with collection disabled
+//*
+//* The median is the cost of allocating itself, while the tail and the
+//* maximum are the pauses a program would see. The collector can only be
+//* configured before it has started, so every configuration runs in a child
+//* process of its own. That only happens with `cargo bench`; otherwise a
+//* short run in this process checks that the measurement works.
+
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use criterion::black_box;
+use dbwgc_sys::{DbwGcAllocator, GC_disable, GC_enable_incremental, GC_init};
+
+use scm_repr::{cons, Scm};
+
+#[global_allocator]
+static A: DbwGcAllocator = DbwGcAllocator;
+
+const OPERATIONS: usize = 100_000;
+const WINDOW: usize = 10_000;
+const LIST_LEN: i64 = 50;
+
+const CONFIGURATIONS: [&str; 3] = ["stop-the-world", "incremental", "leak"];
+
+const MEASURE_ONCE: &str = "--measure-once";
+
+fn make_list(len: i64) -> Scm {
+    (0..len).rev().fold(Scm::NIL, |list, i| cons(Scm::from_int(i), list))
+}
+
+// The latency of each of `operations` allocations of a list, sorted. Each
+// new list replaces the oldest in the window, which becomes garbage.
+fn latencies(operations: usize) -> Vec<Duration> {
+    let mut window = vec![Scm::NIL; WINDOW];
+    // allocated up front, so that recording doesn't allocate
+    let mut latencies = Vec::with_capacity(operations);
+    for i in 0..operations {
+        let start = Instant::now();
+        window[i % WINDOW] = make_list(LIST_LEN);
+        latencies.push(start.elapsed());
+    }
+    black_box(window);
+    latencies.sort_unstable();
+    latencies
+}
+
+// The latency that a `fraction` of all operations stay within.
+fn percentile(sorted: &[Duration], fraction: f64) -> Duration {
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Measures in this process, which must be fresh, and prints the total time
+// and the percentiles in nanoseconds.
+//
+// Panics if there is no such configuration.
+fn measure_once(configuration: &str) {
+    match configuration {
+        "stop-the-world" => {}
+        "incremental" => unsafe { GC_enable_incremental() },
+        "leak" => unsafe { GC_disable() },
+        _ => panic!("unknown configuration {}", configuration),
+    }
+    let sorted = latencies(OPERATIONS);
+    let total: Duration = sorted.iter().sum();
+    let points = [percentile(&sorted, 0.5), percentile(&sorted, 0.99), percentile(&sorted, 0.999), sorted[sorted.len() - 1]];
+    print!("{}", total.as_nanos());
+    for point in &points {
+        print!(" {}", point.as_nanos());
+    }
+    println!();
+}
+
+fn measure_in_child(configuration: &str) -> Vec<Duration> {
+    let exe = env::current_exe().expect("path of the benchmark binary");
+    let output = Command::new(exe).args([MEASURE_ONCE, configuration]).output().expect("child process");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout.split_whitespace().map(|field| Duration::from_nanos(field.parse().expect("number"))).collect()
+}
+
+fn print_pauses() {
+    println!("{:<16} {:>12} {:>12} {:>12} {:>12} {:>12}", "", "total", "p50", "p99", "p99.9", "max");
+    for configuration in CONFIGURATIONS.iter() {
+        let times = measure_in_child(configuration);
+        print!("{:<16}", configuration);
+        for time in times {
+            print!(" {:>12.2?}", time);
+        }
+        println!();
+    }
+}
+
+fn main() {
+    unsafe { GC_init() };
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 3 && args[1] == MEASURE_ONCE {
+        return measure_once(&args[2]);
+    }
+    if args.iter().any(|arg| arg == "--bench") {
+        print_pauses();
+    } else {
+        let sorted = latencies(WINDOW * 2);
+        assert!(percentile(&sorted, 0.5) <= percentile(&sorted, 0.99));
+    }
+}
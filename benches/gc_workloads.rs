@@ -0,0 +1,243 @@
+//* The classic allocation benchmarks, GCBench and the benchmarksgame's
+//* binary-trees, on every representation of values.
+//*
+//* Unlike fib and reverse, they keep a big tree alive for the whole run while
+//* building and dropping many short-lived ones, which is what a collector
+//* has to cope with. As in the main binary, the Boehm GC is the global
+//* allocator, so the dropped trees are actually reclaimed.
+//*
+//* Criterion reports the throughput in tree nodes per second. Peak memory
+//* can't be measured in the same process, because the GC heap never shrinks,
+//* so `cargo bench` also runs every workload once in a child process of its
+//* own, for each representation and with collection on (gc) and off (leak),
+//* and prints the time, the bytes allocated and the peak heap and resident
+//* set sizes of each.
+//*
+//* GCBench also builds its trees top-down, by filling in the children of
+//* existing nodes. Pairs are immutable here, so only the bottom-up half is
+//* ported.
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+#[allow(dead_code)]
+#[path = "representations/cheaper_pairs.rs"]
+mod cheaper_pairs;
+#[allow(dead_code)]
+#[path = "representations/faster_integers.rs"]
+mod faster_integers;
+#[allow(dead_code)]
+#[path = "representations/simple.rs"]
+mod simple;
+#[path = "support/memory.rs"]
+mod memory;
+
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use criterion::black_box;
+use criterion::{Criterion, Throughput};
+use dbwgc_sys::{DbwGcAllocator, GC_disable, GC_get_heap_size, GC_init};
+
+use scm_repr::repr::Representation;
+use scm_repr::Scm;
+
+use memory::Counting;
+
+#[global_allocator]
+static A: Counting<DbwGcAllocator> = Counting(DbwGcAllocator);
+
+// GCBench's sizes, scaled down by four, so that leaking stays affordable.
+const STRETCH_DEPTH: u32 = 16;
+const LONG_LIVED_DEPTH: u32 = 14;
+const ARRAY_SIZE: usize = 125_000;
+const MIN_DEPTH: u32 = 4;
+const MAX_DEPTH: u32 = 14;
+
+// The benchmarksgame runs binary-trees with 21.
+const BINARY_TREES_DEPTH: u32 = 14;
+
+fn tree_nodes(depth: u32) -> u64 {
+    (1 << (depth + 1)) - 1
+}
+
+// A node is a pair of its subtrees, and a leaf is a pair of empty lists.
+fn make_tree<R: Representation>(depth: u32) -> R {
+    if depth == 0 {
+        R::cons(R::nil(), R::nil())
+    } else {
+        R::cons(make_tree(depth - 1), make_tree(depth - 1))
+    }
+}
+
+// The number of nodes in `tree`.
+fn check_tree<R: Representation>(tree: R) -> u64 {
+    let left = tree.car().expect("node");
+    if left.is_null() {
+        1
+    } else {
+        1 + check_tree(left) + check_tree(tree.cdr().expect("node"))
+    }
+}
+
+// Enough trees of `depth` to add up to two stretch trees.
+fn gcbench_iterations(depth: u32) -> u64 {
+    2 * tree_nodes(STRETCH_DEPTH) / tree_nodes(depth)
+}
+
+fn gcbench_nodes() -> u64 {
+    let temporary: u64 = (MIN_DEPTH..=MAX_DEPTH).step_by(2).map(|d| gcbench_iterations(d) * tree_nodes(d)).sum();
+    tree_nodes(STRETCH_DEPTH) + tree_nodes(LONG_LIVED_DEPTH) + temporary
+}
+
+fn gcbench<R: Representation>() -> u64 {
+    // makes the heap grow to its working size
+    black_box(make_tree::<R>(STRETCH_DEPTH));
+
+    let long_lived = make_tree::<R>(LONG_LIVED_DEPTH);
+    let array: Vec<f64> = (0..ARRAY_SIZE).map(|i| 1.0 / (i + 1) as f64).collect();
+
+    for depth in (MIN_DEPTH..=MAX_DEPTH).step_by(2) {
+        for _ in 0..gcbench_iterations(depth) {
+            black_box(make_tree::<R>(depth));
+        }
+    }
+
+    // the long-lived data must have survived all of that
+    assert_eq!(check_tree(long_lived), tree_nodes(LONG_LIVED_DEPTH));
+    assert_eq!(array[999], 1.0 / 1000.0);
+    gcbench_nodes()
+}
+
+fn binary_trees_iterations(depth: u32) -> u64 {
+    1 << (BINARY_TREES_DEPTH - depth + MIN_DEPTH)
+}
+
+fn binary_trees_nodes() -> u64 {
+    let max = BINARY_TREES_DEPTH;
+    let temporary: u64 = (MIN_DEPTH..=max).step_by(2).map(|d| binary_trees_iterations(d) * tree_nodes(d)).sum();
+    tree_nodes(max + 1) + tree_nodes(max) + temporary
+}
+
+fn binary_trees<R: Representation>() -> u64 {
+    let max = BINARY_TREES_DEPTH;
+    let mut check = check_tree(make_tree::<R>(max + 1));
+
+    let long_lived = make_tree::<R>(max);
+
+    for depth in (MIN_DEPTH..=max).step_by(2) {
+        for _ in 0..binary_trees_iterations(depth) {
+            check += check_tree(make_tree::<R>(depth));
+        }
+    }
+
+    check + check_tree(long_lived)
+}
+
+type Workload = fn() -> u64;
+
+type Versions = [(&'static str, Workload); 4];
+
+// Each workload, with the number of nodes it allocates and its version for
+// every representation.
+const WORKLOADS: [(&str, Workload, Versions); 2] = [
+    ("gcbench", gcbench_nodes, [
+        ("scm_repr", gcbench::<Scm>),
+        ("simple", gcbench::<simple::Scm>),
+        ("fastint", gcbench::<faster_integers::Scm>),
+        ("cheapair", gcbench::<cheaper_pairs::Scm>),
+    ]),
+    ("binary-trees", binary_trees_nodes, [
+        ("scm_repr", binary_trees::<Scm>),
+        ("simple", binary_trees::<simple::Scm>),
+        ("fastint", binary_trees::<faster_integers::Scm>),
+        ("cheapair", binary_trees::<cheaper_pairs::Scm>),
+    ]),
+];
+
+fn allocation_throughput(c: &mut Criterion) {
+    for &(name, nodes, runs) in WORKLOADS.iter() {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(params::sample_size(10));
+        group.throughput(Throughput::Elements(nodes()));
+        for &(repr, run) in runs.iter() {
+            group.bench_function(repr, |b| b.iter(run));
+        }
+        group.finish();
+    }
+}
+
+const MEASURE_ONCE: &str = "--measure-once";
+
+// Runs one workload in this process, which must be fresh, and prints its
+// time in nanoseconds, the bytes it allocated, and the GC heap size and peak
+// RSS afterwards.
+//
+// Panics if there is no such workload or allocator.
+fn measure_once(workload: &str, repr: &str, allocator: &str) {
+    let (_, _, runs) = WORKLOADS.iter().find(|w| w.0 == workload).expect("unknown workload");
+    let (_, run) = runs.iter().find(|r| r.0 == repr).expect("unknown representation");
+    match allocator {
+        "gc" => {}
+        "leak" => unsafe { GC_disable() },
+        _ => panic!("unknown allocator {}", allocator),
+    }
+    let start = Instant::now();
+    let (_, usage) = memory::measure(run);
+    let elapsed = start.elapsed();
+    let heap = unsafe { GC_get_heap_size() };
+    println!("{} {} {} {}", elapsed.as_nanos(), usage.allocated, heap, usage.peak_rss.unwrap_or(0));
+}
+
+// The time, bytes allocated, heap size and peak RSS of a run.
+fn measure_in_child(workload: &str, repr: &str, allocator: &str) -> (Duration, usize, usize, usize) {
+    let exe = env::current_exe().expect("path of the benchmark binary");
+    let output = Command::new(exe).args([MEASURE_ONCE, workload, repr, allocator]).output().expect("child process");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let fields: Vec<u64> = stdout.split_whitespace().map(|field| field.parse().expect("number")).collect();
+    (Duration::from_nanos(fields[0]), fields[1] as usize, fields[2] as usize, fields[3] as usize)
+}
+
+fn print_peak_memory() {
+    println!("{:<14} {:<10} {:<6} {:>12} {:>12} {:>12} {:>12} {:>12}", "", "", "", "time", "Mnodes/s", "allocated", "peak heap", "peak RSS");
+    for &(name, nodes, runs) in WORKLOADS.iter() {
+        for &(repr, _) in runs.iter() {
+            for allocator in ["gc", "leak"] {
+                let (time, allocated, heap, rss) = measure_in_child(name, repr, allocator);
+                println!(
+                    "{:<14} {:<10} {:<6} {:>12.2?} {:>12.2} {:>12} {:>12} {:>12}",
+                    name,
+                    repr,
+                    allocator,
+                    time,
+                    nodes() as f64 / time.as_secs_f64() / 1e6,
+                    memory::bytes(allocated),
+                    memory::bytes(heap),
+                    memory::bytes(rss),
+                );
+            }
+        }
+    }
+}
+
+criterion_group!(benches, allocation_throughput);
+
+fn main() {
+    unsafe { GC_init() };
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 5 && args[1] == MEASURE_ONCE {
+        return measure_once(&args[2], &args[3], &args[4]);
+    }
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    // not when the benchmarks are only run once, as by `cargo test --benches`
+    if args.iter().any(|arg| arg == "--bench") {
+        print_peak_memory();
+    }
+}
@@ -0,0 +1,41 @@
+//* Inserting deep keys into a persistent map. The keys are nested lists with
+//* strings in them, so hashing one walks the whole structure. Their hashes are
+//* cached in the object headers after the first insertion; the uncached
+//* variant hashes keys that contain a box, which can't be cached.
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::boxes::make_box;
+use scm_repr::hamt::{make_map, map_assoc};
+use scm_repr::order::{equal_hash, equal_hash32};
+use scm_repr::reader::read_str;
+use scm_repr::{cons, Scm};
+
+const N_KEYS: usize = 1000;
+
+fn deep_key(i: usize) -> Scm {
+    let source = format!("(record {} (\"name-{}\" \"a string long enough to be on the heap\") #(1 2 3 4 5 6 7 8) (nested (deeper ({}))))", i, i, i);
+    read_str(&source).unwrap()
+}
+
+fn insert_all(keys: &[Scm]) -> Scm {
+    keys.iter().fold(make_map(), |map, &key| map_assoc(map, key, Scm::TRUE).unwrap())
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let keys: Vec<_> = (0..N_KEYS).map(deep_key).collect();
+    let boxed: Vec<_> = keys.iter().map(|&key| cons(make_box(Scm::NIL), key)).collect();
+    let key = keys[0];
+
+    c.bench_function("equal_hash deep key", |b| b.iter(|| equal_hash(black_box(key))));
+    c.bench_function("equal_hash32 deep key", |b| b.iter(|| equal_hash32(black_box(key))));
+    c.bench_function("map insert cached keys", |b| b.iter(|| insert_all(black_box(&keys))));
+    c.bench_function("map insert uncached keys", |b| b.iter(|| insert_all(black_box(&boxed))));
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+//* Whole programs through the reference evaluator, so that changes to the
+//* representation show up in the cost of real Scheme code:
+//*
+//*     cargo bench --features interp --bench interp
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use criterion::Criterion;
+
+use scm_repr::eval::{eval, standard_environment};
+use scm_repr::reader::read_all;
+use scm_repr::Scm;
+
+fn programs() -> [(String, String); 2] {
+    let (n, len) = (params::fib_depth(), params::list_len());
+    [
+        (
+            format!("interp fib {}", n),
+            format!("(define (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2))))) (fib {})", n),
+        ),
+        ("interp reverse".to_string(), format!("
+        (define (iota n acc) (if (= n 0) acc (iota (- n 1) (cons n acc))))
+        (define (reverse xs acc) (if (null? xs) acc (reverse (cdr xs) (cons (car xs) acc))))
+        (car (reverse (iota {} '()) '()))", len)),
+    ]
+}
+
+fn run(env: Scm, program: &[Scm]) -> Scm {
+    program.iter().fold(Scm::NIL, |_, &x| eval(x, env).unwrap())
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    for (name, source) in programs().iter() {
+        let program = read_all(source).unwrap();
+        let env = standard_environment();
+        c.bench_function(name, |b| b.iter(|| run(env, &program)));
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+//* Reading and writing large generated data, so that changes to the reader
+//* and the printer show up in the numbers:
+//*
+//*    nested lists   a deep and bushy tree of lists of small integers
+//*    long symbols   a long list of long symbols, some of which need bars
+//*    strings        a long list of strings with escapes and non-ASCII text
+//*
+//* Each is read from its text, written with `Display` into a String and
+//* written to a string port, and read back from what was written. Criterion
+//* reports the throughput in bytes of text.
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::black_box;
+use criterion::{Criterion, Throughput};
+
+use scm_repr::port::{self, with_output_to_string};
+use scm_repr::reader::read_str;
+use scm_repr::{cons, Scm};
+
+const TREE_DEPTH: u32 = 7;
+const TREE_WIDTH: usize = 5;
+const SYMBOLS: usize = 5_000;
+const STRINGS: usize = 5_000;
+
+// A deterministic stream of numbers.
+fn xorshift(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+fn list(items: impl DoubleEndedIterator<Item = Scm>) -> Scm {
+    items.rev().fold(Scm::NIL, |list, x| cons(x, list))
+}
+
+fn nested_lists(depth: u32, next: &mut impl FnMut() -> u64) -> Scm {
+    if depth == 0 {
+        return Scm::from_int((next() % 1000) as i64)
+    }
+    let width = 1 + next() as usize % TREE_WIDTH;
+    let items: Vec<Scm> = (0..width).map(|_| nested_lists(depth - 1, next)).collect();
+    list(items.into_iter())
+}
+
+// Every tenth symbol has a space in its name, so it is written in bars.
+fn long_symbols() -> Scm {
+    let mut next = xorshift(0x9e37_79b9_7f4a_7c15);
+    list((0..SYMBOLS).map(|i| {
+        let separator = if i % 10 == 0 { " " } else { "-" };
+        let name = format!("generated{}symbol{}with-a-rather-long-name-{:x}", separator, i, next());
+        Scm::symbol(&name)
+    }))
+}
+
+fn strings() -> Scm {
+    let mut next = xorshift(0x2545_f491_4f6c_dd1d);
+    list((0..STRINGS).map(|i| {
+        Scm::string(&format!("line {}:\t\"quoted\" \\ text, größer als {}\n", i, next() % 1_000_000))
+    }))
+}
+
+fn data() -> Vec<(&'static str, Scm)> {
+    let mut next = xorshift(0x5851_f42d_4c95_7f2d);
+    vec![
+        ("nested lists", nested_lists(TREE_DEPTH, &mut next)),
+        ("long symbols", long_symbols()),
+        ("strings", strings()),
+    ]
+}
+
+fn write_to_port(x: Scm) -> String {
+    with_output_to_string(|p| port::write(p, x)).unwrap()
+}
+
+fn reading_and_writing(c: &mut Criterion) {
+    for (name, x) in data() {
+        let text = x.to_string();
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function("read", |b| b.iter(|| read_str(black_box(&text)).unwrap()));
+        group.bench_function("write", |b| b.iter(|| black_box(x).to_string()));
+        group.bench_function("write to port", |b| b.iter(|| write_to_port(black_box(x))));
+        group.bench_function("round trip", |b| b.iter(|| read_str(&black_box(x).to_string()).unwrap()));
+        group.finish();
+    }
+}
+
+#[test]
+fn reading_what_was_written_gives_equal_data() {
+    for (name, x) in data() {
+        let text = x.to_string();
+        assert_eq!(write_to_port(x), text, "{}", name);
+        assert!(scm_repr::order::equal(read_str(&text).unwrap(), x), "{}", name);
+    }
+}
+
+criterion_group!(benches, reading_and_writing);
+criterion_main!(benches);
@@ -0,0 +1,182 @@
+//* Runs the same fib and reverse code on every representation of values, so
+//* that they can be compared in one Criterion report:
+//*
+//*    scm_repr   the tagged words of this crate
+//*    simple     every value is a static reference to an enum
+//*    fastint    integers are tagged, everything else is a pointer to an enum
+//*    cheapair   integers, pairs and constants are tagged
+//*
+//* We represent pointers to Scheme values as static references. This has a few
+//* implications:
+//*    1. simple implementation
+//*    2. naive allocation (=no GC) will leak lots of memory
+//*    3. using an explicit garbage collector may be unsound if there is
+//*       nothing that prevents such references to be put in e.g. a Box, where
+//*       the GC cannot find them; resulting in a dangling reference
+//*    4. best strategy is possibly to put the Boehm GC as Rust's global
+//*       allocator. Then the GC manages all allocations and Box,Vec,etal
+//*       are safe to use.
+//*
+//* The fib argument and list length can be changed through the environment,
+//* see support/params.rs.
+//*
+//* `cargo bench` also prints a table of rough timings relative to scm_repr
+//* at the end, for a quick look, and what one run of each benchmark
+//* allocates.
+
+#[macro_use]
+extern crate criterion;
+
+// The representations are kept complete, though fib and reverse don't use
+// every part of them.
+#[allow(dead_code)]
+#[path = "representations/cheaper_pairs.rs"]
+mod cheaper_pairs;
+#[allow(dead_code)]
+#[path = "representations/faster_integers.rs"]
+mod faster_integers;
+#[allow(dead_code)]
+#[path = "representations/simple.rs"]
+mod simple;
+#[path = "support/memory.rs"]
+mod memory;
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use std::alloc::System;
+use std::time::{Duration, Instant};
+
+use criterion::black_box;
+use criterion::Criterion;
+
+use scm_repr::repr::Representation;
+use scm_repr::Scm;
+
+use memory::Counting;
+
+#[global_allocator]
+static A: Counting<System> = Counting(System);
+
+fn fibonacci<R: Representation>(n: R, make_int: fn(i64) -> R) -> R {
+    let n = n.as_integer().expect("int");
+    if n < 2 {
+        make_int(1)
+    } else {
+        let a = fibonacci(make_int(n - 1), make_int).as_integer().unwrap();
+        let b = fibonacci(make_int(n - 2), make_int).as_integer().unwrap();
+        make_int(a + b)
+    }
+}
+
+fn fib<R: Representation>(n: i64) -> R {
+    fibonacci(R::from_int(n), R::from_int)
+}
+
+fn make_list<R: Representation>(len: usize) -> R {
+    let mut list = R::nil();
+    for i in (0..len).rev() {
+        list = R::cons(R::from_int(i as i64), list);
+    }
+    list
+}
+
+fn reverse<R: Representation>(list: R) -> R {
+    if list.is_null() {
+        R::nil()
+    } else {
+        R::cons(reverse(list.cdr().expect("pair")), list.car().expect("pair"))
+    }
+}
+
+fn reverse_list<R: Representation>(len: usize) -> R {
+    reverse(make_list::<R>(len))
+}
+
+fn integer_performance(c: &mut Criterion) {
+    let n = params::fib_depth();
+    let mut group = c.benchmark_group(format!("fib {}", n));
+    group.bench_function("scm_repr", |b| b.iter(|| fib::<Scm>(black_box(n))));
+    group.bench_function("simple", |b| b.iter(|| fib::<simple::Scm>(black_box(n))));
+    group.bench_function("simple, no preboxing", |b| {
+        b.iter(|| fibonacci(black_box(simple::make_boxed_int(n)), simple::make_boxed_int))
+    });
+    group.bench_function("fastint", |b| b.iter(|| fib::<faster_integers::Scm>(black_box(n))));
+    group.bench_function("cheapair", |b| b.iter(|| fib::<cheaper_pairs::Scm>(black_box(n))));
+    group.finish();
+}
+
+fn pair_performance(c: &mut Criterion) {
+    let len = params::list_len();
+    let mut group = c.benchmark_group(format!("reverse {}", len));
+    group.bench_function("scm_repr", |b| b.iter(|| reverse_list::<Scm>(black_box(len))));
+    group.bench_function("simple", |b| b.iter(|| reverse_list::<simple::Scm>(black_box(len))));
+    group.bench_function("fastint", |b| b.iter(|| reverse_list::<faster_integers::Scm>(black_box(len))));
+    group.bench_function("cheapair", |b| b.iter(|| reverse_list::<cheaper_pairs::Scm>(black_box(len))));
+    group.finish();
+}
+
+// The fastest of a few runs, which is steady enough for a summary.
+fn best_of<R>(f: fn() -> R) -> Duration {
+    (0..params::runs())
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+type Timing = fn() -> Duration;
+
+fn print_comparison() {
+    let rows: [(&str, Timing, Timing); 4] = [
+        ("scm_repr", || best_of(|| fib::<Scm>(params::fib_depth())), || best_of(|| reverse_list::<Scm>(params::list_len()))),
+        ("simple", || best_of(|| fib::<simple::Scm>(params::fib_depth())), || best_of(|| reverse_list::<simple::Scm>(params::list_len()))),
+        ("fastint", || best_of(|| fib::<faster_integers::Scm>(params::fib_depth())), || best_of(|| reverse_list::<faster_integers::Scm>(params::list_len()))),
+        ("cheapair", || best_of(|| fib::<cheaper_pairs::Scm>(params::fib_depth())), || best_of(|| reverse_list::<cheaper_pairs::Scm>(params::list_len()))),
+    ];
+    let times: Vec<_> = rows.iter().map(|&(name, fib, reverse)| (name, fib(), reverse())).collect();
+    let (_, base_fib, base_reverse) = times[0];
+    let (fib_name, reverse_name) = (format!("fib {}", params::fib_depth()), format!("reverse {}", params::list_len()));
+    println!("{:<10} {:>12} {:>8} {:>12} {:>8}", "", fib_name, "", reverse_name, "");
+    for (name, fib, reverse) in times {
+        println!(
+            "{:<10} {:>12.2?} {:>7.2}x {:>12.2?} {:>7.2}x",
+            name,
+            fib,
+            fib.as_secs_f64() / base_fib.as_secs_f64(),
+            reverse,
+            reverse.as_secs_f64() / base_reverse.as_secs_f64(),
+        );
+    }
+}
+
+type Run = fn() -> memory::Usage;
+
+fn print_memory() {
+    let rows: [(&str, Run, Run); 4] = [
+        ("scm_repr", || memory::measure(|| fib::<Scm>(params::fib_depth())).1, || memory::measure(|| reverse_list::<Scm>(params::list_len())).1),
+        ("simple", || memory::measure(|| fib::<simple::Scm>(params::fib_depth())).1, || memory::measure(|| reverse_list::<simple::Scm>(params::list_len())).1),
+        ("fastint", || memory::measure(|| fib::<faster_integers::Scm>(params::fib_depth())).1, || memory::measure(|| reverse_list::<faster_integers::Scm>(params::list_len())).1),
+        ("cheapair", || memory::measure(|| fib::<cheaper_pairs::Scm>(params::fib_depth())).1, || memory::measure(|| reverse_list::<cheaper_pairs::Scm>(params::list_len())).1),
+    ];
+    let (fib_name, reverse_name) = (format!("fib {}", params::fib_depth()), format!("reverse {}", params::list_len()));
+    for (name, fib, reverse) in rows.iter() {
+        println!("{:<10} {:<13} {}", name, fib_name, fib());
+        println!("{:<10} {:<13} {}", name, reverse_name, reverse());
+    }
+}
+
+criterion_group!(benches, integer_performance, pair_performance);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    // not when the benchmarks are only run once, as by `cargo test --benches`
+    if std::env::args().any(|arg| arg == "--bench") {
+        print_comparison();
+        print_memory();
+    }
+}
@@ -0,0 +1,212 @@
+use scm_repr::repr::Representation;
+use std::fmt;
+
+const N_TAG_BITS: usize = 2;
+const TAG_MASK: usize = 0b_11;
+const TAG_POINTER: usize = 0b_00;
+const TAG_INTEGER: usize = 0b_01;
+const TAG_PAIR: usize = 0b_10;
+const TAG_SPECIAL: usize = 0b_11;
+
+const SPECIAL_NIL: usize = 0b_0011;
+const SPECIAL_FALSE: usize = 0b_0111;
+const SPECIAL_TRUE: usize = 0b_1011;
+const SPECIAL_EOF: usize = 0b_1111;
+// with the code point above the low byte
+const SPECIAL_CHAR: usize = 0b_0001_0011;
+
+const MASK_IMMEDIATE: usize = 0b01;  // this works because all immediates have 1 in the lsb
+
+#[derive(Copy, Clone)]
+pub struct Scm {
+    value: usize,
+}
+
+impl Scm {
+    fn new(value: ScmValue) -> Self {
+        Scm {
+            value: ref_to_addr(Box::leak(Box::new(value)))
+        }
+    }
+
+    fn nil() -> Self {
+        Scm {
+            value: SPECIAL_NIL
+        }
+    }
+
+    fn from_int(value: i64) -> Self {
+        Scm {
+            value: (value as usize) << N_TAG_BITS | TAG_INTEGER
+        }
+    }
+
+    fn special(value: usize) -> Self {
+        Scm { value }
+    }
+
+    fn is_immediate(&self) -> bool {
+        self.value & MASK_IMMEDIATE != 0
+    }
+
+    fn is_nil(&self) -> bool {
+        self.value == SPECIAL_NIL
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        if self.value & TAG_MASK == TAG_INTEGER {
+            Some((self.value >> N_TAG_BITS) as i64)
+        } else {
+            None
+        }
+    }
+
+    fn as_ref(&self) -> Option<&ScmValue> {
+        if self.value & TAG_MASK == TAG_POINTER {
+            unsafe {
+                Some(int_to_ref(self.value))
+            }
+        } else {
+            None
+        }
+    }
+
+    fn as_pair(&self) -> Option<&(Scm, Scm)> {
+        if self.value & TAG_MASK == TAG_PAIR {
+            unsafe {
+                Some(int_to_ref(self.value - TAG_PAIR))
+            }
+        } else {
+            None
+        }
+    }
+}
+
+unsafe fn int_to_ref<T>(i: usize) -> &'static T {
+    &*(i as *const T)
+}
+
+fn ref_to_addr<T>(r: &T) -> usize {
+    r as *const T as usize
+}
+
+// Decodes the tag instead of printing the raw word. `{:#?}` adds the raw
+// word and tag bits, and the fields of a pair one level deep.
+impl fmt::Debug for Scm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let brief = match self.value & TAG_MASK {
+            TAG_INTEGER => format!("Int({})", self.as_integer().unwrap()),
+            TAG_PAIR => format!("Pair({:#x})", self.value - TAG_PAIR),
+            TAG_POINTER => format!("Vector({:#x})", self.value),
+            _ => match self.value {
+                SPECIAL_NIL => "Nil".to_string(),
+                SPECIAL_FALSE => "False".to_string(),
+                SPECIAL_TRUE => "True".to_string(),
+                SPECIAL_EOF => "Eof".to_string(),
+                _ => format!("Char({:?})", std::char::from_u32((self.value >> 8) as u32).unwrap()),
+            },
+        };
+        if !f.alternate() {
+            return f.write_str(&brief)
+        }
+        let mut s = f.debug_struct("Scm");
+        s.field("value", &format_args!("{}", brief));
+        s.field("raw", &format_args!("{:#x}", self.value));
+        s.field("tag", &format_args!("{:#0w$b}", self.value & TAG_MASK, w = N_TAG_BITS + 2));
+        if let (Some(car), Some(cdr)) = (car(*self), cdr(*self)) {
+            s.field("car", &format_args!("{:?}", car)).field("cdr", &format_args!("{:?}", cdr));
+        }
+        s.finish()
+    }
+}
+
+#[derive(Debug)]
+#[repr(u64)]
+pub enum ScmValue {
+    Vector(&'static[Scm]),
+}
+
+pub fn cons(car: Scm, cdr: Scm) -> Scm {
+    let r = Box::leak(Box::new((car, cdr)));
+    let addr = r as *const _ as usize;
+    debug_assert!(addr & TAG_MASK == 0);
+    Scm {
+        value: addr + TAG_PAIR
+    }
+}
+
+pub fn car(scm: Scm) -> Option<Scm> {
+    scm.as_pair().map(|p| p.0)
+}
+
+pub fn cdr(scm: Scm) -> Option<Scm> {
+    scm.as_pair().map(|p| p.1)
+}
+
+pub fn is_null(scm: Scm) -> bool {
+    scm.is_nil()
+}
+
+impl Representation for Scm {
+    fn nil() -> Self {
+        Scm::nil()
+    }
+
+    fn eof() -> Self {
+        Scm::special(SPECIAL_EOF)
+    }
+
+    fn from_bool(b: bool) -> Self {
+        Scm::special(if b { SPECIAL_TRUE } else { SPECIAL_FALSE })
+    }
+
+    fn from_char(c: char) -> Self {
+        Scm::special((c as usize) << 8 | SPECIAL_CHAR)
+    }
+
+    fn from_int(i: i64) -> Self {
+        Scm::from_int(i)
+    }
+
+    fn cons(car: Self, cdr: Self) -> Self {
+        cons(car, cdr)
+    }
+
+    fn is_eq(self, other: Self) -> bool {
+        self.value == other.value
+    }
+
+    fn as_integer(self) -> Option<i64> {
+        Scm::as_integer(&self)
+    }
+
+    fn car(self) -> Option<Self> {
+        car(self)
+    }
+
+    fn cdr(self) -> Option<Self> {
+        cdr(self)
+    }
+
+    fn is_null(self) -> bool {
+        is_null(self)
+    }
+}
+
+#[test]
+fn integer_vs_pointers() {
+    for i in 0..10 {
+        let x = Scm::from_int(i);
+        let p = cons(x, x);
+
+        assert!(x.as_integer().is_some());
+        assert!(car(x).is_none());
+        assert!(car(p).is_some());
+        assert!(p.as_integer().is_none());
+    }
+}
+
+#[test]
+fn constants_are_singletons() {
+    assert!(scm_repr::repr::shares_singletons::<Scm>());
+}
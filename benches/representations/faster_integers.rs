@@ -0,0 +1,175 @@
+use scm_repr::repr::Representation;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+const N_TAG_BITS: usize = 1;
+const TAG_MASK: usize = 0b_1;
+const TAG_POINTER: usize = 0b_0;
+const TAG_INTEGER: usize = 0b_1;
+
+#[derive(Copy, Clone)]
+pub struct Scm {
+    value: usize,
+}
+
+impl Scm {
+    fn new(value: ScmValue) -> Self {
+        Scm {
+            value: ref_to_addr(Box::leak(Box::new(value)))
+        }
+    }
+
+    fn from_static(value: &'static ScmValue) -> Self {
+        Scm {
+            value: ref_to_addr(value)
+        }
+    }
+
+    fn nil() -> Self {
+        Scm::from_static(&NIL)
+    }
+
+    fn from_int(value: i64) -> Self {
+        Scm {
+            value: (value as usize) << N_TAG_BITS | TAG_INTEGER
+        }
+    }
+
+    fn is_immediate(&self) -> bool {
+        self.value & TAG_MASK != TAG_POINTER
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        if self.value & TAG_MASK == TAG_INTEGER {
+            Some((self.value >> N_TAG_BITS) as i64)
+        } else {
+            None
+        }
+    }
+
+    fn as_ref(&self) -> Option<&ScmValue> {
+        if self.value & TAG_MASK == TAG_POINTER {
+            unsafe {
+                Some(int_to_ref(self.value))
+            }
+        } else {
+            None
+        }
+    }
+
+    fn as_pair(&self) -> Option<&(Scm, Scm)> {
+        match self.as_ref() {
+            Some(ScmValue::Pair(pair)) => Some(pair),
+            _ => None,
+        }
+    }
+}
+
+unsafe fn int_to_ref<T>(i: usize) -> &'static T {
+    &*(i as *const T)
+}
+
+fn ref_to_addr<T>(r: &T) -> usize {
+    r as *const T as usize
+}
+
+pub enum ScmValue {
+    Nil,
+    Bool(bool),
+    Eof,
+    Char(char),
+    Pair((Scm, Scm)),
+}
+
+// Constants are singletons, so making one never allocates.
+static NIL: ScmValue = ScmValue::Nil;
+static TRUE: ScmValue = ScmValue::Bool(true);
+static FALSE: ScmValue = ScmValue::Bool(false);
+static EOF: ScmValue = ScmValue::Eof;
+
+// Characters are interned: each one is allocated the first time it is used.
+fn make_char(c: char) -> Scm {
+    static CHARS: OnceLock<Mutex<HashMap<char, Scm>>> = OnceLock::new();
+    let mut chars = CHARS.get_or_init(Default::default).lock().unwrap();
+    *chars.entry(c).or_insert_with(|| Scm::new(ScmValue::Char(c)))
+}
+
+impl Representation for Scm {
+    fn nil() -> Self {
+        Scm::nil()
+    }
+
+    fn eof() -> Self {
+        Scm::from_static(&EOF)
+    }
+
+    fn from_bool(b: bool) -> Self {
+        Scm::from_static(if b { &TRUE } else { &FALSE })
+    }
+
+    fn from_char(c: char) -> Self {
+        make_char(c)
+    }
+
+    fn from_int(i: i64) -> Self {
+        Scm::from_int(i)
+    }
+
+    fn cons(car: Self, cdr: Self) -> Self {
+        cons(car, cdr)
+    }
+
+    fn is_eq(self, other: Self) -> bool {
+        self.value == other.value
+    }
+
+    fn as_integer(self) -> Option<i64> {
+        Scm::as_integer(&self)
+    }
+
+    fn car(self) -> Option<Self> {
+        car(self)
+    }
+
+    fn cdr(self) -> Option<Self> {
+        cdr(self)
+    }
+
+    fn is_null(self) -> bool {
+        is_null(self)
+    }
+}
+
+pub fn cons(car: Scm, cdr: Scm) -> Scm {
+    Scm::new(ScmValue::Pair((car, cdr)))
+}
+
+pub fn car(scm: Scm) -> Option<Scm> {
+    scm.as_pair().map(|p| p.0)
+}
+
+pub fn cdr(scm: Scm) -> Option<Scm> {
+    scm.as_pair().map(|p| p.1)
+}
+
+pub fn is_null(scm: Scm) -> bool {
+    matches!(scm.as_ref(), Some(ScmValue::Nil))
+}
+
+#[test]
+fn integer_vs_pointers() {
+    for i in 0..10 {
+        let x = Scm::from_int(i);
+        let p = cons(x, x);
+
+        assert!(x.as_integer().is_some());
+        assert!(car(x).is_none());
+        assert!(car(p).is_some());
+        assert!(p.as_integer().is_none());
+    }
+}
+
+#[test]
+fn constants_are_singletons() {
+    assert!(scm_repr::repr::shares_singletons::<Scm>());
+}
@@ -0,0 +1,155 @@
+use scm_repr::repr::Representation;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+pub type Scm = &'static ScmValue;
+
+pub enum ScmValue {
+    Nil,
+    Bool(bool),
+    Eof,
+    Char(char),
+    Integer(i64),
+    Pair(Scm, Scm),
+}
+
+fn make_scm(value: ScmValue) -> Scm {
+    Box::leak(Box::new(value))
+}
+
+// Constants are singletons, so making one never allocates.
+static NIL: ScmValue = ScmValue::Nil;
+static TRUE: ScmValue = ScmValue::Bool(true);
+static FALSE: ScmValue = ScmValue::Bool(false);
+static EOF: ScmValue = ScmValue::Eof;
+
+fn nil() -> Scm {
+    &NIL
+}
+
+// Characters are interned: each one is allocated the first time it is used.
+fn make_char(c: char) -> Scm {
+    static CHARS: OnceLock<Mutex<HashMap<char, Scm>>> = OnceLock::new();
+    let mut chars = CHARS.get_or_init(Default::default).lock().unwrap();
+    chars.entry(c).or_insert_with(|| make_scm(ScmValue::Char(c)))
+}
+
+// Small integers are allocated once up front, and every `make_int` in that
+// range returns the same value. Nearly all integers in fib are small.
+const SMALL_INTS: std::ops::Range<i64> = -1024..1024;
+
+static PREBOXED: [ScmValue; 2048] = {
+    const NIL: ScmValue = ScmValue::Nil;
+    let mut table = [NIL; 2048];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = ScmValue::Integer(SMALL_INTS.start + i as i64);
+        i += 1;
+    }
+    table
+};
+
+fn make_int(i: i64) -> Scm {
+    if SMALL_INTS.contains(&i) {
+        &PREBOXED[(i - SMALL_INTS.start) as usize]
+    } else {
+        make_boxed_int(i)
+    }
+}
+
+pub fn make_boxed_int(i: i64) -> Scm {
+    make_scm(ScmValue::Integer(i))
+}
+
+pub fn cons(car: Scm, cdr: Scm) -> Scm {
+    make_scm(ScmValue::Pair(car, cdr))
+}
+
+impl Representation for Scm {
+    fn nil() -> Self {
+        nil()
+    }
+
+    fn eof() -> Self {
+        &EOF
+    }
+
+    fn from_bool(b: bool) -> Self {
+        if b { &TRUE } else { &FALSE }
+    }
+
+    fn from_char(c: char) -> Self {
+        make_char(c)
+    }
+
+    fn from_int(i: i64) -> Self {
+        make_int(i)
+    }
+
+    fn cons(car: Self, cdr: Self) -> Self {
+        cons(car, cdr)
+    }
+
+    fn is_eq(self, other: Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+
+    fn as_integer(self) -> Option<i64> {
+        as_integer(self)
+    }
+
+    fn car(self) -> Option<Self> {
+        car(self)
+    }
+
+    fn cdr(self) -> Option<Self> {
+        cdr(self)
+    }
+
+    fn is_null(self) -> bool {
+        is_null(self)
+    }
+}
+
+pub fn car(scm: Scm) -> Option<Scm> {
+    match scm {
+        ScmValue::Pair(car, _) => Some(car),
+        _ => None,
+    }
+}
+
+pub fn cdr(scm: Scm) -> Option<Scm> {
+    match scm {
+        ScmValue::Pair(_, cdr) => Some(cdr),
+        _ => None,
+    }
+}
+
+pub fn as_integer(scm: Scm) -> Option<i64> {
+    match scm {
+        ScmValue::Integer(i) => Some(*i),
+        _ => None,
+    }
+}
+
+pub fn is_null(scm: Scm) -> bool {
+    matches!(scm, ScmValue::Nil)
+}
+
+#[test]
+fn integer_vs_pointers() {
+    for i in 0..10 {
+        let x = make_int(i);
+        let p = cons(x, x);
+
+        assert!(x.as_integer().is_some());
+        assert!(car(x).is_none());
+        assert!(car(p).is_some());
+        assert!(p.as_integer().is_none());
+    }
+}
+
+#[test]
+fn constants_are_singletons() {
+    assert!(scm_repr::repr::shares_singletons::<Scm>());
+}
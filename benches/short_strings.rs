@@ -0,0 +1,42 @@
+//* Making, sorting and hashing lots of small strings, like a tokenizer or a
+//* symbol table does. Names of up to 7 bytes are immediates; the same number
+//* of names that are a few bytes longer each need a heap object.
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::order::Sorted;
+use scm_repr::Scm;
+use std::collections::HashSet;
+
+const N: usize = 100_000;
+
+fn names(prefix: &str) -> Vec<String> {
+    (0..N).map(|i| format!("{}{:05}", prefix, (i * 7919) % N)).collect()
+}
+
+fn workload(names: &[String]) -> usize {
+    let mut strings: Vec<Scm> = names.iter().map(|s| Scm::string(s)).collect();
+    strings.sort_by(Scm::total_cmp);
+    let distinct: HashSet<Sorted> = strings.iter().map(|&s| Sorted(s)).collect();
+    distinct.len()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let short = names("k");
+    let long = names("token_");
+    assert!(Scm::string(&short[0]).is_immediate() && !Scm::string(&long[0]).is_immediate());
+    assert_eq!(workload(&short), N);
+    c.bench_function("small strings, immediate", |b| b.iter(|| workload(black_box(&short))));
+    c.bench_function("small strings, heap", |b| b.iter(|| workload(black_box(&long))));
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+//* Summing the first squares of the natural numbers, once through a lazy
+//* stream (one pair and one promise per element, forced on demand) and once
+//* through an eager list that is built up front.
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::num;
+use scm_repr::stream::{stream_car, stream_cdr, stream_iterate, stream_map};
+use scm_repr::{car, cdr, cons, Scm};
+
+fn square(n: Scm) -> Scm {
+    num::mul(n, n).unwrap()
+}
+
+fn sum_stream(n: usize) -> Scm {
+    let naturals = stream_iterate(|i| num::add(i, Scm::from_int(1)).unwrap(), Scm::from_int(0));
+    let mut s = stream_map(square, naturals);
+    let mut sum = Scm::from_int(0);
+    for _ in 0..n {
+        sum = num::add(sum, stream_car(s).unwrap()).unwrap();
+        s = stream_cdr(s).unwrap();
+    }
+    sum
+}
+
+fn sum_list(n: usize) -> Scm {
+    let mut list = Scm::NIL;
+    for i in (0..n as i64).rev() {
+        list = cons(square(Scm::from_int(i)), list);
+    }
+    let mut sum = Scm::from_int(0);
+    while !list.is_nil() {
+        sum = num::add(sum, car(list).unwrap()).unwrap();
+        list = cdr(list).unwrap();
+    }
+    sum
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let n = params::list_len();
+    assert_eq!(sum_stream(n), sum_list(n));
+    c.bench_function("sum of squares, stream", |b| b.iter(|| sum_stream(black_box(n))));
+    c.bench_function("sum of squares, list", |b| b.iter(|| sum_list(black_box(n))));
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
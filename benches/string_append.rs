@@ -0,0 +1,50 @@
+//* Building a string out of a million small pieces, once with ropes and once
+//* by copying into a fresh flat string on every append. The copying version
+//* is quadratic, so it only gets a fraction of the pieces.
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use criterion::{BenchmarkId, Criterion};
+use criterion::black_box;
+
+use scm_repr::rope::string_append;
+use scm_repr::Scm;
+
+fn build_rope(n: usize) -> Scm {
+    let mut s = Scm::string("");
+    for _ in 0..n {
+        s = string_append(s, Scm::string("piece")).unwrap();
+    }
+    // reading it once includes the cost of flattening
+    black_box(s.as_str());
+    s
+}
+
+fn build_flat(n: usize) -> Scm {
+    let mut s = Scm::string("");
+    for _ in 0..n {
+        s = Scm::string(&[s.as_str().unwrap(), "piece"].concat());
+    }
+    s
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    assert_eq!(build_rope(1000).as_str(), build_flat(1000).as_str());
+    let mut group = c.benchmark_group("string append");
+    group.sample_size(params::sample_size(10));
+    for &n in &[1_000, 10_000, 1_000_000] {
+        group.bench_with_input(BenchmarkId::new("rope", n), &n, |b, &n| b.iter(|| build_rope(n)));
+        if n <= 10_000 {
+            group.bench_with_input(BenchmarkId::new("flat", n), &n, |b, &n| b.iter(|| build_flat(n)));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+//* What readers and compilers mostly do with text:
+//*
+//*    intern corpus   interning the tokens of a generated program, where a
+//*                    few keywords make up much of the text and most other
+//*                    names recur only now and then; once into a fresh
+//*                    table and once into the warm global one
+//*    string-append   gluing a name together from a few pieces, as gensyms
+//*                    and mangled names are made, and joining long lines
+//*                    into a rope that is then read
+//*    string search   finding a word near the end of a long string, flat or
+//*                    as a freshly appended rope that must be flattened first
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use criterion::{BatchSize, Criterion};
+use criterion::black_box;
+
+use scm_repr::rope::string_append;
+use scm_repr::symbol::{intern, SymbolTable};
+use scm_repr::Scm;
+
+const KEYWORDS: [&str; 12] = ["define", "lambda", "let", "if", "cond", "else", "car", "cdr", "cons", "null?", "quote", "set!"];
+const CORPUS_TOKENS: usize = 200_000;
+const IDENTIFIERS: u64 = 20_000;
+const LINES: usize = 10_000;
+
+// A deterministic token stream: every other token is a keyword, the rest
+// are identifiers, the lower-numbered ones far more often.
+fn corpus() -> Vec<String> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    (0..CORPUS_TOKENS)
+        .map(|i| match i % 2 {
+            0 => KEYWORDS[next() as usize % KEYWORDS.len()].to_string(),
+            _ => format!("identifier-{}", next() % (next() % IDENTIFIERS + 1)),
+        })
+        .collect()
+}
+
+fn line(i: usize) -> Scm {
+    Scm::string(&format!("(define (procedure-{} x) (+ x {}))\n", i, i))
+}
+
+fn join_lines(lines: &[Scm]) -> Scm {
+    lines.iter().fold(Scm::string(""), |text, &line| string_append(text, line).unwrap())
+}
+
+// The character position of `needle` in `haystack`, like positions are
+// reported everywhere else.
+fn search(haystack: Scm, needle: &str) -> Option<usize> {
+    let text = haystack.as_str().unwrap();
+    text.find(needle).map(|byte| text[..byte].chars().count())
+}
+
+fn interning(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("intern corpus");
+    group.sample_size(params::sample_size(10));
+    group.bench_function("fresh table", |b| {
+        b.iter_batched(
+            SymbolTable::new,
+            |table| {
+                for token in &corpus {
+                    black_box(table.intern(token));
+                }
+                table
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("global table", |b| {
+        b.iter(|| {
+            for token in &corpus {
+                black_box(intern(token));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn appending(c: &mut Criterion) {
+    let lines: Vec<Scm> = (0..LINES).map(line).collect();
+    let mut group = c.benchmark_group("string-append");
+    group.bench_function("names", |b| {
+        b.iter(|| {
+            for i in 0..1000 {
+                let name = string_append(Scm::string("tmp-"), Scm::string(&i.to_string())).unwrap();
+                black_box(string_append(name, Scm::string("-loop")).unwrap());
+            }
+        })
+    });
+    group.bench_function("lines", |b| b.iter(|| black_box(join_lines(&lines)).as_str().map(str::len)));
+    group.finish();
+}
+
+fn searching(c: &mut Criterion) {
+    let lines: Vec<Scm> = (0..LINES).map(line).collect();
+    let flat = Scm::string(join_lines(&lines).as_str().unwrap());
+    let needle = format!("procedure-{} ", LINES - 1);
+    assert_eq!(search(flat, &needle), search(join_lines(&lines), &needle));
+    let mut group = c.benchmark_group("string search");
+    group.bench_function("flat", |b| b.iter(|| search(black_box(flat), &needle)));
+    group.bench_function("rope", |b| {
+        b.iter_batched(|| join_lines(&lines), |rope| search(rope, &needle), BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, interning, appending, searching);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+//* Memory measurements for the benchmarks, since Criterion only reports
+//* time. A benchmark installs `Counting` around its global allocator and
+//* wraps the code of interest in `measure`:
+//*
+//*    #[global_allocator]
+//*    static A: Counting<System> = Counting(System);
+//*
+//*    let (result, usage) = memory::measure(|| reverse_list(10000));
+//*
+//* `allocated` counts every byte requested from the allocator while the code
+//* ran, freed or not, which is what a representation costs. The peak
+//* resident set size comes from the operating system and covers the whole
+//* process. Only on Linux can it be reset, to the current size, so elsewhere
+//* it is the peak since the process started.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct Counting<A>(pub A);
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for Counting<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        self.0.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        self.0.alloc_zeroed(layout)
+    }
+
+    // only the growth counts, as if the block had been extended in place
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Usage {
+    pub allocated: usize,
+    pub allocations: usize,
+    // in bytes, if the platform tells
+    pub peak_rss: Option<usize>,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>12} allocated in {:>9} blocks", bytes(self.allocated), self.allocations)?;
+        match self.peak_rss {
+            Some(rss) => write!(f, ", {:>10} peak RSS", bytes(rss)),
+            None => Ok(()),
+        }
+    }
+}
+
+// A byte count in the largest unit that keeps it above 1.
+pub fn bytes(n: usize) -> String {
+    match n {
+        n if n >= 1 << 30 => format!("{:.2} GiB", n as f64 / (1u64 << 30) as f64),
+        n if n >= 1 << 20 => format!("{:.2} MiB", n as f64 / (1u64 << 20) as f64),
+        n if n >= 1 << 10 => format!("{:.2} KiB", n as f64 / (1u64 << 10) as f64),
+        n => format!("{} B", n),
+    }
+}
+
+// Runs `f` and reports what it allocated. The counters are global, so other
+// threads must not allocate in the meantime.
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, Usage) {
+    reset_peak_rss();
+    let (allocated, allocations) = (ALLOCATED.load(Ordering::Relaxed), ALLOCATIONS.load(Ordering::Relaxed));
+    let result = f();
+    let usage = Usage {
+        allocated: ALLOCATED.load(Ordering::Relaxed) - allocated,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        peak_rss: peak_rss(),
+    };
+    (result, usage)
+}
+
+// The kernel keeps the peak as VmHWM, which, unlike the maximum that
+// getrusage reports, can be reset.
+#[cfg(target_os = "linux")]
+pub fn peak_rss() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: usize = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(target_os = "macos")]
+pub fn peak_rss() -> Option<usize> {
+    // struct rusage: two struct timevals, then ru_maxrss and the rest of
+    // the longs
+    #[repr(C)]
+    struct Rusage {
+        times: [i64; 4],
+        maxrss: i64,
+        rest: [i64; 13],
+    }
+    extern "C" {
+        fn getrusage(who: i32, usage: *mut Rusage) -> i32;
+    }
+    const RUSAGE_SELF: i32 = 0;
+    let mut usage = Rusage { times: [0; 4], maxrss: 0, rest: [0; 13] };
+    if unsafe { getrusage(RUSAGE_SELF, &mut usage) } != 0 {
+        return None
+    }
+    // in bytes, where Linux counts kilobytes
+    Some(usage.maxrss as usize)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn peak_rss() -> Option<usize> {
+    None
+}
+
+// Lowers the peak resident set size to the current one, where possible.
+pub fn reset_peak_rss() {
+    #[cfg(target_os = "linux")]
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
@@ -0,0 +1,46 @@
+//* Benchmark sizes that can be changed without editing the source, so that
+//* the same benches make a quick smoke run on a laptop and a long run on the
+//* benchmarking machine:
+//*
+//*    SCM_BENCH_FIB=15 SCM_BENCH_LIST_LEN=1000 cargo bench -- --sample-size 10
+//*    SCM_BENCH_FIB=25 SCM_BENCH_RUNS=50 cargo bench -- --measurement-time 30
+//*
+//*    SCM_BENCH_FIB        the argument of the fib benchmarks (20)
+//*    SCM_BENCH_LIST_LEN   the length of the lists that are built and walked
+//*                         (10000)
+//*    SCM_BENCH_RUNS       how many runs the printed summaries take the best
+//*                         of (10)
+//*
+//* How often Criterion runs each benchmark is up to its own options,
+//* --sample-size, --measurement-time and --warm-up-time. Benches that ask
+//* for fewer samples because they are slow do so through `sample_size`, so
+//* that --sample-size still wins.
+
+use std::env;
+
+pub fn fib_depth() -> i64 {
+    var("SCM_BENCH_FIB", 20) as i64
+}
+
+pub fn list_len() -> usize {
+    var("SCM_BENCH_LIST_LEN", 10_000)
+}
+
+pub fn runs() -> usize {
+    var("SCM_BENCH_RUNS", 10)
+}
+
+// The --sample-size from the command line, or `default`.
+pub fn sample_size(default: usize) -> usize {
+    let args: Vec<String> = env::args().collect();
+    let given = args.iter().position(|arg| arg == "--sample-size").and_then(|i| args.get(i + 1));
+    given.and_then(|n| n.parse().ok()).unwrap_or(default)
+}
+
+// Panics if the variable is set to something other than a number.
+fn var(name: &str, default: usize) -> usize {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| panic!("{} must be a number, not {:?}", name, value)),
+        Err(_) => default,
+    }
+}
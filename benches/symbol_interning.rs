@@ -0,0 +1,59 @@
+//* Interning a million symbols from 8 threads at once, compared with a single
+//* thread doing all the work. Every thread interns an overlapping range of
+//* names, so the shards see real contention.
+//*
+//* The first iteration creates the symbols; after that interning is a lookup,
+//* which is the common case for a reader that sees the same names over and
+//* over.
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::symbol::intern;
+
+const N_SYMBOLS: usize = 1_000_000;
+const N_THREADS: usize = 8;
+
+fn names() -> Vec<String> {
+    (0..N_SYMBOLS).map(|i| format!("symbol-{}", i)).collect()
+}
+
+fn intern_all(names: &[String]) {
+    for name in names {
+        black_box(intern(name));
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let names = names();
+
+    c.bench_function("intern 1M, 1 thread", |b| b.iter(|| intern_all(&names)));
+
+    c.bench_function("intern 1M, 8 threads", |b| {
+        b.iter(|| {
+            std::thread::scope(|s| {
+                let chunk = N_SYMBOLS / N_THREADS;
+                for t in 0..N_THREADS {
+                    // each thread starts half a chunk into its neighbor's range
+                    let start = (t * chunk + chunk / 2) % N_SYMBOLS;
+                    let names = &names;
+                    s.spawn(move || {
+                        for i in 0..chunk {
+                            black_box(intern(&names[(start + i) % N_SYMBOLS]));
+                        }
+                    });
+                }
+            })
+        })
+    });
+}
+
+criterion_group!{
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
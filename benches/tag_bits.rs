@@ -0,0 +1,64 @@
+//* With three tag bits, strings, symbols and vectors get their own pointer tag.
+//* Type checks are then a mask on the word instead of a load of the object
+//* header from the heap. Here we compare both ways of dispatching over a
+//* mixed stream of values. (Pairs had their own tag before; see cheapair in
+//* the representations bench for how that compares to boxing them in the
+//* enum.)
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use scm_repr::heap::Kind;
+use scm_repr::{cons, is_pair, is_string, Scm};
+
+
+fn mixed_values(n: usize) -> Vec<Scm> {
+    (0..n as i64)
+        .map(|i| match i % 5 {
+            0 => Scm::from_int(i),
+            1 => cons(Scm::from_int(i), Scm::nil()),
+            2 => Scm::string("foo"),
+            3 => Scm::symbol("bar"),
+            _ => Scm::vector(vec![Scm::from_int(i)]),
+        })
+        .collect()
+}
+
+fn count_by_tag(values: &[Scm]) -> (usize, usize) {
+    let mut n_pairs = 0;
+    let mut n_strings = 0;
+    for x in values {
+        if is_pair(*x) { n_pairs += 1 }
+        if is_string(*x) { n_strings += 1 }
+    }
+    (n_pairs, n_strings)
+}
+
+fn count_by_heap_load(values: &[Scm]) -> (usize, usize) {
+    let mut n_pairs = 0;
+    let mut n_strings = 0;
+    for x in values {
+        if is_pair(*x) { n_pairs += 1 }
+        if x.header().map(|h| h.kind()) == Some(Kind::String) { n_strings += 1 }
+    }
+    (n_pairs, n_strings)
+}
+
+fn dispatch_performance(c: &mut Criterion) {
+    let values = mixed_values(10000);
+    c.bench_function("tagbits is_pair/is_string by tag", |b| b.iter(|| count_by_tag(black_box(&values))));
+    c.bench_function("tagbits is_pair/is_string by heap load", |b| b.iter(|| count_by_heap_load(black_box(&values))));
+}
+
+#[test]
+fn both_dispatches_agree() {
+    let values = mixed_values(100);
+    assert_eq!(count_by_tag(&values), count_by_heap_load(&values));
+    assert_eq!(count_by_tag(&values), (20, 20));
+}
+
+criterion_group!(benches, dispatch_performance);
+criterion_main!(benches);
@@ -0,0 +1,288 @@
+//* Unrolled lists: the spine of a list is stored in chunks of several
+//* consecutive elements instead of one pair per element, so that walking a
+//* list touches fewer cache lines and needs fewer allocations.
+//*
+//* A list is a pointer to an element slot inside a chunk. The chunk is
+//* aligned to its own size, so the pointer also tells where the chunk starts
+//* and which slot it points at; `cdr` is the next slot, or the chunk's link
+//* to the following chunk after the last slot. Chunks are filled from the
+//* back, and `cons` puts the new element into the free slot in front of its
+//* cdr if nobody has claimed that slot yet. Otherwise (and when the chunk is
+//* full) it starts a new chunk. Either way the old list is not changed, so
+//* lists can share tails as usual; only `set-cdr!` cannot be supported.
+//*
+//* The benchmarks compare traversal and reversal against the classic cons
+//* cells of `scm_repr`.
+
+#[macro_use]
+extern crate criterion;
+
+#[allow(dead_code)]
+#[path = "support/params.rs"]
+mod params;
+
+use criterion::Criterion;
+use criterion::black_box;
+
+use std::cell::Cell;
+use std::fmt;
+use std::mem::size_of;
+
+use scm_repr::repr::Representation;
+
+fn unrolled_performance(c: &mut Criterion) {
+    let list = make_list(params::list_len());
+    let classic = make_classic_list(params::list_len());
+    assert_eq!(sum(list), sum_classic(classic));
+    c.bench_function("unrolled traverse", |b| b.iter(|| sum(black_box(list))));
+    c.bench_function("classic traverse", |b| b.iter(|| sum_classic(black_box(classic))));
+    c.bench_function("unrolled reverse", |b| b.iter(|| reverse(black_box(list))));
+    c.bench_function("classic reverse", |b| b.iter(|| reverse_classic(black_box(classic))));
+}
+
+fn make_list(len: usize) -> Scm {
+    (0..len as i64).rev().fold(Scm::nil(), |list, i| cons(Scm::from_int(i), list))
+}
+
+fn sum(mut list: Scm) -> i64 {
+    let mut sum = 0;
+    while !is_null(list) {
+        sum += car(list).expect("pair").as_integer().expect("int");
+        list = cdr(list).expect("pair");
+    }
+    sum
+}
+
+fn reverse(mut list: Scm) -> Scm {
+    let mut out = Scm::nil();
+    while !is_null(list) {
+        out = cons(car(list).expect("pair"), out);
+        list = cdr(list).expect("pair");
+    }
+    out
+}
+
+fn make_classic_list(len: usize) -> scm_repr::Scm {
+    (0..len as i64).rev().fold(scm_repr::Scm::NIL, |list, i| scm_repr::cons(scm_repr::Scm::from_int(i), list))
+}
+
+fn sum_classic(mut list: scm_repr::Scm) -> i64 {
+    let mut sum = 0;
+    while !list.is_nil() {
+        sum += scm_repr::car(list).expect("pair").as_integer().expect("int");
+        list = scm_repr::cdr(list).expect("pair");
+    }
+    sum
+}
+
+fn reverse_classic(mut list: scm_repr::Scm) -> scm_repr::Scm {
+    let mut out = scm_repr::Scm::NIL;
+    while !list.is_nil() {
+        out = scm_repr::cons(scm_repr::car(list).expect("pair"), out);
+        list = scm_repr::cdr(list).expect("pair");
+    }
+    out
+}
+
+
+const N_TAG_BITS: usize = 3;
+const TAG_MASK: usize = 0b_111;
+const TAG_INTEGER: usize = 0b_001;
+const TAG_PAIR: usize = 0b_010;
+
+const SPECIAL_NIL: usize = 0b_0000_0011;
+const SPECIAL_FALSE: usize = 0b_0000_1011;
+const SPECIAL_TRUE: usize = 0b_0001_0011;
+const SPECIAL_EOF: usize = 0b_0001_1011;
+// with the code point above the low byte
+const SPECIAL_CHAR: usize = 0b_0010_0011;
+
+#[derive(Copy, Clone)]
+pub struct Scm {
+    value: usize,
+}
+
+impl Scm {
+    fn nil() -> Self {
+        Scm { value: SPECIAL_NIL }
+    }
+
+    fn from_int(value: i64) -> Self {
+        Scm { value: (value as usize) << N_TAG_BITS | TAG_INTEGER }
+    }
+
+    fn is_nil(&self) -> bool {
+        self.value == SPECIAL_NIL
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        if self.value & TAG_MASK == TAG_INTEGER {
+            Some(self.value as i64 >> N_TAG_BITS)
+        } else {
+            None
+        }
+    }
+
+    // The chunk and slot a list points into.
+    fn as_slot(&self) -> Option<(&'static Chunk, usize)> {
+        if self.value & TAG_MASK == TAG_PAIR {
+            let addr = self.value - TAG_PAIR;
+            let base = addr & !(CHUNK_ALIGN - 1);
+            unsafe { Some((&*(base as *const Chunk), (addr - base) / size_of::<Scm>())) }
+        } else {
+            None
+        }
+    }
+
+    fn from_slot(chunk: &'static Chunk, index: usize) -> Self {
+        Scm { value: &chunk.items[index] as *const Cell<Scm> as usize + TAG_PAIR }
+    }
+}
+
+// Decodes the tag instead of printing the raw word. `{:#?}` adds the raw
+// word and tag bits, and the fields of a pair one level deep.
+impl fmt::Debug for Scm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let brief = match self.value & TAG_MASK {
+            TAG_INTEGER => format!("Int({})", self.as_integer().unwrap()),
+            TAG_PAIR => format!("Pair({:#x})", self.value - TAG_PAIR),
+            _ => match self.value {
+                SPECIAL_NIL => "Nil".to_string(),
+                SPECIAL_FALSE => "False".to_string(),
+                SPECIAL_TRUE => "True".to_string(),
+                SPECIAL_EOF => "Eof".to_string(),
+                _ => format!("Char({:?})", std::char::from_u32((self.value >> 8) as u32).unwrap()),
+            },
+        };
+        if !f.alternate() {
+            return f.write_str(&brief)
+        }
+        let mut s = f.debug_struct("Scm");
+        s.field("value", &format_args!("{}", brief));
+        s.field("raw", &format_args!("{:#x}", self.value));
+        s.field("tag", &format_args!("{:#0w$b}", self.value & TAG_MASK, w = N_TAG_BITS + 2));
+        if let (Some(car), Some(cdr)) = (car(*self), cdr(*self)) {
+            s.field("car", &format_args!("{:?}", car)).field("cdr", &format_args!("{:?}", cdr));
+        }
+        s.finish()
+    }
+}
+
+const CHUNK_LEN: usize = 8;
+const CHUNK_ALIGN: usize = 128;
+
+// `items[start..]` are in use; the list continues with `next` after the last one.
+#[repr(C, align(128))]
+struct Chunk {
+    items: [Cell<Scm>; CHUNK_LEN],
+    start: Cell<usize>,
+    next: Scm,
+}
+
+pub fn cons(car: Scm, cdr: Scm) -> Scm {
+    if let Some((chunk, index)) = cdr.as_slot() {
+        if index > 0 && chunk.start.get() == index {
+            // the slot in front of our cdr is still free
+            chunk.items[index - 1].set(car);
+            chunk.start.set(index - 1);
+            return Scm::from_slot(chunk, index - 1)
+        }
+    }
+    let chunk: &'static Chunk = Box::leak(Box::new(Chunk {
+        items: [(); CHUNK_LEN].map(|_| Cell::new(Scm::nil())),
+        start: Cell::new(CHUNK_LEN - 1),
+        next: cdr,
+    }));
+    debug_assert!(chunk as *const Chunk as usize % CHUNK_ALIGN == 0);
+    chunk.items[CHUNK_LEN - 1].set(car);
+    Scm::from_slot(chunk, CHUNK_LEN - 1)
+}
+
+pub fn car(scm: Scm) -> Option<Scm> {
+    scm.as_slot().map(|(chunk, index)| chunk.items[index].get())
+}
+
+pub fn cdr(scm: Scm) -> Option<Scm> {
+    scm.as_slot().map(|(chunk, index)| {
+        if index + 1 < CHUNK_LEN {
+            Scm::from_slot(chunk, index + 1)
+        } else {
+            chunk.next
+        }
+    })
+}
+
+pub fn is_null(scm: Scm) -> bool {
+    scm.is_nil()
+}
+
+impl Representation for Scm {
+    fn nil() -> Self {
+        Scm::nil()
+    }
+
+    fn eof() -> Self {
+        Scm { value: SPECIAL_EOF }
+    }
+
+    fn from_bool(b: bool) -> Self {
+        Scm { value: if b { SPECIAL_TRUE } else { SPECIAL_FALSE } }
+    }
+
+    fn from_char(c: char) -> Self {
+        Scm { value: (c as usize) << 8 | SPECIAL_CHAR }
+    }
+
+    fn from_int(i: i64) -> Self {
+        Scm::from_int(i)
+    }
+
+    fn cons(car: Self, cdr: Self) -> Self {
+        cons(car, cdr)
+    }
+
+    fn is_eq(self, other: Self) -> bool {
+        self.value == other.value
+    }
+
+    fn as_integer(self) -> Option<i64> {
+        Scm::as_integer(&self)
+    }
+
+    fn car(self) -> Option<Self> {
+        car(self)
+    }
+
+    fn cdr(self) -> Option<Self> {
+        cdr(self)
+    }
+
+    fn is_null(self) -> bool {
+        is_null(self)
+    }
+}
+
+#[test]
+fn lists_share_tails() {
+    let tail = make_list(20);
+    assert_eq!(sum(tail), 190);
+    let a = cons(Scm::from_int(100), tail);
+    let b = cons(Scm::from_int(200), tail);
+    assert_eq!(sum(a), 290);
+    assert_eq!(sum(b), 390);
+    assert!(cdr(a).unwrap().is_eq(tail) && cdr(b).unwrap().is_eq(tail));
+    assert_eq!(sum(reverse(a)), 290);
+    assert_eq!(car(reverse(a)).unwrap().as_integer(), Some(19));
+    assert!(scm_repr::repr::shares_singletons::<Scm>());
+}
+
+#[test]
+fn debug_decodes_tags() {
+    let list = cons(Scm::from_int(1), Scm::nil());
+    assert_eq!(format!("{:?}", [Scm::from_int(-3), Scm::nil(), Scm::from_char('x')]), "[Int(-3), Nil, Char('x')]");
+    assert_eq!(format!("{:?}", list), format!("Pair({:#x})", list.value - TAG_PAIR));
+    assert!(format!("{:#?}", list).ends_with("    tag: 0b010,\n    car: Int(1),\n    cdr: Nil,\n}"));
+}
+
+criterion_group!(benches, unrolled_performance);
+criterion_main!(benches);
@@ -0,0 +1,141 @@
+//* Vector and hash table operations over large collections.
+//*
+//* Vectors are immutable for now, so `vector-set!` is measured on the
+//* candidate designs for mutable vectors, each holding the same items:
+//*
+//*    cell      a slice of Cell<Scm>, like boxes without the sync feature
+//*    atomic    a slice of atomic words, like boxes with the sync feature
+//*    refcell   a RefCell<Vec<Scm>>, with a borrow check per access
+//*    copy      a fresh copy with one item replaced, the functional update
+//*              that immutable vectors allow
+//*
+//* The tables map keys of three sorts, fixnums, symbols and lists, to values,
+//* once in the persistent maps of this crate and once in a std HashMap keyed
+//* by `Sorted`, so both compare keys with `equal?`.
+
+#[macro_use]
+extern crate criterion;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{BatchSize, Criterion};
+use criterion::black_box;
+
+use scm_repr::hamt::{make_map, map_assoc, map_ref};
+use scm_repr::order::Sorted;
+use scm_repr::vectors::vector_copy;
+use scm_repr::{cons, Scm};
+
+const N: usize = 100_000;
+const N_KEYS: usize = 10_000;
+// every set of the copying design costs O(N), so it gets far fewer
+const N_COPIES: usize = 10;
+
+// Pseudo-random indices, so that accesses aren't just a linear scan.
+fn indices(n: usize) -> Vec<usize> {
+    (0..n).map(|i| i * 7919 % N).collect()
+}
+
+fn ints(n: usize) -> Vec<Scm> {
+    (0..n).map(|i| Scm::from_int(i as i64)).collect()
+}
+
+fn vector_ref(v: Scm, indices: &[usize]) -> i64 {
+    indices.iter().map(|&i| v.as_vector().unwrap()[i].as_integer().unwrap()).sum()
+}
+
+fn construction(c: &mut Criterion) {
+    let v = Scm::vector(ints(N));
+    let mut group = c.benchmark_group("vector construction");
+    group.bench_function("from items", |b| b.iter_batched(|| ints(N), Scm::vector, BatchSize::LargeInput));
+    group.bench_function("copy", |b| b.iter(|| vector_copy(black_box(v)).unwrap()));
+    group.finish();
+}
+
+fn access(c: &mut Criterion) {
+    let v = Scm::vector(ints(N));
+    let indices = indices(N);
+    c.bench_function("vector-ref", |b| b.iter(|| vector_ref(black_box(v), &indices)));
+
+    let cells: Vec<Cell<Scm>> = ints(N).into_iter().map(Cell::new).collect();
+    let atomics: Vec<AtomicUsize> = ints(N).into_iter().map(|x| AtomicUsize::new(x.to_raw())).collect();
+    let refcell = RefCell::new(ints(N));
+    let mut group = c.benchmark_group("vector-set!");
+    group.bench_function("cell", |b| {
+        b.iter(|| {
+            for &i in &indices {
+                cells[i].set(Scm::from_int(i as i64 + cells[i].get().as_integer().unwrap()));
+            }
+        })
+    });
+    group.bench_function("atomic", |b| {
+        b.iter(|| {
+            for &i in &indices {
+                // the slots only ever hold words from `to_raw` of live values
+                let old = unsafe { Scm::from_raw(atomics[i].load(Ordering::Acquire)) };
+                atomics[i].store(Scm::from_int(i as i64 + old.as_integer().unwrap()).to_raw(), Ordering::Release);
+            }
+        })
+    });
+    group.bench_function("refcell", |b| {
+        b.iter(|| {
+            for &i in &indices {
+                let old = refcell.borrow()[i];
+                refcell.borrow_mut()[i] = Scm::from_int(i as i64 + old.as_integer().unwrap());
+            }
+        })
+    });
+    group.bench_function("copy", |b| {
+        b.iter(|| {
+            indices[..N_COPIES].iter().fold(v, |v, &i| {
+                let mut items = v.as_vector().unwrap().to_vec();
+                items[i] = Scm::from_int(i as i64 + items[i].as_integer().unwrap());
+                Scm::vector(items)
+            })
+        })
+    });
+    group.finish();
+}
+
+fn keys() -> Vec<(&'static str, Vec<Scm>)> {
+    let fixnums = (0..N_KEYS).map(|i| Scm::from_int(i as i64 * 7919)).collect();
+    let symbols = (0..N_KEYS).map(|i| Scm::symbol(&format!("key-{}", i))).collect();
+    let lists = (0..N_KEYS)
+        .map(|i| cons(Scm::symbol("point"), cons(Scm::from_int(i as i64), cons(Scm::from_int(-(i as i64)), Scm::NIL))))
+        .collect();
+    vec![("fixnum", fixnums), ("symbol", symbols), ("list", lists)]
+}
+
+fn insert_map(keys: &[Scm]) -> Scm {
+    keys.iter().fold(make_map(), |map, &key| map_assoc(map, key, key).unwrap())
+}
+
+fn insert_hashmap(keys: &[Scm]) -> HashMap<Sorted, Scm> {
+    keys.iter().map(|&key| (Sorted(key), key)).collect()
+}
+
+fn tables(c: &mut Criterion) {
+    for (sort, keys) in keys() {
+        let map = insert_map(&keys);
+        let hashmap = insert_hashmap(&keys);
+        // structurally equal, but not identical keys
+        let probes: Vec<Scm> = keys.iter().map(|key| scm_repr::reader::read_str(&key.to_string()).unwrap()).collect();
+        assert!(probes.iter().all(|&key| map_ref(map, key).unwrap().is_some() && hashmap.contains_key(&Sorted(key))));
+
+        let mut group = c.benchmark_group(format!("hash table, {} keys", sort));
+        group.bench_function("map insert", |b| b.iter(|| insert_map(black_box(&keys))));
+        group.bench_function("map lookup", |b| {
+            b.iter(|| probes.iter().filter(|&&key| map_ref(map, key).unwrap().is_some()).count())
+        });
+        group.bench_function("HashMap insert", |b| b.iter(|| insert_hashmap(black_box(&keys))));
+        group.bench_function("HashMap lookup", |b| {
+            b.iter(|| probes.iter().filter(|&&key| hashmap.contains_key(&Sorted(key))).count())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, construction, access, tables);
+criterion_main!(benches);
@@ -0,0 +1,98 @@
+//* A minimal read-eval-print loop on top of the public API:
+//*
+//*     $ cargo run --example repl
+//*     > (cons 1 '(2 3))
+//*     (1 2 3)
+//*     > (+ 1/3 (* 2 0.5))
+//*     1.3333333333333333
+//*
+//* The reader parses data from stdin and the printer writes results back.
+//* There are no variables or lambdas, only `quote`, `if` and a handful of
+//* primitives on lists and numbers, each implemented with the function of
+//* the same name in `scm_repr`.
+
+use std::error::Error;
+use std::io::Write;
+
+use scm_repr::{car, cdr, cons, is_null, is_pair, num, port, reader, Scm, ScmKind, TypeError};
+
+fn main() {
+    let stdin = port::open_input(std::io::stdin());
+    loop {
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+        let x = match reader::read(stdin) {
+            Ok(x) if x.is_eof() => break,
+            Ok(x) => x,
+            Err(e) => {
+                println!("read error: {}", e);
+                continue
+            }
+        };
+        match eval(x) {
+            Ok(x) => println!("{}", x),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+    println!();
+}
+
+fn eval(x: Scm) -> Result<Scm, String> {
+    if x.as_symbol().is_some() {
+        return Err(format!("unbound variable: {}", x))
+    }
+    let (op, args) = match x.as_pair() {
+        Some(&pair) => pair,
+        None => return Ok(x),
+    };
+    match op.as_symbol() {
+        Some("quote") => return nth(args, 0),
+        Some("if") => {
+            let branch = if eval(nth(args, 0)?)?.is_true() { 1 } else { 2 };
+            return nth(args, branch).map_or(Ok(Scm::NIL), eval)
+        }
+        _ => {}
+    }
+    let args = list_items(args)?.into_iter().map(eval).collect::<Result<Vec<_>, _>>()?;
+    apply(op, &args).map_err(|e| e.to_string())
+}
+
+fn apply(op: Scm, args: &[Scm]) -> Result<Scm, Box<dyn Error>> {
+    let name = op.as_symbol().ok_or_else(|| format!("not a procedure: {}", op))?;
+    let arg = |i: usize| args.get(i).copied().ok_or_else(|| format!("{}: missing argument {}", name, i + 1));
+    Ok(match name {
+        "+" => args.iter().try_fold(Scm::from_int(0), |acc, &x| num::add(acc, x))?,
+        "*" => args.iter().try_fold(Scm::from_int(1), |acc, &x| num::mul(acc, x))?,
+        "-" if args.len() == 1 => num::sub(Scm::from_int(0), arg(0)?)?,
+        "-" => args[1..].iter().try_fold(arg(0)?, |acc, &x| num::sub(acc, x))?,
+        "/" => num::div(arg(0)?, arg(1)?)?,
+        "<" => Scm::from_bool(num::chain(args, num::lt)?),
+        "=" => Scm::from_bool(num::chain(args, num::eq)?),
+        "car" => car(arg(0)?).ok_or_else(|| TypeError::new(ScmKind::Pair, args[0]))?,
+        "cdr" => cdr(arg(0)?).ok_or_else(|| TypeError::new(ScmKind::Pair, args[0]))?,
+        "cons" => cons(arg(0)?, arg(1)?),
+        "list" => list(args),
+        "length" => Scm::from_int(list_items(arg(0)?)?.len() as i64),
+        "reverse" => list(&list_items(arg(0)?)?.into_iter().rev().collect::<Vec<_>>()),
+        "null?" => Scm::from_bool(is_null(arg(0)?)),
+        "pair?" => Scm::from_bool(is_pair(arg(0)?)),
+        _ => return Err(format!("unknown procedure: {}", name).into()),
+    })
+}
+
+fn list(items: &[Scm]) -> Scm {
+    items.iter().rev().fold(Scm::NIL, |list, &x| cons(x, list))
+}
+
+fn list_items(mut x: Scm) -> Result<Vec<Scm>, String> {
+    let mut items = vec![];
+    while let Some(&(a, d)) = x.as_pair() {
+        items.push(a);
+        x = d;
+    }
+    if is_null(x) { Ok(items) } else { Err(format!("not a proper list: {}", x)) }
+}
+
+fn nth(list: Scm, n: usize) -> Result<Scm, String> {
+    list_items(list)?.get(n).copied().ok_or_else(|| format!("missing argument {}", n + 1))
+}
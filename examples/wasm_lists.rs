@@ -0,0 +1,47 @@
+//* Builds a few lists and prints them, to be run in the browser:
+//*
+//*     cargo build --release --target wasm32-unknown-unknown --example wasm_lists
+//*
+//* There is no Boehm GC on wasm32. Objects come from the crate's own heap
+//* (`heap::alloc`, on top of Rust's default allocator) and are never freed.
+//*
+//* The module exports the printed lists as a pointer into its memory and a
+//* length in bytes:
+//*
+//*     const { instance } = await WebAssembly.instantiateStreaming(fetch("wasm_lists.wasm"));
+//*     const { output_ptr, output_len, memory } = instance.exports;
+//*     const bytes = new Uint8Array(memory.buffer, output_ptr(), output_len());
+//*     console.log(new TextDecoder().decode(bytes));
+
+use std::sync::OnceLock;
+
+use scm_repr::reader::read_str;
+use scm_repr::{cons, Scm};
+
+static OUTPUT: OnceLock<String> = OnceLock::new();
+
+fn list(items: impl DoubleEndedIterator<Item = Scm>) -> Scm {
+    items.rev().fold(Scm::NIL, |list, x| cons(x, list))
+}
+
+fn render() -> String {
+    let numbers = list((1..=5).map(Scm::from_int));
+    let squares = list((1..=5).map(|i| cons(Scm::from_int(i), Scm::from_int(i * i))));
+    let mixed = list(vec![Scm::symbol("hello"), Scm::string("wasm"), Scm::from_char('λ'), Scm::TRUE].into_iter());
+    let read = read_str("(define (square x) (* x x))").unwrap();
+    [numbers, squares, mixed, read].iter().map(|x| format!("{}\n", x)).collect()
+}
+
+fn output() -> &'static str {
+    OUTPUT.get_or_init(render)
+}
+
+#[no_mangle]
+pub extern "C" fn output_ptr() -> *const u8 {
+    output().as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn output_len() -> usize {
+    output().len()
+}
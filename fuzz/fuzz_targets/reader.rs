@@ -0,0 +1,20 @@
+//* Feeds arbitrary text to the reader. It may reject the input, but must not
+//* panic, and whatever it accepts has to print as text that reads back and
+//* prints the same again.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scm_repr::reader::read_str;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    if let Ok(x) = read_str(text) {
+        let printed = x.to_string();
+        let y = read_str(&printed).unwrap_or_else(|e| panic!("{:?} printed as {:?}, which doesn't read: {}", text, printed, e));
+        assert_eq!(y.to_string(), printed, "{:?} doesn't print stably", text);
+    }
+});
@@ -0,0 +1,19 @@
+//* Feeds arbitrary bytes to the snapshot loader. It may reject them as
+//* corrupt, but must not panic, and whatever it loads has to save and load
+//* again as the same snapshot.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scm_repr::snapshot::{load_snapshot, save_snapshot};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(roots) = load_snapshot(data) {
+        let mut saved = vec![];
+        save_snapshot(&roots, &mut saved).expect("loaded values can be saved");
+        let reloaded = load_snapshot(&saved[..]).expect("a saved snapshot loads");
+        let mut resaved = vec![];
+        save_snapshot(&reloaded, &mut resaved).unwrap();
+        assert_eq!(saved, resaved);
+    }
+});
@@ -0,0 +1,20 @@
+//* Builds values from the fuzzer's input through `Scm`'s `Arbitrary` impl and
+//* checks that a snapshot of them loads as equal values that hash the same.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scm_repr::order::{equal, equal_hash};
+use scm_repr::snapshot::{load_snapshot, save_snapshot};
+use scm_repr::Scm;
+
+fuzz_target!(|roots: Vec<Scm>| {
+    let mut saved = vec![];
+    save_snapshot(&roots, &mut saved).expect("arbitrary values can be saved");
+    let loaded = load_snapshot(&saved[..]).unwrap();
+    assert_eq!(loaded.len(), roots.len());
+    for (&x, &y) in roots.iter().zip(&loaded) {
+        assert!(equal(x, y), "{} loaded as {}", x, y);
+        assert_eq!(equal_hash(x), equal_hash(y));
+    }
+});
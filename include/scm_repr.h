@@ -0,0 +1,112 @@
+#ifndef SCM_REPR_H
+#define SCM_REPR_H
+
+/* Generated by cbindgen from src/capi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef uintptr_t ScmRaw;
+
+ScmRaw scm_nil(void);
+
+ScmRaw scm_from_bool(bool b);
+
+ScmRaw scm_from_int(int64_t i);
+
+/*
+ * # Safety
+ * `x` must be a valid value and `out` a valid pointer.
+ */
+bool scm_to_int(ScmRaw x, int64_t *out);
+
+/*
+ * # Safety
+ * `name` must point to a nul-terminated UTF-8 string.
+ */
+ScmRaw scm_symbol(const char *name);
+
+/*
+ * # Safety
+ * `s` must point to a nul-terminated UTF-8 string.
+ */
+ScmRaw scm_string(const char *s);
+
+/*
+ * # Safety
+ * Both arguments must be valid values.
+ */
+ScmRaw scm_cons(ScmRaw car, ScmRaw cdr);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+ScmRaw scm_car(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+ScmRaw scm_cdr(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_null(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_pair(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_integer(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_boolean(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_symbol(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_string(ScmRaw x);
+
+/*
+ * # Safety
+ * `x` must be a valid value.
+ */
+bool scm_is_vector(ScmRaw x);
+
+/*
+ * Returns the external representation of `x`. The result must be released
+ * with `scm_free_string`.
+ *
+ * # Safety
+ * `x` must be a valid value.
+ */
+char *scm_write_to_string(ScmRaw x);
+
+/*
+ * # Safety
+ * `s` must have been returned by `scm_write_to_string` and not freed before.
+ */
+void scm_free_string(char *s);
+
+#endif  /* SCM_REPR_H */
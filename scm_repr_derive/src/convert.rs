@@ -0,0 +1,206 @@
+// `#[derive(ToScm, FromScm)]`. The representations are described in
+// `scm_repr::convert`; the helpers there do most of the work.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, Index, LitStr, Member};
+
+#[derive(Default)]
+struct Options {
+    record: bool,
+    rename: Option<String>,
+}
+
+fn options(attrs: &[Attribute], allow_record: bool) -> syn::Result<Options> {
+    let mut options = Options::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("scm")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("record") && allow_record {
+                options.record = true;
+                Ok(())
+            } else if meta.path.is_ident("rename") {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if allow_record {
+                Err(meta.error("expected `record` or `rename = \"...\"`"))
+            } else {
+                Err(meta.error("expected `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+// `HttpServer` becomes `http-server`, `max_depth` becomes `max-depth`.
+fn kebab_case(ident: &Ident) -> String {
+    let mut name = String::new();
+    let mut previous = '-';
+    for c in ident.unraw().to_string().chars() {
+        if c.is_uppercase() && (previous.is_lowercase() || previous.is_ascii_digit()) {
+            name.push('-');
+        }
+        name.extend(c.to_lowercase());
+        previous = c;
+    }
+    name.replace('_', "-")
+}
+
+fn scheme_name(ident: &Ident, options: &Options) -> String {
+    options.rename.clone().unwrap_or_else(|| kebab_case(ident))
+}
+
+// Each field's member, its name in alists, and the binding it gets in
+// patterns.
+fn members(fields: &Fields) -> syn::Result<Vec<(Member, String, Ident)>> {
+    let mut members = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let options = options(&field.attrs, false)?;
+        let (member, name) = match &field.ident {
+            Some(ident) => (Member::Named(ident.clone()), scheme_name(ident, &options)),
+            None => (Member::Unnamed(Index::from(i)), i.to_string()),
+        };
+        members.push((member, name, format_ident!("field_{}", i)));
+    }
+    Ok(members)
+}
+
+pub fn to_scm_body(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let options = options(&input.attrs, true)?;
+    match &input.data {
+        Data::Struct(data) => {
+            let members = members(&data.fields)?;
+            let values: Vec<_> = members
+                .iter()
+                .map(|(member, _, _)| quote! { ::scm_repr::convert::ToScm::to_scm(&self.#member) })
+                .collect();
+            if options.record {
+                let name = scheme_name(&input.ident, &options);
+                return Ok(quote! { ::scm_repr::convert::record(#name, &[#(#values),*]) })
+            }
+            Ok(match &data.fields {
+                Fields::Named(_) => {
+                    let names = members.iter().map(|(_, name, _)| name);
+                    quote! { ::scm_repr::convert::alist(&[#((#names, #values)),*]) }
+                }
+                Fields::Unnamed(_) if values.len() == 1 => quote! { #(#values)* },
+                _ => quote! { ::scm_repr::convert::list(&[#(#values),*]) },
+            })
+        }
+        Data::Enum(data) => {
+            if data.variants.is_empty() {
+                return Ok(quote! { match *self {} })
+            }
+            let mut arms = vec![];
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let tag = scheme_name(ident, &options(&variant.attrs, false)?);
+                let members = members(&variant.fields)?;
+                let patterns = members.iter().map(|(member, _, binding)| quote! { #member: #binding });
+                let values: Vec<_> = members
+                    .iter()
+                    .map(|(_, _, binding)| quote! { ::scm_repr::convert::ToScm::to_scm(#binding) })
+                    .collect();
+                let value = match &variant.fields {
+                    Fields::Named(_) => {
+                        let names = members.iter().map(|(_, name, _)| name);
+                        quote! {
+                            ::scm_repr::cons(::scm_repr::Scm::symbol(#tag), ::scm_repr::convert::alist(&[#((#names, #values)),*]))
+                        }
+                    }
+                    _ => quote! { ::scm_repr::convert::list(&[::scm_repr::Scm::symbol(#tag), #(#values),*]) },
+                };
+                arms.push(quote! { Self::#ident { #(#patterns),* } => #value, });
+            }
+            Ok(quote! {
+                match self {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(&input.ident, "ToScm can't be derived for unions")),
+    }
+}
+
+pub fn from_scm_body(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let options = options(&input.attrs, true)?;
+    match &input.data {
+        Data::Struct(data) => {
+            let members = members(&data.fields)?;
+            let n = members.len();
+            if options.record {
+                let name = scheme_name(&input.ident, &options);
+                let inits = positional(&members);
+                return Ok(quote! {
+                    let fields = ::scm_repr::convert::record_fields(x, #name, #n)?;
+                    Ok(Self { #(#inits),* })
+                })
+            }
+            Ok(match &data.fields {
+                Fields::Named(_) => {
+                    let inits = named(&members, quote! { x });
+                    quote! { Ok(Self { #(#inits),* }) }
+                }
+                Fields::Unnamed(_) if n == 1 => quote! { Ok(Self(::scm_repr::convert::FromScm::from_scm(x)?)) },
+                _ => {
+                    let inits = positional(&members);
+                    quote! {
+                        let fields = ::scm_repr::convert::elements(x, #n)?;
+                        Ok(Self { #(#inits),* })
+                    }
+                }
+            })
+        }
+        Data::Enum(data) => {
+            let mut arms = vec![];
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let tag = scheme_name(ident, &options(&variant.attrs, false)?);
+                let members = members(&variant.fields)?;
+                let n = members.len();
+                arms.push(match &variant.fields {
+                    Fields::Named(_) => {
+                        let inits = named(&members, quote! { rest });
+                        quote! { Some(#tag) => Ok(Self::#ident { #(#inits),* }), }
+                    }
+                    _ => {
+                        let inits = positional(&members);
+                        quote! {
+                            Some(#tag) => {
+                                let fields = ::scm_repr::convert::elements(rest, #n)?;
+                                Ok(Self::#ident { #(#inits),* })
+                            }
+                        }
+                    }
+                });
+            }
+            Ok(quote! {
+                let (tag, rest) = ::scm_repr::convert::tagged(x)?;
+                match tag.as_symbol() {
+                    #(#arms)*
+                    _ => Err(::scm_repr::ConversionError::UnknownTag(tag)),
+                }
+            })
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(&input.ident, "FromScm can't be derived for unions")),
+    }
+}
+
+// Field initializers from the entries of an alist.
+fn named(members: &[(Member, String, Ident)], alist: TokenStream2) -> Vec<TokenStream2> {
+    members
+        .iter()
+        .map(|(member, name, _)| {
+            quote! { #member: ::scm_repr::convert::FromScm::from_scm(::scm_repr::convert::field(#alist, #name)?)? }
+        })
+        .collect()
+}
+
+// Field initializers from the elements of `fields`, in order.
+fn positional(members: &[(Member, String, Ident)]) -> Vec<TokenStream2> {
+    members
+        .iter()
+        .enumerate()
+        .map(|(i, (member, _, _))| quote! { #member: ::scm_repr::convert::FromScm::from_scm(fields[#i])? })
+        .collect()
+}
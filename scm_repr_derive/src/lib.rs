@@ -0,0 +1,135 @@
+//! Derive macros for scm_repr, re-exported by it with its `derive` feature:
+//! `#[derive(Trace)]` through `scm_repr::trace::Trace`, and
+//! `#[derive(ToScm, FromScm)]` through `scm_repr::convert`.
+//!
+//! The generated `trace` visits every field in declaration order, except
+//! those marked `#[trace(skip)]`. Type parameters must implement the derived
+//! trait.
+
+mod convert;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields, Index, Member, Path};
+
+#[proc_macro_derive(Trace, attributes(trace))]
+pub fn derive_trace(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let method = trace_body(&input).map(|body| {
+        quote! {
+            fn trace(&self, visit: &mut dyn FnMut(::scm_repr::Scm)) {
+                #body
+            }
+        }
+    });
+    implement(input, parse_quote!(::scm_repr::trace::Trace), method)
+}
+
+#[proc_macro_derive(ToScm, attributes(scm))]
+pub fn derive_to_scm(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let method = convert::to_scm_body(&input).map(|body| {
+        quote! {
+            fn to_scm(&self) -> ::scm_repr::Scm {
+                #body
+            }
+        }
+    });
+    implement(input, parse_quote!(::scm_repr::convert::ToScm), method)
+}
+
+#[proc_macro_derive(FromScm, attributes(scm))]
+pub fn derive_from_scm(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let method = convert::from_scm_body(&input).map(|body| {
+        quote! {
+            fn from_scm(x: ::scm_repr::Scm) -> ::std::result::Result<Self, ::scm_repr::ConversionError> {
+                #body
+            }
+        }
+    });
+    implement(input, parse_quote!(::scm_repr::convert::FromScm), method)
+}
+
+// The impl of `trait_path` for the input type, with the trait as a bound on
+// every type parameter.
+fn implement(mut input: DeriveInput, trait_path: Path, method: syn::Result<TokenStream2>) -> TokenStream {
+    let method = match method {
+        Ok(method) => method,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#trait_path));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            #method
+        }
+    }
+    .into()
+}
+
+fn trace_body(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(data) => {
+            let (pattern, calls) = destructure(&data.fields)?;
+            Ok(quote! {
+                let Self #pattern = self;
+                #(#calls)*
+            })
+        }
+        Data::Enum(data) => {
+            let mut arms = vec![];
+            for variant in &data.variants {
+                let name = &variant.ident;
+                let (pattern, calls) = destructure(&variant.fields)?;
+                arms.push(quote! { Self::#name #pattern => { #(#calls)* } });
+            }
+            Ok(quote! {
+                match self {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(&input.ident, "Trace can't be derived for unions")),
+    }
+}
+
+// A pattern that binds every traced field, which works for named, tuple and
+// unit fields alike, and the calls that trace the bindings.
+fn destructure(fields: &Fields) -> syn::Result<(TokenStream2, Vec<TokenStream2>)> {
+    let mut bindings = vec![];
+    let mut calls = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        if skipped(field)? {
+            continue
+        }
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        let binding = format_ident!("field_{}", i);
+        bindings.push(quote! { #member: #binding });
+        calls.push(quote! { ::scm_repr::trace::Trace::trace(#binding, visit); });
+    }
+    Ok((quote! { { #(#bindings,)* .. } }, calls))
+}
+
+fn skipped(field: &Field) -> syn::Result<bool> {
+    let mut skip = false;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("trace")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip`"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
@@ -0,0 +1,72 @@
+//! Random values for fuzzers and property tests, with the `arbitrary`
+//! feature. `Scm` implements `arbitrary::Arbitrary`, so a fuzz target can
+//! take values as its input directly.
+//!
+//! The values are of the kinds that the reader produces, plus eof:
+//! constants, characters, numbers of every kind, strings, symbols, and lists
+//! and vectors nested at most `MAX_DEPTH` deep, so that they can be printed
+//! and compared without running out of stack.
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+use crate::bigint::BigInt;
+use crate::{cons, num, Scm};
+
+pub const MAX_DEPTH: usize = 8;
+
+// The most items in a list or vector.
+const MAX_LEN: usize = 8;
+
+// A value with structure at most `depth` deep, taken from `u`.
+pub fn arbitrary_value(u: &mut Unstructured, depth: usize) -> Result<Scm> {
+    let kinds = if depth == 0 { 10 } else { 13 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Scm::NIL,
+        1 => Scm::from_bool(u.arbitrary()?),
+        2 => Scm::EOF,
+        3 => Scm::from_char(u.arbitrary()?),
+        4 => num::integer(u.arbitrary()?),
+        5 => num::mul(num::integer(u.arbitrary()?), num::integer(u.arbitrary()?)).unwrap(),
+        6 => {
+            let (n, d): (i64, i64) = (u.arbitrary()?, u.arbitrary()?);
+            num::make_rational(BigInt::from_i64(n), BigInt::from_i64(if d == 0 { 1 } else { d })).unwrap()
+        }
+        7 => Scm::from_f64(u.arbitrary()?),
+        8 => Scm::string(&String::arbitrary(u)?),
+        9 => Scm::symbol(&String::arbitrary(u)?),
+        10 => cons(arbitrary_value(u, depth - 1)?, arbitrary_value(u, depth - 1)?),
+        11 => {
+            let items = (0..u.int_in_range(0..=MAX_LEN)?).map(|_| arbitrary_value(u, depth - 1)).collect::<Result<Vec<_>>>()?;
+            items.into_iter().rev().fold(Scm::NIL, |list, item| cons(item, list))
+        }
+        _ => Scm::vector((0..u.int_in_range(0..=MAX_LEN)?).map(|_| arbitrary_value(u, depth - 1)).collect::<Result<_>>()?),
+    })
+}
+
+impl<'a> Arbitrary<'a> for Scm {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_value(u, MAX_DEPTH)
+    }
+}
+
+#[test]
+fn arbitrary_values_are_bounded() {
+    // a list counts as one level, however long it is
+    fn depth(mut x: Scm) -> usize {
+        if let Some(items) = x.as_vector() {
+            return 1 + items.iter().map(|&item| depth(item)).max().unwrap_or(0)
+        }
+        let mut deepest = None;
+        while let Some(&(car, cdr)) = x.as_pair() {
+            deepest = deepest.max(Some(depth(car)));
+            x = cdr;
+        }
+        deepest.map_or(0, |d| 1 + d.max(depth(x)))
+    }
+
+    let bytes: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    let mut u = Unstructured::new(&bytes);
+    for _ in 0..100 {
+        let x = Scm::arbitrary(&mut u).unwrap();
+        assert!(depth(x) <= MAX_DEPTH + MAX_LEN, "{}", x);
+    }
+}
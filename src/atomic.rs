@@ -0,0 +1,102 @@
+//! A shared, mutable slot holding a value, for global variables and lock-free
+//! data structures. Only available with the `sync` feature, because values
+//! can't be shared between threads otherwise.
+//!
+//! The slot stores the raw word, so comparisons in `compare_exchange` are by
+//! identity (`eq?`), just like `==` on `Scm`.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::Scm;
+
+pub struct AtomicScm {
+    raw: AtomicUsize,
+}
+
+impl AtomicScm {
+    pub fn new(value: Scm) -> Self {
+        AtomicScm { raw: AtomicUsize::new(value.to_raw()) }
+    }
+
+    // The slot only ever holds words from `to_raw`, and the objects they
+    // refer to are kept alive as long as they are reachable from here.
+    fn wrap(raw: usize) -> Scm {
+        unsafe { Scm::from_raw(raw) }
+    }
+
+    pub fn load(&self, order: Ordering) -> Scm {
+        AtomicScm::wrap(self.raw.load(order))
+    }
+
+    pub fn store(&self, value: Scm, order: Ordering) {
+        self.raw.store(value.to_raw(), order)
+    }
+
+    pub fn swap(&self, value: Scm, order: Ordering) -> Scm {
+        AtomicScm::wrap(self.raw.swap(value.to_raw(), order))
+    }
+
+    pub fn compare_exchange(&self, current: Scm, new: Scm, success: Ordering, failure: Ordering) -> Result<Scm, Scm> {
+        self.raw
+            .compare_exchange(current.to_raw(), new.to_raw(), success, failure)
+            .map(AtomicScm::wrap)
+            .map_err(AtomicScm::wrap)
+    }
+
+    pub fn compare_exchange_weak(&self, current: Scm, new: Scm, success: Ordering, failure: Ordering) -> Result<Scm, Scm> {
+        self.raw
+            .compare_exchange_weak(current.to_raw(), new.to_raw(), success, failure)
+            .map(AtomicScm::wrap)
+            .map_err(AtomicScm::wrap)
+    }
+
+    pub fn into_inner(self) -> Scm {
+        AtomicScm::wrap(self.raw.into_inner())
+    }
+}
+
+impl Default for AtomicScm {
+    fn default() -> Self {
+        AtomicScm::new(Scm::NIL)
+    }
+}
+
+impl fmt::Debug for AtomicScm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("AtomicScm").field(&self.load(Ordering::Relaxed)).finish()
+    }
+}
+
+#[test]
+fn lock_free_push_from_threads() {
+    use crate::cons;
+
+    // a Treiber stack: push by swinging the head from the old list to a new pair
+    let head = AtomicScm::default();
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let head = &head;
+            s.spawn(move || {
+                for i in 0..100 {
+                    let mut old = head.load(Ordering::Acquire);
+                    loop {
+                        let new = cons(Scm::from_int(t * 100 + i), old);
+                        match head.compare_exchange_weak(old, new, Ordering::AcqRel, Ordering::Acquire) {
+                            Ok(_) => break,
+                            Err(current) => old = current,
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let mut items = vec![];
+    let mut list = head.into_inner();
+    while let Some(&(x, rest)) = list.as_pair() {
+        items.push(x.as_integer().unwrap());
+        list = rest;
+    }
+    items.sort();
+    assert_eq!(items, (0..400).collect::<Vec<_>>());
+}
@@ -0,0 +1,375 @@
+//! Arbitrary precision integers, just enough for the numeric tower.
+//! Nothing clever here: schoolbook multiplication and bitwise long division.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    // zero has no digits and is never negative
+    negative: bool,
+    // little endian, without leading zeros
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt::default()
+    }
+
+    pub fn one() -> Self {
+        BigInt::from_i64(1)
+    }
+
+    pub fn from_i64(i: i64) -> Self {
+        BigInt::from_i128(i as i128)
+    }
+
+    pub fn from_i128(i: i128) -> Self {
+        let mut m = i.unsigned_abs();
+        let mut digits = vec![];
+        while m != 0 {
+            digits.push(m as u32);
+            m >>= 32;
+        }
+        BigInt { negative: i < 0, digits }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None
+        }
+        let m = self.digits.iter().rev().fold(0u64, |acc, &d| acc << 32 | d as u64);
+        if self.negative {
+            if m <= 1 << 63 { Some((m as i64).wrapping_neg()) } else { None }
+        } else {
+            i64::try_from(m).ok()
+        }
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.digits.len() > 4 {
+            return None
+        }
+        let m = self.digits.iter().rev().fold(0u128, |acc, &d| acc << 32 | d as u128);
+        if self.negative {
+            if m <= 1 << 127 { Some((m as i128).wrapping_neg()) } else { None }
+        } else {
+            i128::try_from(m).ok()
+        }
+    }
+
+    fn from_mag(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigInt { negative: negative && !digits.is_empty(), digits }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.digits.first().map(|d| d & 1 == 0).unwrap_or(true)
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt { negative: false, digits: self.digits.clone() }
+    }
+
+    pub fn bit_length(&self) -> usize {
+        match self.digits.last() {
+            None => 0,
+            Some(&top) => self.digits.len() * 32 - top.leading_zeros() as usize,
+        }
+    }
+
+    pub fn shl(&self, bits: usize) -> Self {
+        let mut digits = vec![0; bits / 32];
+        let shift = bits % 32;
+        let mut carry = 0;
+        for &d in &self.digits {
+            digits.push(d << shift | carry);
+            carry = if shift == 0 { 0 } else { d >> (32 - shift) };
+        }
+        digits.push(carry);
+        BigInt::from_mag(self.negative, digits)
+    }
+
+    // Truncating division, like Rust's `/` and `%` on primitive integers.
+    pub fn div_rem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        assert!(!other.is_zero(), "division by zero");
+        let (q, r) = divrem_mag(&self.digits, &other.digits);
+        (BigInt::from_mag(self.negative != other.negative, q), BigInt::from_mag(self.negative, r))
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        while !b.is_zero() {
+            let r = a.div_rem(&b).1;
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let n = self.bit_length();
+        let m = if n <= 64 {
+            self.digits.iter().rev().fold(0u64, |acc, &d| acc << 32 | d as u64) as f64
+        } else {
+            // keep the top 64 bits and fold everything below into a sticky
+            // bit, so the conversion to f64 still rounds correctly
+            let shift = n - 64;
+            let mut top = 0u64;
+            for i in (shift..n).rev() {
+                top = top << 1 | self.bit(i) as u64;
+            }
+            if (0..shift).any(|i| self.bit(i)) {
+                top |= 1;
+            }
+            top as f64 * 2f64.powi(shift as i32)
+        };
+        if self.negative { -m } else { m }
+    }
+
+    // Only integral, finite floats have an exact integer value.
+    pub fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() || f.fract() != 0.0 {
+            return None
+        }
+        let bits = f.to_bits();
+        let exp = ((bits >> 52) & 0x7ff) as i32;
+        if exp == 0 {
+            return Some(BigInt::zero())
+        }
+        let mantissa = BigInt::from_i64((bits & ((1 << 52) - 1) | 1 << 52) as i64);
+        let e = exp - 1075;
+        let m = if e >= 0 {
+            mantissa.shl(e as usize)
+        } else {
+            mantissa.div_rem(&BigInt::one().shl(-e as usize)).0
+        };
+        Some(if f < 0.0 { -&m } else { m })
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.digits.get(i / 32).map(|d| d >> (i % 32) & 1 == 1).unwrap_or(false)
+    }
+
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        assert!((2..=36).contains(&radix));
+        if self.is_zero() {
+            return "0".to_string()
+        }
+        let mut out = vec![];
+        let mut m = self.digits.clone();
+        while !m.is_empty() {
+            let r = divrem_small(&mut m, radix);
+            out.push(std::char::from_digit(r, radix).unwrap());
+        }
+        if self.negative {
+            out.push('-');
+        }
+        out.iter().rev().collect()
+    }
+
+    pub fn parse_radix(s: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if digits.is_empty() {
+            return None
+        }
+        let mut m: Vec<u32> = vec![];
+        for ch in digits.chars() {
+            let d = ch.to_digit(radix)?;
+            let mut carry = d as u64;
+            for x in m.iter_mut() {
+                let y = *x as u64 * radix as u64 + carry;
+                *x = y as u32;
+                carry = y >> 32;
+            }
+            if carry != 0 {
+                m.push(carry as u32);
+            }
+        }
+        Some(BigInt::from_mag(negative, m))
+    }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut out = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u64;
+    for (i, &x) in a.iter().enumerate() {
+        let y = x as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        out.push(y as u32);
+        carry = y >> 32;
+    }
+    out.push(carry as u32);
+    out
+}
+
+// requires a >= b
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &x) in a.iter().enumerate() {
+        let mut y = x as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = if y < 0 { y += 1 << 32; 1 } else { 0 };
+        out.push(y as u32);
+    }
+    debug_assert_eq!(borrow, 0);
+    while out.last() == Some(&0) {
+        out.pop();
+    }
+    out
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let z = x as u64 * y as u64 + out[i + j] as u64 + carry;
+            out[i + j] = z as u32;
+            carry = z >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    out
+}
+
+// divides m in place and returns the remainder
+fn divrem_small(m: &mut Vec<u32>, d: u32) -> u32 {
+    let mut r = 0u64;
+    for x in m.iter_mut().rev() {
+        let y = r << 32 | *x as u64;
+        *x = (y / d as u64) as u32;
+        r = y % d as u64;
+    }
+    while m.last() == Some(&0) {
+        m.pop();
+    }
+    r as u32
+}
+
+fn divrem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if b.len() == 1 {
+        let mut q = a.to_vec();
+        let r = divrem_small(&mut q, b[0]);
+        return (q, vec![r])
+    }
+    let mut q = vec![0u32; a.len()];
+    let mut r: Vec<u32> = vec![];
+    for i in (0..a.len() * 32).rev() {
+        let mut carry = a[i / 32] >> (i % 32) & 1;
+        for x in r.iter_mut() {
+            let next = *x >> 31;
+            *x = *x << 1 | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            r.push(carry);
+        }
+        if cmp_mag(&r, b) != Ordering::Less {
+            r = sub_mag(&r, b);
+            q[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (q, r)
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.digits, &other.digits),
+            (true, true) => cmp_mag(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+    fn neg(self) -> BigInt {
+        BigInt::from_mag(!self.negative, self.digits.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_mag(self.negative, add_mag(&self.digits, &other.digits))
+        }
+        match cmp_mag(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::from_mag(other.negative, sub_mag(&other.digits, &self.digits)),
+            _ => BigInt::from_mag(self.negative, sub_mag(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_mag(self.negative != other.negative, mul_mag(&self.digits, &other.digits))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_string_radix(10))
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BigInt({})", self)
+    }
+}
+
+#[test]
+fn bigint_arithmetic() {
+    let a = BigInt::parse_radix("123456789012345678901234567890", 10).unwrap();
+    let b = BigInt::parse_radix("-987654321098765432109876543210", 10).unwrap();
+    assert_eq!((&a + &b).to_string(), "-864197532086419753208641975320");
+    assert_eq!((&a - &b).to_string(), "1111111110111111111011111111100");
+    assert_eq!((&a * &b).to_string(), "-121932631137021795226185032733622923332237463801111263526900");
+    let (q, r) = b.div_rem(&a);
+    assert_eq!((q.to_string(), r.to_string()), ("-8".to_string(), "-9000000000900000000090".to_string()));
+    assert_eq!(a.gcd(&b).to_string(), "9000000000900000000090");
+    assert_eq!(BigInt::from_i64(i64::MIN).to_i64(), Some(i64::MIN));
+    assert_eq!(BigInt::from_f64(-1e20).unwrap().to_string(), "-100000000000000000000");
+    assert_eq!(BigInt::parse_radix("ff", 16).unwrap().to_string_radix(2), "11111111");
+    assert_eq!(a.to_f64(), 1.2345678901234568e29);
+}
@@ -0,0 +1,172 @@
+//! Bit vectors (in the spirit of SRFI 178): fixed-length vectors of booleans,
+//! packed 64 to a word.
+//!
+//! Besides being an eighth of the size of a vector of booleans, they make
+//! compact sets of small integers, where the logical operations are set
+//! union, intersection and so on, one word at a time.
+//!
+//! Bits past the length in the last word are always kept clear, so that
+//! counting and comparing can work on whole words.
+
+use crate::heap::{self, HeapObject, Kind};
+use crate::lock::Lock;
+use crate::{Scm, ScmKind, TypeError};
+
+const WORD_BITS: usize = 64;
+
+pub(crate) struct Bitvector {
+    len: usize,
+    words: Lock<Box<[u64]>>,
+}
+
+impl HeapObject for Bitvector {
+    const KIND: Kind = Kind::Bitvector;
+}
+
+impl Bitvector {
+    fn new(len: usize, words: Vec<u64>) -> Self {
+        debug_assert_eq!(words.len(), len.div_ceil(WORD_BITS));
+        let mut words = words.into_boxed_slice();
+        if let Some(last) = words.last_mut() {
+            *last &= tail_mask(len);
+        }
+        Bitvector { len, words: Lock::new(words) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn words(&self) -> Vec<u64> {
+        self.words.lock().to_vec()
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.check_index(i);
+        self.words.lock()[i / WORD_BITS] >> (i % WORD_BITS) & 1 == 1
+    }
+
+    fn set(&self, i: usize, bit: bool) {
+        self.check_index(i);
+        let word = &mut self.words.lock()[i / WORD_BITS];
+        let mask = 1 << (i % WORD_BITS);
+        if bit {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    fn check_index(&self, i: usize) {
+        assert!(i < self.len, "bit index {} out of range for bitvector of length {}", i, self.len);
+    }
+}
+
+// The bits of the last word that are part of a bitvector of length `len`.
+fn tail_mask(len: usize) -> u64 {
+    match len % WORD_BITS {
+        0 => u64::MAX,
+        n => (1 << n) - 1,
+    }
+}
+
+impl Scm {
+    pub fn is_bitvector(&self) -> bool {
+        self.as_object::<Bitvector>().is_some()
+    }
+}
+
+fn expect_bitvector(bv: Scm) -> Result<&'static Bitvector, TypeError> {
+    bv.as_object::<Bitvector>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Bitvector, bv))
+}
+
+pub fn make_bitvector(len: usize, fill: bool) -> Scm {
+    let word = if fill { u64::MAX } else { 0 };
+    Scm::from_object(heap::leak(Bitvector::new(len, vec![word; len.div_ceil(WORD_BITS)])))
+}
+
+pub fn bitvector(bits: impl IntoIterator<Item = bool>) -> Scm {
+    let mut words = vec![];
+    let mut len = 0;
+    for bit in bits {
+        if len % WORD_BITS == 0 {
+            words.push(0);
+        }
+        if bit {
+            *words.last_mut().unwrap() |= 1 << (len % WORD_BITS);
+        }
+        len += 1;
+    }
+    Scm::from_object(heap::leak(Bitvector::new(len, words)))
+}
+
+pub fn bitvector_length(bv: Scm) -> Result<usize, TypeError> {
+    expect_bitvector(bv).map(Bitvector::len)
+}
+
+/// Panics if `i` is not a valid index, like indexing a slice does.
+pub fn bit_ref(bv: Scm, i: usize) -> Result<bool, TypeError> {
+    expect_bitvector(bv).map(|obj| obj.get(i))
+}
+
+/// Panics if `i` is not a valid index, like indexing a slice does.
+pub fn bit_set(bv: Scm, i: usize, bit: bool) -> Result<(), TypeError> {
+    expect_bitvector(bv).map(|obj| obj.set(i, bit))
+}
+
+// Population count: the number of bits that are set.
+pub fn bit_count(bv: Scm) -> Result<usize, TypeError> {
+    let obj = expect_bitvector(bv)?;
+    Ok(obj.words.lock().iter().map(|w| w.count_ones() as usize).sum())
+}
+
+pub fn bitvector_not(bv: Scm) -> Result<Scm, TypeError> {
+    let obj = expect_bitvector(bv)?;
+    let words = obj.words.lock().iter().map(|w| !w).collect();
+    Ok(Scm::from_object(heap::leak(Bitvector::new(obj.len, words))))
+}
+
+/// Panics if the bitvectors differ in length.
+pub fn bitvector_and(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    zip_words(a, b, |x, y| x & y)
+}
+
+/// Panics if the bitvectors differ in length.
+pub fn bitvector_or(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    zip_words(a, b, |x, y| x | y)
+}
+
+/// Panics if the bitvectors differ in length.
+pub fn bitvector_xor(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    zip_words(a, b, |x, y| x ^ y)
+}
+
+fn zip_words(a: Scm, b: Scm, op: impl Fn(u64, u64) -> u64) -> Result<Scm, TypeError> {
+    let (x, y) = (expect_bitvector(a)?, expect_bitvector(b)?);
+    assert_eq!(x.len, y.len, "bitvectors differ in length");
+    // copy one side first, so that an operation on a bitvector and itself
+    // doesn't take the same lock twice
+    let xs = x.words();
+    let words = xs.iter().zip(y.words.lock().iter()).map(|(&x, &y)| op(x, y)).collect();
+    Ok(Scm::from_object(heap::leak(Bitvector::new(x.len, words))))
+}
+
+#[test]
+fn bitvectors_as_small_sets() {
+    let evens = bitvector((0..100).map(|i| i % 2 == 0));
+    let small = make_bitvector(100, false);
+    for i in 0..10 {
+        bit_set(small, i, true).unwrap();
+    }
+    assert_eq!(bit_count(evens).unwrap(), 50);
+    assert_eq!(bit_count(bitvector_and(evens, small).unwrap()).unwrap(), 5);
+    assert_eq!(bit_count(bitvector_or(evens, small).unwrap()).unwrap(), 55);
+    assert_eq!(bit_count(bitvector_xor(evens, evens).unwrap()).unwrap(), 0);
+    // the unused bits of the last word stay clear
+    assert_eq!(bit_count(bitvector_not(small).unwrap()).unwrap(), 90);
+    assert!(bit_ref(evens, 98).unwrap() && !bit_ref(evens, 99).unwrap());
+    assert_eq!(bitvector_length(make_bitvector(0, true)).unwrap(), 0);
+
+    assert_eq!(bitvector(vec![true, false, true, true]).to_string(), "#*1011");
+    assert_eq!(bit_count(Scm::NIL).unwrap_err().to_string(), "expected bitvector, got empty list");
+}
@@ -0,0 +1,90 @@
+//! Boxes (SRFI 111): a heap object with a single mutable slot. Unlike a pair
+//! they can't be confused with list structure, so they are what a compiler
+//! should use for mutable variables captured by closures.
+
+use crate::heap::{self, HeapObject, Kind};
+use crate::{MutationError, Scm, ScmKind, TypeError};
+
+// The slot is atomic with the `sync` feature, since boxes may then be shared
+// between threads.
+#[cfg(not(feature = "sync"))]
+pub(crate) struct ScmBox(std::cell::Cell<Scm>);
+
+#[cfg(feature = "sync")]
+pub(crate) struct ScmBox(crate::AtomicScm);
+
+#[cfg(not(feature = "sync"))]
+impl ScmBox {
+    fn new(value: Scm) -> Self {
+        ScmBox(std::cell::Cell::new(value))
+    }
+
+    pub fn get(&self) -> Scm {
+        self.0.get()
+    }
+
+    fn set(&self, value: Scm) {
+        self.0.set(value)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl ScmBox {
+    fn new(value: Scm) -> Self {
+        ScmBox(crate::AtomicScm::new(value))
+    }
+
+    pub fn get(&self) -> Scm {
+        self.0.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    fn set(&self, value: Scm) {
+        self.0.store(value, std::sync::atomic::Ordering::Release)
+    }
+}
+
+impl HeapObject for ScmBox {
+    const KIND: Kind = Kind::Box;
+}
+
+impl Scm {
+    pub fn is_box(&self) -> bool {
+        self.as_object::<ScmBox>().is_some()
+    }
+}
+
+pub fn make_box(value: Scm) -> Scm {
+    Scm::from_object(heap::leak(ScmBox::new(value)))
+}
+
+pub fn unbox(b: Scm) -> Result<Scm, TypeError> {
+    match b.as_object::<ScmBox>() {
+        Some(obj) => Ok(obj.get()),
+        None => Err(TypeError::new(ScmKind::Box, b)),
+    }
+}
+
+// Fails if the box is frozen.
+pub fn set_box(b: Scm, value: Scm) -> Result<(), MutationError> {
+    match b.as_object::<ScmBox>() {
+        Some(_) if b.is_frozen() => Err(MutationError::Immutable(b)),
+        Some(obj) => {
+            obj.set(value);
+            Ok(())
+        }
+        None => Err(TypeError::new(ScmKind::Box, b).into()),
+    }
+}
+
+#[test]
+fn boxes_are_mutable_cells() {
+    let b = make_box(Scm::from_int(1));
+    let alias = b;
+    set_box(alias, Scm::symbol("two")).unwrap();
+    assert_eq!(unbox(b).unwrap(), Scm::symbol("two"));
+    assert!(b.is_box() && b.as_pair().is_none());
+    assert_eq!(b.to_string(), "#&two");
+
+    let p = crate::cons(Scm::NIL, Scm::NIL);
+    assert_eq!(unbox(p).unwrap_err().to_string(), "expected box, got pair");
+}
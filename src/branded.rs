@@ -0,0 +1,117 @@
+//! Safe subset of the representation where values are tied to the heap that
+//! allocated them.
+//!
+//! The plain `Scm` hands out `&'static` references to pairs, which is only
+//! sound as long as something (leaking or the Boehm GC) keeps the memory
+//! alive. Here every value borrows its `Heap`, so the borrow checker makes
+//! sure no value outlives the storage it points into. The price is that
+//! values cannot be stored anywhere that lives longer than the heap.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use crate::heap::{self, Object};
+
+type RawPair = Object<crate::Pair>;
+
+pub struct Heap {
+    #[allow(clippy::vec_box)]  // boxed so pairs keep their address when the vector grows
+    pairs: RefCell<Vec<Box<RawPair>>>,
+}
+
+#[derive(Copy, Clone)]
+pub struct Scm<'h> {
+    raw: crate::Scm,
+    _heap: PhantomData<&'h Heap>,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Heap {
+            pairs: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn cons<'h>(&'h self, car: Scm<'h>, cdr: Scm<'h>) -> Scm<'h> {
+        let pair = heap::alloc(crate::Pair::new(car.raw, cdr.raw));
+        // only dropped together with the heap, which outlives 'h
+        let raw = crate::Scm::from_object(&*pair);
+        self.pairs.borrow_mut().push(pair);
+        Scm::wrap(raw)
+    }
+
+    pub fn n_pairs(&self) -> usize {
+        self.pairs.borrow().len()
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Heap::new()
+    }
+}
+
+impl<'h> Scm<'h> {
+    fn wrap(raw: crate::Scm) -> Self {
+        Scm {
+            raw,
+            _heap: PhantomData,
+        }
+    }
+
+    pub fn nil() -> Self {
+        Scm::wrap(crate::Scm::nil())
+    }
+
+    pub fn from_int(value: i64) -> Self {
+        Scm::wrap(crate::Scm::from_int(value))
+    }
+
+    pub fn is_nil(self) -> bool {
+        self.raw.is_nil()
+    }
+
+    pub fn is_pair(self) -> bool {
+        self.raw.as_pair().is_some()
+    }
+
+    pub fn as_integer(self) -> Option<i64> {
+        self.raw.as_integer()
+    }
+
+    pub fn as_pair(self) -> Option<(Scm<'h>, Scm<'h>)> {
+        self.raw.as_pair().map(|&(a, d)| (Scm::wrap(a), Scm::wrap(d)))
+    }
+
+    pub fn car(self) -> Option<Scm<'h>> {
+        self.as_pair().map(|p| p.0)
+    }
+
+    pub fn cdr(self) -> Option<Scm<'h>> {
+        self.as_pair().map(|p| p.1)
+    }
+}
+
+impl fmt::Debug for Scm<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.raw.fmt(f)
+    }
+}
+
+#[test]
+fn values_live_as_long_as_the_heap() {
+    let heap = Heap::new();
+    let mut list = Scm::nil();
+    for i in 0..10 {
+        list = heap.cons(Scm::from_int(i), list);
+    }
+    assert_eq!(heap.n_pairs(), 10);
+
+    let mut expect = 9;
+    while let Some((a, d)) = list.as_pair() {
+        assert_eq!(a.as_integer(), Some(expect));
+        list = d;
+        expect -= 1;
+    }
+    assert!(list.is_nil());
+}
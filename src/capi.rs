@@ -0,0 +1,172 @@
+//! C interface. Values cross the boundary as their raw word (see
+//! `Scm::to_raw`); zero is never a valid value and is returned whenever an
+//! operation does not apply, e.g. `scm_car` of a non-pair.
+//!
+//! The header in `include/scm_repr.h` is generated with
+//!     cbindgen --config cbindgen.toml --output include/scm_repr.h
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use crate::*;
+
+pub type ScmRaw = usize;
+
+unsafe fn scm(x: ScmRaw) -> Scm {
+    Scm::from_raw(x)
+}
+
+fn raw(x: Option<Scm>) -> ScmRaw {
+    x.map(Scm::to_raw).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn scm_nil() -> ScmRaw {
+    Scm::NIL.to_raw()
+}
+
+#[no_mangle]
+pub extern "C" fn scm_from_bool(b: bool) -> ScmRaw {
+    Scm::from_bool(b).to_raw()
+}
+
+// Integers outside the fixnum range (which is smaller on 32-bit targets)
+// become bignums.
+#[no_mangle]
+pub extern "C" fn scm_from_int(i: i64) -> ScmRaw {
+    num::integer(i).to_raw()
+}
+
+/// # Safety
+/// `x` must be a valid value and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn scm_to_int(x: ScmRaw, out: *mut i64) -> bool {
+    match scm(x).as_integer() {
+        Some(i) => {
+            *out = i;
+            true
+        }
+        None => false,
+    }
+}
+
+/// # Safety
+/// `name` must point to a nul-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn scm_symbol(name: *const c_char) -> ScmRaw {
+    raw(CStr::from_ptr(name).to_str().ok().map(Scm::symbol))
+}
+
+/// # Safety
+/// `s` must point to a nul-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn scm_string(s: *const c_char) -> ScmRaw {
+    raw(CStr::from_ptr(s).to_str().ok().map(Scm::string))
+}
+
+/// # Safety
+/// Both arguments must be valid values.
+#[no_mangle]
+pub unsafe extern "C" fn scm_cons(car: ScmRaw, cdr: ScmRaw) -> ScmRaw {
+    cons(scm(car), scm(cdr)).to_raw()
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_car(x: ScmRaw) -> ScmRaw {
+    raw(car(scm(x)))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_cdr(x: ScmRaw) -> ScmRaw {
+    raw(cdr(scm(x)))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_null(x: ScmRaw) -> bool {
+    is_null(scm(x))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_pair(x: ScmRaw) -> bool {
+    is_pair(scm(x))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_integer(x: ScmRaw) -> bool {
+    is_integer(scm(x))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_boolean(x: ScmRaw) -> bool {
+    is_boolean(scm(x))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_symbol(x: ScmRaw) -> bool {
+    is_symbol(scm(x))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_string(x: ScmRaw) -> bool {
+    is_string(scm(x))
+}
+
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_is_vector(x: ScmRaw) -> bool {
+    is_vector(scm(x))
+}
+
+/// Returns the external representation of `x`. The result must be released
+/// with `scm_free_string`.
+///
+/// # Safety
+/// `x` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn scm_write_to_string(x: ScmRaw) -> *mut c_char {
+    // strings escape their nul bytes, but symbols don't
+    let repr = scm(x).to_string().replace('\0', "\\x0;");
+    CString::new(repr).unwrap().into_raw()
+}
+
+/// # Safety
+/// `s` must have been returned by `scm_write_to_string` and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn scm_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[test]
+fn c_api_round_trip() {
+    unsafe {
+        let list = scm_cons(scm_from_int(1), scm_cons(scm_symbol(b"x\0".as_ptr() as *const c_char), scm_nil()));
+        assert!(scm_is_pair(list));
+        assert_eq!(scm_car(scm_from_int(3)), 0);
+
+        let mut i = 0;
+        assert!(scm_to_int(scm_car(list), &mut i));
+        assert_eq!(i, 1);
+
+        let s = scm_write_to_string(list);
+        assert_eq!(CStr::from_ptr(s).to_str(), Ok("(1 x)"));
+        scm_free_string(s);
+    }
+}
@@ -0,0 +1,75 @@
+//! Uniform downcasting, so new types don't need yet another `as_*` method.
+
+use crate::{Scm, TypeError};
+
+pub trait ScmCast<'a>: Sized {
+    fn try_cast(scm: &'a Scm) -> Result<Self, TypeError>;
+}
+
+impl Scm {
+    pub fn try_as<'a, T: ScmCast<'a>>(&'a self) -> Result<T, TypeError> {
+        T::try_cast(self)
+    }
+}
+
+impl ScmCast<'_> for Scm {
+    fn try_cast(scm: &Scm) -> Result<Self, TypeError> {
+        Ok(*scm)
+    }
+}
+
+impl ScmCast<'_> for i64 {
+    fn try_cast(scm: &Scm) -> Result<Self, TypeError> {
+        scm.expect_integer()
+    }
+}
+
+impl ScmCast<'_> for bool {
+    fn try_cast(scm: &Scm) -> Result<Self, TypeError> {
+        scm.expect_bool()
+    }
+}
+
+impl ScmCast<'_> for char {
+    fn try_cast(scm: &Scm) -> Result<Self, TypeError> {
+        scm.expect_char()
+    }
+}
+
+impl ScmCast<'_> for (Scm, Scm) {
+    fn try_cast(scm: &Scm) -> Result<Self, TypeError> {
+        scm.expect_pair().copied()
+    }
+}
+
+impl<'a> ScmCast<'a> for &'a (Scm, Scm) {
+    fn try_cast(scm: &'a Scm) -> Result<Self, TypeError> {
+        scm.expect_pair()
+    }
+}
+
+// Strings only; use `expect_symbol` for the names of symbols.
+impl<'a> ScmCast<'a> for &'a str {
+    fn try_cast(scm: &'a Scm) -> Result<Self, TypeError> {
+        scm.expect_str()
+    }
+}
+
+impl<'a> ScmCast<'a> for &'a [Scm] {
+    fn try_cast(scm: &'a Scm) -> Result<Self, TypeError> {
+        scm.expect_vector()
+    }
+}
+
+#[test]
+fn casts() {
+    use crate::{cons, ScmKind};
+
+    let p = cons(Scm::from_int(1), Scm::string("two"));
+    let (a, d) = p.try_as::<(Scm, Scm)>().unwrap();
+    assert_eq!(a.try_as::<i64>(), Ok(1));
+    assert_eq!(d.try_as::<&str>(), Ok("two"));
+    assert_eq!(Scm::TRUE.try_as::<bool>(), Ok(true));
+    assert_eq!(a.try_as::<&str>().unwrap_err().actual, ScmKind::Integer);
+    assert_eq!(p.try_as::<&[Scm]>().unwrap_err().expected, ScmKind::Vector);
+}
@@ -0,0 +1,226 @@
+//! Channels for message passing between threads, around `std::sync::mpsc`.
+//!
+//! With the `sync` feature values are shared, and the receiver gets the very
+//! object that was sent. Without it values can't cross threads, so a message
+//! travels as its written representation and is read back by the receiving
+//! thread, as a deep copy. Then only data that can be read back can be sent:
+//! lists and vectors of numbers, characters, strings, symbols and booleans,
+//! nested no deeper than the reader accepts by default.
+//!
+//! `channel` makes the two ends as plain Rust values, which can be moved to
+//! other threads in either mode and wrapped as Scheme objects there.
+
+use std::sync::mpsc;
+use crate::heap::{self, HeapObject, Kind};
+use crate::lock::Lock;
+use crate::{ChannelError, Scm, ScmKind, TypeError};
+
+#[cfg(feature = "sync")]
+struct Message(Scm);
+
+#[cfg(not(feature = "sync"))]
+struct Message(String);
+
+#[cfg(feature = "sync")]
+impl Message {
+    fn pack(x: Scm) -> Result<Self, ChannelError> {
+        Ok(Message(x))
+    }
+
+    fn unpack(self) -> Scm {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+impl Message {
+    fn pack(x: Scm) -> Result<Self, ChannelError> {
+        transferable(x)?;
+        Ok(Message(x.to_string()))
+    }
+
+    fn unpack(self) -> Scm {
+        crate::reader::read_str(&self.0).expect("messages are written data")
+    }
+}
+
+// Finds a part of `x` that would not read back as an equal value. Without
+// recursion, so that deeply nested values can't overflow the stack.
+//
+// The depth of each part is counted as the reader counts it: the items of a
+// list or vector are one deeper than the list, and so is a dotted tail, but
+// the rest of a list is not.
+#[cfg(not(feature = "sync"))]
+fn transferable(x: Scm) -> Result<(), ChannelError> {
+    let mut todo = vec![(x, 1)];
+    while let Some((x, depth)) = todo.pop() {
+        if depth > crate::reader::DEFAULT_MAX_DEPTH {
+            return Err(ChannelError::NotTransferable(x))
+        }
+        match x.kind() {
+            ScmKind::Nil | ScmKind::Boolean | ScmKind::Char | ScmKind::String | ScmKind::Symbol => {}
+            ScmKind::Integer | ScmKind::Bignum | ScmKind::Rational | ScmKind::Flonum => {}
+            ScmKind::Pair => {
+                let &(car, cdr) = x.as_pair().unwrap();
+                match cdr.kind() {
+                    ScmKind::Nil => {}
+                    ScmKind::Pair => todo.push((cdr, depth)),
+                    _ => todo.push((cdr, depth + 1)),
+                }
+                todo.push((car, depth + 1));
+            }
+            ScmKind::Vector => todo.extend(x.as_vector().unwrap().iter().rev().map(|&item| (item, depth + 1))),
+            _ => return Err(ChannelError::NotTransferable(x)),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct Sender(mpsc::Sender<Message>);
+
+pub struct Receiver(mpsc::Receiver<Message>);
+
+impl Sender {
+    // Fails if `x` can't be sent, or if the receiver is gone.
+    pub fn send(&self, x: Scm) -> Result<(), ChannelError> {
+        self.0.send(Message::pack(x)?).map_err(|_| ChannelError::Disconnected)
+    }
+}
+
+impl Receiver {
+    // Blocks until a message arrives, or until all senders are gone.
+    pub fn receive(&self) -> Result<Scm, ChannelError> {
+        self.0.recv().map(Message::unpack).map_err(|_| ChannelError::Disconnected)
+    }
+
+    // A message if one is waiting.
+    pub fn try_receive(&self) -> Result<Option<Scm>, ChannelError> {
+        match self.0.try_recv() {
+            Ok(message) => Ok(Some(message.unpack())),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(ChannelError::Disconnected),
+        }
+    }
+}
+
+pub fn channel() -> (Sender, Receiver) {
+    let (tx, rx) = mpsc::channel();
+    (Sender(tx), Receiver(rx))
+}
+
+impl HeapObject for Sender {
+    const KIND: Kind = Kind::Sender;
+}
+
+// A `Receiver` isn't `Sync`, but with the `sync` feature every object may be
+// shared.
+pub(crate) struct ScmReceiver(Lock<Receiver>);
+
+impl HeapObject for ScmReceiver {
+    const KIND: Kind = Kind::Receiver;
+}
+
+impl Scm {
+    pub fn is_channel_sender(&self) -> bool {
+        self.as_object::<Sender>().is_some()
+    }
+
+    pub fn is_channel_receiver(&self) -> bool {
+        self.as_object::<ScmReceiver>().is_some()
+    }
+}
+
+fn expect_sender(s: Scm) -> Result<&'static Sender, TypeError> {
+    s.as_object::<Sender>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Sender, s))
+}
+
+fn expect_receiver(r: Scm) -> Result<&'static ScmReceiver, TypeError> {
+    r.as_object::<ScmReceiver>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Receiver, r))
+}
+
+pub fn make_sender(s: Sender) -> Scm {
+    Scm::from_object(heap::leak(s))
+}
+
+pub fn make_receiver(r: Receiver) -> Scm {
+    Scm::from_object(heap::leak(ScmReceiver(Lock::new(r))))
+}
+
+// Both ends of a new channel as objects.
+pub fn make_channel() -> (Scm, Scm) {
+    let (tx, rx) = channel();
+    (make_sender(tx), make_receiver(rx))
+}
+
+// Another handle on the channel of `s`, to move to a different thread.
+pub fn clone_sender(s: Scm) -> Result<Sender, TypeError> {
+    expect_sender(s).cloned()
+}
+
+pub fn channel_send(s: Scm, x: Scm) -> Result<(), ChannelError> {
+    expect_sender(s)?.send(x)
+}
+
+pub fn channel_receive(r: Scm) -> Result<Scm, ChannelError> {
+    expect_receiver(r)?.0.lock().receive()
+}
+
+pub fn channel_try_receive(r: Scm) -> Result<Option<Scm>, ChannelError> {
+    expect_receiver(r)?.0.lock().try_receive()
+}
+
+#[test]
+fn values_travel_between_threads() {
+    let (tx, rx) = make_channel();
+    let remote = clone_sender(tx).unwrap();
+    let worker = std::thread::spawn(move || {
+        for i in 0..3 {
+            let message = crate::reader::read_str(&format!("(job {} #(\"x\" 1/2 #\\a) 1e3)", i)).unwrap();
+            remote.send(message).unwrap();
+        }
+    });
+    for i in 0..3 {
+        let job = channel_receive(rx).unwrap();
+        assert_eq!(job.to_string(), format!("(job {} #(\"x\" 1/2 #\\a) 1000.0)", i));
+    }
+    worker.join().unwrap();
+    assert_eq!(channel_try_receive(rx), Ok(None));
+
+    channel_send(tx, Scm::symbol("local")).unwrap();
+    assert_eq!(channel_receive(rx), Ok(Scm::symbol("local")));
+    assert!(tx.is_channel_sender() && rx.is_channel_receiver());
+    assert_eq!(rx.to_string(), "#<channel-receiver>");
+    #[cfg(not(feature = "sync"))]
+    assert_eq!(channel_send(tx, crate::port::open_output_string()).unwrap_err().to_string(), "can't send #<output-port> to another thread");
+
+    // Too deep to read back without the `sync` feature, which sends the
+    // object itself.
+    let deep = (0..300).fold(Scm::NIL, |x, _| crate::cons(x, Scm::NIL));
+    #[cfg(not(feature = "sync"))]
+    assert!(matches!(channel_send(tx, deep), Err(ChannelError::NotTransferable(_))));
+    #[cfg(feature = "sync")]
+    {
+        channel_send(tx, deep).unwrap();
+        assert_eq!(channel_receive(rx), Ok(deep));
+    }
+    #[cfg(not(feature = "sync"))]
+    {
+        let depth = crate::reader::DEFAULT_MAX_DEPTH;
+        let deepest = (1..depth).fold(Scm::NIL, |x, _| crate::cons(x, Scm::NIL));
+        let dotted = (2..depth).fold(crate::cons(Scm::NIL, Scm::from_int(1)), |x, _| crate::cons(x, Scm::NIL));
+        for x in [deepest, dotted] {
+            channel_send(tx, x).unwrap();
+            assert_eq!(channel_receive(rx).unwrap().to_string(), x.to_string());
+        }
+        assert!(channel_send(tx, Scm::vector(vec![deepest])).is_err());
+        let too_deep = (0..100_000).fold(Scm::NIL, |x, _| crate::cons(x, Scm::NIL));
+        assert!(matches!(transferable(too_deep), Err(ChannelError::NotTransferable(_))));
+    }
+
+    let (tx, rx) = channel();
+    drop(rx);
+    assert_eq!(tx.send(Scm::NIL), Err(ChannelError::Disconnected));
+    let (tx, _) = make_channel();
+    assert_eq!(channel_receive(tx).unwrap_err().to_string(), "expected channel receiver, got channel sender");
+}
@@ -0,0 +1,213 @@
+//! Character classification and case mapping, and character sets.
+//!
+//! The predicates follow the Unicode properties that Rust's `char` exposes.
+//! Case mapping is per character, as `char-upcase` and friends require; where
+//! Unicode maps a character to several (like ß to SS) the character is left
+//! alone. Strings have to be mapped as a whole to get those right.
+//!
+//! A char-set is an immutable set of code points, stored as sorted, disjoint
+//! ranges so that even sets like "all letters" are small.
+
+use std::ops::RangeInclusive;
+use crate::heap::{self, HeapObject, Kind};
+use crate::{Scm, ScmKind, TypeError};
+
+const MAX_CODE_POINT: u32 = char::MAX as u32;
+
+pub fn char_alphabetic(c: Scm) -> Result<bool, TypeError> {
+    c.expect_char().map(char::is_alphabetic)
+}
+
+pub fn char_numeric(c: Scm) -> Result<bool, TypeError> {
+    c.expect_char().map(char::is_numeric)
+}
+
+pub fn char_whitespace(c: Scm) -> Result<bool, TypeError> {
+    c.expect_char().map(char::is_whitespace)
+}
+
+pub fn char_upper_case(c: Scm) -> Result<bool, TypeError> {
+    c.expect_char().map(char::is_uppercase)
+}
+
+pub fn char_lower_case(c: Scm) -> Result<bool, TypeError> {
+    c.expect_char().map(char::is_lowercase)
+}
+
+// The zeros of the most common runs of decimal digits (Unicode category Nd),
+// which always come as ten consecutive code points. Not exhaustive: the
+// standard library has no access to the full Unicode tables.
+const DIGIT_ZEROS: [u32; 44] = [
+    0x30, 0x660, 0x6f0, 0x7c0, 0x966, 0x9e6, 0xa66, 0xae6, 0xb66, 0xbe6, 0xc66, 0xce6, 0xd66, 0xde6, 0xe50,
+    0xed0, 0xf20, 0x1040, 0x1090, 0x17e0, 0x1810, 0x1946, 0x19d0, 0x1a80, 0x1a90, 0x1b50, 0x1bb0, 0x1c40,
+    0x1c50, 0xa620, 0xa8d0, 0xa900, 0xa9d0, 0xa9f0, 0xaa50, 0xabf0, 0xff10, 0x104a0, 0x11066, 0x1d7ce,
+    0x1d7d8, 0x1d7e2, 0x1d7ec, 0x1d7f6,
+];
+
+// The value of a decimal digit, in any script that has them.
+pub fn digit_value(c: Scm) -> Result<Option<u32>, TypeError> {
+    let c = c.expect_char()? as u32;
+    Ok(DIGIT_ZEROS.iter().find(|&&zero| (zero..zero + 10).contains(&c)).map(|zero| c - zero))
+}
+
+fn map_single(c: char, mut mapped: impl Iterator<Item = char>) -> char {
+    match (mapped.next(), mapped.next()) {
+        (Some(m), None) => m,
+        _ => c,
+    }
+}
+
+pub fn char_upcase(c: Scm) -> Result<Scm, TypeError> {
+    let c = c.expect_char()?;
+    Ok(Scm::from_char(map_single(c, c.to_uppercase())))
+}
+
+pub fn char_downcase(c: Scm) -> Result<Scm, TypeError> {
+    let c = c.expect_char()?;
+    Ok(Scm::from_char(map_single(c, c.to_lowercase())))
+}
+
+// Simple case folding is lower case for almost all characters; the Turkish
+// dotted and dotless i's are left alone, as R7RS requires.
+pub fn char_foldcase(c: Scm) -> Result<Scm, TypeError> {
+    match c.expect_char()? {
+        'İ' | 'ı' => Ok(c),
+        _ => char_downcase(c),
+    }
+}
+
+// Sorted, disjoint and non-adjacent inclusive ranges of code points.
+pub(crate) struct CharSet(Box<[(u32, u32)]>);
+
+impl HeapObject for CharSet {
+    const KIND: Kind = Kind::CharSet;
+}
+
+impl CharSet {
+    fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        match self.0.binary_search_by_key(&c, |&(lo, _)| lo) {
+            Ok(_) => true,
+            Err(0) => false,
+            Err(i) => c <= self.0[i - 1].1,
+        }
+    }
+}
+
+fn normalize(mut ranges: Vec<(u32, u32)>) -> Scm {
+    ranges.retain(|(lo, hi)| lo <= hi);
+    ranges.sort_unstable();
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (lo, hi) in ranges {
+        match merged.last_mut() {
+            Some(last) if lo <= last.1.saturating_add(1) => last.1 = last.1.max(hi),
+            _ => merged.push((lo, hi)),
+        }
+    }
+    Scm::from_object(heap::leak(CharSet(merged.into_boxed_slice())))
+}
+
+impl Scm {
+    pub fn is_char_set(&self) -> bool {
+        self.as_object::<CharSet>().is_some()
+    }
+}
+
+fn expect_char_set(cs: Scm) -> Result<&'static CharSet, TypeError> {
+    cs.as_object::<CharSet>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::CharSet, cs))
+}
+
+pub fn make_char_set(ranges: impl IntoIterator<Item = RangeInclusive<char>>) -> Scm {
+    normalize(ranges.into_iter().map(|r| (*r.start() as u32, *r.end() as u32)).collect())
+}
+
+pub fn string_to_char_set(s: &str) -> Scm {
+    make_char_set(s.chars().map(|c| c..=c))
+}
+
+pub fn char_set_contains(cs: Scm, c: char) -> Result<bool, TypeError> {
+    expect_char_set(cs).map(|set| set.contains(c))
+}
+
+pub fn char_set_union(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    let (a, b) = (expect_char_set(a)?, expect_char_set(b)?);
+    Ok(normalize(a.0.iter().chain(&*b.0).copied().collect()))
+}
+
+pub fn char_set_complement(cs: Scm) -> Result<Scm, TypeError> {
+    Ok(normalize(complement(&expect_char_set(cs)?.0)))
+}
+
+pub fn char_set_intersection(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    let (a, b) = (expect_char_set(a)?, expect_char_set(b)?);
+    Ok(normalize(intersect(&a.0, &b.0)))
+}
+
+pub fn char_set_difference(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    let (a, b) = (expect_char_set(a)?, expect_char_set(b)?);
+    Ok(normalize(intersect(&a.0, &complement(&b.0))))
+}
+
+fn complement(ranges: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut out = vec![];
+    let mut next = 0;
+    for &(lo, hi) in ranges {
+        if lo > next {
+            out.push((next, lo - 1));
+        }
+        next = hi + 1;
+    }
+    if next <= MAX_CODE_POINT {
+        out.push((next, MAX_CODE_POINT));
+    }
+    out
+}
+
+fn intersect(a: &[(u32, u32)], b: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut out = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let lo = a[i].0.max(b[j].0);
+        let hi = a[i].1.min(b[j].1);
+        if lo <= hi {
+            out.push((lo, hi));
+        }
+        // drop whichever range ends first; it can't overlap anything further
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    out
+}
+
+#[test]
+fn classification_beyond_ascii() {
+    let c = Scm::from_char;
+    assert!(char_alphabetic(c('λ')).unwrap() && !char_alphabetic(c('1')).unwrap());
+    assert!(char_whitespace(c('\u{3000}')).unwrap());
+    assert_eq!(char_upcase(c('ä')).unwrap(), c('Ä'));
+    assert_eq!(char_upcase(c('ß')).unwrap(), c('ß'));
+    assert_eq!(char_foldcase(c('Σ')).unwrap(), c('σ'));
+    assert_eq!(digit_value(c('7')).unwrap(), Some(7));
+    assert_eq!(digit_value(c('٣')).unwrap(), Some(3));
+    assert_eq!(digit_value(c('x')).unwrap(), None);
+    assert_eq!(char_numeric(Scm::from_int(1)).unwrap_err().to_string(), "expected character, got integer");
+}
+
+#[test]
+fn char_set_algebra() {
+    let lower = make_char_set(vec!['a'..='z']);
+    let vowels = string_to_char_set("aeiou");
+    let consonants = char_set_difference(lower, vowels).unwrap();
+    assert!(char_set_contains(consonants, 'b').unwrap());
+    assert!(!char_set_contains(consonants, 'e').unwrap());
+    assert!(!char_set_contains(consonants, 'B').unwrap());
+
+    let not_lower = char_set_complement(lower).unwrap();
+    assert!(char_set_contains(not_lower, '\u{10FFFF}').unwrap() && !char_set_contains(not_lower, 'q').unwrap());
+    let everything = char_set_union(lower, not_lower).unwrap();
+    assert!(char_set_contains(everything, '\0').unwrap());
+    assert!(!char_set_contains(char_set_intersection(lower, not_lower).unwrap(), 'a').unwrap());
+}
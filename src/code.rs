@@ -0,0 +1,114 @@
+//! Code objects: compiled procedures as first-class values, for a bytecode
+//! VM to store in environments and closures like any other value.
+//!
+//! The instructions are plain bytes whose meaning is up to the VM. Constants
+//! live in an ordinary vector, so that everything an instruction can refer to
+//! is reachable from the code object through normal values. The debug info
+//! maps instruction offsets to the source location they were compiled from.
+
+use crate::heap::{self, HeapObject, Kind};
+use crate::syntax::SourceLocation;
+use crate::{Scm, ScmKind, TypeError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Arity {
+    pub required: usize,
+    // takes any number of further arguments as a list
+    pub rest: bool,
+}
+
+impl Arity {
+    pub fn accepts(&self, n: usize) -> bool {
+        n == self.required || (self.rest && n > self.required)
+    }
+}
+
+pub(crate) struct CodeObject {
+    name: Scm,
+    arity: Arity,
+    code: Box<[u8]>,
+    constants: Scm,
+    // sorted by offset; each entry covers the instructions up to the next one
+    debug: Box<[(usize, SourceLocation)]>,
+}
+
+impl HeapObject for CodeObject {
+    const KIND: Kind = Kind::Code;
+}
+
+impl Scm {
+    pub fn is_code_object(&self) -> bool {
+        self.as_object::<CodeObject>().is_some()
+    }
+}
+
+fn expect_code(c: Scm) -> Result<&'static CodeObject, TypeError> {
+    c.as_object::<CodeObject>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Code, c))
+}
+
+// `name` is a symbol, or #f for anonymous procedures.
+pub fn make_code_object(
+    name: Scm,
+    arity: Arity,
+    code: Vec<u8>,
+    constants: Vec<Scm>,
+    mut debug: Vec<(usize, SourceLocation)>,
+) -> Scm {
+    debug.sort_by_key(|&(offset, _)| offset);
+    let code = CodeObject {
+        name,
+        arity,
+        code: code.into_boxed_slice(),
+        constants: Scm::vector(constants),
+        debug: debug.into_boxed_slice(),
+    };
+    Scm::from_object(heap::leak(code))
+}
+
+pub fn code_name(c: Scm) -> Result<Scm, TypeError> {
+    expect_code(c).map(|c| c.name)
+}
+
+pub fn code_arity(c: Scm) -> Result<Arity, TypeError> {
+    expect_code(c).map(|c| c.arity)
+}
+
+pub fn code_bytes(c: Scm) -> Result<&'static [u8], TypeError> {
+    expect_code(c).map(|c| &*c.code)
+}
+
+// The constant vector.
+pub fn code_constants(c: Scm) -> Result<Scm, TypeError> {
+    expect_code(c).map(|c| c.constants)
+}
+
+// Where the instruction at `offset` came from, if the compiler recorded it.
+pub fn code_location(c: Scm, offset: usize) -> Result<Option<&'static SourceLocation>, TypeError> {
+    let debug = &expect_code(c)?.debug;
+    let i = debug.partition_point(|&(start, _)| start <= offset);
+    Ok(i.checked_sub(1).map(|i| &debug[i].1))
+}
+
+#[test]
+fn code_objects_are_values() {
+    let at = |line| SourceLocation { file: Some("fact.scm".into()), line, column: 1 };
+    let code = make_code_object(
+        Scm::symbol("fact"),
+        Arity { required: 1, rest: false },
+        vec![0x01, 0x00, 0x02, 0x10, 0x01],
+        vec![Scm::from_int(1), Scm::symbol("*")],
+        vec![(3, at(2)), (0, at(1))],
+    );
+    assert!(code.is_code_object() && code.kind() == ScmKind::Code);
+    assert_eq!(code.to_string(), "#<code fact>");
+    assert_eq!(code_bytes(code).unwrap()[3], 0x10);
+    assert_eq!(code_constants(code).unwrap().as_vector().unwrap()[1], Scm::symbol("*"));
+    assert!(code_arity(code).unwrap().accepts(1) && !code_arity(code).unwrap().accepts(2));
+    assert_eq!(code_location(code, 2).unwrap().unwrap().line, 1);
+    assert_eq!(code_location(code, 4).unwrap().unwrap().to_string(), "fact.scm:2:1");
+
+    let anonymous = make_code_object(Scm::FALSE, Arity { required: 0, rest: true }, vec![], vec![], vec![]);
+    assert_eq!(anonymous.to_string(), "#<code>");
+    assert_eq!(code_location(anonymous, 0).unwrap(), None);
+    assert_eq!(code_name(Scm::NIL).unwrap_err().to_string(), "expected code object, got empty list");
+}
@@ -0,0 +1,112 @@
+//! Escape-only continuations: `call/cc` restricted to jumping out of the
+//! dynamic extent of the call that created the continuation, which is enough
+//! for early exits, generators built on threads, and exception-like control
+//! flow.
+//!
+//! `invoke` unwinds the Rust stack up to the matching `call_with_escape`, so
+//! destructors run and locks are released on the way. That makes escapes
+//! unavailable where panics abort (as on wasm32 by default). A continuation
+//! is one-shot in the sense that it is dead once its extent has been left,
+//! however that happened.
+
+use std::convert::Infallible;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{self, ThreadId};
+use crate::heap::{self, HeapObject, Kind};
+use crate::lock::Lock;
+use crate::{Scm, ScmKind, TypeError};
+
+pub(crate) struct Continuation {
+    live: Lock<bool>,
+    // unwinding can't cross threads
+    thread: ThreadId,
+}
+
+impl HeapObject for Continuation {
+    const KIND: Kind = Kind::Continuation;
+}
+
+// The unwinding payload, caught by the `call_with_escape` that made `target`.
+struct Escape {
+    target: Scm,
+    value: Scm,
+}
+
+// Escapes are only ever caught on the thread that started them.
+#[cfg(not(feature = "sync"))]
+unsafe impl Send for Escape {}
+
+// Ends the extent of a continuation, also when leaving it by unwinding.
+struct Extent(&'static Continuation);
+
+impl Drop for Extent {
+    fn drop(&mut self) {
+        *self.0.live.lock() = false;
+    }
+}
+
+impl Scm {
+    pub fn is_continuation(&self) -> bool {
+        self.as_object::<Continuation>().is_some()
+    }
+}
+
+// Calls `f` with a fresh continuation. Invoking it while `f` runs makes
+// `call_with_escape` return the value passed to `invoke`.
+pub fn call_with_escape<E>(f: impl FnOnce(Scm) -> Result<Scm, E>) -> Result<Scm, E> {
+    let obj = heap::leak(Continuation { live: Lock::new(true), thread: thread::current().id() });
+    let k = Scm::from_object(obj);
+    let extent = Extent(&obj.body);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(k)));
+    drop(extent);
+    match result {
+        Ok(result) => result,
+        Err(payload) => match payload.downcast::<Escape>() {
+            Ok(escape) if escape.target == k => Ok(escape.value),
+            // an escape to an outer continuation, or a real panic
+            Ok(escape) => panic::resume_unwind(escape),
+            Err(payload) => panic::resume_unwind(payload),
+        },
+    }
+}
+
+// Returns `value` from the `call_with_escape` that made `k`.
+//
+// Panics if that call has already returned, or if called from another thread.
+pub fn invoke(k: Scm, value: Scm) -> Result<Infallible, TypeError> {
+    let obj = k.as_object::<Continuation>().ok_or_else(|| TypeError::new(ScmKind::Continuation, k))?;
+    assert!(*obj.live.lock(), "continuation invoked outside of its extent");
+    assert!(obj.thread == thread::current().id(), "continuation invoked from another thread");
+    panic::resume_unwind(Box::new(Escape { target: k, value }))
+}
+
+#[test]
+fn escapes_unwind_to_their_call() {
+    let find_first_negative = |items: &[i64]| {
+        call_with_escape(|k| {
+            for &i in items {
+                if i < 0 {
+                    invoke(k, Scm::from_int(i))?;
+                }
+            }
+            Ok::<_, TypeError>(Scm::FALSE)
+        })
+    };
+    assert_eq!(find_first_negative(&[1, -2, -3]), Ok(Scm::from_int(-2)));
+    assert_eq!(find_first_negative(&[1, 2]), Ok(Scm::FALSE));
+
+    // escaping from an inner call to an outer continuation skips the inner one
+    let mut inner_finished = false;
+    let outer = call_with_escape(|outer| {
+        let inner = call_with_escape(|_| invoke(outer, Scm::symbol("out")).map(|_| Scm::NIL));
+        inner_finished = true;
+        inner
+    });
+    assert_eq!(outer, Ok(Scm::symbol("out")));
+    assert!(!inner_finished);
+
+    let dead = call_with_escape(Ok::<_, TypeError>).unwrap();
+    assert!(dead.is_continuation());
+    assert!(panic::catch_unwind(|| invoke(dead, Scm::NIL)).is_err());
+    assert_eq!(invoke(Scm::NIL, Scm::NIL).unwrap_err().to_string(), "expected continuation, got empty list");
+}
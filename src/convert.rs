@@ -0,0 +1,338 @@
+//! Converting Rust data to Scheme data and back, for embedders that pass
+//! configuration and results across the boundary.
+//!
+//! `ToScm` and `FromScm` are implemented for the basic types, for vectors (as
+//! lists) and for hash maps (as persistent maps), and they can be derived for
+//! structs and enums with the `derive` feature. Derived impls use these
+//! representations, with names in kebab-case (`max_depth` becomes
+//! `max-depth`, `HttpServer` becomes `http-server`):
+//!
+//! * structs with named fields are alists, `((name . "x") (max-depth . 3))`;
+//!   reading ignores the order of the entries and any extra ones
+//! * tuple structs are lists of their fields, except that a struct with a
+//!   single field is converted like the field itself
+//! * unit structs are the empty list
+//! * enum variants are tagged lists, `(circle 2.0)`, `(rect (w . 1) (h . 2))`
+//!   or `(none)`
+//!
+//! `#[scm(record)]` turns a struct into a record instead: a vector tagged with
+//! the name of the type, `#(point 1 2)`. `#[scm(rename = "...")]` overrides
+//! the name of a type, a field or a variant.
+//!
+//! ```ignore
+//! #[derive(ToScm, FromScm)]
+//! struct Config {
+//!     name: String,
+//!     #[scm(rename = "depth")]
+//!     max_depth: u32,
+//!     mode: Mode,
+//! }
+//!
+//! #[derive(ToScm, FromScm)]
+//! enum Mode {
+//!     Fast,
+//!     Careful { retries: u8 },
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, Hash};
+use crate::bigint::BigInt;
+use crate::hamt::{make_map, map_assoc, map_entries};
+use crate::lists::vec_to_list;
+use crate::num::make_integer;
+use crate::{cons, ConversionError, Scm, ScmKind, TypeError};
+
+#[cfg(feature = "derive")]
+pub use scm_repr_derive::{FromScm, ToScm};
+
+pub trait ToScm {
+    fn to_scm(&self) -> Scm;
+}
+
+pub trait FromScm: Sized {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError>;
+}
+
+impl ToScm for Scm {
+    fn to_scm(&self) -> Scm {
+        *self
+    }
+}
+
+impl FromScm for Scm {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        Ok(x)
+    }
+}
+
+impl ToScm for bool {
+    fn to_scm(&self) -> Scm {
+        Scm::from_bool(*self)
+    }
+}
+
+impl FromScm for bool {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        Ok(x.expect_bool()?)
+    }
+}
+
+impl ToScm for char {
+    fn to_scm(&self) -> Scm {
+        Scm::from_char(*self)
+    }
+}
+
+impl FromScm for char {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        Ok(x.expect_char()?)
+    }
+}
+
+macro_rules! integers {
+    ($($t:ty),*) => {
+        $(impl ToScm for $t {
+            fn to_scm(&self) -> Scm {
+                make_integer(BigInt::from_i128(*self as i128))
+            }
+        }
+
+        impl FromScm for $t {
+            fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+                // through i128, which holds every value of every type here
+                let i = match x.as_bignum() {
+                    Some(i) => i.to_i128().ok_or(ConversionError::OutOfRange(x))?,
+                    None => x.expect_integer()? as i128,
+                };
+                <$t>::try_from(i).map_err(|_| ConversionError::OutOfRange(x))
+            }
+        })*
+    };
+}
+
+integers!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl ToScm for f64 {
+    fn to_scm(&self) -> Scm {
+        Scm::from_f64(*self)
+    }
+}
+
+// Exact integers are accepted too, so that `3` works where a float is expected.
+impl FromScm for f64 {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        match (x.as_f64(), x.as_integer(), x.as_bignum()) {
+            (Some(f), _, _) => Ok(f),
+            (_, Some(i), _) => Ok(i as f64),
+            (_, _, Some(i)) => Ok(i.to_f64()),
+            _ => Err(TypeError::new(ScmKind::Flonum, x).into()),
+        }
+    }
+}
+
+impl ToScm for f32 {
+    fn to_scm(&self) -> Scm {
+        Scm::from_f64(*self as f64)
+    }
+}
+
+impl FromScm for f32 {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        f64::from_scm(x).map(|f| f as f32)
+    }
+}
+
+impl ToScm for str {
+    fn to_scm(&self) -> Scm {
+        Scm::string(self)
+    }
+}
+
+impl ToScm for String {
+    fn to_scm(&self) -> Scm {
+        Scm::string(self)
+    }
+}
+
+impl FromScm for String {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        Ok(x.expect_str()?.to_owned())
+    }
+}
+
+impl<T: ToScm + ?Sized> ToScm for &T {
+    fn to_scm(&self) -> Scm {
+        (**self).to_scm()
+    }
+}
+
+impl<T: ToScm + ?Sized> ToScm for Box<T> {
+    fn to_scm(&self) -> Scm {
+        (**self).to_scm()
+    }
+}
+
+impl<T: FromScm> FromScm for Box<T> {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        T::from_scm(x).map(Box::new)
+    }
+}
+
+// `None` is #f, so `Some(false)` comes back as `None`.
+impl<T: ToScm> ToScm for Option<T> {
+    fn to_scm(&self) -> Scm {
+        self.as_ref().map_or(Scm::FALSE, T::to_scm)
+    }
+}
+
+impl<T: FromScm> FromScm for Option<T> {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        if x == Scm::FALSE {
+            Ok(None)
+        } else {
+            T::from_scm(x).map(Some)
+        }
+    }
+}
+
+// Slices and vectors are lists.
+impl<T: ToScm> ToScm for [T] {
+    fn to_scm(&self) -> Scm {
+        self.iter().rev().fold(Scm::NIL, |list, x| cons(x.to_scm(), list))
+    }
+}
+
+impl<T: ToScm> ToScm for Vec<T> {
+    fn to_scm(&self) -> Scm {
+        self[..].to_scm()
+    }
+}
+
+impl<T: FromScm> FromScm for Vec<T> {
+    fn from_scm(mut x: Scm) -> Result<Self, ConversionError> {
+        let mut items = vec![];
+        while !x.is_nil() {
+            let &(car, cdr) = x.expect_pair()?;
+            items.push(T::from_scm(car)?);
+            x = cdr;
+        }
+        Ok(items)
+    }
+}
+
+// Hash maps are persistent maps. Keys that convert to equal values end up as
+// one entry.
+impl<K: ToScm, V: ToScm, S> ToScm for HashMap<K, V, S> {
+    fn to_scm(&self) -> Scm {
+        self.iter().fold(make_map(), |m, (k, v)| map_assoc(m, k.to_scm(), v.to_scm()).expect("a map"))
+    }
+}
+
+impl<K: FromScm + Eq + Hash, V: FromScm, S: BuildHasher + Default> FromScm for HashMap<K, V, S> {
+    fn from_scm(x: Scm) -> Result<Self, ConversionError> {
+        map_entries(x)?.map(|(k, v)| Ok((K::from_scm(k)?, V::from_scm(v)?))).collect()
+    }
+}
+
+impl<K: ToScm, V: ToScm, S> From<HashMap<K, V, S>> for Scm {
+    fn from(m: HashMap<K, V, S>) -> Self {
+        m.to_scm()
+    }
+}
+
+impl<K: FromScm + Eq + Hash, V: FromScm, S: BuildHasher + Default> TryFrom<Scm> for HashMap<K, V, S> {
+    type Error = ConversionError;
+
+    fn try_from(x: Scm) -> Result<Self, ConversionError> {
+        Self::from_scm(x)
+    }
+}
+
+// The building blocks of derived impls, which hand-written ones can use as
+// well.
+
+pub fn list(items: &[Scm]) -> Scm {
+    vec_to_list(items)
+}
+
+pub fn alist(entries: &[(&str, Scm)]) -> Scm {
+    entries.iter().rev().fold(Scm::NIL, |list, &(name, value)| cons(cons(Scm::symbol(name), value), list))
+}
+
+pub fn record(name: &str, fields: &[Scm]) -> Scm {
+    let mut items = vec![Scm::symbol(name)];
+    items.extend_from_slice(fields);
+    Scm::vector(items)
+}
+
+// The value of the first entry named `name`.
+pub fn field(mut alist: Scm, name: &'static str) -> Result<Scm, ConversionError> {
+    while !alist.is_nil() {
+        let &(entry, rest) = alist.expect_pair()?;
+        let &(key, value) = entry.expect_pair()?;
+        if key.as_symbol() == Some(name) {
+            return Ok(value)
+        }
+        alist = rest;
+    }
+    Err(ConversionError::MissingField(name))
+}
+
+// The elements of a proper list of exactly `n` elements.
+pub fn elements(list: Scm, n: usize) -> Result<Vec<Scm>, ConversionError> {
+    let items = Vec::<Scm>::from_scm(list)?;
+    if items.len() == n {
+        Ok(items)
+    } else {
+        Err(ConversionError::Length(list))
+    }
+}
+
+// The fields of a record of type `name` with `n` fields.
+pub fn record_fields(x: Scm, name: &str, n: usize) -> Result<Vec<Scm>, ConversionError> {
+    match x.expect_vector()? {
+        [tag, fields @ ..] if tag.as_symbol() == Some(name) && fields.len() == n => Ok(fields.to_vec()),
+        [tag, ..] if tag.as_symbol() == Some(name) => Err(ConversionError::Length(x)),
+        [tag, ..] => Err(ConversionError::UnknownTag(*tag)),
+        [] => Err(ConversionError::Length(x)),
+    }
+}
+
+// The tag of a tagged list and the rest of the list.
+pub fn tagged(x: Scm) -> Result<(Scm, Scm), ConversionError> {
+    let &(tag, rest) = x.expect_pair()?;
+    tag.expect_symbol()?;
+    Ok((tag, rest))
+}
+
+#[test]
+fn basic_values_round_trip() {
+    fn round_trip<T: ToScm + FromScm>(x: T) -> T {
+        T::from_scm(x.to_scm()).unwrap()
+    }
+
+    assert_eq!(round_trip(u64::MAX >> 1), u64::MAX >> 1);
+    assert_eq!(round_trip(u64::MAX), u64::MAX);
+    assert_eq!(round_trip(usize::MAX), usize::MAX);
+    assert_eq!(round_trip(i64::MIN), i64::MIN);
+    assert!(matches!(u64::from_scm((-1i8).to_scm()), Err(ConversionError::OutOfRange(_))));
+    assert!(matches!(i64::from_scm(u64::MAX.to_scm()), Err(ConversionError::OutOfRange(_))));
+    assert_eq!(round_trip(-3i8), -3);
+    assert_eq!(round_trip(vec![Some("a".to_string()), None]), [Some("a".to_string()), None]);
+    assert_eq!(round_trip(vec![1.5f64]), [1.5]);
+    assert_eq!(f64::from_scm(Scm::from_int(3)), Ok(3.0));
+    assert_eq!(u8::from_scm(Scm::from_int(256)), Err(ConversionError::OutOfRange(Scm::from_int(256))));
+    let ages: HashMap<String, u8> = vec![("ann".to_string(), 31), ("bob".to_string(), 27)].into_iter().collect();
+    assert_eq!(HashMap::try_from(Scm::from(ages.clone())), Ok(ages));
+
+    let config = alist(&[("name", Scm::string("x")), ("depth", Scm::from_int(3))]);
+    assert_eq!(u32::from_scm(field(config, "depth").unwrap()), Ok(3));
+    assert_eq!(field(config, "mode"), Err(ConversionError::MissingField("mode")));
+    let short = list(&[Scm::NIL]);
+    assert_eq!(elements(short, 2), Err(ConversionError::Length(short)));
+    let point = record("point", &[Scm::from_int(1), Scm::from_int(2)]);
+    assert_eq!(record_fields(point, "point", 2), Ok(vec![Scm::from_int(1), Scm::from_int(2)]));
+    assert!(matches!(record_fields(point, "size", 2), Err(ConversionError::UnknownTag(_))));
+}
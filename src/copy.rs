@@ -0,0 +1,105 @@
+//! Deep copies of data, which share nothing with the original but symbols.
+//!
+//! A copy has the same shape as the original, down to sharing: an object
+//! that is reachable along two paths is copied once, and cycles through
+//! boxes are copied as cycles. Pairs, vectors, multiple values, boxes,
+//! strings and heap-allocated numbers are copied. Objects that aren't plain
+//! data, like procedures and ports, are shared with the original instead.
+//!
+//! `deep_copy` puts the copy on the ordinary heap, where it outlives any
+//! region the original was allocated in. `deep_copy_into` puts it into a
+//! region, to be freed with it.
+
+use std::collections::HashMap;
+use crate::boxes::{make_box, set_box, unbox};
+use crate::heap::{self, Region};
+use crate::num::{make_integer, make_rational};
+use crate::{cons, Scm, ScmView};
+
+impl Scm {
+    pub fn deep_copy(&self) -> Scm {
+        let copy = copy(*self);
+        heap::relocate(copy, 0, None);
+        copy
+    }
+
+    pub fn deep_copy_into(&self, region: &Region) -> Scm {
+        region.claim(copy(*self))
+    }
+}
+
+fn copy(root: Scm) -> Scm {
+    // by the original; a pair or vector only after all its items
+    let mut copies: HashMap<Scm, Scm> = HashMap::new();
+    let mut boxes = vec![];
+    let mut todo = vec![(root, false)];
+    while let Some((x, items_copied)) = todo.pop() {
+        if x.is_immediate() || copies.contains_key(&x) {
+            continue
+        }
+        let copied = |y: &Scm| if y.is_immediate() { *y } else { copies[y] };
+        let copy = match x.classify() {
+            ScmView::Pair(&(car, cdr)) if items_copied => cons(copied(&car), copied(&cdr)),
+            ScmView::Vector(items) if items_copied => Scm::vector(items.iter().map(copied).collect()),
+            ScmView::Values(items) if items_copied => crate::values::values(items.iter().map(copied).collect()),
+            ScmView::Pair(&(car, cdr)) => {
+                todo.extend([(x, true), (cdr, false), (car, false)]);
+                continue
+            }
+            ScmView::Vector(items) | ScmView::Values(items) => {
+                todo.push((x, true));
+                todo.extend(items.iter().rev().map(|&item| (item, false)));
+                continue
+            }
+            // the box is made first and filled in at the end, which is what
+            // lets cycles through it be copied
+            ScmView::Box(contents) => {
+                todo.push((contents, false));
+                boxes.push(x);
+                make_box(Scm::NIL)
+            }
+            ScmView::String(s) => Scm::string(s),
+            ScmView::Flonum(f) => Scm::from_f64(f),
+            ScmView::Bignum(i) => make_integer(i.clone()),
+            ScmView::Rational(n, d) => make_rational(n.clone(), d.clone()).unwrap(),
+            _ => x,
+        };
+        copies.insert(x, copy);
+    }
+    for original in boxes {
+        let contents = unbox(original).unwrap();
+        let contents = if contents.is_immediate() { contents } else { copies[&contents] };
+        set_box(copies[&original], contents).unwrap();
+    }
+    if root.is_immediate() { root } else { copies[&root] }
+}
+
+#[test]
+fn copies_preserve_sharing_and_cycles() {
+    use crate::order::equal;
+    let shared = Scm::string("a rather long shared string");
+    let b = make_box(Scm::NIL);
+    let cycle = cons(b, Scm::from_f64(1.5));
+    set_box(b, cycle).unwrap();
+    let original = Scm::vector(vec![cons(shared, shared), cycle, Scm::symbol("sym"), Scm::from_int(3)]);
+
+    let copy = original.deep_copy();
+    assert!(equal(copy.as_vector().unwrap()[0], original.as_vector().unwrap()[0]));
+    let &(a, d) = copy.as_vector().unwrap()[0].as_pair().unwrap();
+    assert!(a == d && a != shared);
+    let copied_cycle = copy.as_vector().unwrap()[1];
+    let &(copied_box, _) = copied_cycle.as_pair().unwrap();
+    assert!(copied_box != b && unbox(copied_box).unwrap() == copied_cycle);
+    assert_eq!(copy.as_vector().unwrap()[2], Scm::symbol("sym"));
+
+    let count = heap::AllocationCount::start();
+    let kept = unsafe {
+        heap::scoped(|outer| {
+            let list = heap::scoped(|_| cons(Scm::from_int(1), cons(Scm::from_int(2), Scm::NIL)).deep_copy_into(outer));
+            assert_eq!(outer.len(), 2);
+            list.deep_copy()
+        })
+    };
+    assert_eq!(kept.to_string(), "(1 2)");
+    assert_eq!(count.outstanding(), [(heap::Kind::Pair, 2)]);
+}
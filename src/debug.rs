@@ -0,0 +1,200 @@
+//! Tools for looking at values while debugging the representation or code
+//! built on it.
+//!
+//! `dump_dot` draws the structure reachable from a value as a box-and-pointer
+//! diagram in Graphviz's DOT language:
+//!
+//! ```text
+//! dump_dot(x, File::create("x.dot")?)?;
+//! $ dot -Tsvg x.dot > x.svg
+//! ```
+//!
+//! Every heap object is one node, however many references to it there are,
+//! so shared tails and cycles through boxes show up as such. Immediate values
+//! are written into the field that holds them.
+//!
+//! The `Debug` format of values decodes their tag rather than printing the
+//! raw word: `Int(5)`, `Nil`, `Char('a')` or `Pair(0x55d0c3a0)`, where other
+//! heap objects are named after the kind in their header. `{:#?}` adds the
+//! raw word, the tag bits and the header, and shows the fields of a heap
+//! object one level deep.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use crate::{Scm, ScmView, TAG_MASK};
+
+// Long atoms are cut off, so that a big string doesn't swamp the picture.
+const MAX_LABEL: usize = 40;
+
+fn node(x: Scm) -> String {
+    format!("n{:x}", x.to_raw())
+}
+
+// In record fields, the characters that delimit fields have to be escaped
+// as well.
+fn label(x: Scm, record: bool) -> String {
+    let text = x.to_string();
+    let mut label = String::new();
+    for (i, c) in text.chars().enumerate() {
+        if i == MAX_LABEL {
+            label.push('…');
+            break
+        }
+        if matches!(c, '"' | '\\') || (record && matches!(c, '{' | '}' | '|' | '<' | '>')) {
+            label.push('\\');
+        }
+        label.push(c);
+    }
+    label
+}
+
+// The fields of a node drawn as a record, or None for an atom.
+fn fields(x: Scm) -> Option<Vec<Scm>> {
+    match x.classify() {
+        ScmView::Pair(&(car, cdr)) => Some(vec![car, cdr]),
+        ScmView::Vector(items) | ScmView::Values(items) => Some(items.to_vec()),
+        ScmView::Box(x) => Some(vec![x]),
+        _ => None,
+    }
+}
+
+// Writes the structure reachable from `x` as a DOT graph.
+pub fn dump_dot(x: Scm, mut w: impl Write) -> io::Result<()> {
+    writeln!(w, "digraph scm {{")?;
+    writeln!(w, "    node [fontname=monospace];")?;
+    let mut seen = HashSet::new();
+    let mut todo = vec![x];
+    seen.insert(x);
+    while let Some(x) = todo.pop() {
+        let fields = match fields(x) {
+            Some(fields) => fields,
+            None => {
+                writeln!(w, "    {} [shape=box, label=\"{}\"];", node(x), label(x, false))?;
+                continue
+            }
+        };
+        let cells: Vec<_> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, &field)| format!("<f{}> {}", i, if field.is_immediate() { label(field, true) } else { String::new() }))
+            .collect();
+        let kind = x.kind().to_string();
+        writeln!(w, "    {} [shape=record, tooltip=\"{}\", label=\"{}\"];", node(x), kind, cells.join("|"))?;
+        for (i, &field) in fields.iter().enumerate() {
+            if field.is_immediate() {
+                continue
+            }
+            writeln!(w, "    {}:f{} -> {};", node(x), i, node(field))?;
+            if seen.insert(field) {
+                todo.push(field);
+            }
+        }
+    }
+    writeln!(w, "}}")
+}
+
+// The name of an immediate and what it holds, as in `Int(5)`.
+fn immediate(x: Scm) -> (&'static str, Option<String>) {
+    match x.classify() {
+        ScmView::Nil => ("Nil", None),
+        ScmView::Boolean(true) => ("True", None),
+        ScmView::Boolean(false) => ("False", None),
+        ScmView::Eof => ("Eof", None),
+        ScmView::Char(c) => ("Char", Some(format!("{:?}", c))),
+        ScmView::Integer(i) => ("Int", Some(i.to_string())),
+        ScmView::String(s) => ("ShortStr", Some(format!("{:?}", s))),
+        _ => unreachable!(),
+    }
+}
+
+// Prints a value without recursing, whatever the formatter asks for.
+struct Brief(Scm);
+
+impl fmt::Debug for Brief {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let x = self.0;
+        match x.header() {
+            Some(header) => write!(f, "{:?}({:#x})", header.kind(), header as *const _ as usize),
+            None => match immediate(x) {
+                (name, Some(value)) => write!(f, "{}({})", name, value),
+                (name, None) => f.write_str(name),
+            },
+        }
+    }
+}
+
+impl fmt::Debug for Scm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let x = *self;
+        if !f.alternate() {
+            return Brief(x).fmt(f)
+        }
+        let raw = x.to_raw();
+        let header = match x.header() {
+            Some(header) => header,
+            None => {
+                let (name, value) = immediate(x);
+                let mut s = f.debug_struct(name);
+                if let Some(value) = value {
+                    s.field("value", &format_args!("{}", value));
+                }
+                return s.field("raw", &format_args!("{:#x}", raw)).field("tag", &format_args!("{:#05b}", raw & TAG_MASK)).finish()
+            }
+        };
+        let mut s = f.debug_struct(&format!("{:?}", header.kind()));
+        s.field("addr", &format_args!("{:#x}", header as *const _ as usize));
+        s.field("tag", &format_args!("{:#05b}", raw & TAG_MASK));
+        s.field("header", header);
+        match x.classify() {
+            ScmView::Pair(&(car, cdr)) => s.field("car", &Brief(car)).field("cdr", &Brief(cdr)),
+            ScmView::Vector(items) | ScmView::Values(items) => {
+                s.field("items", &items.iter().map(|&item| Brief(item)).collect::<Vec<_>>())
+            }
+            ScmView::Box(contents) => s.field("contents", &Brief(contents)),
+            ScmView::Symbol(name) => s.field("name", &name),
+            ScmView::String(text) => s.field("text", &text),
+            _ => s.field("value", &format_args!("{}", x)),
+        };
+        s.finish()
+    }
+}
+
+#[test]
+fn shared_structure_is_drawn_once() {
+    use crate::cons;
+
+    let tail = cons(Scm::string("a long shared string"), Scm::NIL);
+    let x = Scm::vector(vec![tail, cons(Scm::from_int(1), tail), Scm::from_int(2)]);
+    let mut dot = vec![];
+    dump_dot(x, &mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+
+    let nodes = dot.lines().filter(|line| line.contains(" [shape=")).count();
+    let edges = dot.lines().filter(|line| line.contains(" -> ")).count();
+    assert_eq!((nodes, edges), (4, 4));
+    assert!(dot.contains("label=\"<f0> |<f1> |<f2> 2\""));
+    assert!(dot.contains("label=\"\\\"a long shared string\\\"\""));
+
+    let mut dot = vec![];
+    dump_dot(Scm::from_int(7), &mut dot).unwrap();
+    assert!(String::from_utf8(dot).unwrap().contains("[shape=box, label=\"7\"]"));
+}
+
+#[test]
+fn debug_decodes_tags() {
+    assert_eq!(format!("{:?}", Scm::from_int(5)), "Int(5)");
+    assert_eq!(format!("{:?}", [Scm::NIL, Scm::TRUE, Scm::EOF]), "[Nil, True, Eof]");
+    assert_eq!(format!("{:?}", Scm::from_char('λ')), "Char('λ')");
+    assert_eq!(format!("{:?}", Scm::string("ab")), "ShortStr(\"ab\")");
+    assert_eq!(format!("{:#?}", Scm::from_int(5)), "Int {\n    value: 5,\n    raw: 0x29,\n    tag: 0b001,\n}");
+
+    let inner = crate::cons(Scm::from_int(2), Scm::NIL);
+    let x = crate::cons(Scm::from_int(1), inner);
+    let addr = |x: Scm| x.header().unwrap() as *const _ as usize;
+    assert_eq!(format!("{:?}", x), format!("Pair({:#x})", addr(x)));
+    let pretty = format!("{:#?}", x);
+    assert!(pretty.starts_with(&format!("Pair {{\n    addr: {:#x},\n    tag: 0b010,\n", addr(x))));
+    assert!(pretty.ends_with(&format!("    car: Int(1),\n    cdr: Pair({:#x}),\n}}", addr(inner))));
+    assert!(format!("{:?}", crate::boxes::make_box(x)).starts_with("Box(0x"));
+}
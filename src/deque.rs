@@ -0,0 +1,137 @@
+//! Persistent double-ended queues (Okasaki's banker's deque).
+//!
+//! A deque is a front list and a reversed rear list. Pushing and popping at
+//! either end is a cons or a cdr; whenever one list grows more than `C` times
+//! as long as the other, both are rebalanced to half of the elements each.
+//! Rebalancing is O(n) but happens only every O(n) operations, so all
+//! operations are amortized O(1).
+
+use crate::heap::{self, HeapObject, Kind};
+use crate::lists::vec_to_list;
+use crate::{cons, Scm, ScmKind, TypeError};
+
+const C: usize = 3;
+
+pub(crate) struct Deque {
+    front: Scm,
+    front_len: usize,
+    rear: Scm,
+    rear_len: usize,
+}
+
+impl HeapObject for Deque {
+    const KIND: Kind = Kind::Deque;
+}
+
+impl Deque {
+    fn items(&self) -> Vec<Scm> {
+        let mut items = list_items(self.front);
+        let mut rear = list_items(self.rear);
+        rear.reverse();
+        items.extend(rear);
+        items
+    }
+}
+
+fn list_items(mut list: Scm) -> Vec<Scm> {
+    let mut items = vec![];
+    while let Some(&(x, d)) = list.as_pair() {
+        items.push(x);
+        list = d;
+    }
+    items
+}
+
+// Allocates a deque, rebalancing the two lists first if necessary.
+fn alloc(front: Scm, front_len: usize, rear: Scm, rear_len: usize) -> Scm {
+    let n = front_len + rear_len;
+    let deque = if front_len > C * rear_len + 1 || rear_len > C * front_len + 1 {
+        let items = Deque { front, front_len, rear, rear_len }.items();
+        let (front, rear) = items.split_at(n / 2);
+        let mut rear = rear.to_vec();
+        rear.reverse();
+        Deque { front: vec_to_list(front), front_len: front.len(), rear: vec_to_list(&rear), rear_len: rear.len() }
+    } else {
+        Deque { front, front_len, rear, rear_len }
+    };
+    Scm::from_object(heap::leak(deque))
+}
+
+impl Scm {
+    pub fn is_deque(&self) -> bool {
+        self.as_object::<Deque>().is_some()
+    }
+}
+
+fn expect_deque(q: Scm) -> Result<&'static Deque, TypeError> {
+    q.as_object::<Deque>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Deque, q))
+}
+
+pub fn make_deque() -> Scm {
+    alloc(Scm::NIL, 0, Scm::NIL, 0)
+}
+
+pub fn deque_len(q: Scm) -> Result<usize, TypeError> {
+    expect_deque(q).map(|d| d.front_len + d.rear_len)
+}
+
+// The elements from front to back.
+pub fn deque_items(q: Scm) -> Result<Vec<Scm>, TypeError> {
+    expect_deque(q).map(Deque::items)
+}
+
+pub fn push_front(q: Scm, x: Scm) -> Result<Scm, TypeError> {
+    let d = expect_deque(q)?;
+    Ok(alloc(cons(x, d.front), d.front_len + 1, d.rear, d.rear_len))
+}
+
+pub fn push_back(q: Scm, x: Scm) -> Result<Scm, TypeError> {
+    let d = expect_deque(q)?;
+    Ok(alloc(d.front, d.front_len, cons(x, d.rear), d.rear_len + 1))
+}
+
+// The first element and a deque of the rest, or `None` if the deque is empty.
+pub fn pop_front(q: Scm) -> Result<Option<(Scm, Scm)>, TypeError> {
+    let d = expect_deque(q)?;
+    Ok(match (d.front.as_pair(), d.rear.as_pair()) {
+        (Some(&(x, rest)), _) => Some((x, alloc(rest, d.front_len - 1, d.rear, d.rear_len))),
+        // the balance invariant leaves at most one element in the rear then
+        (None, Some(&(x, _))) => Some((x, make_deque())),
+        (None, None) => None,
+    })
+}
+
+// The last element and a deque of the rest, or `None` if the deque is empty.
+pub fn pop_back(q: Scm) -> Result<Option<(Scm, Scm)>, TypeError> {
+    let d = expect_deque(q)?;
+    Ok(match (d.rear.as_pair(), d.front.as_pair()) {
+        (Some(&(x, rest)), _) => Some((x, alloc(d.front, d.front_len, rest, d.rear_len - 1))),
+        (None, Some(&(x, _))) => Some((x, make_deque())),
+        (None, None) => None,
+    })
+}
+
+#[test]
+fn deques_work_at_both_ends() {
+    let mut q = make_deque();
+    for i in 0..100 {
+        q = push_back(q, Scm::from_int(i)).unwrap();
+    }
+    let full = q;
+    for i in 0..50 {
+        let (x, rest) = pop_front(q).unwrap().unwrap();
+        assert_eq!(x, Scm::from_int(i));
+        q = rest;
+    }
+    for i in (50..100).rev() {
+        let (x, rest) = pop_back(q).unwrap().unwrap();
+        assert_eq!(x, Scm::from_int(i));
+        q = rest;
+    }
+    assert_eq!(pop_front(q).unwrap(), None);
+    assert_eq!(deque_len(full).unwrap(), 100);
+
+    let q = push_front(push_back(make_deque(), Scm::from_int(2)).unwrap(), Scm::from_int(1)).unwrap();
+    assert_eq!(q.to_string(), "#<deque 1 2>");
+    assert_eq!(pop_back(Scm::NIL).unwrap_err().to_string(), "expected deque, got empty list");
+}
@@ -0,0 +1,154 @@
+//! Environments: frames of variable bindings with a pointer to the enclosing
+//! frame.
+//!
+//! Most frames are small (the arguments of a procedure), and for those a
+//! linear scan over a vector beats hashing, since symbols compare by address.
+//! Frames that grow beyond a handful of bindings (like the global one)
+//! switch to a hash map.
+
+use std::collections::HashMap;
+use crate::heap::{self, HeapObject, Kind};
+use crate::lock::Lock;
+use crate::{EnvError, Scm, ScmKind, TypeError};
+
+const SMALL_FRAME: usize = 8;
+
+enum Bindings {
+    Small(Vec<(Scm, Scm)>),
+    Large(HashMap<Scm, Scm>),
+}
+
+impl Bindings {
+    fn get(&self, name: Scm) -> Option<Scm> {
+        match self {
+            Bindings::Small(items) => items.iter().find(|(n, _)| *n == name).map(|&(_, v)| v),
+            Bindings::Large(map) => map.get(&name).copied(),
+        }
+    }
+
+    fn get_mut(&mut self, name: Scm) -> Option<&mut Scm> {
+        match self {
+            Bindings::Small(items) => items.iter_mut().find(|(n, _)| *n == name).map(|(_, v)| v),
+            Bindings::Large(map) => map.get_mut(&name),
+        }
+    }
+
+    fn insert(&mut self, name: Scm, value: Scm) {
+        if let Some(slot) = self.get_mut(name) {
+            *slot = value;
+            return
+        }
+        match self {
+            Bindings::Small(items) if items.len() < SMALL_FRAME => items.push((name, value)),
+            Bindings::Small(items) => {
+                let mut map: HashMap<Scm, Scm> = items.drain(..).collect();
+                map.insert(name, value);
+                *self = Bindings::Large(map);
+            }
+            Bindings::Large(map) => {
+                map.insert(name, value);
+            }
+        }
+    }
+}
+
+pub(crate) struct Environment {
+    parent: Option<Scm>,
+    bindings: Lock<Bindings>,
+}
+
+impl HeapObject for Environment {
+    const KIND: Kind = Kind::Environment;
+}
+
+pub fn make_environment(parent: Option<Scm>) -> Result<Scm, TypeError> {
+    if let Some(p) = parent {
+        frame(p)?;
+    }
+    let env = Environment {
+        parent,
+        bindings: Lock::new(Bindings::Small(vec![])),
+    };
+    Ok(Scm::from_object(heap::leak(env)))
+}
+
+fn frame(env: Scm) -> Result<&'static Environment, TypeError> {
+    env.as_object::<Environment>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Environment, env))
+}
+
+fn check_symbol(name: Scm) -> Result<(), TypeError> {
+    name.expect_symbol().map(|_| ())
+}
+
+impl Scm {
+    pub fn is_environment(&self) -> bool {
+        self.as_object::<Environment>().is_some()
+    }
+}
+
+pub fn parent(env: Scm) -> Result<Option<Scm>, TypeError> {
+    Ok(frame(env)?.parent)
+}
+
+// Finds the innermost binding of `name`.
+pub fn lookup(env: Scm, name: Scm) -> Result<Scm, EnvError> {
+    check_symbol(name)?;
+    let mut env = Some(env);
+    while let Some(e) = env {
+        let f = frame(e)?;
+        if let Some(value) = f.bindings.lock().get(name) {
+            return Ok(value)
+        }
+        env = f.parent;
+    }
+    Err(EnvError::Unbound(name))
+}
+
+// Binds `name` in this frame, replacing an earlier binding in the same frame.
+pub fn define(env: Scm, name: Scm, value: Scm) -> Result<(), TypeError> {
+    check_symbol(name)?;
+    frame(env)?.bindings.lock().insert(name, value);
+    Ok(())
+}
+
+// Changes the innermost existing binding of `name`.
+pub fn set(env: Scm, name: Scm, value: Scm) -> Result<(), EnvError> {
+    check_symbol(name)?;
+    let mut env = Some(env);
+    while let Some(e) = env {
+        let f = frame(e)?;
+        if let Some(slot) = f.bindings.lock().get_mut(name) {
+            *slot = value;
+            return Ok(())
+        }
+        env = f.parent;
+    }
+    Err(EnvError::Unbound(name))
+}
+
+#[test]
+fn nested_frames() {
+    let global = make_environment(None).unwrap();
+    let x = Scm::symbol("x");
+    define(global, x, Scm::from_int(1)).unwrap();
+    for i in 0..20 {
+        define(global, Scm::symbol(&format!("g{}", i)), Scm::from_int(i)).unwrap();
+    }
+
+    let local = make_environment(Some(global)).unwrap();
+    assert_eq!(lookup(local, x).unwrap(), Scm::from_int(1));
+    define(local, x, Scm::from_int(2)).unwrap();
+    assert_eq!(lookup(local, x).unwrap(), Scm::from_int(2));
+    assert_eq!(lookup(global, x).unwrap(), Scm::from_int(1));
+
+    set(local, Scm::symbol("g7"), Scm::TRUE).unwrap();
+    assert_eq!(lookup(global, Scm::symbol("g7")).unwrap(), Scm::TRUE);
+    assert_eq!(lookup(global, Scm::symbol("g19")).unwrap(), Scm::from_int(19));
+
+    let y = Scm::symbol("y");
+    assert!(matches!(lookup(local, y), Err(EnvError::Unbound(s)) if s == y));
+    assert!(matches!(set(local, y, Scm::NIL), Err(EnvError::Unbound(_))));
+    assert!(define(local, Scm::from_int(1), Scm::NIL).is_err());
+    assert_eq!(parent(local).unwrap(), Some(global));
+    assert_eq!(local.to_string(), "#<environment>");
+}
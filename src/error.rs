@@ -0,0 +1,407 @@
+//! Typed accessors that report what went wrong instead of returning `None`,
+//! so interpreters can turn a failed access into a Scheme condition.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use crate::{Scm, ScmKind};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub expected: ScmKind,
+    pub actual: ScmKind,
+    pub value: Scm,
+}
+
+impl TypeError {
+    pub fn new(expected: ScmKind, value: Scm) -> Self {
+        TypeError {
+            expected,
+            actual: value.kind(),
+            value,
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {}, got {}", self.expected, self.actual)
+    }
+}
+
+impl Error for TypeError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NumError {
+    Type(TypeError),
+    DivisionByZero,
+    // infinities and NaNs have no exact counterpart
+    NotFinite,
+    OutOfRange,
+}
+
+impl From<TypeError> for NumError {
+    fn from(e: TypeError) -> Self {
+        NumError::Type(e)
+    }
+}
+
+impl fmt::Display for NumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumError::Type(e) => e.fmt(f),
+            NumError::DivisionByZero => f.write_str("division by zero"),
+            NumError::NotFinite => f.write_str("no exact representation for a non-finite number"),
+            NumError::OutOfRange => f.write_str("number out of range"),
+        }
+    }
+}
+
+impl Error for NumError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MutationError {
+    Type(TypeError),
+    // the object was frozen
+    Immutable(Scm),
+}
+
+impl From<TypeError> for MutationError {
+    fn from(e: TypeError) -> Self {
+        MutationError::Type(e)
+    }
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MutationError::Type(e) => e.fmt(f),
+            MutationError::Immutable(x) => write!(f, "cannot modify immutable {}", x.kind()),
+        }
+    }
+}
+
+impl Error for MutationError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    Type(TypeError),
+    // an integer that doesn't fit the Rust type
+    OutOfRange(Scm),
+    MissingField(&'static str),
+    // a list or record with too few or too many elements
+    Length(Scm),
+    // a tag that names no variant, or a record of another type
+    UnknownTag(Scm),
+}
+
+impl From<TypeError> for ConversionError {
+    fn from(e: TypeError) -> Self {
+        ConversionError::Type(e)
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionError::Type(e) => e.fmt(f),
+            ConversionError::OutOfRange(x) => write!(f, "{} is out of range", x),
+            ConversionError::MissingField(name) => write!(f, "missing field {}", name),
+            ConversionError::Length(x) => write!(f, "wrong number of elements in {}", x),
+            ConversionError::UnknownTag(x) => write!(f, "unknown tag {}", x),
+        }
+    }
+}
+
+impl Error for ConversionError {}
+
+#[derive(Debug)]
+pub enum PortError {
+    Type(TypeError),
+    NotAnInputPort,
+    NotAnOutputPort,
+    Closed,
+    Io(io::Error),
+}
+
+impl From<TypeError> for PortError {
+    fn from(e: TypeError) -> Self {
+        PortError::Type(e)
+    }
+}
+
+impl From<io::Error> for PortError {
+    fn from(e: io::Error) -> Self {
+        PortError::Io(e)
+    }
+}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PortError::Type(e) => e.fmt(f),
+            PortError::NotAnInputPort => f.write_str("not an input port"),
+            PortError::NotAnOutputPort => f.write_str("not an output port"),
+            PortError::Closed => f.write_str("port is closed"),
+            PortError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for PortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PortError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Port(PortError),
+    // the input ended in the middle of a datum
+    UnexpectedEof,
+    Syntax(String),
+    // lists, vectors, boxes and quotes nested deeper than `ReadOptions::max_depth`
+    TooDeep,
+}
+
+impl From<PortError> for ReadError {
+    fn from(e: PortError) -> Self {
+        ReadError::Port(e)
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Port(e) => e.fmt(f),
+            ReadError::UnexpectedEof => f.write_str("unexpected end of input"),
+            ReadError::Syntax(msg) => f.write_str(msg),
+            ReadError::TooDeep => f.write_str("data nested too deeply"),
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Port(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnvError {
+    Type(TypeError),
+    Unbound(Scm),
+}
+
+impl From<TypeError> for EnvError {
+    fn from(e: TypeError) -> Self {
+        EnvError::Type(e)
+    }
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnvError::Type(e) => e.fmt(f),
+            EnvError::Unbound(name) => write!(f, "unbound variable {}", name),
+        }
+    }
+}
+
+impl Error for EnvError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChannelError {
+    Type(TypeError),
+    // a value that can't be copied to another thread
+    NotTransferable(Scm),
+    // the other end was dropped
+    Disconnected,
+}
+
+impl From<TypeError> for ChannelError {
+    fn from(e: TypeError) -> Self {
+        ChannelError::Type(e)
+    }
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChannelError::Type(e) => e.fmt(f),
+            ChannelError::NotTransferable(x) => write!(f, "can't send {} to another thread", x),
+            ChannelError::Disconnected => f.write_str("channel is disconnected"),
+        }
+    }
+}
+
+impl Error for ChannelError {}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    // a value that has no snapshot representation, like a port
+    Unsupported(Scm),
+    // the input is not a snapshot, or it was cut short
+    Corrupt,
+    Io(io::Error),
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => SnapshotError::Corrupt,
+            _ => SnapshotError::Io(e),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Unsupported(x) => write!(f, "can't put {} into a snapshot", x),
+            SnapshotError::Corrupt => f.write_str("corrupt snapshot"),
+            SnapshotError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "interp")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    Type(TypeError),
+    Num(NumError),
+    Env(EnvError),
+    // a malformed special form
+    Syntax(Scm),
+    NotAProcedure(Scm),
+    // the procedure and the arguments it was called with
+    Arity(Scm, usize),
+}
+
+#[cfg(feature = "interp")]
+impl From<TypeError> for EvalError {
+    fn from(e: TypeError) -> Self {
+        EvalError::Type(e)
+    }
+}
+
+#[cfg(feature = "interp")]
+impl From<NumError> for EvalError {
+    fn from(e: NumError) -> Self {
+        EvalError::Num(e)
+    }
+}
+
+#[cfg(feature = "interp")]
+impl From<EnvError> for EvalError {
+    fn from(e: EnvError) -> Self {
+        EvalError::Env(e)
+    }
+}
+
+#[cfg(feature = "interp")]
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Type(e) => e.fmt(f),
+            EvalError::Num(e) => e.fmt(f),
+            EvalError::Env(e) => e.fmt(f),
+            EvalError::Syntax(x) => write!(f, "bad syntax {}", x),
+            EvalError::NotAProcedure(x) => write!(f, "not a procedure: {}", x),
+            EvalError::Arity(p, n) => write!(f, "{} called with {} arguments", p, n),
+        }
+    }
+}
+
+#[cfg(feature = "interp")]
+impl Error for EvalError {}
+
+#[cfg(feature = "sync")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ThreadError {
+    Type(TypeError),
+    // locking a mutex again from the thread that holds it
+    AlreadyLocked(Scm),
+    NotLocked(Scm),
+    AlreadyJoined(Scm),
+    Panicked(Scm),
+}
+
+#[cfg(feature = "sync")]
+impl From<TypeError> for ThreadError {
+    fn from(e: TypeError) -> Self {
+        ThreadError::Type(e)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl fmt::Display for ThreadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThreadError::Type(e) => e.fmt(f),
+            ThreadError::AlreadyLocked(m) => write!(f, "{} is already locked by this thread", m),
+            ThreadError::NotLocked(m) => write!(f, "{} is not locked by this thread", m),
+            ThreadError::AlreadyJoined(t) => write!(f, "{} was already joined", t),
+            ThreadError::Panicked(t) => write!(f, "{} panicked", t),
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl Error for ThreadError {}
+
+impl Scm {
+    pub fn expect_integer(&self) -> Result<i64, TypeError> {
+        self.as_integer().ok_or_else(|| TypeError::new(ScmKind::Integer, *self))
+    }
+
+    pub fn expect_char(&self) -> Result<char, TypeError> {
+        self.as_char().ok_or_else(|| TypeError::new(ScmKind::Char, *self))
+    }
+
+    pub fn expect_bool(&self) -> Result<bool, TypeError> {
+        self.as_bool().ok_or_else(|| TypeError::new(ScmKind::Boolean, *self))
+    }
+
+    pub fn expect_pair(&self) -> Result<&(Scm, Scm), TypeError> {
+        self.as_pair().ok_or_else(|| TypeError::new(ScmKind::Pair, *self))
+    }
+
+    pub fn expect_symbol(&self) -> Result<&str, TypeError> {
+        self.as_symbol().ok_or_else(|| TypeError::new(ScmKind::Symbol, *self))
+    }
+
+    pub fn expect_str(&self) -> Result<&str, TypeError> {
+        self.as_str().ok_or_else(|| TypeError::new(ScmKind::String, *self))
+    }
+
+    pub fn expect_vector(&self) -> Result<&[Scm], TypeError> {
+        self.as_vector().ok_or_else(|| TypeError::new(ScmKind::Vector, *self))
+    }
+}
+
+#[test]
+fn errors_record_both_kinds_and_the_value() {
+    let x = Scm::from_int(42);
+    let err = x.expect_pair().unwrap_err();
+    assert_eq!(err.expected, ScmKind::Pair);
+    assert_eq!(err.actual, ScmKind::Integer);
+    assert_eq!(err.value, x);
+    assert_eq!(err.to_string(), "expected pair, got integer");
+    assert_eq!(x.expect_integer(), Ok(42));
+}
@@ -0,0 +1,229 @@
+//! A reference tree-walking evaluator, with the `interp` feature.
+//!
+//! It is neither fast nor complete. Its purpose is to run real Scheme
+//! programs on top of the representation, so that changes to it can be
+//! tested and benchmarked end to end.
+//!
+//! The special forms are `quote`, `if`, `define`, `set!`, `lambda`, `begin`
+//! and `let`. They are recognized by name, so they can't be shadowed. Calls
+//! in tail position don't grow the Rust stack. Procedures are foreign
+//! objects, and unspecified values are the empty list.
+
+use std::ops::RangeInclusive;
+use crate::env::{self, make_environment};
+use crate::foreign::make_foreign;
+use crate::lists::{append, dotted_length, vec_to_list};
+use crate::proc::{self, make_primitive_1, make_primitive_2, Primitive1, Primitive2, Procedure, VARIADIC};
+use crate::{car, cdr, cons, num, order, EvalError, Scm, ScmKind, TypeError};
+
+pub type Primitive = fn(&[Scm]) -> Result<Scm, EvalError>;
+
+// `f` is only called with a number of arguments in `arity`.
+pub fn make_primitive(arity: RangeInclusive<usize>, f: Primitive) -> Scm {
+    make_foreign("procedure", Procedure::Primitive(f, *arity.start(), *arity.end()))
+}
+
+pub fn eval(mut x: Scm, mut env: Scm) -> Result<Scm, EvalError> {
+    loop {
+        if x.as_symbol().is_some() {
+            return Ok(env::lookup(env, x)?)
+        }
+        let (op, args) = match x.as_pair() {
+            Some(&pair) => pair,
+            None => return Ok(x),
+        };
+        match op.as_symbol() {
+            Some("quote") => return first(x, args),
+            Some("if") => {
+                let (test, branches) = split(x, args)?;
+                let (consequent, alternative) = split(x, branches)?;
+                x = if eval(test, env)?.is_true() {
+                    consequent
+                } else if alternative.is_nil() {
+                    return Ok(Scm::NIL)
+                } else {
+                    first(x, alternative)?
+                };
+                continue
+            }
+            Some("define") => {
+                let (target, rest) = split(x, args)?;
+                let (name, value) = match target.as_pair() {
+                    // (define (name . params) body ...)
+                    Some(&(name, params)) => (name, make_closure(params, rest, env)),
+                    None => (target, eval(first(x, rest)?, env)?),
+                };
+                env::define(env, name, value)?;
+                return Ok(Scm::NIL)
+            }
+            Some("set!") => {
+                let (name, rest) = split(x, args)?;
+                let value = eval(first(x, rest)?, env)?;
+                env::set(env, name, value)?;
+                return Ok(Scm::NIL)
+            }
+            Some("lambda") => {
+                let (params, body) = split(x, args)?;
+                return Ok(make_closure(params, body, env))
+            }
+            Some("begin") if args.is_nil() => return Ok(Scm::NIL),
+            Some("begin") => {
+                x = eval_body(x, args, env)?;
+                continue
+            }
+            Some("let") => {
+                let (bindings, body) = split(x, args)?;
+                let frame = make_environment(Some(env))?;
+                for binding in list_items(x, bindings)? {
+                    let (name, rest) = split(x, binding)?;
+                    env::define(frame, name, eval(first(x, rest)?, env)?)?;
+                }
+                env = frame;
+                x = eval_body(x, body, env)?;
+                continue
+            }
+            _ => {}
+        }
+        let f = eval(op, env)?;
+        let args = list_items(x, args)?.into_iter().map(|arg| eval(arg, env)).collect::<Result<Vec<_>, _>>()?;
+        match proc::checked(f, args.len())? {
+            Procedure::Closure { params, body, env: closure_env, .. } => {
+                env = bind(params, &args, closure_env)?;
+                x = eval_body(body, body, env)?;
+            }
+            p => return proc::call(p, &args),
+        }
+    }
+}
+
+pub fn apply(f: Scm, args: &[Scm]) -> Result<Scm, EvalError> {
+    proc::call(proc::checked(f, args.len())?, args)
+}
+
+pub(crate) fn call_closure(params: Scm, body: Scm, env: Scm, args: &[Scm]) -> Result<Scm, EvalError> {
+    let env = bind(params, args, env)?;
+    eval(eval_body(body, body, env)?, env)
+}
+
+fn make_closure(params: Scm, body: Scm, env: Scm) -> Scm {
+    let (required, rest) = dotted_length(params);
+    make_foreign("procedure", Procedure::Closure { params, body, env, required, rest: !rest.is_nil() })
+}
+
+// A new frame with the parameters bound to the arguments, whose number
+// must already have been checked against the arity. A dotted last parameter
+// takes the remaining arguments as a list.
+fn bind(mut params: Scm, args: &[Scm], env: Scm) -> Result<Scm, EvalError> {
+    let frame = make_environment(Some(env))?;
+    let mut rest = args;
+    while let Some(&(name, more)) = params.as_pair() {
+        env::define(frame, name, rest[0])?;
+        params = more;
+        rest = &rest[1..];
+    }
+    if !params.is_nil() {
+        env::define(frame, params, vec_to_list(rest))?;
+    }
+    Ok(frame)
+}
+
+// Evaluates all but the last expression of a body, and returns the last one
+// for the caller to evaluate in tail position.
+fn eval_body(form: Scm, body: Scm, env: Scm) -> Result<Scm, EvalError> {
+    let items = list_items(form, body)?;
+    let (&last, init) = items.split_last().ok_or(EvalError::Syntax(form))?;
+    for &x in init {
+        eval(x, env)?;
+    }
+    Ok(last)
+}
+
+fn split(form: Scm, x: Scm) -> Result<(Scm, Scm), EvalError> {
+    x.as_pair().copied().ok_or(EvalError::Syntax(form))
+}
+
+fn first(form: Scm, x: Scm) -> Result<Scm, EvalError> {
+    split(form, x).map(|(a, _)| a)
+}
+
+fn list_items(form: Scm, mut x: Scm) -> Result<Vec<Scm>, EvalError> {
+    let mut items = vec![];
+    while let Some(&(a, d)) = x.as_pair() {
+        items.push(a);
+        x = d;
+    }
+    if x.is_nil() { Ok(items) } else { Err(EvalError::Syntax(form)) }
+}
+
+fn compare(args: &[Scm], cmp: fn(Scm, Scm) -> Result<bool, TypeError>) -> Result<Scm, EvalError> {
+    Ok(Scm::from_bool(num::chain(args, cmp)?))
+}
+
+// A global environment with the basic procedures on numbers and lists.
+pub fn standard_environment() -> Scm {
+    let primitives: [(&str, RangeInclusive<usize>, Primitive); 10] = [
+        ("+", 0..=VARIADIC, |args| Ok(args.iter().try_fold(Scm::from_int(0), |acc, &x| num::add(acc, x))?)),
+        ("*", 0..=VARIADIC, |args| Ok(args.iter().try_fold(Scm::from_int(1), |acc, &x| num::mul(acc, x))?)),
+        ("-", 1..=VARIADIC, |args| match args {
+            [x] => Ok(num::sub(Scm::from_int(0), *x)?),
+            [x, rest @ ..] => Ok(rest.iter().try_fold(*x, |acc, &y| num::sub(acc, y))?),
+            [] => unreachable!(),
+        }),
+        ("=", 1..=VARIADIC, |args| compare(args, num::eq)),
+        ("<", 1..=VARIADIC, |args| compare(args, num::lt)),
+        (">", 1..=VARIADIC, |args| compare(args, num::gt)),
+        ("<=", 1..=VARIADIC, |args| compare(args, num::le)),
+        (">=", 1..=VARIADIC, |args| compare(args, num::ge)),
+        ("list", 0..=VARIADIC, |args| Ok(vec_to_list(args))),
+        ("append", 0..=VARIADIC, |args| Ok(append(args)?)),
+    ];
+    let unary: [(&str, Primitive1); 5] = [
+        ("car", |x| car(x).ok_or_else(|| TypeError::new(ScmKind::Pair, x).into())),
+        ("cdr", |x| cdr(x).ok_or_else(|| TypeError::new(ScmKind::Pair, x).into())),
+        ("null?", |x| Ok(Scm::from_bool(x.is_nil()))),
+        ("pair?", |x| Ok(Scm::from_bool(x.as_pair().is_some()))),
+        ("not", |x| Ok(Scm::from_bool(!x.is_true()))),
+    ];
+    let binary: [(&str, Primitive2); 4] = [
+        ("/", |x, y| Ok(num::div(x, y)?)),
+        ("cons", |x, y| Ok(cons(x, y))),
+        ("eq?", |x, y| Ok(Scm::from_bool(x == y))),
+        ("equal?", |x, y| Ok(Scm::from_bool(order::equal(x, y)))),
+    ];
+    let env = make_environment(None).unwrap();
+    for (name, arity, f) in primitives {
+        env::define(env, Scm::symbol(name), make_primitive(arity, f)).unwrap();
+    }
+    for (name, f) in unary {
+        env::define(env, Scm::symbol(name), make_primitive_1(f)).unwrap();
+    }
+    for (name, f) in binary {
+        env::define(env, Scm::symbol(name), make_primitive_2(f)).unwrap();
+    }
+    env
+}
+
+#[test]
+fn programs_run() {
+    let env = standard_environment();
+    let run = |src: &str| {
+        crate::reader::read_all(src).unwrap().into_iter().try_fold(Scm::NIL, |_, x| eval(x, env))
+    };
+    run("(define (fact n) (if (< n 2) 1 (* n (fact (- n 1)))))").unwrap();
+    assert_eq!(run("(fact 25)").unwrap().to_string(), "15511210043330985984000000");
+
+    // many iterations in tail position, without growing the stack
+    let count = run("(define (count n acc) (if (= n 0) acc (count (- n 1) (+ acc 1)))) (count 100000 0)");
+    assert_eq!(count.unwrap(), Scm::from_int(100_000));
+
+    run("(define (make-counter) (let ((n 0)) (lambda () (set! n (+ n 1)) n)))").unwrap();
+    assert_eq!(run("(define c (make-counter)) (c) (c) (c)").unwrap(), Scm::from_int(3));
+    assert_eq!(run("((lambda (a . rest) rest) 1 2 3)").unwrap().to_string(), "(2 3)");
+    assert_eq!(run("(begin (define x '(1 2)) (equal? x (list 1 2)))").unwrap(), Scm::TRUE);
+
+    assert_eq!(run("(car 1)").unwrap_err().to_string(), "expected pair, got integer");
+    assert_eq!(run("(fact)").unwrap_err().to_string(), "#<procedure> called with 0 arguments");
+    assert_eq!(run("(undefined)").unwrap_err().to_string(), "unbound variable undefined");
+    assert_eq!(run("(1 2)").unwrap_err().to_string(), "not a procedure: 1");
+    assert_eq!(run("(if)").unwrap_err().to_string(), "bad syntax (if)");
+}
@@ -0,0 +1,192 @@
+//! Arbitrary Rust values wrapped in a heap object, so embedders can pass
+//! things like sockets or database handles through Scheme code. Scheme only
+//! sees an opaque object with a type name; Rust gets the value back with a
+//! checked downcast.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+use crate::heap::{self, HeapObject, Kind};
+use crate::trace::Trace;
+use crate::{Scm, ScmKind, TypeError};
+
+// `as_any` stands in for trait upcasting, which needs a newer compiler
+#[cfg(not(feature = "sync"))]
+pub trait ForeignValue: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+#[cfg(not(feature = "sync"))]
+impl<T: Any> ForeignValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(feature = "sync")]
+pub trait ForeignValue: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+#[cfg(feature = "sync")]
+impl<T: Any + Send + Sync> ForeignValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+type Finalizer = Box<dyn FnOnce(&mut dyn Any)>;
+#[cfg(feature = "sync")]
+type Finalizer = Box<dyn FnOnce(&mut dyn Any) + Send + Sync>;
+
+// Calls the value's `Trace` impl, for values that have one.
+type Tracer = fn(&dyn Any, &mut dyn FnMut(Scm));
+
+pub(crate) struct Foreign {
+    name: Box<str>,
+    value: Box<dyn ForeignValue>,
+    finalizer: Option<Finalizer>,
+    tracer: Option<Tracer>,
+}
+
+impl Foreign {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HeapObject for Foreign {
+    const KIND: Kind = Kind::Foreign;
+}
+
+// Only runs if the object is ever freed; leaked objects live forever.
+impl Drop for Foreign {
+    fn drop(&mut self) {
+        if let Some(finalize) = self.finalizer.take() {
+            finalize((*self.value).as_any_mut());
+        }
+    }
+}
+
+pub fn make_foreign<T: ForeignValue>(name: &str, value: T) -> Scm {
+    Scm::from_object(heap::leak(new_foreign(name, value, None)))
+}
+
+// Like `make_foreign`, for values that refer to other values, so that
+// `trace::children` can find those.
+pub fn make_traced_foreign<T: ForeignValue + Trace>(name: &str, value: T) -> Scm {
+    let mut foreign = new_foreign(name, value, None);
+    foreign.tracer = Some(|value, visit| value.downcast_ref::<T>().unwrap().trace(visit));
+    Scm::from_object(heap::leak(foreign))
+}
+
+pub(crate) fn trace(x: Scm, visit: &mut dyn FnMut(Scm)) {
+    if let Some(obj) = x.as_object::<Foreign>() {
+        if let Some(tracer) = obj.tracer {
+            tracer((*obj.value).as_any(), visit);
+        }
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+pub fn make_foreign_with_finalizer<T: ForeignValue>(name: &str, value: T, finalize: impl FnOnce(&mut T) + 'static) -> Scm {
+    Scm::from_object(heap::leak(with_finalizer(name, value, finalize)))
+}
+
+#[cfg(feature = "sync")]
+pub fn make_foreign_with_finalizer<T: ForeignValue>(name: &str, value: T, finalize: impl FnOnce(&mut T) + Send + Sync + 'static) -> Scm {
+    Scm::from_object(heap::leak(with_finalizer(name, value, finalize)))
+}
+
+#[cfg(not(feature = "sync"))]
+fn with_finalizer<T: ForeignValue>(name: &str, value: T, finalize: impl FnOnce(&mut T) + 'static) -> Foreign {
+    let hook: Finalizer = Box::new(move |any: &mut dyn Any| finalize(any.downcast_mut().unwrap()));
+    new_foreign(name, value, Some(hook))
+}
+
+#[cfg(feature = "sync")]
+fn with_finalizer<T: ForeignValue>(name: &str, value: T, finalize: impl FnOnce(&mut T) + Send + Sync + 'static) -> Foreign {
+    let hook: Finalizer = Box::new(move |any: &mut dyn Any| finalize(any.downcast_mut().unwrap()));
+    new_foreign(name, value, Some(hook))
+}
+
+fn new_foreign<T: ForeignValue>(name: &str, value: T, finalizer: Option<Finalizer>) -> Foreign {
+    Foreign {
+        name: name.into(),
+        value: Box::new(value),
+        finalizer,
+        tracer: None,
+    }
+}
+
+impl Scm {
+    pub fn is_foreign(&self) -> bool {
+        self.as_object::<Foreign>().is_some()
+    }
+
+    // None if this is not a foreign object, or if it holds some other type.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (*self.as_object::<Foreign>()?.value).as_any().downcast_ref()
+    }
+
+    pub fn expect_foreign<T: Any>(&self) -> Result<&T, TypeError> {
+        self.downcast_ref().ok_or_else(|| TypeError::new(ScmKind::Foreign, *self))
+    }
+}
+
+// The name given when the object was made.
+pub fn type_name(x: Scm) -> Option<&'static str> {
+    x.as_object::<Foreign>().map(|obj| obj.body.name())
+}
+
+pub type PrintHook = Arc<dyn Fn(Scm, &mut fmt::Formatter) -> fmt::Result + Send + Sync>;
+
+static PRINT_HOOKS: OnceLock<RwLock<HashMap<String, PrintHook>>> = OnceLock::new();
+
+fn print_hooks() -> &'static RwLock<HashMap<String, PrintHook>> {
+    PRINT_HOOKS.get_or_init(Default::default)
+}
+
+// Makes the printer use `hook` for every foreign object with the given type
+// name, instead of the default `#<name>`. Pair it with a reader dispatch
+// macro to give the type a syntax that reads back.
+pub fn set_print_hook(name: &str, hook: impl Fn(Scm, &mut fmt::Formatter) -> fmt::Result + Send + Sync + 'static) {
+    print_hooks().write().unwrap().insert(name.to_string(), Arc::new(hook));
+}
+
+pub(crate) fn print_hook(name: &str) -> Option<PrintHook> {
+    print_hooks().read().unwrap().get(name).cloned()
+}
+
+#[test]
+fn foreign_values_round_trip() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Debug, PartialEq)]
+    struct Socket(u16);
+
+    let s = make_foreign("socket", Socket(8080));
+    assert_eq!(s.downcast_ref::<Socket>(), Some(&Socket(8080)));
+    assert_eq!(s.downcast_ref::<String>(), None);
+    assert_eq!(Scm::NIL.downcast_ref::<Socket>(), None);
+    assert_eq!(type_name(s), Some("socket"));
+    assert_eq!(s.to_string(), "#<socket>");
+    assert_eq!(Scm::TRUE.expect_foreign::<Socket>().unwrap_err().to_string(), "expected foreign object, got boolean");
+
+    let closed = Arc::new(AtomicUsize::new(0));
+    let c = closed.clone();
+    let obj = heap::alloc(with_finalizer("socket", Socket(1), move |s: &mut Socket| {
+        c.store(s.0 as usize, Ordering::SeqCst)
+    }));
+    drop(obj);
+    assert_eq!(closed.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,91 @@
+//! Freezing: marking data as never to be modified again, as R7RS requires of
+//! literal constants.
+//!
+//! Setting a frozen box, pair, vector or string fails with
+//! `MutationError::Immutable`, whether through `set_box`, `lists::set_car`,
+//! `lists::set_cdr`, `vectors::vector_set` or `strings::string_set`.
+//!
+//! `order::equal_hash32` caches hashes in the headers of frozen and unfrozen
+//! objects alike, but not of those that contain an unfrozen box, which may
+//! change at any time. Changing anything else takes one of the unsafe
+//! setters, and then `order::forget_hash` for its containers.
+//!
+//! Symbols are always frozen, like immediate values. Their headers are never
+//! written, since they are shared by all threads.
+
+use crate::heap::{Kind, FLAG_FROZEN};
+use crate::{Scm, ScmView};
+
+impl Scm {
+    // Freezes `self` and everything reachable from it through pairs,
+    // vectors, multiple values, boxes and syntax objects.
+    pub fn freeze(&self) -> Scm {
+        let mut todo = vec![*self];
+        while let Some(x) = todo.pop() {
+            // this also stops at cycles, which go through a box
+            let header = match x.header() {
+                Some(header) if header.kind() != Kind::Symbol && header.flags() & FLAG_FROZEN == 0 => header,
+                _ => continue,
+            };
+            header.set_flag(FLAG_FROZEN, true);
+            match x.classify() {
+                ScmView::Pair(&(car, cdr)) => todo.extend([car, cdr]),
+                ScmView::Box(x) | ScmView::Syntax(x, _) => todo.push(x),
+                ScmView::Vector(items) | ScmView::Values(items) => todo.extend_from_slice(items),
+                _ => {}
+            }
+        }
+        *self
+    }
+
+    // Immediate values and symbols are always frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.header().is_none_or(|header| header.kind() == Kind::Symbol || header.flags() & FLAG_FROZEN != 0)
+    }
+}
+
+#[test]
+fn frozen_boxes_cant_be_set() {
+    use crate::boxes::{make_box, set_box, unbox};
+    let b = make_box(Scm::NIL);
+    let x = Scm::vector(vec![crate::cons(Scm::from_int(1), b), Scm::string("a string long enough for the heap"), Scm::symbol("frozen-symbol")]);
+    set_box(b, x).unwrap();
+    assert!(!x.is_frozen() && Scm::from_int(1).is_frozen());
+    assert_eq!(x.freeze(), x);
+    assert!(x.is_frozen() && b.is_frozen() && x.as_vector().unwrap()[1].is_frozen());
+    let symbol_flags = Scm::symbol("frozen-symbol").header().unwrap().flags();
+    assert!(Scm::symbol("frozen-symbol").is_frozen() && symbol_flags & FLAG_FROZEN == 0);
+    assert_eq!(set_box(b, Scm::NIL).unwrap_err().to_string(), "cannot modify immutable box");
+    assert_eq!(unbox(b).unwrap(), x);
+
+    use crate::reader::{ReadOptions, Reader};
+    let options = ReadOptions::default().frozen();
+    let literal = Reader::with_options(crate::port::open_input_string("(#&1 2)"), options).read().unwrap();
+    assert!(set_box(crate::car(literal).unwrap(), Scm::NIL).is_err());
+}
+
+#[test]
+fn frozen_pairs_vectors_and_strings_cant_be_set() {
+    use crate::lists::{set_car, set_cdr};
+    use crate::strings::string_set;
+    use crate::vectors::vector_set;
+    use crate::MutationError;
+    let pair = crate::cons(Scm::from_int(1), Scm::NIL);
+    let v = Scm::vector(vec![Scm::from_int(1)]);
+    let s = Scm::string("a string long enough for the heap");
+    unsafe {
+        set_car(pair, Scm::from_int(2)).unwrap();
+        vector_set(v, 0, Scm::from_int(2)).unwrap();
+        string_set(s, 0, 'A').unwrap();
+        pair.freeze();
+        v.freeze();
+        s.freeze();
+        assert_eq!(set_car(pair, Scm::NIL), Err(MutationError::Immutable(pair)));
+        assert_eq!(set_cdr(pair, Scm::NIL), Err(MutationError::Immutable(pair)));
+        assert_eq!(vector_set(v, 0, Scm::NIL), Err(MutationError::Immutable(v)));
+        assert_eq!(string_set(s, 0, 'a').unwrap_err().to_string(), "cannot modify immutable string");
+    }
+    assert_eq!(pair.to_string(), "(2)");
+    assert_eq!(v.to_string(), "#(2)");
+    assert_eq!(s.as_str(), Some("A string long enough for the heap"));
+}
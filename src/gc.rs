@@ -0,0 +1,146 @@
+//! Roots for collectors, with the `sync` feature (without it, values can't
+//! be stored in statics at all).
+//!
+//! The Boehm GC finds values in statics by scanning the data segment, but a
+//! precise collector only sees what it is told about. Globals that hold
+//! values register them here, and Rust code that keeps values anywhere else
+//! holds them in a `Rooted`. `for_each_root` lists all of them for any
+//! collector. Interned symbols are not roots: the symbol table doesn't keep
+//! them alive, and a collector frees the unreachable ones with
+//! `SymbolTable::sweep_unmarked`.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, Once, OnceLock};
+use crate::{AtomicScm, Scm};
+
+static ROOTS: Mutex<Vec<&'static AtomicScm>> = Mutex::new(Vec::new());
+
+// The addresses of the slots of all `Rooted`s.
+static ROOTED: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+// Registering the same slot again does nothing.
+pub fn register_root(root: &'static AtomicScm) {
+    let mut roots = ROOTS.lock().unwrap();
+    if !roots.iter().any(|&r| std::ptr::eq(r, root)) {
+        roots.push(root);
+    }
+}
+
+// A global variable holding a value, which registers itself as a root when
+// it is first set:
+//
+//     static EMPTY_ENVIRONMENT: StaticRoot = StaticRoot::new();
+pub struct StaticRoot {
+    slot: OnceLock<AtomicScm>,
+    registered: Once,
+}
+
+impl StaticRoot {
+    pub const fn new() -> Self {
+        StaticRoot { slot: OnceLock::new(), registered: Once::new() }
+    }
+
+    // The empty list until it is set.
+    pub fn get(&'static self) -> Scm {
+        self.slot.get().map_or(Scm::NIL, |slot| slot.load(Ordering::Acquire))
+    }
+
+    pub fn set(&'static self, value: Scm) {
+        let slot = self.slot.get_or_init(AtomicScm::default);
+        self.registered.call_once(|| register_root(slot));
+        slot.store(value, Ordering::Release);
+    }
+}
+
+impl Default for StaticRoot {
+    fn default() -> Self {
+        StaticRoot::new()
+    }
+}
+
+// A value that stays a root for as long as the handle lives, so that it can
+// be kept in any Rust data structure. The value lives in a slot of its own,
+// which a moving collector would update, so read it with `get` every time.
+pub struct Rooted {
+    slot: Box<AtomicScm>,
+}
+
+impl Rooted {
+    pub fn new(value: Scm) -> Self {
+        let slot = Box::new(AtomicScm::new(value));
+        ROOTED.lock().unwrap().insert(&*slot as *const AtomicScm as usize);
+        Rooted { slot }
+    }
+
+    pub fn get(&self) -> Scm {
+        self.slot.load(Ordering::Acquire)
+    }
+
+    pub fn set(&self, value: Scm) {
+        self.slot.store(value, Ordering::Release)
+    }
+}
+
+impl Drop for Rooted {
+    fn drop(&mut self) {
+        ROOTED.lock().unwrap().remove(&(&*self.slot as *const AtomicScm as usize));
+    }
+}
+
+impl Clone for Rooted {
+    fn clone(&self) -> Self {
+        Rooted::new(self.get())
+    }
+}
+
+impl fmt::Debug for Rooted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Rooted").field(&self.get()).finish()
+    }
+}
+
+// Calls `f` with the value of every registered root and every `Rooted`.
+pub fn for_each_root(f: impl FnMut(Scm)) {
+    let mut values: Vec<Scm> = ROOTS.lock().unwrap().iter().map(|root| root.load(Ordering::Acquire)).collect();
+    // a slot is only freed after it was removed, which the lock prevents meanwhile
+    values.extend(ROOTED.lock().unwrap().iter().map(|&addr| unsafe { &*(addr as *const AtomicScm) }.load(Ordering::Acquire)));
+    values.into_iter().for_each(f);
+}
+
+#[test]
+fn registered_roots_are_listed() {
+    static GLOBAL: StaticRoot = StaticRoot::new();
+    assert_eq!(GLOBAL.get(), Scm::NIL);
+    let list = crate::cons(Scm::from_int(1), Scm::NIL);
+    GLOBAL.set(list);
+    let slot: &'static AtomicScm = Box::leak(Box::new(AtomicScm::new(Scm::string("held by an embedder"))));
+    register_root(slot);
+    register_root(slot);
+    let sym = Scm::symbol("a-rooted-symbol");
+
+    let mut found = vec![];
+    for_each_root(|x| found.push(x));
+    assert!(found.contains(&list) && found.contains(&slot.load(Ordering::Relaxed)) && !found.contains(&sym));
+    assert_eq!(found.iter().filter(|&&x| x == slot.load(Ordering::Relaxed)).count(), 1);
+}
+
+#[test]
+fn rooted_values_are_roots_until_dropped() {
+    let roots = || {
+        let mut found = vec![];
+        for_each_root(|x| found.push(x));
+        found
+    };
+    let list = crate::cons(Scm::from_int(2), Scm::NIL);
+    let mut held = vec![Rooted::new(list)];
+    held.push(held[0].clone());
+    assert_eq!(roots().iter().filter(|&&x| x == list).count(), 2);
+    let other = crate::cons(Scm::from_int(3), Scm::NIL);
+    held[1].set(other);
+    assert_eq!(held[1].get(), other);
+    assert!(roots().contains(&other));
+    held.clear();
+    assert!(!roots().contains(&list) && !roots().contains(&other));
+}
@@ -0,0 +1,303 @@
+//! Persistent maps: immutable hash array mapped tries with keys compared by
+//! `equal?`.
+//!
+//! Every update returns a new map that shares all untouched nodes with the
+//! old one, so adding or removing a key costs O(log32 n) allocations instead
+//! of copying an association list.
+//!
+//! Each level of the trie consumes 5 bits of the key's hash. A branch only
+//! stores the children that exist, with a bitmap telling which ones those are.
+//! Keys whose full 32-bit hashes collide share a leaf.
+
+// Nodes are shared between maps, and only with the `sync` feature between threads.
+#[cfg(not(feature = "sync"))]
+use std::rc::Rc as Shared;
+#[cfg(feature = "sync")]
+use std::sync::Arc as Shared;
+use crate::heap::{self, HeapObject, Kind};
+use crate::order::{equal, equal_hash32};
+use crate::{cons, Scm, ScmKind, TypeError};
+
+const BITS: u32 = 5;
+const MASK: u32 = (1 << BITS) - 1;
+
+enum Node {
+    // all entries have the same hash
+    Leaf(u32, Vec<(Scm, Scm)>),
+    Branch(u32, Vec<Shared<Node>>),
+}
+
+fn bit(hash: u32, shift: u32) -> u32 {
+    1 << (hash >> shift & MASK)
+}
+
+// Position of a child in the compressed children vector of a branch.
+fn index(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl Node {
+    fn get(&self, hash: u32, shift: u32, key: Scm) -> Option<Scm> {
+        match self {
+            Node::Leaf(h, entries) if *h == hash => entries.iter().find(|e| equal(e.0, key)).map(|e| e.1),
+            Node::Leaf(..) => None,
+            Node::Branch(bitmap, children) => {
+                let bit = bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None
+                }
+                children[index(*bitmap, bit)].get(hash, shift + BITS, key)
+            }
+        }
+    }
+
+    // The updated node, and whether the key is new.
+    fn assoc(self: &Shared<Self>, hash: u32, shift: u32, key: Scm, value: Scm) -> (Shared<Node>, bool) {
+        match &**self {
+            Node::Leaf(h, entries) if *h == hash => {
+                let mut entries = entries.clone();
+                let added = match entries.iter_mut().find(|e| equal(e.0, key)) {
+                    Some(e) => {
+                        e.1 = value;
+                        false
+                    }
+                    None => {
+                        entries.push((key, value));
+                        true
+                    }
+                };
+                (Shared::new(Node::Leaf(hash, entries)), added)
+            }
+            // the hashes differ, so they will go separate ways at some level
+            Node::Leaf(h, _) => {
+                Shared::new(Node::Branch(bit(*h, shift), vec![self.clone()])).assoc(hash, shift, key, value)
+            }
+            Node::Branch(bitmap, children) => {
+                let bit = bit(hash, shift);
+                let i = index(*bitmap, bit);
+                let mut children = children.clone();
+                if bitmap & bit == 0 {
+                    children.insert(i, Shared::new(Node::Leaf(hash, vec![(key, value)])));
+                    return (Shared::new(Node::Branch(bitmap | bit, children)), true)
+                }
+                let (child, added) = children[i].assoc(hash, shift + BITS, key, value);
+                children[i] = child;
+                (Shared::new(Node::Branch(*bitmap, children)), added)
+            }
+        }
+    }
+
+    // `None` if the key isn't there, otherwise the node without it (which is
+    // `None` when nothing is left).
+    fn dissoc(&self, hash: u32, shift: u32, key: Scm) -> Option<Option<Shared<Node>>> {
+        match self {
+            Node::Leaf(h, entries) => {
+                if *h != hash {
+                    return None
+                }
+                let i = entries.iter().position(|e| equal(e.0, key))?;
+                let mut entries = entries.clone();
+                entries.remove(i);
+                Some(if entries.is_empty() { None } else { Some(Shared::new(Node::Leaf(hash, entries))) })
+            }
+            Node::Branch(bitmap, children) => {
+                let bit = bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None
+                }
+                let i = index(*bitmap, bit);
+                let mut bitmap = *bitmap;
+                let mut children = children.clone();
+                match children[i].dissoc(hash, shift + BITS, key)? {
+                    Some(child) => children[i] = child,
+                    None => {
+                        children.remove(i);
+                        bitmap &= !bit;
+                    }
+                }
+                // a branch with a single leaf left is just that leaf, which
+                // keeps lookups short after many removals
+                Some(match children.as_slice() {
+                    [] => None,
+                    [only] if matches!(**only, Node::Leaf(..)) => Some(only.clone()),
+                    _ => Some(Shared::new(Node::Branch(bitmap, children))),
+                })
+            }
+        }
+    }
+}
+
+pub(crate) struct PersistentMap {
+    root: Option<Shared<Node>>,
+    len: usize,
+}
+
+impl HeapObject for PersistentMap {
+    const KIND: Kind = Kind::Map;
+}
+
+impl Scm {
+    pub fn is_map(&self) -> bool {
+        self.as_object::<PersistentMap>().is_some()
+    }
+}
+
+fn expect_map(m: Scm) -> Result<&'static PersistentMap, TypeError> {
+    m.as_object::<PersistentMap>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Map, m))
+}
+
+fn alloc(root: Option<Shared<Node>>, len: usize) -> Scm {
+    Scm::from_object(heap::leak(PersistentMap { root, len }))
+}
+
+pub fn make_map() -> Scm {
+    alloc(None, 0)
+}
+
+pub fn map_len(m: Scm) -> Result<usize, TypeError> {
+    expect_map(m).map(|obj| obj.len)
+}
+
+pub fn map_ref(m: Scm, key: Scm) -> Result<Option<Scm>, TypeError> {
+    let obj = expect_map(m)?;
+    Ok(obj.root.as_ref().and_then(|root| root.get(equal_hash32(key), 0, key)))
+}
+
+// A map like `m`, but with `key` bound to `value`.
+pub fn map_assoc(m: Scm, key: Scm, value: Scm) -> Result<Scm, TypeError> {
+    let obj = expect_map(m)?;
+    let hash = equal_hash32(key);
+    Ok(match &obj.root {
+        None => alloc(Some(Shared::new(Node::Leaf(hash, vec![(key, value)]))), 1),
+        Some(root) => {
+            let (root, added) = root.assoc(hash, 0, key, value);
+            alloc(Some(root), obj.len + added as usize)
+        }
+    })
+}
+
+// A map like `m`, but without `key`. Returns `m` itself if it has no such key.
+pub fn map_dissoc(m: Scm, key: Scm) -> Result<Scm, TypeError> {
+    let obj = expect_map(m)?;
+    Ok(match obj.root.as_ref().and_then(|root| root.dissoc(equal_hash32(key), 0, key)) {
+        None => m,
+        Some(root) => alloc(root, obj.len - 1),
+    })
+}
+
+// All entries of both maps; where both have a key, the value from `b` wins.
+pub fn map_merge(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    expect_map(a)?;
+    if map_len(b)? > map_len(a)? {
+        // insert the smaller map into the larger one, but keep `b` winning
+        map_entries(a)?.try_fold(b, |acc, (k, v)| match map_ref(b, k)? {
+            Some(_) => Ok(acc),
+            None => map_assoc(acc, k, v),
+        })
+    } else {
+        map_entries(b)?.try_fold(a, |acc, (k, v)| map_assoc(acc, k, v))
+    }
+}
+
+// Iterates over the entries of a map in no particular (but a deterministic) order.
+pub fn map_entries(m: Scm) -> Result<Entries, TypeError> {
+    let obj = expect_map(m)?;
+    Ok(Entries {
+        stack: vec![obj.root.as_slice()],
+        leaf: &[],
+    })
+}
+
+// A map of the entries of an association list. As with `assoc`, the first
+// entry for a key is the one that counts.
+pub fn alist_to_map(alist: Scm) -> Result<Scm, TypeError> {
+    let mut m = make_map();
+    let mut rest = alist;
+    while !rest.is_nil() {
+        let &(entry, next) = rest.expect_pair()?;
+        let &(key, value) = entry.expect_pair()?;
+        if map_ref(m, key)?.is_none() {
+            m = map_assoc(m, key, value)?;
+        }
+        rest = next;
+    }
+    Ok(m)
+}
+
+// The entries of a map as an association list, in the order of `map_entries`.
+pub fn map_to_alist(m: Scm) -> Result<Scm, TypeError> {
+    let entries: Vec<_> = map_entries(m)?.collect();
+    Ok(entries.into_iter().rev().fold(Scm::NIL, |list, (k, v)| cons(cons(k, v), list)))
+}
+
+pub struct Entries {
+    // the siblings still to visit on each level
+    stack: Vec<&'static [Shared<Node>]>,
+    leaf: &'static [(Scm, Scm)],
+}
+
+impl Iterator for Entries {
+    type Item = (Scm, Scm);
+
+    fn next(&mut self) -> Option<(Scm, Scm)> {
+        loop {
+            if let Some((&entry, rest)) = self.leaf.split_first() {
+                self.leaf = rest;
+                return Some(entry)
+            }
+            let top = self.stack.last_mut()?;
+            match top.split_first() {
+                None => {
+                    self.stack.pop();
+                }
+                Some((node, rest)) => {
+                    *top = rest;
+                    match &**node {
+                        Node::Leaf(_, entries) => self.leaf = entries,
+                        Node::Branch(_, children) => self.stack.push(children),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn updates_leave_the_original_intact() {
+    let key = |i: i64| crate::cons(Scm::from_int(i), Scm::string("k"));
+    let mut m = make_map();
+    for i in 0..1000 {
+        m = map_assoc(m, key(i), Scm::from_int(i)).unwrap();
+    }
+    let old = m;
+    for i in (0..1000).step_by(2) {
+        m = map_dissoc(m, key(i)).unwrap();
+    }
+    m = map_assoc(m, key(1), Scm::TRUE).unwrap();
+
+    assert_eq!(map_len(old).unwrap(), 1000);
+    assert_eq!(map_len(m).unwrap(), 500);
+    // equal keys find the entry, even though they are different pairs
+    assert_eq!(map_ref(old, key(2)).unwrap(), Some(Scm::from_int(2)));
+    assert_eq!(map_ref(m, key(2)).unwrap(), None);
+    assert_eq!(map_ref(m, key(1)).unwrap(), Some(Scm::TRUE));
+    assert_eq!(map_entries(m).unwrap().count(), 500);
+    assert_eq!(map_dissoc(m, key(2)).unwrap(), m);
+
+    let merged = map_merge(old, m).unwrap();
+    assert_eq!(map_len(merged).unwrap(), 1000);
+    assert_eq!(map_ref(merged, key(1)).unwrap(), Some(Scm::TRUE));
+    assert_eq!(map_ref(merged, key(2)).unwrap(), Some(Scm::from_int(2)));
+    assert_eq!(map_assoc(make_map(), Scm::symbol("a"), Scm::from_int(1)).unwrap().to_string(), "#<map (a . 1)>");
+}
+
+#[test]
+fn alists_convert_to_maps_and_back() {
+    let alist = crate::reader::read_str("((a . 1) (b . 2) (a . 3))").unwrap();
+    let m = alist_to_map(alist).unwrap();
+    assert_eq!(map_len(m).unwrap(), 2);
+    assert_eq!(map_ref(m, Scm::symbol("a")).unwrap(), Some(Scm::from_int(1)));
+    let back = map_to_alist(m).unwrap();
+    assert_eq!(alist_to_map(back).unwrap().to_string(), m.to_string());
+    assert!(alist_to_map(crate::reader::read_str("(a)").unwrap()).is_err());
+}
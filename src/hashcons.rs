@@ -0,0 +1,95 @@
+//! Hash-consing: allocating pairs and strings through a table, so that
+//! structurally equal values are the same object and `equal?` on them is
+//! just `eq?`.
+//!
+//! This only pays off for values that are never mutated and are built many
+//! times over, like the subexpressions a compiler generates. A pair made
+//! through the table is only shared if its car and cdr were too, so whole
+//! trees have to be built bottom-up through the same table (or passed through
+//! `share` once).
+
+use std::collections::HashMap;
+use crate::lock::Lock;
+use crate::{Scm, Str};
+
+pub struct HashCons {
+    pairs: Lock<HashMap<(Scm, Scm), Scm>>,
+    strings: Lock<HashMap<&'static str, Scm>>,
+}
+
+impl HashCons {
+    pub fn new() -> Self {
+        HashCons {
+            pairs: Lock::new(HashMap::new()),
+            strings: Lock::new(HashMap::new()),
+        }
+    }
+
+    pub fn cons(&self, car: Scm, cdr: Scm) -> Scm {
+        // keys compare by identity, which is structural equality for shared children
+        *self.pairs.lock().entry((car, cdr)).or_insert_with(|| crate::cons(car, cdr))
+    }
+
+    pub fn string(&self, s: &str) -> Scm {
+        let mut strings = self.strings.lock();
+        if let Some(&x) = strings.get(s) {
+            return x
+        }
+        let x = Scm::string(s);
+        if x.is_immediate() {
+            // short strings are shared already
+            return x
+        }
+        // the key borrows the contents of the string object itself
+        strings.insert(x.as_object::<Str>().unwrap().body.as_str(), x);
+        x
+    }
+
+    // The shared version of a tree of pairs and strings. Anything else (like
+    // vectors) is left as it is, but pairs and strings inside are not shared
+    // then.
+    pub fn share(&self, x: Scm) -> Scm {
+        let mut items = vec![];
+        let mut rest = x;
+        while let Some(&(a, d)) = rest.as_pair() {
+            items.push(self.share(a));
+            rest = d;
+        }
+        let tail = match rest.as_str() {
+            Some(s) => self.string(s),
+            None => rest,
+        };
+        items.into_iter().rev().fold(tail, |acc, x| self.cons(x, acc))
+    }
+
+    // The number of distinct pairs and strings in the table.
+    pub fn len(&self) -> usize {
+        self.pairs.lock().len() + self.strings.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for HashCons {
+    fn default() -> Self {
+        HashCons::new()
+    }
+}
+
+#[test]
+fn equal_trees_are_identical() {
+    let table = HashCons::new();
+    let expr = |n| crate::reader::read_str(&format!("(+ (* x x) \"{}\" (* x x))", n)).unwrap();
+    let a = table.share(expr(1));
+    let b = table.share(expr(1));
+    let c = table.share(expr(2));
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    // (* x x) occurs twice, but is one object
+    let nth = |n| crate::car((0..n).fold(a, |x, _| crate::cdr(x).unwrap())).unwrap();
+    assert_eq!(nth(1), nth(3));
+    assert_eq!(table.string("1"), nth(2));
+    assert_eq!(table.cons(Scm::NIL, Scm::NIL), table.cons(Scm::NIL, Scm::NIL));
+}
@@ -0,0 +1,681 @@
+//! All heap objects are allocated through here, so that the low bits of their
+//! addresses are guaranteed to be free for tagging.
+//!
+//! Every object starts with a one-word header, so its kind (and size, GC bits
+//! and cached hash) can be read from nothing but a pointer to it.
+//!
+//! Code that makes a lot of short-lived garbage, like a macro expander, can
+//! allocate in a region with `scoped`, which frees it all at once at the end.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "heap-walk")]
+use std::{collections::BTreeSet, sync::Mutex};
+use crate::trace::{children, Trace};
+use crate::Scm;
+
+pub const HEAP_ALIGN: usize = 8;
+
+const _: () = assert!(HEAP_ALIGN >= 1 << crate::N_TAG_BITS);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Kind {
+    Pair,
+    Symbol,
+    String,
+    Vector,
+    Flonum,
+    Bignum,
+    Ratnum,
+    Box,
+    Values,
+    Promise,
+    Port,
+    Foreign,
+    Environment,
+    Syntax,
+    Bitvector,
+    Map,
+    Deque,
+    CharSet,
+    Code,
+    Continuation,
+    Parameter,
+    Mutex,
+    CondVar,
+    Thread,
+    Sender,
+    Receiver,
+    Time,
+    Duration,
+    RandomState,
+    Regex,
+}
+
+impl Kind {
+    const ALL: [Kind; 30] = [
+        Kind::Pair,
+        Kind::Symbol,
+        Kind::String,
+        Kind::Vector,
+        Kind::Flonum,
+        Kind::Bignum,
+        Kind::Ratnum,
+        Kind::Box,
+        Kind::Values,
+        Kind::Promise,
+        Kind::Port,
+        Kind::Foreign,
+        Kind::Environment,
+        Kind::Syntax,
+        Kind::Bitvector,
+        Kind::Map,
+        Kind::Deque,
+        Kind::CharSet,
+        Kind::Code,
+        Kind::Continuation,
+        Kind::Parameter,
+        Kind::Mutex,
+        Kind::CondVar,
+        Kind::Thread,
+        Kind::Sender,
+        Kind::Receiver,
+        Kind::Time,
+        Kind::Duration,
+        Kind::RandomState,
+        Kind::Regex,
+    ];
+}
+
+pub trait HeapObject: Sized {
+    const KIND: Kind;
+}
+
+// Header layout, from the least significant bit:
+//   8 bits kind | 8 bits flags | 16 bits size in words | 32 bits hash
+const KIND_SHIFT: u32 = 0;
+const FLAGS_SHIFT: u32 = 8;
+const SIZE_SHIFT: u32 = 16;
+const HASH_SHIFT: u32 = 32;
+
+pub const FLAG_MARK: u8 = 0b_0000_0001;
+pub const FLAG_HASHED: u8 = 0b_0000_0010;
+// Freed at the end of its region, in debug builds, which keep the memory.
+pub const FLAG_DEAD: u8 = 0b_0000_0100;
+pub const FLAG_FROZEN: u8 = 0b_0000_1000;
+// Has an entry in the property table.
+pub const FLAG_PROPERTIES: u8 = 0b_0001_0000;
+// Has an entry in the reader's span table.
+pub const FLAG_SPAN: u8 = 0b_0010_0000;
+
+// With the `sync` feature objects may be shared between threads, so the
+// mutable header bits have to be atomic.
+#[cfg(not(feature = "sync"))]
+type Bits = Cell<u64>;
+#[cfg(feature = "sync")]
+type Bits = std::sync::atomic::AtomicU64;
+
+pub struct Header {
+    bits: Bits,
+}
+
+impl Header {
+    fn new(kind: Kind, size: usize) -> Self {
+        let words = size.div_ceil(HEAP_ALIGN);
+        assert!(words <= u16::MAX as usize);
+        Header {
+            bits: Bits::new((kind as u64) << KIND_SHIFT | (words as u64) << SIZE_SHIFT)
+        }
+    }
+
+    #[cfg(not(feature = "sync"))]
+    fn load(&self) -> u64 {
+        self.bits.get()
+    }
+
+    #[cfg(not(feature = "sync"))]
+    fn update(&self, f: impl Fn(u64) -> u64) {
+        self.bits.set(f(self.bits.get()));
+    }
+
+    #[cfg(feature = "sync")]
+    fn load(&self) -> u64 {
+        self.bits.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    #[cfg(feature = "sync")]
+    fn update(&self, f: impl Fn(u64) -> u64) {
+        use std::sync::atomic::Ordering;
+        let _ = self.bits.fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| Some(f(bits)));
+    }
+
+    pub fn kind(&self) -> Kind {
+        Kind::ALL[(self.load() >> KIND_SHIFT) as u8 as usize]
+    }
+
+    // Size of the object itself in bytes (not counting anything it owns out of line).
+    pub fn size(&self) -> usize {
+        (self.load() >> SIZE_SHIFT) as u16 as usize * HEAP_ALIGN
+    }
+
+    pub fn flags(&self) -> u8 {
+        (self.load() >> FLAGS_SHIFT) as u8
+    }
+
+    pub fn set_flag(&self, flag: u8, on: bool) {
+        let mask = (flag as u64) << FLAGS_SHIFT;
+        self.update(|bits| if on { bits | mask } else { bits & !mask });
+    }
+
+    pub fn hash(&self) -> Option<u32> {
+        let bits = self.load();
+        if (bits >> FLAGS_SHIFT) as u8 & FLAG_HASHED != 0 {
+            Some((bits >> HASH_SHIFT) as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_hash(&self, hash: u32) {
+        let hashed = (FLAG_HASHED as u64) << FLAGS_SHIFT;
+        self.update(|bits| bits & !((u32::MAX as u64) << HASH_SHIFT) | (hash as u64) << HASH_SHIFT | hashed);
+    }
+}
+
+impl fmt::Debug for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Header")
+            .field("kind", &self.kind())
+            .field("size", &self.size())
+            .field("flags", &self.flags())
+            .field("hash", &self.hash())
+            .finish()
+    }
+}
+
+#[repr(C, align(8))]
+pub struct Object<T> {
+    pub header: Header,
+    pub body: T,
+}
+
+impl<T> Deref for Object<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.body
+    }
+}
+
+impl<T> Drop for Object<T> {
+    fn drop(&mut self) {
+        let counter = &COUNTERS[self.header.kind() as usize];
+        counter.count.fetch_sub(1, Ordering::Relaxed);
+        counter.bytes.fetch_sub(self.header.size(), Ordering::Relaxed);
+        NET_ALLOCATIONS.with(|net| net[self.header.kind() as usize].set(net[self.header.kind() as usize].get() - 1));
+        if self.header.flags() & FLAG_PROPERTIES != 0 || self.header.kind() == Kind::Symbol {
+            crate::properties::forget(&self.header);
+        }
+        if self.header.flags() & FLAG_SPAN != 0 {
+            crate::reader::forget_span(&self.header);
+        }
+        #[cfg(feature = "heap-walk")]
+        live_objects().remove(&(&self.header as *const Header as usize));
+    }
+}
+
+struct Counter {
+    count: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+// Live objects per kind, updated by `alloc` and `Object::drop`.
+static COUNTERS: [Counter; Kind::ALL.len()] =
+    [const { Counter { count: AtomicUsize::new(0), bytes: AtomicUsize::new(0) } }; Kind::ALL.len()];
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct KindCensus {
+    pub count: usize,
+    // the objects themselves, not counting anything they own out of line
+    pub bytes: usize,
+}
+
+// How many objects of each kind are alive, as `census` found them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Census([KindCensus; Kind::ALL.len()]);
+
+impl Census {
+    pub fn get(&self, kind: Kind) -> KindCensus {
+        self.0[kind as usize]
+    }
+
+    // The kinds that have live objects.
+    pub fn iter(&self) -> impl Iterator<Item = (Kind, KindCensus)> + '_ {
+        Kind::ALL.iter().map(move |&kind| (kind, self.get(kind))).filter(|(_, c)| c.count > 0)
+    }
+
+    pub fn total(&self) -> KindCensus {
+        self.0.iter().fold(KindCensus::default(), |total, c| KindCensus {
+            count: total.count + c.count,
+            bytes: total.bytes + c.bytes,
+        })
+    }
+}
+
+impl fmt::Display for Census {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<12} {:>10} {:>12}", "kind", "objects", "bytes")?;
+        for (kind, c) in self.iter() {
+            writeln!(f, "{:<12} {:>10} {:>12}", format!("{:?}", kind), c.count, c.bytes)?;
+        }
+        let total = self.total();
+        write!(f, "{:<12} {:>10} {:>12}", "total", total.count, total.bytes)
+    }
+}
+
+// Counts the heap objects that are alive, per kind. Objects are counted from
+// allocation until they are dropped; a garbage collector that frees memory
+// without dropping it leaves them counted. The counters are updated
+// independently, so while other threads allocate the numbers may be slightly
+// off.
+pub fn census() -> Census {
+    Census(std::array::from_fn(|i| KindCensus {
+        count: COUNTERS[i].count.load(Ordering::Relaxed),
+        bytes: COUNTERS[i].bytes.load(Ordering::Relaxed),
+    }))
+}
+
+thread_local! {
+    // Objects allocated minus objects dropped on this thread, per kind.
+    static NET_ALLOCATIONS: [Cell<isize>; Kind::ALL.len()] = const { [const { Cell::new(0) }; Kind::ALL.len()] };
+}
+
+fn net_allocations() -> [isize; Kind::ALL.len()] {
+    NET_ALLOCATIONS.with(|net| std::array::from_fn(|i| net[i].get()))
+}
+
+// Counts the objects that the current thread allocates and doesn't drop again
+// from when it was started, to check that code doesn't cons when it shouldn't.
+// Only this thread's allocations are counted, so other threads don't get in the
+// way, but an object dropped on another thread is still outstanding here.
+pub struct AllocationCount {
+    start: [isize; Kind::ALL.len()],
+}
+
+impl AllocationCount {
+    pub fn start() -> Self {
+        AllocationCount { start: net_allocations() }
+    }
+
+    // How many more objects of each kind there are than at the start, for the
+    // kinds where that isn't zero. Dropping objects that were allocated
+    // before the start makes the count negative.
+    pub fn outstanding(&self) -> Vec<(Kind, isize)> {
+        let now = net_allocations();
+        Kind::ALL.iter().map(|&kind| (kind, now[kind as usize] - self.start[kind as usize])).filter(|&(_, n)| n != 0).collect()
+    }
+
+    // Panics if there are outstanding objects.
+    pub fn assert_none_outstanding(&self) {
+        let outstanding = self.outstanding();
+        assert!(outstanding.is_empty(), "objects allocated and not dropped: {:?}", outstanding);
+    }
+}
+
+// Calls `f` and panics if that leaves objects allocated.
+pub fn assert_no_net_allocations<R>(f: impl FnOnce() -> R) -> R {
+    let count = AllocationCount::start();
+    let result = f();
+    count.assert_none_outstanding();
+    result
+}
+
+// The addresses of all live objects, with the `heap-walk` feature. Objects
+// are only freed after they have been removed, so holding the lock keeps
+// every object in the set alive.
+#[cfg(feature = "heap-walk")]
+static LIVE_OBJECTS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+#[cfg(feature = "heap-walk")]
+fn live_objects() -> std::sync::MutexGuard<'static, BTreeSet<usize>> {
+    // the set is consistent even if a thread panicked while holding the lock
+    LIVE_OBJECTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(feature = "heap-walk")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LiveObject {
+    // where the object's header is
+    pub addr: usize,
+    pub kind: Kind,
+    pub size: usize,
+}
+
+// All heap objects that are alive, in order of address, with the `heap-walk`
+// feature. Like `census`, this sees objects from allocation until they are
+// dropped. The objects are listed when `iter_objects` is called, so objects
+// allocated or dropped while iterating don't show up or go away.
+#[cfg(feature = "heap-walk")]
+pub fn iter_objects() -> impl Iterator<Item = LiveObject> {
+    let objects: Vec<_> = live_objects()
+        .iter()
+        .map(|&addr| {
+            let header = unsafe { &*std::ptr::with_exposed_provenance::<Header>(addr) };
+            LiveObject { addr, kind: header.kind(), size: header.size() }
+        })
+        .collect();
+    objects.into_iter()
+}
+
+// With the `tracing` feature, every allocation is a trace event with target
+// `scm_repr::heap`, recording the kind and size of the object and where it
+// was allocated. That is the function in this crate that made the object,
+// like `cons`; put spans around interpreter code to see who called it.
+#[cfg_attr(feature = "tracing", track_caller)]
+pub fn alloc<T: HeapObject>(body: T) -> Box<Object<T>> {
+    let obj = Box::new(Object {
+        header: Header::new(T::KIND, std::mem::size_of::<Object<T>>()),
+        body,
+    });
+    // The layout asks for the alignment, but we don't trust every allocator
+    // (or GC) out there to honor it. A misaligned object would silently
+    // corrupt its tag, so better crash right here.
+    let addr = &*obj as *const Object<T> as usize;
+    assert!(addr % HEAP_ALIGN == 0, "allocator returned misaligned object at {:#x}", addr);
+    let counter = &COUNTERS[T::KIND as usize];
+    counter.count.fetch_add(1, Ordering::Relaxed);
+    counter.bytes.fetch_add(obj.header.size(), Ordering::Relaxed);
+    NET_ALLOCATIONS.with(|net| net[T::KIND as usize].set(net[T::KIND as usize].get() + 1));
+    #[cfg(feature = "heap-walk")]
+    live_objects().insert(addr);
+    #[cfg(feature = "tracing")]
+    tracing::trace!(
+        target: "scm_repr::heap",
+        kind = ?T::KIND,
+        size = obj.header.size(),
+        location = %std::panic::Location::caller(),
+        "alloc"
+    );
+    obj
+}
+
+#[cfg_attr(feature = "tracing", track_caller)]
+pub fn leak<T: HeapObject>(body: T) -> &'static Object<T> {
+    let obj = Box::leak(alloc(body));
+    if reclaimable(T::KIND) {
+        REGIONS.with(|regions| {
+            if let Some(region) = regions.borrow_mut().last_mut() {
+                region.insert(obj as *const Object<T> as usize, free::<T>);
+            }
+        });
+    }
+    obj
+}
+
+// The objects allocated in a region, by address and with the function that
+// frees them.
+type RegionObjects = HashMap<usize, unsafe fn(usize)>;
+
+thread_local! {
+    // The active regions of this thread, innermost last.
+    static REGIONS: RefCell<Vec<RegionObjects>> = const { RefCell::new(vec![]) };
+}
+
+// Only objects that nothing outside refers to behind the scenes can be
+// freed with their region. Symbols are in the symbol table, and substrings
+// and subvectors borrow the contents of the string or vector they were cut
+// from, so those are left to the collector.
+fn reclaimable(kind: Kind) -> bool {
+    matches!(kind, Kind::Pair | Kind::Flonum | Kind::Bignum | Kind::Ratnum | Kind::Box | Kind::Values)
+}
+
+// Debug builds only drop the object and mark it dead, so that using it
+// afterwards panics instead of reading freed memory.
+unsafe fn free<T>(addr: usize) {
+    let obj = addr as *mut Object<T>;
+    if cfg!(debug_assertions) {
+        (*obj).header.set_flag(FLAG_DEAD, true);
+        std::ptr::drop_in_place(obj);
+    } else {
+        drop(Box::from_raw(obj));
+    }
+}
+
+pub struct Region {
+    depth: usize,
+    // regions belong to the thread that allocates in them
+    _thread: PhantomData<*const ()>,
+}
+
+impl Region {
+    // Keeps `x`, and everything reachable from it that was allocated in this
+    // region or one nested in it, from being freed with the region. It moves
+    // to the enclosing region, if there is one.
+    pub fn escape(&self, x: Scm) -> Scm {
+        relocate(x, self.depth, self.depth.checked_sub(1));
+        x
+    }
+
+    // Makes `x`, and everything reachable from it that was allocated in a
+    // region nested in this one, belong to this region.
+    pub(crate) fn claim(&self, x: Scm) -> Scm {
+        relocate(x, self.depth, Some(self.depth));
+        x
+    }
+
+    // How many objects would be freed if the region ended now.
+    pub fn len(&self) -> usize {
+        REGIONS.with(|regions| regions.borrow()[self.depth..].iter().map(HashMap::len).sum())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Moves the objects reachable from `x` that were allocated in region `from`
+// or one nested in it to region `into`, or out of all regions. Only pairs,
+// boxes, multiple values, vectors and syntax objects are looked into, so
+// values kept in anything else have to be moved by themselves.
+pub(crate) fn relocate(x: Scm, from: usize, into: Option<usize>) {
+    REGIONS.with(|regions| {
+        let mut regions = regions.borrow_mut();
+        let mut moved = vec![];
+        let mut seen = std::collections::HashSet::new();
+        let mut todo = vec![x];
+        while let Some(x) = todo.pop() {
+            let addr = match x.header() {
+                Some(header) => header as *const Header as usize,
+                None => continue,
+            };
+            if !seen.insert(addr) {
+                continue
+            }
+            let free = regions[from..].iter_mut().find_map(|region| region.remove(&addr));
+            if free.is_none() && !may_refer_to_younger(x) {
+                continue
+            }
+            moved.extend(free.map(|free| (addr, free)));
+            todo.extend(children(x));
+        }
+        if let Some(into) = into {
+            regions[into].extend(moved);
+        }
+    });
+}
+
+// Objects can't refer to anything allocated after them, except through a box
+// set later. Vectors and syntax objects aren't tracked, so their age is
+// unknown.
+fn may_refer_to_younger(x: Scm) -> bool {
+    matches!(x.header().map(Header::kind), Some(Kind::Box | Kind::Vector | Kind::Syntax))
+}
+
+// An object reachable from `x` that is still in region `from` or one nested
+// in it, if there is one.
+fn local_object(x: Scm, from: usize) -> Option<Scm> {
+    REGIONS.with(|regions| {
+        let regions = regions.borrow();
+        let mut seen = std::collections::HashSet::new();
+        let mut todo = vec![x];
+        while let Some(x) = todo.pop() {
+            let addr = match x.header() {
+                Some(header) => header as *const Header as usize,
+                None => continue,
+            };
+            if !seen.insert(addr) {
+                continue
+            }
+            if regions[from..].iter().any(|region| region.contains_key(&addr)) {
+                return Some(x)
+            }
+            todo.extend(children(x));
+        }
+        None
+    })
+}
+
+struct RegionGuard;
+
+impl Drop for RegionGuard {
+    fn drop(&mut self) {
+        let objects = REGIONS.with(|regions| regions.borrow_mut().pop()).unwrap();
+        // after a panic the objects may still be in use by whoever catches it
+        if std::thread::panicking() {
+            return
+        }
+        for (addr, free) in objects {
+            unsafe { free(addr) }
+        }
+    }
+}
+
+/// Calls `f` with a new region, and frees the pairs, boxes, numbers and
+/// multiple values that it allocates on this thread when it returns, except
+/// for those passed to `region.escape`.
+///
+/// Panics if the result of `f` still refers to an object of the region, in
+/// which case nothing is freed. Debug builds also panic when a freed object
+/// is used later, but release builds really free the memory.
+///
+/// # Safety
+/// Objects allocated in the region that weren't passed to `region.escape`
+/// must not be used after `scoped` returns. The result is checked, but
+/// nothing else is: such an object must not have been stored anywhere that
+/// outlives the region, like a box or a vector made outside it, a global, a
+/// thread-local or a Rust data structure.
+pub unsafe fn scoped<R: Trace>(f: impl FnOnce(&Region) -> R) -> R {
+    let depth = REGIONS.with(|regions| {
+        let mut regions = regions.borrow_mut();
+        regions.push(HashMap::new());
+        regions.len() - 1
+    });
+    let _guard = RegionGuard;
+    let result = f(&Region { depth, _thread: PhantomData });
+    result.trace(&mut |x| {
+        if let Some(local) = local_object(x, depth) {
+            panic!("{} escapes the region it was allocated in", local.kind())
+        }
+    });
+    result
+}
+
+#[test]
+fn objects_are_aligned() {
+    use crate::Scm;
+    use std::mem::size_of;
+    for _ in 0..100 {
+        let p = Scm::from_int(0);
+        let a = leak(crate::Pair::new(p, p));
+        assert_eq!(a as *const _ as usize % HEAP_ALIGN, 0);
+        assert_eq!(a.header.kind(), Kind::Pair);
+        assert_eq!(a.header.size(), size_of::<Header>() + 2 * size_of::<Scm>());
+    }
+}
+
+#[test]
+fn header_fields_are_independent() {
+    let h = Header::new(Kind::Vector, 16);
+    h.set_flag(FLAG_MARK, true);
+    h.set_hash(u32::MAX);
+    assert_eq!(h.kind(), Kind::Vector);
+    assert_eq!(h.size(), 16);
+    assert_eq!(h.hash(), Some(u32::MAX));
+    h.set_flag(FLAG_MARK, false);
+    assert_eq!(h.flags(), FLAG_HASHED);
+}
+
+#[test]
+fn allocations_are_counted_per_thread() {
+    use crate::Scm;
+    let list = crate::cons(Scm::from_int(1), Scm::NIL);
+    assert_eq!(assert_no_net_allocations(|| crate::car(list)), Some(Scm::from_int(1)));
+    assert_no_net_allocations(|| drop(alloc(crate::Pair::new(Scm::NIL, Scm::NIL))));
+
+    let count = AllocationCount::start();
+    let strings = Scm::vector(vec![Scm::string("a rather long string"), Scm::string("and another one")]);
+    assert_eq!(count.outstanding(), [(Kind::String, 2), (Kind::Vector, 1)]);
+    assert!(std::panic::catch_unwind(|| count.assert_none_outstanding()).is_err());
+    std::hint::black_box(strings);
+}
+
+#[cfg(feature = "heap-walk")]
+#[test]
+fn live_objects_can_be_walked() {
+    use crate::Scm;
+    let pair = alloc(crate::Pair::new(Scm::NIL, Scm::NIL));
+    let addr = &*pair as *const Object<crate::Pair> as usize;
+    let found = |addr| iter_objects().find(|obj| obj.addr == addr);
+    let expected = LiveObject { addr, kind: Kind::Pair, size: std::mem::size_of::<Object<crate::Pair>>() };
+    assert_eq!(found(addr), Some(expected));
+    drop(pair);
+    assert_eq!(found(addr), None);
+}
+
+#[test]
+fn regions_free_what_does_not_escape() {
+    let count = AllocationCount::start();
+    let kept = unsafe { scoped(|region| {
+        let garbage: Vec<Scm> = (0..1000).map(|i| crate::cons(Scm::from_int(i), Scm::NIL)).collect();
+        let list = crate::cons(Scm::from_f64(0.5), crate::cons(garbage[0], Scm::NIL));
+        let inner = scoped(|inner| {
+            crate::cons(Scm::from_int(1), Scm::NIL);
+            inner.escape(crate::cons(list, Scm::NIL))
+        });
+        assert_eq!(region.len(), 1000 + 4);
+        region.escape(inner)
+    }) };
+    assert_eq!(count.outstanding(), [(Kind::Pair, 4), (Kind::Flonum, 1)]);
+    assert_eq!(kept.to_string(), "((0.5 (0)))");
+    // symbols outlive any region
+    assert_eq!(unsafe { scoped(|_| Scm::symbol("region-symbol")) }, Scm::symbol("region-symbol"));
+
+    #[cfg(debug_assertions)]
+    {
+        let b = crate::boxes::make_box(Scm::NIL);
+        unsafe { scoped(|_| crate::boxes::set_box(b, crate::cons(Scm::NIL, Scm::NIL)).unwrap()) };
+        let dangling = crate::boxes::unbox(b).unwrap();
+        assert!(std::panic::catch_unwind(|| dangling.as_pair().is_some()).is_err());
+    }
+}
+
+#[test]
+fn results_that_escape_are_rejected() {
+    let escaping = |f: fn() -> Scm| std::panic::catch_unwind(|| unsafe { scoped(|_| f()) }).is_err();
+    assert!(escaping(|| crate::cons(Scm::NIL, Scm::NIL)));
+    assert!(escaping(|| Scm::vector(vec![Scm::from_f64(1.5)])));
+    let outside = crate::boxes::make_box(Scm::NIL);
+    assert!(std::panic::catch_unwind(|| unsafe {
+        scoped(|_| {
+            crate::boxes::set_box(outside, crate::cons(Scm::NIL, Scm::NIL)).unwrap();
+            outside
+        })
+    })
+    .is_err());
+    // Nothing is freed when the region ends with a panic.
+    assert!(crate::boxes::unbox(outside).unwrap().as_pair().is_some());
+}
@@ -0,0 +1,249 @@
+//! Exhaustive dispatch over the type of a value.
+
+use std::fmt;
+use crate::bigint::BigInt;
+use crate::heap::Kind;
+use crate::syntax::SourceLocation;
+use crate::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ScmKind {
+    Nil,
+    Boolean,
+    Eof,
+    Char,
+    Integer,
+    Pair,
+    Symbol,
+    String,
+    Vector,
+    Flonum,
+    Bignum,
+    Rational,
+    Box,
+    Values,
+    Promise,
+    Port,
+    Foreign,
+    Environment,
+    Syntax,
+    Bitvector,
+    Map,
+    Deque,
+    CharSet,
+    Code,
+    Continuation,
+    Parameter,
+    Mutex,
+    CondVar,
+    Thread,
+    Sender,
+    Receiver,
+    Time,
+    Duration,
+    RandomState,
+    Regex,
+    // Never returned by `kind()`; stands for any of the numeric kinds when
+    // an operation expects a number.
+    Number,
+}
+
+impl fmt::Display for ScmKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ScmKind::Nil => "empty list",
+            ScmKind::Boolean => "boolean",
+            ScmKind::Eof => "eof object",
+            ScmKind::Char => "character",
+            ScmKind::Integer => "integer",
+            ScmKind::Pair => "pair",
+            ScmKind::Symbol => "symbol",
+            ScmKind::String => "string",
+            ScmKind::Vector => "vector",
+            ScmKind::Flonum => "flonum",
+            ScmKind::Bignum => "bignum",
+            ScmKind::Rational => "rational",
+            ScmKind::Box => "box",
+            ScmKind::Values => "multiple values",
+            ScmKind::Promise => "promise",
+            ScmKind::Port => "port",
+            ScmKind::Foreign => "foreign object",
+            ScmKind::Environment => "environment",
+            ScmKind::Syntax => "syntax object",
+            ScmKind::Bitvector => "bitvector",
+            ScmKind::Map => "map",
+            ScmKind::Deque => "deque",
+            ScmKind::CharSet => "char-set",
+            ScmKind::Code => "code object",
+            ScmKind::Continuation => "continuation",
+            ScmKind::Parameter => "parameter",
+            ScmKind::Mutex => "mutex",
+            ScmKind::CondVar => "condition variable",
+            ScmKind::Thread => "thread",
+            ScmKind::Sender => "channel sender",
+            ScmKind::Receiver => "channel receiver",
+            ScmKind::Time => "time",
+            ScmKind::Duration => "duration",
+            ScmKind::RandomState => "random state",
+            ScmKind::Regex => "regex",
+            ScmKind::Number => "number",
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ScmView<'a> {
+    Nil,
+    Boolean(bool),
+    Eof,
+    Char(char),
+    Integer(i64),
+    Pair(&'a (Scm, Scm)),
+    Symbol(&'a str),
+    String(&'a str),
+    Vector(&'a [Scm]),
+    Flonum(f64),
+    Bignum(&'a BigInt),
+    Rational(&'a BigInt, &'a BigInt),
+    Box(Scm),
+    Values(&'a [Scm]),
+    Promise,
+    Port,
+    Foreign(&'a str),
+    Environment,
+    Syntax(Scm, &'a SourceLocation),
+    Bitvector,
+    Map,
+    Deque,
+    CharSet,
+    Code,
+    Continuation,
+    Parameter,
+    Mutex,
+    CondVar,
+    Thread,
+    Sender,
+    Receiver,
+    Time(std::time::SystemTime),
+    Duration(std::time::Duration),
+    RandomState,
+    Regex,
+}
+
+impl Scm {
+    pub fn kind(&self) -> ScmKind {
+        match self.tag() {
+            TAG_INTEGER => ScmKind::Integer,
+            TAG_PAIR => ScmKind::Pair,
+            TAG_SYMBOL => ScmKind::Symbol,
+            TAG_STRING => ScmKind::String,
+            TAG_VECTOR => ScmKind::Vector,
+            TAG_SPECIAL => match self.addr() {
+                SPECIAL_NIL => ScmKind::Nil,
+                SPECIAL_TRUE | SPECIAL_FALSE => ScmKind::Boolean,
+                SPECIAL_EOF => ScmKind::Eof,
+                _ if self.is_char() => ScmKind::Char,
+                _ if self.is_string() => ScmKind::String,
+                _ => unreachable!("invalid special value {:#x}", self.addr()),
+            },
+            _ => match self.header().unwrap().kind() {
+                Kind::Pair => ScmKind::Pair,
+                Kind::Symbol => ScmKind::Symbol,
+                Kind::String => ScmKind::String,
+                Kind::Vector => ScmKind::Vector,
+                Kind::Flonum => ScmKind::Flonum,
+                Kind::Bignum => ScmKind::Bignum,
+                Kind::Ratnum => ScmKind::Rational,
+                Kind::Box => ScmKind::Box,
+                Kind::Values => ScmKind::Values,
+                Kind::Promise => ScmKind::Promise,
+                Kind::Port => ScmKind::Port,
+                Kind::Foreign => ScmKind::Foreign,
+                Kind::Environment => ScmKind::Environment,
+                Kind::Syntax => ScmKind::Syntax,
+                Kind::Bitvector => ScmKind::Bitvector,
+                Kind::Map => ScmKind::Map,
+                Kind::Deque => ScmKind::Deque,
+                Kind::CharSet => ScmKind::CharSet,
+                Kind::Code => ScmKind::Code,
+                Kind::Continuation => ScmKind::Continuation,
+                Kind::Parameter => ScmKind::Parameter,
+                Kind::Mutex => ScmKind::Mutex,
+                Kind::CondVar => ScmKind::CondVar,
+                Kind::Thread => ScmKind::Thread,
+                Kind::Sender => ScmKind::Sender,
+                Kind::Receiver => ScmKind::Receiver,
+                Kind::Time => ScmKind::Time,
+                Kind::Duration => ScmKind::Duration,
+                Kind::RandomState => ScmKind::RandomState,
+                Kind::Regex => ScmKind::Regex,
+            },
+        }
+    }
+
+    pub fn classify(&self) -> ScmView<'_> {
+        match self.kind() {
+            ScmKind::Nil => ScmView::Nil,
+            ScmKind::Boolean => ScmView::Boolean(self.is_true()),
+            ScmKind::Eof => ScmView::Eof,
+            ScmKind::Char => ScmView::Char(self.as_char().unwrap()),
+            ScmKind::Integer => ScmView::Integer(self.as_integer().unwrap()),
+            ScmKind::Pair => ScmView::Pair(self.as_pair().unwrap()),
+            ScmKind::Symbol => ScmView::Symbol(self.as_symbol().unwrap()),
+            ScmKind::String => ScmView::String(self.as_str().unwrap()),
+            ScmKind::Vector => ScmView::Vector(self.as_vector().unwrap()),
+            ScmKind::Flonum => ScmView::Flonum(self.as_f64().unwrap()),
+            ScmKind::Bignum => ScmView::Bignum(self.as_bignum().unwrap()),
+            ScmKind::Rational => {
+                let (n, d) = self.as_rational().unwrap();
+                ScmView::Rational(n, d)
+            }
+            ScmKind::Box => ScmView::Box(crate::boxes::unbox(*self).unwrap()),
+            ScmKind::Values => ScmView::Values(self.as_values()),
+            ScmKind::Promise => ScmView::Promise,
+            ScmKind::Port => ScmView::Port,
+            ScmKind::Environment => ScmView::Environment,
+            ScmKind::Syntax => ScmView::Syntax(crate::syntax::datum(*self), crate::syntax::location(*self).unwrap()),
+            ScmKind::Bitvector => ScmView::Bitvector,
+            ScmKind::Map => ScmView::Map,
+            ScmKind::Deque => ScmView::Deque,
+            ScmKind::CharSet => ScmView::CharSet,
+            ScmKind::Code => ScmView::Code,
+            ScmKind::Continuation => ScmView::Continuation,
+            ScmKind::Parameter => ScmView::Parameter,
+            ScmKind::Mutex => ScmView::Mutex,
+            ScmKind::CondVar => ScmView::CondVar,
+            ScmKind::Thread => ScmView::Thread,
+            ScmKind::Sender => ScmView::Sender,
+            ScmKind::Receiver => ScmView::Receiver,
+            ScmKind::Time => ScmView::Time(crate::time::time_value(*self).unwrap()),
+            ScmKind::Duration => ScmView::Duration(crate::time::duration_value(*self).unwrap()),
+            ScmKind::RandomState => ScmView::RandomState,
+            ScmKind::Regex => ScmView::Regex,
+            ScmKind::Foreign => ScmView::Foreign(crate::foreign::type_name(*self).unwrap()),
+            ScmKind::Number => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn classify_covers_all_kinds() {
+    fn describe(x: Scm) -> String {
+        match x.classify() {
+            ScmView::Nil => "()".to_string(),
+            ScmView::Boolean(b) => format!("{}", b),
+            ScmView::Eof => "eof".to_string(),
+            ScmView::Integer(i) => format!("{}", i),
+            ScmView::Pair(&(a, d)) => format!("({} . {})", describe(a), describe(d)),
+            ScmView::Symbol(s) => s.to_string(),
+            ScmView::String(s) => format!("{:?}", s),
+            ScmView::Vector(items) => format!("#({})", items.iter().map(|&x| describe(x)).collect::<Vec<_>>().join(" ")),
+            _ => x.to_string(),
+        }
+    }
+
+    let x = cons(Scm::symbol("a"), cons(Scm::string("b"), Scm::vector(vec![Scm::TRUE, Scm::from_int(-1), Scm::EOF])));
+    assert_eq!(describe(x), "(a . (\"b\" . #(true -1 eof)))");
+    assert_eq!(x.kind(), ScmKind::Pair);
+    assert_eq!(Scm::FALSE.kind(), ScmKind::Boolean);
+}
@@ -0,0 +1,49 @@
+//! Static assertions on the memory layout that tagging relies on, checked
+//! for whatever target the crate is built for. A type or target that breaks
+//! one of them fails the build instead of corrupting tags at run time.
+
+use std::mem::{align_of, offset_of, size_of};
+use crate::heap::{HeapObject, Object, HEAP_ALIGN};
+use crate::*;
+
+// A value is one word, and `Option<Scm>` too (see lib.rs).
+const _: () = assert!(size_of::<Scm>() == size_of::<usize>());
+const _: () = assert!(align_of::<Scm>() == align_of::<usize>());
+
+// The tags fit below the alignment of heap objects, and they are told apart
+// from immediates by `MASK_IMMEDIATE` alone.
+const _: () = assert!(TAG_MASK == (1 << N_TAG_BITS) - 1 && HEAP_ALIGN > TAG_MASK);
+const fn is_immediate_tag(tag: usize) -> bool {
+    tag & MASK_IMMEDIATE == IMMEDIATE_BITS
+}
+const _: () = assert!(is_immediate_tag(TAG_INTEGER) && is_immediate_tag(TAG_SPECIAL));
+const _: () = assert!(!is_immediate_tag(TAG_POINTER) && !is_immediate_tag(TAG_PAIR));
+const _: () = assert!(!is_immediate_tag(TAG_SYMBOL) && !is_immediate_tag(TAG_STRING) && !is_immediate_tag(TAG_VECTOR));
+
+// Every character fits above the special tag, and the specials differ in
+// their low byte.
+const _: () = assert!(char::MAX as usize <= usize::MAX >> CHAR_SHIFT);
+const _: () = assert!(SPECIAL_SHORT_STRING < 1 << CHAR_SHIFT && SPECIAL_CHAR < 1 << CHAR_SHIFT);
+const _: () = assert!(MAX_FIXNUM >= (1 << 28) - 1);
+
+// Every kind of object can be tagged, starts with its header, and is small
+// enough for the header's size field.
+const fn fits<T: HeapObject>() -> bool {
+    align_of::<Object<T>>() >= HEAP_ALIGN
+        && offset_of!(Object<T>, header) == 0
+        && size_of::<Object<T>>().div_ceil(HEAP_ALIGN) <= u16::MAX as usize
+}
+
+const _: () = assert!(fits::<Pair>() && fits::<Str>() && fits::<Vector>());
+const _: () = assert!(fits::<symbol::Symbol>() && fits::<boxes::ScmBox>() && fits::<values::Values>());
+const _: () = assert!(fits::<num::Flonum>() && fits::<num::Bignum>() && fits::<num::Ratnum>());
+const _: () = assert!(fits::<promise::Promise>() && fits::<port::Port>() && fits::<foreign::Foreign>());
+const _: () = assert!(fits::<env::Environment>() && fits::<syntax::Syntax>() && fits::<bitvector::Bitvector>());
+const _: () = assert!(fits::<hamt::PersistentMap>() && fits::<deque::Deque>() && fits::<chars::CharSet>());
+const _: () = assert!(fits::<code::CodeObject>() && fits::<continuation::Continuation>() && fits::<parameter::Parameter>());
+const _: () = assert!(fits::<channel::Sender>() && fits::<channel::ScmReceiver>());
+const _: () = assert!(fits::<time::Time>() && fits::<time::ScmDuration>() && fits::<random::RandomState>());
+#[cfg(feature = "sync")]
+const _: () = assert!(fits::<threads::ScmMutex>() && fits::<threads::ScmCondVar>() && fits::<threads::ScmThread>());
+#[cfg(feature = "regex")]
+const _: () = assert!(fits::<regex::ScmRegex>());
@@ -0,0 +1,659 @@
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "sync")]
+mod atomic;
+pub mod bigint;
+pub mod bitvector;
+pub mod boxes;
+pub mod branded;
+pub mod capi;
+mod cast;
+pub mod channel;
+pub mod chars;
+pub mod code;
+pub mod continuation;
+pub mod convert;
+pub mod copy;
+pub mod debug;
+pub mod deque;
+pub mod env;
+mod error;
+#[cfg(feature = "interp")]
+pub mod eval;
+pub mod foreign;
+mod freeze;
+#[cfg(feature = "sync")]
+pub mod gc;
+pub mod hamt;
+pub mod hashcons;
+pub mod heap;
+mod kind;
+mod layout_tests;
+pub mod lists;
+mod lock;
+pub mod num;
+pub mod parameter;
+pub mod port;
+pub mod order;
+mod printer;
+#[cfg(feature = "interp")]
+pub mod proc;
+pub mod promise;
+pub mod properties;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod random;
+pub mod reader;
+#[cfg(feature = "regex")]
+pub mod regex;
+pub mod repr;
+pub mod rope;
+pub mod snapshot;
+pub mod stream;
+pub mod strings;
+pub mod symbol;
+pub mod syntax;
+#[cfg(feature = "sync")]
+pub mod threads;
+pub mod time;
+pub mod trace;
+pub mod values;
+pub mod vectors;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use std::borrow::Cow;
+use std::cell::{Cell, UnsafeCell};
+use std::mem::size_of;
+use std::ptr::{self, NonNull};
+use std::sync::OnceLock;
+use heap::{HeapObject, Header, Kind, Object};
+
+#[cfg(feature = "sync")]
+pub use atomic::AtomicScm;
+pub use cast::ScmCast;
+pub use error::{ChannelError, ConversionError, EnvError, MutationError, NumError, PortError, ReadError, SnapshotError, TypeError};
+#[cfg(feature = "interp")]
+pub use error::EvalError;
+#[cfg(feature = "sync")]
+pub use error::ThreadError;
+pub use kind::{ScmKind, ScmView};
+pub use printer::Displayed;
+
+const N_TAG_BITS: usize = 3;
+const TAG_MASK: usize = 0b_111;
+const TAG_POINTER: usize = 0b_000;
+const TAG_INTEGER: usize = 0b_001;
+const TAG_PAIR: usize = 0b_010;
+const TAG_SPECIAL: usize = 0b_011;
+const TAG_SYMBOL: usize = 0b_100;
+const TAG_STRING: usize = 0b_101;
+const TAG_VECTOR: usize = 0b_110;
+
+// Fixnums are a word minus the tag bits: 61 bits on 64-bit targets, 29 on 32-bit.
+pub const MIN_FIXNUM: i64 = isize::MIN as i64 >> N_TAG_BITS;
+pub const MAX_FIXNUM: i64 = isize::MAX as i64 >> N_TAG_BITS;
+
+const SPECIAL_NIL: usize = 0b_00 << N_TAG_BITS | TAG_SPECIAL;
+const SPECIAL_FALSE: usize = 0b_01 << N_TAG_BITS | TAG_SPECIAL;
+const SPECIAL_TRUE: usize = 0b_10 << N_TAG_BITS | TAG_SPECIAL;
+const SPECIAL_EOF: usize = 0b_11 << N_TAG_BITS | TAG_SPECIAL;
+
+// Characters are specials too, with the code point above the low byte.
+const SPECIAL_CHAR: usize = 0b_100 << N_TAG_BITS | TAG_SPECIAL;
+const CHAR_SHIFT: usize = 8;
+
+// Strings of up to 7 bytes (3 on 32-bit targets) are specials too: the low
+// byte is the tag and the other bytes are the string itself, padded with
+// zeros. The bytes are laid out so that they are in order in memory on both
+// little- and big-endian targets, and `as_str` can point right into the word.
+// Strings containing NUL are never short, so the length is the number of
+// bytes before the first zero.
+const SPECIAL_SHORT_STRING: usize = 0b_101 << N_TAG_BITS | TAG_SPECIAL;
+const SHORT_STRING_MAX: usize = size_of::<usize>() - 1;
+const SHORT_STRING_OFFSET: usize = if cfg!(target_endian = "little") { 1 } else { 0 };
+
+// integers and specials are the only tags with the lsb set and bit 2 clear
+const MASK_IMMEDIATE: usize = 0b101;
+const IMMEDIATE_BITS: usize = 0b001;
+
+// Zero must never be a valid encoding, so that `Option<Scm>` can use it as None.
+// Pointers are non-null, and every immediate has a non-zero tag.
+const _: () = assert!(TAG_INTEGER != 0 && TAG_SPECIAL != 0);
+const _: () = assert!(size_of::<Option<Scm>>() == size_of::<usize>());
+const _: () = assert!(size_of::<Option<branded::Scm>>() == size_of::<usize>());
+
+// The word is kept as a pointer rather than a `usize` so that values pointing
+// into the heap keep their provenance. Immediates are pointers without
+// provenance and must never be dereferenced.
+// `==` is identity (Scheme's `eq?`). Since `Scm` wraps a pointer it can't be
+// used in patterns, but the constants below work in match guards.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Scm {
+    value: NonNull<u8>,
+}
+
+// Values are not `Send` by default: headers are mutated through plain `Cell`s
+// and would race. The `sync` feature makes every mutable part of an object
+// atomic, and then values can be shared freely.
+#[cfg(feature = "sync")]
+unsafe impl Send for Scm {}
+#[cfg(feature = "sync")]
+unsafe impl Sync for Scm {}
+
+impl Scm {
+    pub const NIL: Scm = Scm::nil();
+    pub const TRUE: Scm = Scm::from_bool(true);
+    pub const FALSE: Scm = Scm::from_bool(false);
+    pub const EOF: Scm = Scm::eof();
+
+    pub fn symbol(name: &str) -> Self {
+        symbol::intern(name)
+    }
+
+    pub fn string(s: &str) -> Self {
+        if s.len() <= SHORT_STRING_MAX && !s.contains('\0') {
+            return Scm::short_string(s)
+        }
+        Scm::from_object(heap::leak(Str::new(s)))
+    }
+
+    fn short_string(s: &str) -> Self {
+        let mut bytes = [0; size_of::<usize>()];
+        bytes[SHORT_STRING_OFFSET..SHORT_STRING_OFFSET + s.len()].copy_from_slice(s.as_bytes());
+        let tag_byte = if cfg!(target_endian = "little") { 0 } else { size_of::<usize>() - 1 };
+        bytes[tag_byte] = SPECIAL_SHORT_STRING as u8;
+        Scm::immediate(usize::from_ne_bytes(bytes))
+    }
+
+    // A string that points at `s` instead of copying it, for large literal
+    // tables and the like.
+    pub fn from_static_str(s: &'static str) -> Self {
+        Scm::from_object(heap::leak(Str::borrowed(s)))
+    }
+
+    pub fn vector(items: Vec<Scm>) -> Self {
+        Scm::from_object(heap::leak(Vector::new(Cow::Owned(items))))
+    }
+
+    // A new vector with a copy of `items`.
+    pub fn vector_from_slice(items: &[Scm]) -> Self {
+        Scm::vector(items.to_vec())
+    }
+
+    pub const fn nil() -> Self {
+        Scm::immediate(SPECIAL_NIL)
+    }
+
+    pub const fn eof() -> Self {
+        Scm::immediate(SPECIAL_EOF)
+    }
+
+    pub const fn from_bool(b: bool) -> Self {
+        Scm::immediate(if b { SPECIAL_TRUE } else { SPECIAL_FALSE })
+    }
+
+    pub const fn from_char(c: char) -> Self {
+        Scm::immediate((c as usize) << CHAR_SHIFT | SPECIAL_CHAR)
+    }
+
+    // `value` must be in fixnum range; see `num::integer` for arbitrary integers.
+    pub const fn from_int(value: i64) -> Self {
+        debug_assert!(MIN_FIXNUM <= value && value <= MAX_FIXNUM);
+        Scm::immediate((value as usize) << N_TAG_BITS | TAG_INTEGER)
+    }
+
+    const fn immediate(bits: usize) -> Self {
+        debug_assert!(bits & MASK_IMMEDIATE == IMMEDIATE_BITS);
+        Scm {
+            // immediate tags are never zero
+            value: unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(bits)) }
+        }
+    }
+
+    /// The raw word, for passing values through FFI or JIT-generated code.
+    /// The pointer's provenance is exposed so `from_raw` can pick it up again.
+    pub fn to_raw(self) -> usize {
+        self.value.as_ptr().expose_provenance()
+    }
+
+    /// # Safety
+    /// `raw` must have been obtained from `to_raw`, and the object it refers
+    /// to (if any) must still be alive. Immediates can be round-tripped freely.
+    pub unsafe fn from_raw(raw: usize) -> Self {
+        Scm {
+            value: NonNull::new(ptr::with_exposed_provenance_mut(raw)).expect("zero is not a valid Scm")
+        }
+    }
+
+    fn addr(&self) -> usize {
+        self.value.as_ptr().addr()
+    }
+
+    fn tag(&self) -> usize {
+        self.addr() & TAG_MASK
+    }
+
+    pub fn is_immediate(&self) -> bool {
+        self.addr() & MASK_IMMEDIATE == IMMEDIATE_BITS
+    }
+
+    pub fn is_symbol(&self) -> bool {
+        self.tag() == TAG_SYMBOL
+    }
+
+    pub fn is_string(&self) -> bool {
+        self.tag() == TAG_STRING || self.is_short_string()
+    }
+
+    fn is_short_string(&self) -> bool {
+        self.addr() & 0xff == SPECIAL_SHORT_STRING
+    }
+
+    pub fn is_vector(&self) -> bool {
+        self.tag() == TAG_VECTOR
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.addr() == SPECIAL_NIL
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.addr() == SPECIAL_EOF
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.addr() {
+            SPECIAL_TRUE => Some(true),
+            SPECIAL_FALSE => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        if self.addr() & 0xff == SPECIAL_CHAR {
+            char::from_u32((self.addr() >> CHAR_SHIFT) as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_char(&self) -> bool {
+        self.addr() & 0xff == SPECIAL_CHAR
+    }
+
+    // Everything but #f counts as true in a conditional.
+    pub fn is_true(&self) -> bool {
+        self.addr() != SPECIAL_FALSE
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        if self.tag() == TAG_INTEGER {
+            Some((self.addr() as isize >> N_TAG_BITS) as i64)  // arithmetic shift keeps the sign
+        } else {
+            None
+        }
+    }
+
+    // Every heap object starts with a header, whatever its tag.
+    pub fn header(&self) -> Option<&Header> {
+        if self.is_immediate() {
+            None
+        } else {
+            let tag = self.tag();
+            unsafe {
+                let obj: &Object<()> = ptr_to_ref(self.untagged(tag));
+                Some(&obj.header)
+            }
+        }
+    }
+
+    pub fn as_symbol(&self) -> Option<&str> {
+        self.as_object::<symbol::Symbol>().map(|sym| sym.name())
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if self.is_short_string() {
+            // the word is its own storage; its bytes are plain data, since
+            // immediates have no provenance
+            let word = unsafe { &*(self as *const Scm as *const [u8; size_of::<usize>()]) };
+            let bytes = &word[SHORT_STRING_OFFSET..SHORT_STRING_OFFSET + SHORT_STRING_MAX];
+            let len = bytes.iter().position(|&b| b == 0).unwrap_or(SHORT_STRING_MAX);
+            return Some(unsafe { std::str::from_utf8_unchecked(&bytes[..len]) })
+        }
+        self.as_object::<Str>().map(|s| s.as_str())
+    }
+
+    pub fn as_vector(&self) -> Option<&[Scm]> {
+        self.as_object::<Vector>().map(|v| v.items())
+    }
+
+    pub fn as_pair(&self) -> Option<&(Scm, Scm)> {
+        // only the unsafe functions in `lists` write to pairs, and only while
+        // nothing is borrowing them
+        self.as_object::<Pair>().map(|obj| unsafe { &*obj.body.0.get() })
+    }
+
+    // The object must stay alive for as long as the returned value is in use.
+    pub(crate) fn from_object<T: HeapObject>(obj: &Object<T>) -> Self {
+        let tag = tag_of_kind(T::KIND);
+        let ptr = NonNull::from(obj).cast::<u8>();
+        Scm {
+            // tagging an aligned, non-null pointer can't make it null
+            value: unsafe { NonNull::new_unchecked(ptr.as_ptr().map_addr(|a| a + tag)) }
+        }
+    }
+
+    fn untagged(&self, tag: usize) -> *const u8 {
+        self.value.as_ptr().map_addr(|a| a - tag)
+    }
+
+    pub(crate) fn as_object<T: HeapObject>(&self) -> Option<&'static Object<T>> {
+        let tag = tag_of_kind(T::KIND);
+        if self.tag() != tag {
+            return None
+        }
+        // Only the header may be read before the kind is known: a reference
+        // to an `Object<T>` that is really something smaller would reach past
+        // the end of its allocation.
+        if tag == TAG_POINTER && self.header()?.kind() != T::KIND {
+            return None
+        }
+        let obj: &Object<T> = unsafe { ptr_to_ref(self.untagged(tag)) };
+        debug_assert_eq!(obj.header.kind(), T::KIND);
+        debug_assert!(obj.header.flags() & heap::FLAG_DEAD == 0, "object used after its region ended");
+        Some(obj)
+    }
+}
+
+// Kinds that have no tag of their own live behind TAG_POINTER and are told
+// apart by their header.
+const fn tag_of_kind(kind: Kind) -> usize {
+    match kind {
+        Kind::Pair => TAG_PAIR,
+        Kind::Symbol => TAG_SYMBOL,
+        Kind::String => TAG_STRING,
+        Kind::Vector => TAG_VECTOR,
+        Kind::Flonum | Kind::Bignum | Kind::Ratnum | Kind::Box | Kind::Values | Kind::Promise | Kind::Port | Kind::Foreign | Kind::Environment | Kind::Syntax | Kind::Bitvector | Kind::Map | Kind::Deque | Kind::CharSet | Kind::Code | Kind::Continuation | Kind::Parameter | Kind::Mutex | Kind::CondVar | Kind::Thread | Kind::Sender | Kind::Receiver | Kind::Time | Kind::Duration | Kind::RandomState | Kind::Regex => TAG_POINTER,
+    }
+}
+
+unsafe fn ptr_to_ref<T>(p: *const u8) -> &'static T {
+    &*(p as *const T)
+}
+
+// Pairs are immutable to safe code. The cell is there for the destructive
+// list operations in `lists`, which relink the cdrs of lists nobody else has
+// seen yet.
+pub(crate) struct Pair(UnsafeCell<(Scm, Scm)>);
+
+impl Pair {
+    pub(crate) fn new(car: Scm, cdr: Scm) -> Self {
+        Pair(UnsafeCell::new((car, cdr)))
+    }
+}
+
+impl HeapObject for Pair {
+    const KIND: Kind = Kind::Pair;
+}
+
+// Flat, or a rope node made by `rope::string_append` that is only flattened
+// once something asks for its contents. Flat strings may borrow static data.
+// The cells are there for `strings::string_set`, which copies borrowed text
+// before changing it.
+struct Str {
+    flat: UnsafeCell<OnceLock<Cow<'static, str>>>,
+    parts: Option<(Scm, Scm)>,
+    len: Cell<usize>,
+}
+
+impl Str {
+    fn new(s: &str) -> Self {
+        Str { flat: UnsafeCell::new(OnceLock::from(Cow::Owned(s.to_owned()))), parts: None, len: Cell::new(s.len()) }
+    }
+
+    fn borrowed(s: &'static str) -> Self {
+        Str { flat: UnsafeCell::new(OnceLock::from(Cow::Borrowed(s))), parts: None, len: Cell::new(s.len()) }
+    }
+
+    fn flat(&self) -> &OnceLock<Cow<'static, str>> {
+        unsafe { &*self.flat.get() }
+    }
+
+    fn as_str(&self) -> &str {
+        self.flat().get_or_init(|| Cow::Owned(rope::flatten(self)))
+    }
+}
+
+impl HeapObject for Str {
+    const KIND: Kind = Kind::String;
+}
+
+// May borrow the items of another vector (see `vectors::vector_slice`).
+// Vectors are immutable to safe code; the cell is there for
+// `vectors::vector_set`, which copies borrowed items before writing.
+pub(crate) struct Vector(UnsafeCell<Cow<'static, [Scm]>>);
+
+impl Vector {
+    pub(crate) fn new(items: Cow<'static, [Scm]>) -> Self {
+        Vector(UnsafeCell::new(items))
+    }
+
+    pub(crate) fn items(&self) -> &[Scm] {
+        unsafe { &*self.0.get() }
+    }
+}
+
+impl HeapObject for Vector {
+    const KIND: Kind = Kind::Vector;
+}
+
+pub fn cons(car: Scm, cdr: Scm) -> Scm {
+    Scm::from_object(heap::leak(Pair::new(car, cdr)))
+}
+
+pub fn car(scm: Scm) -> Option<Scm> {
+    scm.as_pair().map(|p| p.0)
+}
+
+pub fn cdr(scm: Scm) -> Option<Scm> {
+    scm.as_pair().map(|p| p.1)
+}
+
+pub fn is_pair(scm: Scm) -> bool {
+    scm.as_pair().is_some()
+}
+
+pub fn is_integer(scm: Scm) -> bool {
+    scm.as_integer().is_some()
+}
+
+pub fn is_null(scm: Scm) -> bool {
+    scm.is_nil()
+}
+
+pub fn is_number(scm: Scm) -> bool {
+    num::is_number(scm)
+}
+
+pub fn is_boolean(scm: Scm) -> bool {
+    scm.as_bool().is_some()
+}
+
+pub fn is_symbol(scm: Scm) -> bool {
+    scm.is_symbol()
+}
+
+pub fn is_string(scm: Scm) -> bool {
+    scm.is_string()
+}
+
+pub fn is_vector(scm: Scm) -> bool {
+    scm.is_vector()
+}
+
+#[test]
+fn integer_vs_pointers() {
+    for i in 0..10 {
+        let x = Scm::from_int(i);
+        let p = cons(x, x);
+
+        assert!(is_integer(x));
+        assert!(!is_pair(x));
+        assert!(is_pair(p));
+        assert!(!is_integer(p));
+    }
+}
+
+#[test]
+fn negative_fixnums_keep_their_sign() {
+    for i in [-1, -2, -12345] {
+        assert_eq!(Scm::from_int(i).as_integer(), Some(i));
+    }
+    assert!(Scm::from_int(-8).as_integer().unwrap() < 0);
+}
+
+#[test]
+fn pointer_tags_are_exclusive() {
+    let values = [
+        Scm::from_int(-3),
+        Scm::nil(),
+        cons(Scm::nil(), Scm::nil()),
+        Scm::symbol("foo"),
+        Scm::string("too long to be immediate"),
+        Scm::vector(vec![Scm::from_int(1)]),
+    ];
+    for (i, x) in values.iter().enumerate() {
+        let kinds = [
+            is_integer(*x),
+            is_null(*x),
+            is_pair(*x),
+            is_symbol(*x),
+            is_string(*x),
+            is_vector(*x),
+        ];
+        for (j, &k) in kinds.iter().enumerate() {
+            assert_eq!(k, i == j);
+        }
+        assert_eq!(x.is_immediate(), i < 2);
+    }
+    assert_eq!(values[3].as_symbol(), Some("foo"));
+    assert_eq!(values[4].as_str(), Some("too long to be immediate"));
+}
+
+#[test]
+fn constants_in_guards_and_tables() {
+    const TABLE: [(&str, Scm); 4] = [
+        ("nil", Scm::NIL),
+        ("true", Scm::TRUE),
+        ("false", Scm::FALSE),
+        ("eof", Scm::EOF),
+    ];
+
+    fn name(x: Scm) -> &'static str {
+        match x {
+            x if x == Scm::NIL => "nil",
+            x if x == Scm::TRUE => "true",
+            x if x == Scm::FALSE => "false",
+            x if x.is_eof() => "eof",
+            _ => "other",
+        }
+    }
+
+    for &(n, x) in TABLE.iter() {
+        assert_eq!(name(x), n);
+        assert!(x.is_immediate());
+    }
+    assert_eq!(name(Scm::from_int(0)), "other");
+    assert!(is_boolean(Scm::FALSE) && !Scm::FALSE.is_true());
+    assert!(!is_boolean(Scm::NIL) && Scm::NIL.is_true());
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn values_cross_threads() {
+    let list = cons(Scm::from_int(1), cons(Scm::string("two"), Scm::NIL));
+    let printed = std::thread::spawn(move || {
+        list.header().unwrap().set_hash(42);
+        list.to_string()
+    });
+    assert_eq!(printed.join().unwrap(), "(1 \"two\")");
+    assert_eq!(list.header().unwrap().hash(), Some(42));
+}
+
+#[test]
+fn raw_round_trip() {
+    let values = [Scm::from_int(-7), Scm::NIL, cons(Scm::TRUE, Scm::NIL), Scm::string("x")];
+    for &x in values.iter() {
+        let y = unsafe { Scm::from_raw(x.to_raw()) };
+        assert_eq!(x, y);
+        assert_eq!(x.kind(), y.kind());
+    }
+    assert_eq!(car(unsafe { Scm::from_raw(values[2].to_raw()) }), Some(Scm::TRUE));
+}
+
+#[test]
+fn static_strings_are_not_copied() {
+    static HELP: &str = "(car pair) returns the first element of a pair";
+    let s = Scm::from_static_str(HELP);
+    assert!(s.is_string());
+    assert_eq!(s.as_str().unwrap().as_ptr(), HELP.as_ptr());
+    assert_eq!(s.to_string(), format!("{:?}", HELP));
+}
+
+#[test]
+fn short_strings_are_immediate() {
+    let longest = &"1234567"[..SHORT_STRING_MAX];
+    for s in ["", "a", "λ", longest] {
+        let x = Scm::string(s);
+        assert!(x.is_immediate() && x.is_string());
+        assert_eq!(x.as_str(), Some(s));
+        assert_eq!(x, Scm::string(s));
+    }
+    assert!(!Scm::string(&"12345678"[..SHORT_STRING_MAX + 1]).is_immediate());
+    assert!(!Scm::string("a\0b").is_immediate());
+    assert_eq!(Scm::string("a\0b").as_str(), Some("a\0b"));
+    assert_eq!(Scm::string("ab").kind(), ScmKind::String);
+    assert_eq!(Scm::string("ab").to_string(), "\"ab\"");
+}
+
+#[test]
+fn fixnums_fill_the_word() {
+    for i in [MIN_FIXNUM, -1, 0, 1, MAX_FIXNUM] {
+        assert_eq!(Scm::from_int(i).as_integer(), Some(i));
+    }
+    assert_eq!(Scm::from_int(-1).to_raw(), usize::MAX & !TAG_MASK | TAG_INTEGER);
+    assert_eq!(Scm::from_char(char::MAX).as_char(), Some(char::MAX));
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn layout_64_bit() {
+    assert_eq!(size_of::<Scm>(), 8);
+    assert_eq!(MAX_FIXNUM, (1 << 60) - 1);
+    assert_eq!(SHORT_STRING_MAX, 7);
+}
+
+#[cfg(target_pointer_width = "32")]
+#[test]
+fn layout_32_bit() {
+    assert_eq!(size_of::<Scm>(), 4);
+    assert_eq!(MAX_FIXNUM, (1 << 28) - 1);
+    assert_eq!(SHORT_STRING_MAX, 3);
+}
+
+// The first character of a short string is the second byte in memory on
+// little-endian targets, and the most significant byte on big-endian ones.
+#[cfg(target_endian = "little")]
+#[test]
+fn short_string_layout_little_endian() {
+    assert_eq!(Scm::string("ab").to_raw(), 0x6261 << 8 | SPECIAL_SHORT_STRING);
+}
+
+#[cfg(target_endian = "big")]
+#[test]
+fn short_string_layout_big_endian() {
+    let top = (size_of::<usize>() - 1) * 8;
+    assert_eq!(Scm::string("ab").to_raw(), 0x61 << top | 0x62 << (top - 8) | SPECIAL_SHORT_STRING);
+}
@@ -0,0 +1,398 @@
+//! Lists: moving between Scheme lists and Rust collections, the dotted
+//! (improper) lists that parameter lists and alist entries are made of, the
+//! building blocks of quasiquotation, and a subset of SRFI 1.
+//!
+//! All conversions copy. Building a list allocates fresh pairs, and reading
+//! one into a `Vec` copies the elements out, so neither side shares structure
+//! with the other; the elements themselves are shared, as values always are.
+//! None of the functions that walk a list detect cycles.
+
+use std::collections::HashSet;
+use crate::order::Sorted;
+use crate::{cons, num, MutationError, Pair, Scm, ScmKind, TypeError};
+
+pub fn vec_to_list(items: &[Scm]) -> Scm {
+    items.iter().rev().fold(Scm::NIL, |list, &x| cons(x, list))
+}
+
+// The elements of a proper list. Fails on an improper list, with the final
+// cdr as the value in the error.
+pub fn list_to_vec(mut list: Scm) -> Result<Vec<Scm>, TypeError> {
+    let mut items = vec![];
+    while !list.is_nil() {
+        let &(x, rest) = list.as_pair().ok_or_else(|| TypeError::new(ScmKind::Pair, list))?;
+        items.push(x);
+        list = rest;
+    }
+    Ok(items)
+}
+
+// `items` consed onto `tail`, like Scheme's `cons*`: a dotted list unless
+// `tail` is a list.
+pub fn list_star(items: &[Scm], tail: Scm) -> Scm {
+    items.iter().rev().fold(tail, |list, &x| cons(x, list))
+}
+
+// The last pair of a proper or dotted list.
+pub fn last_pair(list: Scm) -> Result<Scm, TypeError> {
+    let mut pair = list;
+    let mut rest = pair.expect_pair()?.1;
+    while let Some(&(_, more)) = rest.as_pair() {
+        pair = rest;
+        rest = more;
+    }
+    Ok(pair)
+}
+
+// The number of pairs in a list and the cdr of the last one, which is the
+// empty list for a proper list. Anything that is not a pair has no pairs and
+// is its own final cdr.
+pub fn dotted_length(mut list: Scm) -> (usize, Scm) {
+    let mut n = 0;
+    while let Some(&(_, rest)) = list.as_pair() {
+        n += 1;
+        list = rest;
+    }
+    (n, list)
+}
+
+// The elements of all lists, ending in the last argument, which is shared
+// rather than copied and need not be a list at all (`append` in Scheme).
+// Every other argument must be a proper list.
+pub fn append(lists: &[Scm]) -> Result<Scm, TypeError> {
+    let (&last, init) = match lists.split_last() {
+        Some(split) => split,
+        None => return Ok(Scm::NIL),
+    };
+    let mut items = vec![];
+    for &list in init {
+        items.extend(list_to_vec(list)?);
+    }
+    Ok(list_star(&items, last))
+}
+
+// Fresh pairs for the spine of a proper or dotted list, keeping its final
+// cdr. Anything that is not a pair is returned as it is.
+pub fn list_copy(mut list: Scm) -> Scm {
+    let mut items = vec![];
+    while let Some(&(x, rest)) = list.as_pair() {
+        items.push(x);
+        list = rest;
+    }
+    list_star(&items, list)
+}
+
+// The elements of the proper list `reversed` in reverse order, consed onto
+// `tail`. Expanders that collect the items before a splice in reverse don't
+// need to reverse them first.
+pub fn append_reverse(mut reversed: Scm, mut tail: Scm) -> Result<Scm, TypeError> {
+    while !reversed.is_nil() {
+        let &(x, rest) = reversed.expect_pair()?;
+        tail = cons(x, tail);
+        reversed = rest;
+    }
+    Ok(tail)
+}
+
+// What `(p ... ,@spliced . tail)` builds: `prefix`, then the elements of
+// `spliced`, then `tail`. A splice at the very end is shared instead of
+// copied, as quasiquote allows; otherwise it is copied, so that the result
+// never shares a pair that is followed by something else.
+pub fn splice(prefix: &[Scm], spliced: Scm, tail: Scm) -> Result<Scm, TypeError> {
+    let rest = if tail.is_nil() { spliced } else { append(&[spliced, tail])? };
+    Ok(list_star(prefix, rest))
+}
+
+// The pairs of a proper list, checking that all of them can be relinked
+// before the caller changes any.
+fn mutable_pairs(mut list: Scm) -> Result<Vec<Scm>, MutationError> {
+    let mut pairs = vec![];
+    while !list.is_nil() {
+        let &(_, rest) = list.expect_pair()?;
+        mutable_pair(list)?;
+        pairs.push(list);
+        list = rest;
+    }
+    Ok(pairs)
+}
+
+/// Sets the car of `pair`, like `set-car!`. Fails if `pair` is not a pair
+/// or is frozen. Containers of `pair` keep their cached hashes, see
+/// `order::forget_hash`.
+///
+/// # Safety
+/// `pair` must be private to the caller, as for `append_destructive`.
+pub unsafe fn set_car(pair: Scm, car: Scm) -> Result<(), MutationError> {
+    mutable_pair(pair)?;
+    replace(pair, |fields| fields.0 = car);
+    Ok(())
+}
+
+/// Sets the cdr of `pair`, like `set-cdr!`. Fails if `pair` is not a pair
+/// or is frozen. Containers of `pair` keep their cached hashes, see
+/// `order::forget_hash`.
+///
+/// # Safety
+/// As for `set_car`.
+pub unsafe fn set_cdr(pair: Scm, cdr: Scm) -> Result<(), MutationError> {
+    mutable_pair(pair)?;
+    replace(pair, |fields| fields.1 = cdr);
+    Ok(())
+}
+
+fn mutable_pair(pair: Scm) -> Result<(), MutationError> {
+    pair.expect_pair()?;
+    if pair.is_frozen() {
+        return Err(MutationError::Immutable(pair))
+    }
+    Ok(())
+}
+
+// The fields are not behind a `Cell`, so nothing may be borrowing the pair.
+unsafe fn replace(pair: Scm, f: impl FnOnce(&mut (Scm, Scm))) {
+    let obj = pair.as_object::<Pair>().expect("a pair");
+    f(&mut *obj.body.0.get());
+    // a cached hash covers the old fields
+    crate::order::forget_hash(pair);
+}
+
+/// `append`, but relinking the last cdr of each list instead of copying it,
+/// like `append!`. Fails without changing anything if any but the last
+/// argument is not a proper list or contains a frozen pair.
+///
+/// # Safety
+/// The pairs of all but the last list must be private to the caller: freshly
+/// allocated, not hash-consed, and not reachable from anything else that is
+/// in use, including references into them returned by `as_pair`.
+pub unsafe fn append_destructive(lists: &[Scm]) -> Result<Scm, MutationError> {
+    let (&last, init) = match lists.split_last() {
+        Some(split) => split,
+        None => return Ok(Scm::NIL),
+    };
+    let pairs = init.iter().map(|&list| mutable_pairs(list)).collect::<Result<Vec<_>, _>>()?;
+    let mut result = last;
+    for (&list, pairs) in init.iter().zip(&pairs).rev() {
+        if let Some(&last_pair) = pairs.last() {
+            replace(last_pair, |fields| fields.1 = result);
+            result = list;
+        }
+    }
+    Ok(result)
+}
+
+/// Reverses a proper list in place, like `reverse!`, and returns the new
+/// head, which was the last pair. Fails without changing anything if `list`
+/// is not a proper list or contains a frozen pair.
+///
+/// # Safety
+/// As for `append_destructive`.
+pub unsafe fn reverse_destructive(list: Scm) -> Result<Scm, MutationError> {
+    let pairs = mutable_pairs(list)?;
+    let mut result = Scm::NIL;
+    for pair in pairs {
+        replace(pair, |fields| fields.1 = result);
+        result = pair;
+    }
+    Ok(result)
+}
+
+// A subset of SRFI 1. The predicates are Rust closures, and the lists must be
+// proper lists. Results are always fresh lists that share no pairs with the
+// arguments.
+
+pub fn filter(list: Scm, mut pred: impl FnMut(Scm) -> bool) -> Result<Scm, TypeError> {
+    let mut items = list_to_vec(list)?;
+    items.retain(|&x| pred(x));
+    Ok(vec_to_list(&items))
+}
+
+// The elements that satisfy `pred` and those that don't.
+pub fn partition(list: Scm, mut pred: impl FnMut(Scm) -> bool) -> Result<(Scm, Scm), TypeError> {
+    let (yes, no): (Vec<_>, Vec<_>) = list_to_vec(list)?.into_iter().partition(|&x| pred(x));
+    Ok((vec_to_list(&yes), vec_to_list(&no)))
+}
+
+// The first of all elements that are `equal?`, in their original order.
+pub fn delete_duplicates(list: Scm) -> Result<Scm, TypeError> {
+    let mut seen = HashSet::new();
+    let mut items = list_to_vec(list)?;
+    items.retain(|&x| seen.insert(Sorted(x)));
+    Ok(vec_to_list(&items))
+}
+
+// `count` numbers from `start`, `step` apart. Each is computed as
+// `start + i * step`, so flonum steps don't accumulate rounding errors.
+pub fn iota(count: usize, start: Scm, step: Scm) -> Result<Scm, TypeError> {
+    let items = (0..count as i64)
+        .map(|i| num::add(start, num::mul(Scm::from_int(i), step)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    // check the arguments even when there are no elements
+    if count == 0 {
+        num::add(start, step)?;
+    }
+    Ok(vec_to_list(&items))
+}
+
+pub fn count(list: Scm, mut pred: impl FnMut(Scm) -> bool) -> Result<usize, TypeError> {
+    Ok(list_to_vec(list)?.into_iter().filter(|&x| pred(x)).count())
+}
+
+// The first element that satisfies `pred`. Like SRFI 1's `find`, this only
+// looks at the list as far as it has to.
+pub fn find(mut list: Scm, mut pred: impl FnMut(Scm) -> bool) -> Result<Option<Scm>, TypeError> {
+    while !list.is_nil() {
+        let &(x, rest) = list.expect_pair()?;
+        if pred(x) {
+            return Ok(Some(x))
+        }
+        list = rest;
+    }
+    Ok(None)
+}
+
+pub fn any(list: Scm, pred: impl FnMut(Scm) -> bool) -> Result<bool, TypeError> {
+    find(list, pred).map(|x| x.is_some())
+}
+
+pub fn every(list: Scm, mut pred: impl FnMut(Scm) -> bool) -> Result<bool, TypeError> {
+    find(list, |x| !pred(x)).map(|x| x.is_none())
+}
+
+// A list of lists, the first of which holds the first element of each list,
+// and so on. Stops at the end of the shortest list.
+pub fn zip(lists: &[Scm]) -> Result<Scm, TypeError> {
+    let columns = lists.iter().map(|&list| list_to_vec(list)).collect::<Result<Vec<_>, _>>()?;
+    let len = columns.iter().map(Vec::len).min().unwrap_or(0);
+    let rows: Vec<_> = (0..len).map(|i| vec_to_list(&columns.iter().map(|c| c[i]).collect::<Vec<_>>())).collect();
+    Ok(vec_to_list(&rows))
+}
+
+// The inverse of `zip` for `n` lists: the i-th result holds the i-th element
+// of each element of `list`, which must all have at least `n` elements.
+pub fn unzip(list: Scm, n: usize) -> Result<Vec<Scm>, TypeError> {
+    let mut columns = vec![vec![]; n];
+    for row in list_to_vec(list)? {
+        let mut rest = row;
+        for column in &mut columns {
+            let &(x, more) = rest.expect_pair()?;
+            column.push(x);
+            rest = more;
+        }
+    }
+    Ok(columns.iter().map(|column| vec_to_list(column)).collect())
+}
+
+#[test]
+fn conversions_copy() {
+    let items = [Scm::from_int(1), Scm::string("two"), Scm::NIL];
+    let list = vec_to_list(&items);
+    assert_eq!(list.to_string(), "(1 \"two\" ())");
+    assert_eq!(list_to_vec(list), Ok(items.to_vec()));
+    assert_ne!(vec_to_list(&items), list);
+    assert_eq!(list_to_vec(Scm::NIL), Ok(vec![]));
+
+    let dotted = cons(Scm::from_int(1), Scm::from_int(2));
+    assert_eq!(list_to_vec(dotted).unwrap_err().value, Scm::from_int(2));
+
+    let v = Scm::vector_from_slice(&items);
+    let copy = crate::vectors::vector_to_vec(v).unwrap();
+    assert_eq!(copy, items);
+    assert_ne!(Scm::vector_from_slice(&items), v);
+}
+
+#[test]
+fn dotted_lists() {
+    let one_two = [Scm::from_int(1), Scm::from_int(2)];
+    let params = list_star(&one_two, Scm::symbol("rest"));
+    assert_eq!(params.to_string(), "(1 2 . rest)");
+    assert_eq!(dotted_length(params), (2, Scm::symbol("rest")));
+    assert_eq!(last_pair(params).unwrap().to_string(), "(2 . rest)");
+    assert_eq!(list_star(&[], Scm::NIL), Scm::NIL);
+
+    let proper = list_star(&one_two, Scm::NIL);
+    assert_eq!(dotted_length(proper), (2, Scm::NIL));
+    assert_eq!(crate::car(last_pair(proper).unwrap()), Some(Scm::from_int(2)));
+    assert_eq!(dotted_length(Scm::from_int(5)), (0, Scm::from_int(5)));
+    assert!(last_pair(Scm::NIL).is_err());
+}
+
+#[test]
+fn appending_shares_only_the_last_list() {
+    let list = |s| crate::reader::read_str(s).unwrap();
+    let (a, b, c) = (list("(1 2)"), list("(3)"), list("(4 . 5)"));
+    let abc = append(&[a, Scm::NIL, b, c]).unwrap();
+    assert_eq!(abc.to_string(), "(1 2 3 4 . 5)");
+    assert_eq!(last_pair(abc).unwrap(), c);
+    assert_eq!(append(&[a, Scm::from_int(6)]).unwrap().to_string(), "(1 2 . 6)");
+    assert_eq!(append(&[]), Ok(Scm::NIL));
+    assert!(append(&[c, a]).is_err());
+
+    let copy = list_copy(c);
+    assert_ne!(copy, c);
+    assert_eq!(copy.to_string(), "(4 . 5)");
+    assert_eq!(list_copy(Scm::TRUE), Scm::TRUE);
+
+    let x = Scm::symbol("x");
+    assert_eq!(append_reverse(a, b).unwrap().to_string(), "(2 1 3)");
+    let at_end = splice(&[x], a, Scm::NIL).unwrap();
+    assert_eq!(crate::cdr(at_end), Some(a));
+    let inside = splice(&[x], a, b).unwrap();
+    assert_eq!(inside.to_string(), "(x 1 2 3)");
+    assert_eq!(last_pair(inside).unwrap(), b);
+}
+
+#[test]
+fn destructive_operations_relink_pairs() {
+    let list = |s| crate::reader::read_str(s).unwrap();
+    let (a, b, c) = (list("(1 2)"), list("(3)"), list("(4)"));
+    let second = crate::cdr(a).unwrap();
+    let hash = crate::order::equal_hash32(second);
+    assert_eq!(second.header().unwrap().hash(), Some(hash));
+    let abc = unsafe { append_destructive(&[a, Scm::NIL, b, c]) }.unwrap();
+    assert_eq!(abc, a);
+    assert_eq!(abc.to_string(), "(1 2 3 4)");
+    assert_eq!(crate::cdr(second), Some(b));
+    // the relinked pair's cached hash covered its old cdr
+    assert_eq!(second.header().unwrap().hash(), None);
+
+    let reversed = unsafe { reverse_destructive(abc) }.unwrap();
+    assert_eq!(reversed, c);
+    assert_eq!(reversed.to_string(), "(4 3 2 1)");
+    assert_eq!(crate::cdr(a), Some(Scm::NIL));
+
+    let frozen = list("(5 6)");
+    frozen.freeze();
+    let d = list("(7)");
+    assert_eq!(unsafe { append_destructive(&[d, frozen, Scm::NIL]) }, Err(MutationError::Immutable(frozen)));
+    assert_eq!(crate::cdr(d), Some(Scm::NIL));
+    assert!(unsafe { reverse_destructive(list("(1 . 2)")) }.is_err());
+}
+
+#[test]
+fn srfi_1_subset() {
+    let list = |s| crate::reader::read_str(s).unwrap();
+    let is_odd = |x: Scm| x.as_integer().is_some_and(|i| i % 2 != 0);
+    let xs = list("(1 2 3 4 5)");
+    assert_eq!(filter(xs, is_odd).unwrap().to_string(), "(1 3 5)");
+    let (odd, even) = partition(xs, is_odd).unwrap();
+    assert_eq!((odd.to_string(), even.to_string()), ("(1 3 5)".into(), "(2 4)".into()));
+    assert_eq!(count(xs, is_odd), Ok(3));
+    assert_eq!(find(xs, |x| !is_odd(x)), Ok(Some(Scm::from_int(2))));
+    assert_eq!(find(list("(1 2 . 3)"), |x| x == Scm::from_int(2)), Ok(Some(Scm::from_int(2))));
+    assert_eq!((any(xs, is_odd), every(xs, is_odd), every(Scm::NIL, is_odd)), (Ok(true), Ok(false), Ok(true)));
+    assert!(filter(list("(1 . 2)"), is_odd).is_err());
+
+    let dups = list("((a) 1 (a) b 1 \"s\" \"s\")");
+    assert_eq!(delete_duplicates(dups).unwrap().to_string(), "((a) 1 b \"s\")");
+
+    assert_eq!(iota(5, Scm::from_int(0), Scm::from_int(1)).unwrap().to_string(), "(0 1 2 3 4)");
+    assert_eq!(iota(3, Scm::from_int(1), Scm::from_f64(0.5)).unwrap().to_string(), "(1.0 1.5 2.0)");
+    assert!(iota(0, Scm::NIL, Scm::from_int(1)).is_err());
+
+    let zipped = zip(&[xs, list("(a b c)")]).unwrap();
+    assert_eq!(zipped.to_string(), "((1 a) (2 b) (3 c))");
+    let unzipped = unzip(zipped, 2).unwrap();
+    assert_eq!(unzipped[0].to_string(), "(1 2 3)");
+    assert_eq!(unzipped[1].to_string(), "(a b c)");
+    assert!(unzip(zipped, 3).is_err());
+}
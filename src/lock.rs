@@ -0,0 +1,30 @@
+//! Interior mutability for heap objects: a `RefCell` normally, and a `Mutex`
+//! with the `sync` feature, where objects may be shared between threads.
+
+#[cfg(not(feature = "sync"))]
+pub(crate) struct Lock<T>(std::cell::RefCell<T>);
+
+#[cfg(not(feature = "sync"))]
+impl<T> Lock<T> {
+    pub fn new(value: T) -> Self {
+        Lock(std::cell::RefCell::new(value))
+    }
+
+    pub fn lock(&self) -> std::cell::RefMut<'_, T> {
+        self.0.borrow_mut()
+    }
+}
+
+#[cfg(feature = "sync")]
+pub(crate) struct Lock<T>(std::sync::Mutex<T>);
+
+#[cfg(feature = "sync")]
+impl<T> Lock<T> {
+    pub fn new(value: T) -> Self {
+        Lock(std::sync::Mutex::new(value))
+    }
+
+    pub fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        self.0.lock().unwrap()
+    }
+}
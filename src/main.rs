@@ -1,10 +1,14 @@
 use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
 use dbwgc_sys::{DbwGcAllocator, GC_init, GC_collect_a_little, GC_set_free_space_divisor};
+use scm_repr::{Scm, cons, car, cdr, is_null};
 
+#[cfg(not(target_arch = "wasm32"))]
 #[global_allocator]
 static A: DbwGcAllocator = DbwGcAllocator;
 
 fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
     unsafe {
         GC_init();
         GC_set_free_space_divisor(1);
@@ -48,118 +52,3 @@ fn reverse(list: Scm) -> Scm {
         cons(reverse(cdr(list).expect("pair")), car(list).expect("pair"))
     }
 }
-
-const N_TAG_BITS: usize = 2;
-const TAG_MASK: usize = 0b_11;
-const TAG_POINTER: usize = 0b_00;
-const TAG_INTEGER: usize = 0b_01;
-const TAG_PAIR: usize = 0b_10;
-const TAG_SPECIAL: usize = 0b_11;
-
-const SPECIAL_NIL: usize = 0b_0011;
-
-const MASK_IMMEDIATE: usize = 0b01;  // this works because all immediates have 1 in the lsb
-
-#[derive(Debug, Copy, Clone)]
-pub struct Scm {
-    value: usize,
-}
-
-impl Scm {
-    fn new(value: ScmValue) -> Self {
-        Scm {
-            value: ref_to_addr(Box::leak(Box::new(value)))
-        }
-    }
-
-    fn nil() -> Self {
-        Scm {
-            value: SPECIAL_NIL
-        }
-    }
-
-    fn from_int(value: i64) -> Self {
-        Scm {
-            value: (value as usize) << N_TAG_BITS | TAG_INTEGER
-        }
-    }
-
-    fn is_immediate(&self) -> bool {
-        self.value & MASK_IMMEDIATE != 0
-    }
-
-    fn is_nil(&self) -> bool {
-        self.value == SPECIAL_NIL
-    }
-
-    fn as_integer(&self) -> Option<i64> {
-        if self.value & TAG_MASK == TAG_INTEGER {
-            Some((self.value >> N_TAG_BITS) as i64)
-        } else {
-            None
-        }
-    }
-
-    fn as_ref(&self) -> Option<&ScmValue> {
-        if self.value & TAG_MASK == TAG_POINTER {
-            unsafe {
-                Some(int_to_ref(self.value))
-            }
-        } else {
-            None
-        }
-    }
-
-    fn as_pair(&self) -> Option<&(Scm, Scm)> {
-        if self.value & TAG_MASK == TAG_PAIR {
-            unsafe {
-                Some(int_to_ref(self.value - TAG_PAIR))
-            }
-        } else {
-            None
-        }
-    }
-}
-
-unsafe fn int_to_ref<T>(i: usize) -> &'static T {
-    &*(i as *const T)
-}
-
-fn ref_to_addr<T>(r: &T) -> usize {
-    r as *const T as usize
-}
-
-#[derive(Debug)]
-#[repr(u64)]
-pub enum ScmValue {
-    Vector(&'static[Scm]),
-}
-
-pub fn cons(car: Scm, cdr: Scm) -> Scm {
-    let r = Box::leak(Box::new((car, cdr)));
-    let addr = r as *const _ as usize;
-    debug_assert!(addr & TAG_MASK == 0);
-    Scm {
-        value: addr + TAG_PAIR
-    }
-}
-
-pub fn car(scm: Scm) -> Option<Scm> {
-    scm.as_pair().map(|p| p.0)
-}
-
-pub fn cdr(scm: Scm) -> Option<Scm> {
-    scm.as_pair().map(|p| p.1)
-}
-
-pub fn is_pair(scm: Scm) -> bool {
-    scm.as_pair().is_some()
-}
-
-pub fn is_integer(scm: Scm) -> bool {
-    scm.as_integer().is_some()
-}
-
-pub fn is_null(scm: Scm) -> bool {
-    scm.is_nil()
-}
@@ -0,0 +1,780 @@
+//! Generic arithmetic over the numeric tower: fixnums, bignums, exact
+//! rationals and flonums.
+//!
+//! Fixnum operands take a fast path. Everything else is lifted to either an
+//! exact fraction or a float, computed there, and brought back to the
+//! smallest representation that fits.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::ops;
+use crate::bigint::BigInt;
+use crate::heap::{self, HeapObject, Kind};
+use crate::{NumError, Scm, ScmKind, ScmView, TypeError, MAX_FIXNUM, MIN_FIXNUM, TAG_INTEGER, TAG_MASK};
+
+pub(crate) struct Flonum(f64);
+
+impl HeapObject for Flonum {
+    const KIND: Kind = Kind::Flonum;
+}
+
+pub(crate) struct Bignum(BigInt);
+
+impl HeapObject for Bignum {
+    const KIND: Kind = Kind::Bignum;
+}
+
+// always normalized: den > 1 and gcd(num, den) = 1
+pub(crate) struct Ratnum(BigInt, BigInt);
+
+impl HeapObject for Ratnum {
+    const KIND: Kind = Kind::Ratnum;
+}
+
+impl Scm {
+    pub fn from_f64(x: f64) -> Self {
+        Scm::from_object(heap::leak(Flonum(x)))
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_object::<Flonum>().map(|x| x.0)
+    }
+
+    pub fn as_bignum(&self) -> Option<&BigInt> {
+        self.as_object::<Bignum>().map(|x| &x.0)
+    }
+
+    pub fn as_rational(&self) -> Option<(&BigInt, &BigInt)> {
+        self.as_object::<Ratnum>().map(|x| (&x.0, &x.1))
+    }
+}
+
+pub fn is_number(x: Scm) -> bool {
+    matches!(
+        x.kind(),
+        ScmKind::Integer | ScmKind::Bignum | ScmKind::Rational | ScmKind::Flonum
+    )
+}
+
+pub fn integer(i: i64) -> Scm {
+    if (MIN_FIXNUM..=MAX_FIXNUM).contains(&i) {
+        Scm::from_int(i)
+    } else {
+        Scm::from_object(heap::leak(Bignum(BigInt::from_i64(i))))
+    }
+}
+
+pub fn make_integer(i: BigInt) -> Scm {
+    match i.to_i64() {
+        Some(i) => integer(i),
+        None => Scm::from_object(heap::leak(Bignum(i))),
+    }
+}
+
+pub fn make_rational(num: BigInt, den: BigInt) -> Result<Scm, NumError> {
+    if den.is_zero() {
+        return Err(NumError::DivisionByZero)
+    }
+    let g = num.gcd(&den);
+    let (mut num, mut den) = (num.div_rem(&g).0, den.div_rem(&g).0);
+    if den.is_negative() {
+        num = -&num;
+        den = -&den;
+    }
+    if den == BigInt::one() {
+        Ok(make_integer(num))
+    } else {
+        Ok(Scm::from_object(heap::leak(Ratnum(num, den))))
+    }
+}
+
+pub(crate) enum Num {
+    Exact(BigInt, BigInt),
+    Inexact(f64),
+}
+
+impl Num {
+    pub(crate) fn from_scm(x: Scm) -> Result<Num, TypeError> {
+        match x.classify() {
+            ScmView::Integer(i) => Ok(Num::Exact(BigInt::from_i64(i), BigInt::one())),
+            ScmView::Bignum(i) => Ok(Num::Exact(i.clone(), BigInt::one())),
+            ScmView::Rational(n, d) => Ok(Num::Exact(n.clone(), d.clone())),
+            ScmView::Flonum(x) => Ok(Num::Inexact(x)),
+            _ => Err(TypeError::new(ScmKind::Number, x)),
+        }
+    }
+
+    pub(crate) fn to_f64(&self) -> f64 {
+        match self {
+            Num::Exact(n, d) => ratio_to_f64(n, d),
+            Num::Inexact(x) => *x,
+        }
+    }
+}
+
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+fn arith(op: Op, a: Scm, b: Scm) -> Result<Scm, NumError> {
+    let x = Num::from_scm(a)?;
+    let y = Num::from_scm(b)?;
+    match (x, y) {
+        (Num::Exact(n1, d1), Num::Exact(n2, d2)) => match op {
+            Op::Add => make_rational(&(&n1 * &d2) + &(&n2 * &d1), &d1 * &d2),
+            Op::Sub => make_rational(&(&n1 * &d2) - &(&n2 * &d1), &d1 * &d2),
+            Op::Mul => make_rational(&n1 * &n2, &d1 * &d2),
+            Op::Div => make_rational(&n1 * &d2, &d1 * &n2),
+        },
+        (x, y) => {
+            let (x, y) = (x.to_f64(), y.to_f64());
+            Ok(Scm::from_f64(match op {
+                Op::Add => x + y,
+                Op::Sub => x - y,
+                Op::Mul => x * y,
+                Op::Div => x / y,
+            }))
+        }
+    }
+}
+
+fn type_error(e: NumError) -> TypeError {
+    match e {
+        NumError::Type(e) => e,
+        _ => unreachable!("only division can fail for reasons other than types"),
+    }
+}
+
+pub fn add(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    if let (Some(x), Some(y)) = (a.as_integer(), b.as_integer()) {
+        // the sum of two fixnums always fits into an i64
+        return Ok(integer(x + y))
+    }
+    arith(Op::Add, a, b).map_err(type_error)
+}
+
+pub fn sub(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    if let (Some(x), Some(y)) = (a.as_integer(), b.as_integer()) {
+        return Ok(integer(x - y))
+    }
+    arith(Op::Sub, a, b).map_err(type_error)
+}
+
+pub fn mul(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    if let (Some(x), Some(y)) = (a.as_integer(), b.as_integer()) {
+        if let Some(z) = x.checked_mul(y) {
+            return Ok(integer(z))
+        }
+    }
+    arith(Op::Mul, a, b).map_err(type_error)
+}
+
+pub fn div(a: Scm, b: Scm) -> Result<Scm, NumError> {
+    if let (Some(x), Some(y)) = (a.as_integer(), b.as_integer()) {
+        if y != 0 && x % y == 0 {
+            return Ok(integer(x / y))
+        }
+    }
+    arith(Op::Div, a, b)
+}
+
+// A finite float as an exact fraction m * 2^e (not reduced).
+pub(crate) fn f64_to_exact(x: f64) -> (BigInt, BigInt) {
+    debug_assert!(x.is_finite());
+    let bits = x.to_bits();
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let frac = (bits & ((1 << 52) - 1)) as i64;
+    let (m, e) = if exp == 0 { (frac, -1074) } else { (frac | 1 << 52, exp - 1075) };
+    let m = BigInt::from_i64(if x < 0.0 { -m } else { m });
+    if e >= 0 {
+        (m.shl(e as usize), BigInt::one())
+    } else {
+        (m, BigInt::one().shl(-e as usize))
+    }
+}
+
+// Exact and inexact numbers are compared exactly (the float is converted to
+// a fraction, not the other way around), which keeps comparisons transitive.
+// Returns None if either operand is a NaN.
+pub fn compare(a: Scm, b: Scm) -> Result<Option<Ordering>, TypeError> {
+    if let Some(lt) = fixnum_lt(a, b) {
+        return Ok(Some(if lt { Ordering::Less } else if a == b { Ordering::Equal } else { Ordering::Greater }))
+    }
+    let x = Num::from_scm(a)?;
+    let y = Num::from_scm(b)?;
+    Ok(match (x, y) {
+        (Num::Inexact(x), Num::Inexact(y)) => x.partial_cmp(&y),
+        (Num::Exact(n1, d1), Num::Exact(n2, d2)) => Some((&n1 * &d2).cmp(&(&n2 * &d1))),
+        (Num::Exact(n, d), Num::Inexact(x)) => compare_exact_inexact(&n, &d, x),
+        (Num::Inexact(x), Num::Exact(n, d)) => compare_exact_inexact(&n, &d, x).map(Ordering::reverse),
+    })
+}
+
+fn compare_exact_inexact(n: &BigInt, d: &BigInt, x: f64) -> Option<Ordering> {
+    if x.is_nan() {
+        None
+    } else if x.is_infinite() {
+        Some(if x > 0.0 { Ordering::Less } else { Ordering::Greater })
+    } else {
+        let (n2, d2) = f64_to_exact(x);
+        Some((n * &d2).cmp(&(&n2 * d)))
+    }
+}
+
+pub fn eq(a: Scm, b: Scm) -> Result<bool, TypeError> {
+    Ok(compare(a, b)? == Some(Ordering::Equal))
+}
+
+pub fn lt(a: Scm, b: Scm) -> Result<bool, TypeError> {
+    Ok(compare(a, b)? == Some(Ordering::Less))
+}
+
+pub fn gt(a: Scm, b: Scm) -> Result<bool, TypeError> {
+    Ok(compare(a, b)? == Some(Ordering::Greater))
+}
+
+pub fn le(a: Scm, b: Scm) -> Result<bool, TypeError> {
+    Ok(matches!(compare(a, b)?, Some(Ordering::Less) | Some(Ordering::Equal)))
+}
+
+pub fn ge(a: Scm, b: Scm) -> Result<bool, TypeError> {
+    Ok(matches!(compare(a, b)?, Some(Ordering::Greater) | Some(Ordering::Equal)))
+}
+
+// For n-ary comparisons like `(< 1 1.5 2)`. All arguments are type-checked,
+// even after the result is known.
+pub fn chain(args: &[Scm], cmp: fn(Scm, Scm) -> Result<bool, TypeError>) -> Result<bool, TypeError> {
+    let mut result = true;
+    for w in args.windows(2) {
+        result &= cmp(w[0], w[1])?;
+    }
+    if args.len() == 1 {
+        Num::from_scm(args[0])?;
+    }
+    Ok(result)
+}
+
+// Correctly rounded n/d (d > 0), also when n and d are too large for floats.
+fn ratio_to_f64(n: &BigInt, d: &BigInt) -> f64 {
+    if n.is_zero() {
+        return 0.0
+    }
+    // scale so that the integer quotient has at least 65 significant bits
+    let shift = 65 + d.bit_length() as i64 - n.bit_length() as i64;
+    if shift > 1074 {
+        // maybe subnormal: round once to a multiple of 2^-1074, as scaling
+        // a rounded 53 bit float down would round a second time
+        let (mut q, r) = n.abs().shl(1074).div_rem(d);
+        let twice = r.shl(1);
+        if twice > *d || (twice == *d && !q.is_even()) {
+            q = &q + &BigInt::one();
+        }
+        if q.bit_length() <= 53 {
+            let x = ldexp(q.to_f64(), -1074);
+            return if n.is_negative() { -x } else { x }
+        }
+    }
+    let (num, den) = if shift >= 0 {
+        (n.abs().shl(shift as usize), d.clone())
+    } else {
+        (n.abs(), d.shl(-shift as usize))
+    };
+    let (mut q, r) = num.div_rem(&den);
+    if !r.is_zero() && q.is_even() {
+        // sticky bit, so the rounding below sees that we're above a tie
+        q = &q + &BigInt::one();
+    }
+    let x = ldexp(q.to_f64(), -shift);
+    if n.is_negative() { -x } else { x }
+}
+
+fn ldexp(mut x: f64, mut e: i64) -> f64 {
+    while e > 1000 {
+        x *= 2f64.powi(1000);
+        e -= 1000;
+    }
+    while e < -1000 {
+        x *= 2f64.powi(-1000);
+        e += 1000;
+    }
+    x * 2f64.powi(e as i32)
+}
+
+pub fn is_exact(x: Scm) -> Result<bool, TypeError> {
+    Ok(matches!(Num::from_scm(x)?, Num::Exact(..)))
+}
+
+pub fn exact_to_inexact(x: Scm) -> Result<Scm, TypeError> {
+    match Num::from_scm(x)? {
+        Num::Inexact(_) => Ok(x),
+        n => Ok(Scm::from_f64(n.to_f64())),
+    }
+}
+
+// The exact value of a float is always a dyadic fraction, e.g. 0.1 becomes
+// 3602879701896397/36028797018963968. Use `rationalize` for simpler results.
+pub fn inexact_to_exact(x: Scm) -> Result<Scm, NumError> {
+    match Num::from_scm(x)? {
+        Num::Exact(..) => Ok(x),
+        Num::Inexact(f) if !f.is_finite() => Err(NumError::NotFinite),
+        Num::Inexact(f) => {
+            let (n, d) = f64_to_exact(f);
+            make_rational(n, d)
+        }
+    }
+}
+
+// The simplest rational that differs from x by no more than y. The result
+// is inexact if either argument is.
+pub fn rationalize(x: Scm, y: Scm) -> Result<Scm, NumError> {
+    let (a, b) = (Num::from_scm(x)?, Num::from_scm(y)?);
+    let inexact = matches!(a, Num::Inexact(_)) || matches!(b, Num::Inexact(_));
+    let (fa, fb) = (a.to_f64(), b.to_f64());
+    if inexact && !(fa.is_finite() && fb.is_finite()) {
+        // every number is within an infinite y of 0, no finite y gets close
+        // to an infinite x, and nothing is close to NaN
+        return Ok(Scm::from_f64(match (fa.is_infinite(), fb.is_infinite()) {
+            _ if fa.is_nan() || fb.is_nan() => f64::NAN,
+            (false, true) => 0.0,
+            (true, false) => fa,
+            _ => f64::NAN,
+        }))
+    }
+    let lo = inexact_to_exact(sub(x, num_abs(y)?)?)?;
+    let hi = inexact_to_exact(add(x, num_abs(y)?)?)?;
+    let (n, d) = simplest_rational(fraction(lo), fraction(hi));
+    let r = make_rational(n, d)?;
+    if inexact { Ok(exact_to_inexact(r)?) } else { Ok(r) }
+}
+
+fn num_abs(x: Scm) -> Result<Scm, TypeError> {
+    if lt(x, Scm::from_int(0))? { sub(Scm::from_int(0), x) } else { Ok(x) }
+}
+
+fn fraction(x: Scm) -> (BigInt, BigInt) {
+    match Num::from_scm(x) {
+        Ok(Num::Exact(n, d)) => (n, d),
+        _ => unreachable!("expected an exact number"),
+    }
+}
+
+fn floor_div(n: &BigInt, d: &BigInt) -> BigInt {
+    let (q, r) = n.div_rem(d);
+    if r.is_negative() { &q - &BigInt::one() } else { q }
+}
+
+// lo <= hi, both with positive denominators
+fn simplest_rational(lo: (BigInt, BigInt), hi: (BigInt, BigInt)) -> (BigInt, BigInt) {
+    if lo.0.is_negative() && !hi.0.is_negative() {
+        return (BigInt::zero(), BigInt::one())
+    }
+    if hi.0.is_negative() {
+        let (n, d) = simplest_positive((-&hi.0, hi.1), (-&lo.0, lo.1));
+        return (-&n, d)
+    }
+    simplest_positive(lo, hi)
+}
+
+fn simplest_positive(lo: (BigInt, BigInt), hi: (BigInt, BigInt)) -> (BigInt, BigInt) {
+    let fl = floor_div(&lo.0, &lo.1);
+    if &fl * &lo.1 == lo.0 {
+        return (fl, BigInt::one())
+    }
+    if fl < floor_div(&hi.0, &hi.1) {
+        return (&fl + &BigInt::one(), BigInt::one())
+    }
+    // fl + 1 / simplest(1 / (hi - fl), 1 / (lo - fl))
+    let (n, d) = simplest_positive(
+        (hi.1.clone(), &hi.0 - &(&fl * &hi.1)),
+        (lo.1.clone(), &lo.0 - &(&fl * &lo.1)),
+    );
+    (&(&fl * &n) + &d, n)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rounding {
+    Floor,
+    Ceiling,
+    Truncate,
+    // to the nearest integer, ties to even
+    Round,
+}
+
+// Rounds to an integer of the same exactness.
+pub fn round(x: Scm, mode: Rounding) -> Result<Scm, TypeError> {
+    match Num::from_scm(x)? {
+        Num::Inexact(f) => Ok(Scm::from_f64(match mode {
+            Rounding::Floor => f.floor(),
+            Rounding::Ceiling => f.ceil(),
+            Rounding::Truncate => f.trunc(),
+            Rounding::Round => f.round_ties_even(),
+        })),
+        Num::Exact(n, d) => {
+            let fl = floor_div(&n, &d);
+            let rem = &n - &(&fl * &d);
+            let q = if rem.is_zero() {
+                fl
+            } else {
+                let up = match mode {
+                    Rounding::Floor => false,
+                    Rounding::Ceiling => true,
+                    Rounding::Truncate => n.is_negative(),
+                    Rounding::Round => match rem.shl(1).cmp(&d) {
+                        Ordering::Less => false,
+                        Ordering::Greater => true,
+                        Ordering::Equal => !fl.is_even(),
+                    },
+                };
+                if up { &fl + &BigInt::one() } else { fl }
+            };
+            Ok(make_integer(q))
+        }
+    }
+}
+
+// Exact numbers are written in the given radix. Flonums are always written
+// in decimal, as the shortest string that reads back to the same float.
+pub fn to_string(x: Scm, radix: u32) -> Result<String, TypeError> {
+    assert!(matches!(radix, 2 | 8 | 10 | 16), "unsupported radix {}", radix);
+    Ok(match Num::from_scm(x)? {
+        Num::Exact(n, d) if d == BigInt::one() => n.to_string_radix(radix),
+        Num::Exact(n, d) => format!("{}/{}", n.to_string_radix(radix), d.to_string_radix(radix)),
+        Num::Inexact(_) => x.to_string(),
+    })
+}
+
+// Parses the numeric syntax of the reader: optional #x/#b/#o/#d and #e/#i
+// prefixes, then an integer, n/d, a decimal with optional exponent (radix 10
+// only), or one of +inf.0, -inf.0 and +nan.0. A radix prefix overrides
+// `radix`. Returns None if the string is not a number.
+pub fn parse(s: &str, radix: u32) -> Option<Scm> {
+    assert!(matches!(radix, 2 | 8 | 10 | 16), "unsupported radix {}", radix);
+    let mut radix = radix;
+    let mut radix_prefix = false;
+    let mut exact = None;
+    let mut s = s;
+    while let Some(rest) = s.strip_prefix('#') {
+        match rest.bytes().next()?.to_ascii_lowercase() {
+            b'x' if !radix_prefix => radix = 16,
+            b'd' if !radix_prefix => radix = 10,
+            b'o' if !radix_prefix => radix = 8,
+            b'b' if !radix_prefix => radix = 2,
+            b'e' if exact.is_none() => exact = Some(true),
+            b'i' if exact.is_none() => exact = Some(false),
+            _ => return None,
+        }
+        radix_prefix |= !matches!(rest.as_bytes()[0], b'e' | b'E' | b'i' | b'I');
+        s = &rest[1..];
+    }
+    let x = parse_real(s, radix, exact == Some(true))?;
+    match exact {
+        Some(true) => inexact_to_exact(x).ok(),
+        Some(false) => exact_to_inexact(x).ok(),
+        None => Some(x),
+    }
+}
+
+// Exact decimals with a larger power of ten are rejected, since the scale
+// alone would take a bignum of megabytes.
+const MAX_EXACT_EXPONENT: u64 = 10_000;
+
+fn parse_real(s: &str, radix: u32, exact: bool) -> Option<Scm> {
+    match s {
+        "+inf.0" => return Some(Scm::from_f64(f64::INFINITY)),
+        "-inf.0" => return Some(Scm::from_f64(f64::NEG_INFINITY)),
+        "+nan.0" | "-nan.0" => return Some(Scm::from_f64(f64::NAN)),
+        _ => {}
+    }
+    if let Some((n, d)) = s.split_once('/') {
+        if d.starts_with(['+', '-']) {
+            return None
+        }
+        let (n, d) = (BigInt::parse_radix(n, radix)?, BigInt::parse_radix(d, radix)?);
+        return make_rational(n, d).ok()
+    }
+    if let Some(i) = BigInt::parse_radix(s, radix) {
+        return Some(make_integer(i))
+    }
+    if radix != 10 {
+        return None
+    }
+    let (mantissa, exp) = parse_decimal(s)?;
+    if exact {
+        if exp.unsigned_abs() > MAX_EXACT_EXPONENT {
+            return None
+        }
+        let scale = power_of_ten(exp.unsigned_abs());
+        if exp >= 0 { Some(make_integer(&mantissa * &scale)) } else { make_rational(mantissa, scale).ok() }
+    } else {
+        // the syntax was checked, and std's parser rounds correctly
+        s.parse().ok().map(Scm::from_f64)
+    }
+}
+
+// By squaring and multiplying, so with O(log n) multiplications.
+fn power_of_ten(mut n: u64) -> BigInt {
+    let (mut result, mut base) = (BigInt::one(), BigInt::from_i64(10));
+    while n > 0 {
+        if n & 1 != 0 {
+            result = &result * &base;
+        }
+        base = &base * &base;
+        n >>= 1;
+    }
+    result
+}
+
+// [sign] digits [. digits] [e [sign] digits], with at least one mantissa
+// digit. Returns the mantissa as an integer and the power of ten to scale by,
+// or None if that power doesn't fit an i64.
+fn parse_decimal(s: &str) -> Option<(BigInt, i64)> {
+    let (body, exp) = match s.find(['e', 'E']) {
+        Some(i) => {
+            let e = &s[i + 1..];
+            let digits = e.strip_prefix(['+', '-']).unwrap_or(e);
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None
+            }
+            (&s[..i], e.parse::<i64>().ok()?)
+        }
+        None => (s, 0),
+    };
+    let (int, frac) = body.split_once('.').unwrap_or((body, ""));
+    let unsigned = int.strip_prefix(['+', '-']).unwrap_or(int);
+    if unsigned.len() + frac.len() == 0 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None
+    }
+    let sign = &int[..int.len() - unsigned.len()];
+    let mantissa = BigInt::parse_radix(&format!("{}0{}{}", sign, unsigned, frac), 10)?;
+    Some((mantissa, exp.checked_sub(i64::try_from(frac.len()).ok()?)?))
+}
+
+// Fixnum fast paths that work on the tagged words directly. With both tags
+// equal to TAG_INTEGER, (x<<3 | 1) + (y<<3 | 1) - 1 is (x+y)<<3 | 1, so no
+// untagging or retagging is needed. Overflow of the tagged word is exactly
+// overflow of the fixnum range.
+
+fn both_fixnums(a: Scm, b: Scm) -> bool {
+    ((a.addr() ^ TAG_INTEGER) | (b.addr() ^ TAG_INTEGER)) & TAG_MASK == 0
+}
+
+pub fn fixnum_add(a: Scm, b: Scm) -> Option<Scm> {
+    if !both_fixnums(a, b) {
+        return None
+    }
+    let sum = (a.addr() as isize).checked_add(b.addr() as isize - TAG_INTEGER as isize)?;
+    Some(Scm::immediate(sum as usize))
+}
+
+pub fn fixnum_sub(a: Scm, b: Scm) -> Option<Scm> {
+    if !both_fixnums(a, b) {
+        return None
+    }
+    let diff = (a.addr() as isize).checked_sub(b.addr() as isize - TAG_INTEGER as isize)?;
+    Some(Scm::immediate(diff as usize))
+}
+
+pub fn fixnum_mul(a: Scm, b: Scm) -> Option<Scm> {
+    if !both_fixnums(a, b) {
+        return None
+    }
+    let x = a.addr() as isize >> crate::N_TAG_BITS;
+    let prod = x.checked_mul(b.addr() as isize - TAG_INTEGER as isize)?;
+    Some(Scm::immediate(prod as usize | TAG_INTEGER))
+}
+
+// Same-tag words compare like the numbers they encode.
+pub fn fixnum_lt(a: Scm, b: Scm) -> Option<bool> {
+    if !both_fixnums(a, b) {
+        return None
+    }
+    Some((a.addr() as isize) < (b.addr() as isize))
+}
+
+/// # Safety
+/// Both arguments must be fixnums and their sum must be in fixnum range.
+/// Otherwise the result may be a forged pointer.
+pub unsafe fn fixnum_add_unchecked(a: Scm, b: Scm) -> Scm {
+    debug_assert!(fixnum_add(a, b).is_some());
+    Scm::immediate(a.addr().wrapping_add(b.addr()).wrapping_sub(TAG_INTEGER))
+}
+
+/// # Safety
+/// Both arguments must be fixnums and their difference must be in fixnum range.
+pub unsafe fn fixnum_sub_unchecked(a: Scm, b: Scm) -> Scm {
+    debug_assert!(fixnum_sub(a, b).is_some());
+    Scm::immediate(a.addr().wrapping_sub(b.addr()).wrapping_add(TAG_INTEGER))
+}
+
+/// # Safety
+/// Both arguments must be fixnums.
+pub unsafe fn fixnum_lt_unchecked(a: Scm, b: Scm) -> bool {
+    debug_assert!(both_fixnums(a, b));
+    (a.addr() as isize) < (b.addr() as isize)
+}
+
+// Operator sugar for host code that already knows its operands are numbers.
+// Type errors panic; use the functions above to handle them.
+
+impl ops::Add for Scm {
+    type Output = Scm;
+    fn add(self, other: Scm) -> Scm {
+        add(self, other).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl ops::Sub for Scm {
+    type Output = Scm;
+    fn sub(self, other: Scm) -> Scm {
+        sub(self, other).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl ops::Mul for Scm {
+    type Output = Scm;
+    fn mul(self, other: Scm) -> Scm {
+        mul(self, other).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl ops::Div for Scm {
+    type Output = Scm;
+    fn div(self, other: Scm) -> Scm {
+        div(self, other).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl ops::Neg for Scm {
+    type Output = Scm;
+    fn neg(self) -> Scm {
+        Scm::from_int(0) - self
+    }
+}
+
+#[test]
+fn arithmetic_across_the_tower() {
+    let big = integer(MAX_FIXNUM) + Scm::from_int(1);
+    assert_eq!(big.kind(), ScmKind::Bignum);
+    assert_eq!((big - Scm::from_int(1)).as_integer(), Some(MAX_FIXNUM));
+    assert_eq!((big * big).to_string(), ((MAX_FIXNUM as i128 + 1) * (MAX_FIXNUM as i128 + 1)).to_string());
+
+    let third = Scm::from_int(1) / Scm::from_int(3);
+    assert_eq!(third.to_string(), "1/3");
+    assert_eq!((third + third + third).as_integer(), Some(1));
+    assert_eq!((-third).to_string(), "-1/3");
+    assert_eq!((Scm::from_f64(0.5) + Scm::from_int(1) / Scm::from_int(2)).as_f64(), Some(1.0));
+
+    assert_eq!(div(Scm::from_int(1), Scm::from_int(0)), Err(NumError::DivisionByZero));
+    assert_eq!(add(Scm::NIL, Scm::from_int(1)).unwrap_err().expected, ScmKind::Number);
+}
+
+#[test]
+fn tagged_fixnum_ops() {
+    let (a, b) = (Scm::from_int(-12), Scm::from_int(5));
+    assert_eq!(fixnum_add(a, b), Some(Scm::from_int(-7)));
+    assert_eq!(fixnum_sub(a, b), Some(Scm::from_int(-17)));
+    assert_eq!(fixnum_mul(a, b), Some(Scm::from_int(-60)));
+    assert_eq!(fixnum_lt(a, b), Some(true));
+    assert_eq!(unsafe { fixnum_add_unchecked(a, b) }, Scm::from_int(-7));
+    assert_eq!(unsafe { fixnum_sub_unchecked(b, a) }, Scm::from_int(17));
+
+    assert_eq!(fixnum_add(Scm::from_int(MAX_FIXNUM), Scm::from_int(1)), None);
+    assert_eq!(fixnum_mul(Scm::from_int(MIN_FIXNUM), Scm::from_int(-1)), None);
+    assert_eq!(fixnum_add(Scm::NIL, Scm::from_int(1)), None);
+    assert_eq!(fixnum_lt(Scm::from_int(1), Scm::TRUE), None);
+}
+
+#[test]
+fn comparisons_across_the_tower() {
+    let third = Scm::from_int(1) / Scm::from_int(3);
+    let big = integer(MAX_FIXNUM) * Scm::from_int(4);
+    assert!(chain(&[Scm::from_int(1), Scm::from_f64(1.5), Scm::from_int(2)], lt).unwrap());
+    assert!(!chain(&[Scm::from_int(1), Scm::from_int(3), Scm::from_int(2)], lt).unwrap());
+    assert!(lt(third, Scm::from_f64(0.34)).unwrap());
+    assert!(gt(third, Scm::from_f64(0.3333)).unwrap());
+    // 1/3 is not exactly representable as a float
+    assert!(!eq(third, Scm::from_f64(1.0 / 3.0)).unwrap());
+    assert!(eq(Scm::from_f64(0.5), Scm::from_int(1) / Scm::from_int(2)).unwrap());
+    assert!(lt(Scm::from_f64(-1e300), big).unwrap());
+    assert!(lt(big, Scm::from_f64(f64::INFINITY)).unwrap());
+    // 2^62 + 1 compares greater than the float 2^62, although they convert to the same float
+    let two62 = integer(1 << 62);
+    assert!(gt(two62 + Scm::from_int(1), Scm::from_f64((1u64 << 62) as f64)).unwrap());
+    assert!(!le(Scm::from_f64(f64::NAN), Scm::from_int(1)).unwrap());
+    assert!(!ge(Scm::from_f64(f64::NAN), Scm::from_int(1)).unwrap());
+    assert!(eq(Scm::from_int(1), Scm::symbol("x")).is_err());
+}
+
+#[test]
+fn exactness_conversions() {
+    let tenth = inexact_to_exact(Scm::from_f64(0.1)).unwrap();
+    assert_eq!(tenth.to_string(), "3602879701896397/36028797018963968");
+    assert_eq!(exact_to_inexact(tenth).unwrap().as_f64(), Some(0.1));
+    assert_eq!(inexact_to_exact(Scm::from_f64(-2.0)).unwrap(), Scm::from_int(-2));
+    assert_eq!(inexact_to_exact(Scm::from_f64(f64::NAN)), Err(NumError::NotFinite));
+
+    // too large for a float on its own, but the ratio is fine
+    let huge = make_integer(BigInt::one().shl(2000));
+    let r = (huge + Scm::from_int(1)) / (huge * Scm::from_int(3));
+    assert_eq!(exact_to_inexact(r).unwrap().as_f64(), Some(1.0 / 3.0));
+    // (2.5 + 2^-80) * 2^-1074 rounds up to 3 * 2^-1074, not to the even 2
+    let n = &BigInt::from_i64(5).shl(79) + &BigInt::one();
+    let r = make_rational(n, BigInt::one().shl(1154)).unwrap();
+    assert_eq!(exact_to_inexact(r).unwrap().as_f64(), Some(f64::from_bits(3)));
+    assert_eq!(exact_to_inexact(-r).unwrap().as_f64(), Some(-f64::from_bits(3)));
+
+    let third = Scm::from_int(1) / Scm::from_int(3);
+    let r = rationalize(inexact_to_exact(Scm::from_f64(0.3)).unwrap(), Scm::from_int(1) / Scm::from_int(10)).unwrap();
+    assert!(eq(r, third).unwrap());
+    assert_eq!(rationalize(Scm::from_f64(0.3), Scm::from_f64(0.1)).unwrap().as_f64(), Some(1.0 / 3.0));
+    let inf = Scm::from_f64(f64::INFINITY);
+    assert_eq!(rationalize(inf, Scm::from_int(3)).unwrap().as_f64(), Some(f64::INFINITY));
+    assert_eq!(rationalize(-inf, Scm::from_f64(3.0)).unwrap().as_f64(), Some(f64::NEG_INFINITY));
+    assert_eq!(rationalize(Scm::from_int(3), inf).unwrap().as_f64(), Some(0.0));
+    assert!(rationalize(inf, inf).unwrap().as_f64().unwrap().is_nan());
+    for (x, y) in [(f64::NAN, 1.0), (1.0, f64::NAN), (f64::NAN, f64::INFINITY)] {
+        assert!(rationalize(Scm::from_f64(x), Scm::from_f64(y)).unwrap().as_f64().unwrap().is_nan());
+    }
+
+    let seven_halves = Scm::from_int(7) / Scm::from_int(2);
+    assert_eq!(round(seven_halves, Rounding::Round).unwrap(), Scm::from_int(4));
+    assert_eq!(round(-seven_halves, Rounding::Round).unwrap(), Scm::from_int(-4));
+    assert_eq!(round(Scm::from_int(5) / Scm::from_int(2), Rounding::Round).unwrap(), Scm::from_int(2));
+    assert_eq!(round(-seven_halves, Rounding::Truncate).unwrap(), Scm::from_int(-3));
+    assert_eq!(round(-seven_halves, Rounding::Floor).unwrap(), Scm::from_int(-4));
+    assert_eq!(round(Scm::from_f64(2.5), Rounding::Round).unwrap().as_f64(), Some(2.0));
+}
+
+#[test]
+fn number_syntax_round_trips() {
+    let big = make_integer(BigInt::parse_radix("-123456789abcdef0123456789", 16).unwrap());
+    for &radix in &[2, 8, 10, 16] {
+        for &x in &[Scm::from_int(0), Scm::from_int(-255), big, Scm::from_int(-7) / Scm::from_int(12)] {
+            let s = to_string(x, radix).unwrap();
+            assert!(eq(parse(&s, radix).unwrap(), x).unwrap(), "{} in radix {}", s, radix);
+        }
+    }
+    assert_eq!(to_string(Scm::from_int(255), 16).unwrap(), "ff");
+    assert_eq!(to_string(Scm::from_int(-5) / Scm::from_int(2), 2).unwrap(), "-101/10");
+    for &f in &[0.1, -1.5e300, 5e-324, 1.0 / 3.0] {
+        assert_eq!(parse(&to_string(Scm::from_f64(f), 10).unwrap(), 10).unwrap().as_f64(), Some(f));
+    }
+
+    assert_eq!(parse("#xff", 10), Some(Scm::from_int(255)));
+    assert_eq!(parse("#e#b101", 10), Some(Scm::from_int(5)));
+    assert_eq!(parse("#i1/4", 10).unwrap().as_f64(), Some(0.25));
+    assert_eq!(parse(".5e1", 10).unwrap().as_f64(), Some(5.0));
+    assert_eq!(parse("-inf.0", 16).unwrap().as_f64(), Some(f64::NEG_INFINITY));
+    assert!(eq(parse("#e1.2", 10).unwrap(), Scm::from_int(6) / Scm::from_int(5)).unwrap());
+    assert_eq!(parse("#e1.5e2", 10), Some(Scm::from_int(150)));
+    assert_eq!(to_string(parse("#e1e30", 10).unwrap(), 10).unwrap(), format!("1{}", "0".repeat(30)));
+    assert!(eq(parse("#e25e-3", 10).unwrap(), Scm::from_int(1) / Scm::from_int(40)).unwrap());
+    assert_eq!(parse("#e1e999999999", 10), None);
+    assert_eq!(parse("1e999999999", 10).unwrap().as_f64(), Some(f64::INFINITY));
+    for bad in &["", "+", ".", "1e", "1/-2", "1/0", "inf", "nan", "#x1.5", "#x#b1", "1.2.3", "abc"] {
+        assert_eq!(parse(bad, 10), None, "{:?}", bad);
+    }
+}
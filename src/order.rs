@@ -0,0 +1,303 @@
+//! A total order over all values, so they can be sorted and used as keys in
+//! ordered collections without a caller-supplied comparator.
+//!
+//! Kinds are ordered as numbers < characters < strings < symbols < booleans < () < pairs
+//! < vectors < boxes < multiple values < eof < opaque objects. Numbers
+//! compare numerically, with exact before inexact when they are numerically
+//! equal and NaNs after everything else. Pairs and vectors compare element by
+//! element. Opaque objects like promises have no contents to compare, so they
+//! are ordered by address; that is stable for as long as they live, but not
+//! from one run to the next.
+
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use crate::heap::{Header, Kind, FLAG_HASHED};
+use crate::num::{self, Num};
+use crate::{cons, Scm, ScmKind, ScmView, TypeError};
+
+fn rank(x: &Scm) -> u8 {
+    match x.kind() {
+        ScmKind::Integer | ScmKind::Bignum | ScmKind::Rational | ScmKind::Flonum | ScmKind::Number => 0,
+        ScmKind::Char => 1,
+        ScmKind::String => 2,
+        ScmKind::Symbol => 3,
+        ScmKind::Boolean => 4,
+        ScmKind::Nil => 5,
+        ScmKind::Pair => 6,
+        ScmKind::Vector => 7,
+        ScmKind::Box => 8,
+        ScmKind::Values => 9,
+        ScmKind::Eof => 10,
+        ScmKind::Promise => 11,
+        ScmKind::Port => 12,
+        ScmKind::Foreign => 13,
+        ScmKind::Environment => 14,
+        ScmKind::Syntax => 15,
+        ScmKind::Bitvector => 16,
+        ScmKind::Map => 17,
+        ScmKind::Deque => 18,
+        ScmKind::CharSet => 19,
+        ScmKind::Code => 20,
+        ScmKind::Continuation => 21,
+        ScmKind::Parameter => 22,
+        ScmKind::Mutex => 23,
+        ScmKind::CondVar => 24,
+        ScmKind::Thread => 25,
+        ScmKind::Sender => 26,
+        ScmKind::Receiver => 27,
+        ScmKind::Time => 28,
+        ScmKind::Duration => 29,
+        ScmKind::RandomState => 30,
+        ScmKind::Regex => 31,
+    }
+}
+
+impl Scm {
+    pub fn total_cmp(&self, other: &Scm) -> Ordering {
+        let (mut a, mut b) = (*self, *other);
+        loop {
+            if a == b {
+                return Ordering::Equal
+            }
+            let ord = rank(&a).cmp(&rank(&b)).then_with(|| match (a.classify(), b.classify()) {
+                (ScmView::Pair(&(x, _)), ScmView::Pair(&(y, _))) => x.total_cmp(&y),
+                (ScmView::Vector(xs), ScmView::Vector(ys)) => cmp_slices(xs, ys),
+                (ScmView::Values(xs), ScmView::Values(ys)) => cmp_slices(xs, ys),
+                (ScmView::Char(x), ScmView::Char(y)) => x.cmp(&y),
+                (ScmView::String(x), ScmView::String(y)) => x.cmp(y),
+                (ScmView::Symbol(x), ScmView::Symbol(y)) => x.cmp(y),
+                (ScmView::Boolean(x), ScmView::Boolean(y)) => x.cmp(&y),
+                (ScmView::Box(x), ScmView::Box(y)) => x.total_cmp(&y),
+                (ScmView::Time(x), ScmView::Time(y)) => x.cmp(&y),
+                (ScmView::Duration(x), ScmView::Duration(y)) => x.cmp(&y),
+                _ if rank(&a) == 0 => cmp_numbers(a, b),
+                _ => a.to_raw().cmp(&b.to_raw()),
+            });
+            // walk down the spine of lists instead of recursing
+            match (ord, a.as_pair(), b.as_pair()) {
+                (Ordering::Equal, Some(&(_, x)), Some(&(_, y))) => {
+                    a = x;
+                    b = y;
+                }
+                _ => return ord,
+            }
+        }
+    }
+}
+
+fn cmp_slices(xs: &[Scm], ys: &[Scm]) -> Ordering {
+    xs.iter()
+        .zip(ys)
+        .map(|(x, y)| x.total_cmp(y))
+        .find(|&o| o != Ordering::Equal)
+        .unwrap_or_else(|| xs.len().cmp(&ys.len()))
+}
+
+fn cmp_numbers(a: Scm, b: Scm) -> Ordering {
+    let (x, y) = (Num::from_scm(a).unwrap(), Num::from_scm(b).unwrap());
+    match (&x, &y) {
+        (Num::Inexact(f), Num::Inexact(g)) if f.is_nan() || g.is_nan() => {
+            f.is_nan().cmp(&g.is_nan()).then(f.total_cmp(g))
+        }
+        (Num::Inexact(f), _) if f.is_nan() => Ordering::Greater,
+        (_, Num::Inexact(g)) if g.is_nan() => Ordering::Less,
+        _ => num::compare(a, b).unwrap().unwrap().then_with(|| match (x, y) {
+            (Num::Exact(..), Num::Inexact(_)) => Ordering::Less,
+            (Num::Inexact(_), Num::Exact(..)) => Ordering::Greater,
+            // tells -0.0 and 0.0 apart
+            (Num::Inexact(f), Num::Inexact(g)) => f.total_cmp(&g),
+            (Num::Exact(..), Num::Exact(..)) => Ordering::Equal,
+        }),
+    }
+}
+
+// Structural equality, as in `equal?`.
+pub fn equal(a: Scm, b: Scm) -> bool {
+    a.total_cmp(&b) == Ordering::Equal
+}
+
+// A hash that agrees with `equal`: values that compare equal hash the same.
+pub fn equal_hash(x: Scm) -> u64 {
+    let mut h = DefaultHasher::new();
+    hash_into(x, &mut h);
+    h.finish()
+}
+
+// `equal_hash` folded to 32 bits, as hash tables use it. Heap objects
+// remember it in their header, so hashing a large key again is free. Objects
+// that contain a box are not cached, unless it is frozen, since the box may
+// change in between.
+pub fn equal_hash32(x: Scm) -> u32 {
+    let header = x.header();
+    if let Some(hash) = header.and_then(Header::hash) {
+        return hash
+    }
+    let mut h = DefaultHasher::new();
+    let immutable = hash_into(x, &mut h);
+    let hash = h.finish();
+    let hash = (hash ^ hash >> 32) as u32;
+    match header {
+        Some(header) if immutable => header.set_hash(hash),
+        _ => {}
+    }
+    hash
+}
+
+// Forgets the hash cached in the header of `x`. The unsafe setters, like
+// `lists::set_cdr` and `vectors::vector_set`, forget the hash of the object
+// they change, but the hash of a container covers its contents too: after
+// changing an object that other objects contain, call this on each of them
+// that may have been hashed, or they keep a stale hash.
+pub fn forget_hash(x: Scm) {
+    match x.header() {
+        // never written, since symbols are shared, and their hash never changes
+        Some(header) if header.kind() == Kind::Symbol => {}
+        Some(header) => header.set_flag(FLAG_HASHED, false),
+        None => {}
+    }
+}
+
+// Whether the hash can never change.
+fn hash_into(mut x: Scm, h: &mut DefaultHasher) -> bool {
+    let mut immutable = true;
+    loop {
+        rank(&x).hash(h);
+        match x.classify() {
+            ScmView::Pair(&(a, d)) => {
+                immutable &= hash_into(a, h);
+                x = d;
+                continue
+            }
+            ScmView::Nil | ScmView::Eof => {}
+            ScmView::Boolean(b) => b.hash(h),
+            ScmView::Char(c) => c.hash(h),
+            ScmView::Integer(i) => i.hash(h),
+            ScmView::Bignum(i) => i.hash(h),
+            ScmView::Rational(n, d) => (n, d).hash(h),
+            ScmView::Flonum(f) => f.to_bits().hash(h),
+            ScmView::String(s) | ScmView::Symbol(s) => s.hash(h),
+            ScmView::Vector(items) | ScmView::Values(items) => {
+                items.len().hash(h);
+                items.iter().for_each(|&x| immutable &= hash_into(x, h));
+            }
+            ScmView::Box(contents) => {
+                immutable &= hash_into(contents, h) & x.is_frozen();
+            }
+            ScmView::Time(t) => t.hash(h),
+            ScmView::Duration(d) => d.hash(h),
+            _ => x.to_raw().hash(h),
+        }
+        return immutable
+    }
+}
+
+// Orders by `Scm::total_cmp`, so that structurally equal values are the same
+// key in a `BTreeMap` or `HashMap` (plain `==` on `Scm` is identity).
+#[derive(Debug, Copy, Clone)]
+pub struct Sorted(pub Scm);
+
+impl PartialEq for Sorted {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Sorted {}
+
+impl Hash for Sorted {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u32(equal_hash32(self.0))
+    }
+}
+
+impl PartialOrd for Sorted {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Sorted {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// A new, sorted list with the elements of a proper list. The sort is stable.
+pub fn sort_list(list: Scm) -> Result<Scm, TypeError> {
+    let mut items = vec![];
+    let mut rest = list;
+    while let Some(&(x, d)) = rest.as_pair() {
+        items.push(x);
+        rest = d;
+    }
+    if !rest.is_nil() {
+        return Err(TypeError::new(ScmKind::Pair, rest))
+    }
+    items.sort_by(Scm::total_cmp);
+    Ok(items.into_iter().rev().fold(Scm::NIL, |acc, x| cons(x, acc)))
+}
+
+#[test]
+fn values_sort_across_kinds() {
+    use std::collections::BTreeMap;
+
+    let list = |items: &[Scm]| items.iter().rev().fold(Scm::NIL, |acc, &x| cons(x, acc));
+    let input = list(&[
+        Scm::EOF,
+        Scm::vector(vec![Scm::from_int(1)]),
+        list(&[Scm::from_int(1), Scm::from_int(3)]),
+        list(&[Scm::from_int(1), Scm::from_int(2)]),
+        Scm::NIL,
+        Scm::TRUE,
+        Scm::FALSE,
+        Scm::symbol("b"),
+        Scm::symbol("a"),
+        Scm::string("z"),
+        Scm::from_f64(f64::NAN),
+        Scm::from_f64(1.0),
+        Scm::from_int(1),
+        Scm::from_int(1) / Scm::from_int(2),
+        Scm::from_int(-3),
+    ]);
+    assert_eq!(
+        sort_list(input).unwrap().to_string(),
+        "(-3 1/2 1 1.0 +nan.0 \"z\" a b #f #t () (1 2) (1 3) #(1) #<eof>)"
+    );
+
+    let mut map = BTreeMap::new();
+    map.insert(Sorted(Scm::string("key")), 1);
+    map.insert(Sorted(Scm::string("key")), 2);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map[&Sorted(Scm::string("key"))], 2);
+}
+
+#[test]
+fn hashes_of_immutable_values_are_cached() {
+    let key = crate::reader::read_str("(deep (key \"with a long string\") #(1 2.5 3/4))").unwrap();
+    let same = crate::reader::read_str("(deep (key \"with a long string\") #(1 2.5 3/4))").unwrap();
+    assert_eq!(key.header().unwrap().hash(), None);
+    let hash = equal_hash32(key);
+    assert_eq!(key.header().unwrap().hash(), Some(hash));
+    assert_eq!(equal_hash32(key), hash);
+    assert_eq!(equal_hash32(same), hash);
+
+    let b = crate::boxes::make_box(Scm::from_int(1));
+    let boxed = cons(b, Scm::NIL);
+    let before = equal_hash32(boxed);
+    assert_eq!(boxed.header().unwrap().hash(), None);
+    crate::boxes::set_box(b, Scm::from_int(2)).unwrap();
+    assert_ne!(equal_hash32(boxed), before);
+    assert_eq!(equal_hash32(boxed), equal_hash32(cons(crate::boxes::make_box(Scm::from_int(2)), Scm::NIL)));
+
+    // A setter only forgets the hash of what it changes, not of its containers.
+    let inner = Scm::vector(vec![Scm::from_int(1)]);
+    let outer = cons(inner, Scm::NIL);
+    let stale = equal_hash32(outer);
+    unsafe { crate::vectors::vector_set(inner, 0, Scm::from_int(2)).unwrap() };
+    let fresh = crate::reader::read_str("(#(2))").unwrap();
+    assert_eq!(equal_hash32(inner), equal_hash32(crate::car(fresh).unwrap()));
+    assert_eq!(equal_hash32(outer), stale);
+    forget_hash(outer);
+    assert_eq!(equal_hash32(outer), equal_hash32(fresh));
+}
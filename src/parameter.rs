@@ -0,0 +1,109 @@
+//! Parameter objects (SRFI 39 / R7RS `make-parameter` and `parameterize`):
+//! variables with dynamic scope, for state like the current output port that
+//! a caller wants to override for everything it calls.
+//!
+//! A parameter holds its global value. `parameterize` binds new values for
+//! the dynamic extent of a call on the current thread only; other threads
+//! keep seeing their own bindings, or the global value. The bindings are
+//! undone however the extent is left, including by an escaping continuation
+//! or a panic.
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use crate::heap::{self, HeapObject, Kind};
+use crate::{Scm, ScmKind, TypeError};
+
+pub(crate) struct Parameter {
+    value: Scm,
+}
+
+impl HeapObject for Parameter {
+    const KIND: Kind = Kind::Parameter;
+}
+
+thread_local! {
+    // innermost binding last
+    static BINDINGS: RefCell<Vec<(Scm, Scm)>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Scm {
+    pub fn is_parameter(&self) -> bool {
+        self.as_object::<Parameter>().is_some()
+    }
+}
+
+fn expect_parameter(p: Scm) -> Result<&'static Parameter, TypeError> {
+    p.as_object::<Parameter>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Parameter, p))
+}
+
+pub fn make_parameter(value: Scm) -> Scm {
+    Scm::from_object(heap::leak(Parameter { value }))
+}
+
+// The innermost binding of `p` on this thread, or its global value.
+pub fn parameter_value(p: Scm) -> Result<Scm, TypeError> {
+    let global = expect_parameter(p)?.value;
+    Ok(BINDINGS.with(|bindings| {
+        bindings.borrow().iter().rev().find(|&&(q, _)| q == p).map_or(global, |&(_, value)| value)
+    }))
+}
+
+// Bindings made by `bind`, undone when this is dropped. It can't leave the
+// thread, whose bindings it refers to.
+pub struct DynamicBinding {
+    depth: usize,
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for DynamicBinding {
+    fn drop(&mut self) {
+        // also undoes bindings of inner guards that were leaked
+        BINDINGS.with(|bindings| bindings.borrow_mut().truncate(self.depth))
+    }
+}
+
+// Binds each parameter to its value until the returned guard is dropped.
+// Nothing is bound if one of them is not a parameter.
+pub fn bind(bindings: &[(Scm, Scm)]) -> Result<DynamicBinding, TypeError> {
+    for &(p, _) in bindings {
+        expect_parameter(p)?;
+    }
+    BINDINGS.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let depth = stack.len();
+        stack.extend_from_slice(bindings);
+        Ok(DynamicBinding { depth, _thread: PhantomData })
+    })
+}
+
+// Calls `f` with the parameters bound to the values.
+pub fn parameterize<R>(bindings: &[(Scm, Scm)], f: impl FnOnce() -> R) -> Result<R, TypeError> {
+    let _binding = bind(bindings)?;
+    Ok(f())
+}
+
+#[test]
+fn bindings_follow_the_dynamic_extent() {
+    let radix = make_parameter(Scm::from_int(10));
+    let prompt = make_parameter(Scm::string("> "));
+    assert!(radix.is_parameter() && radix.to_string() == "#<parameter>");
+
+    let inner = parameterize(&[(radix, Scm::from_int(2))], || {
+        let outer = parameter_value(radix).unwrap();
+        let inner = parameterize(&[(radix, Scm::from_int(16))], || parameter_value(radix).unwrap());
+        (outer, inner.unwrap(), parameter_value(prompt).unwrap())
+    });
+    assert_eq!(inner, Ok((Scm::from_int(2), Scm::from_int(16), Scm::string("> "))));
+    assert_eq!(parameter_value(radix), Ok(Scm::from_int(10)));
+
+    // escaping out of the extent undoes the binding
+    let escaped = crate::continuation::call_with_escape(|k| {
+        parameterize(&[(radix, Scm::from_int(8))], || crate::continuation::invoke(k, parameter_value(radix)?).map(|_| Scm::NIL))?
+    });
+    assert_eq!(escaped, Ok(Scm::from_int(8)));
+    assert_eq!(parameter_value(radix), Ok(Scm::from_int(10)));
+
+    assert!(bind(&[(radix, Scm::NIL), (Scm::NIL, Scm::NIL)]).is_err());
+    assert_eq!(parameter_value(radix), Ok(Scm::from_int(10)));
+    assert_eq!(parameter_value(Scm::NIL).unwrap_err().to_string(), "expected parameter, got empty list");
+}
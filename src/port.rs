@@ -0,0 +1,281 @@
+//! Ports: input and output streams of characters wrapping `std::io` readers
+//! and writers, or strings in memory. Text is always UTF-8.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use crate::heap::{self, HeapObject, Kind};
+use crate::lock::Lock;
+use crate::{PortError, Scm, ScmKind, TypeError};
+
+#[cfg(not(feature = "sync"))]
+pub trait PortRead: BufRead + 'static {}
+#[cfg(not(feature = "sync"))]
+impl<R: BufRead + 'static> PortRead for R {}
+#[cfg(not(feature = "sync"))]
+pub trait PortWrite: Write + 'static {}
+#[cfg(not(feature = "sync"))]
+impl<W: Write + 'static> PortWrite for W {}
+
+#[cfg(feature = "sync")]
+pub trait PortRead: BufRead + Send + 'static {}
+#[cfg(feature = "sync")]
+impl<R: BufRead + Send + 'static> PortRead for R {}
+#[cfg(feature = "sync")]
+pub trait PortWrite: Write + Send + 'static {}
+#[cfg(feature = "sync")]
+impl<W: Write + Send + 'static> PortWrite for W {}
+
+enum State {
+    Input {
+        reader: Box<dyn PortRead>,
+        // None if nothing was peeked, Some(None) if the peek hit the end
+        peeked: Option<Option<char>>,
+        // of the next character, both counting from 1
+        line: u32,
+        column: u32,
+    },
+    Output(Box<dyn PortWrite>),
+    StringOutput(Vec<u8>),
+    Closed { input: bool },
+}
+
+pub(crate) struct Port(Lock<State>);
+
+impl HeapObject for Port {
+    const KIND: Kind = Kind::Port;
+}
+
+fn alloc(state: State) -> Scm {
+    Scm::from_object(heap::leak(Port(Lock::new(state))))
+}
+
+fn port(p: Scm) -> Result<&'static Port, TypeError> {
+    p.as_object::<Port>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Port, p))
+}
+
+pub fn open_input<R: Read>(reader: R) -> Scm
+where
+    BufReader<R>: PortRead,
+{
+    open_buffered_input(BufReader::new(reader))
+}
+
+pub fn open_buffered_input(reader: impl PortRead) -> Scm {
+    alloc(State::Input { reader: Box::new(reader), peeked: None, line: 1, column: 1 })
+}
+
+pub fn open_output(writer: impl PortWrite) -> Scm {
+    alloc(State::Output(Box::new(writer)))
+}
+
+pub fn open_input_string(s: &str) -> Scm {
+    open_buffered_input(io::Cursor::new(s.as_bytes().to_vec()))
+}
+
+pub fn open_output_string() -> Scm {
+    alloc(State::StringOutput(vec![]))
+}
+
+// Everything written to a string port so far, as a new string.
+pub fn get_output_string(p: Scm) -> Result<Scm, PortError> {
+    match &*port(p)?.0.lock() {
+        // only whole strings are ever written, so this is valid UTF-8
+        State::StringOutput(buffer) => Ok(Scm::string(std::str::from_utf8(buffer).unwrap())),
+        State::Closed { input: false } => Err(PortError::Closed),
+        _ => Err(PortError::Type(TypeError::new(ScmKind::Port, p))),
+    }
+}
+
+// Collects what `f` writes to a fresh string port.
+pub fn with_output_to_string(f: impl FnOnce(Scm) -> Result<(), PortError>) -> Result<String, PortError> {
+    let p = open_output_string();
+    f(p)?;
+    let s = get_output_string(p)?;
+    Ok(s.as_str().unwrap().to_string())
+}
+
+pub fn is_port(p: Scm) -> bool {
+    p.as_object::<Port>().is_some()
+}
+
+pub fn is_input_port(p: Scm) -> bool {
+    matches!(port(p).map(|port| matches!(*port.0.lock(), State::Input { .. })), Ok(true))
+}
+
+pub fn is_output_port(p: Scm) -> bool {
+    matches!(port(p).map(|port| matches!(*port.0.lock(), State::Output(_) | State::StringOutput(_))), Ok(true))
+}
+
+fn decode_char(reader: &mut dyn PortRead) -> io::Result<Option<char>> {
+    let first = match reader.fill_buf()?.first() {
+        None => return Ok(None),
+        Some(&b) => b,
+    };
+    let len = match first {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 0,
+    };
+    let mut bytes = [0; 4];
+    if len == 0 || reader.read_exact(&mut bytes[..len]).is_err() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8"))
+    }
+    match std::str::from_utf8(&bytes[..len]) {
+        Ok(s) => Ok(s.chars().next()),
+        Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8")),
+    }
+}
+
+fn next_char(p: Scm, consume: bool) -> Result<Scm, PortError> {
+    let mut state = port(p)?.0.lock();
+    match &mut *state {
+        State::Input { reader, peeked, line, column } => {
+            let c = match peeked.take() {
+                Some(c) => c,
+                None => decode_char(&mut **reader)?,
+            };
+            if !consume {
+                *peeked = Some(c);
+            } else if c == Some('\n') {
+                *line += 1;
+                *column = 1;
+            } else if c.is_some() {
+                *column += 1;
+            }
+            Ok(c.map(Scm::from_char).unwrap_or(Scm::EOF))
+        }
+        State::Output(_) | State::StringOutput(_) | State::Closed { input: false } => Err(PortError::NotAnInputPort),
+        State::Closed { input: true } => Err(PortError::Closed),
+    }
+}
+
+// Line and column of the next character of an input port, counting from 1.
+pub fn position(p: Scm) -> Result<(u32, u32), PortError> {
+    match &*port(p)?.0.lock() {
+        State::Input { line, column, .. } => Ok((*line, *column)),
+        State::Closed { input: true } => Err(PortError::Closed),
+        _ => Err(PortError::NotAnInputPort),
+    }
+}
+
+// The next character, or the eof object at the end of the input.
+pub fn read_char(p: Scm) -> Result<Scm, PortError> {
+    next_char(p, true)
+}
+
+pub fn peek_char(p: Scm) -> Result<Scm, PortError> {
+    next_char(p, false)
+}
+
+fn with_writer<T>(p: Scm, f: impl FnOnce(&mut dyn PortWrite) -> io::Result<T>) -> Result<T, PortError> {
+    match &mut *port(p)?.0.lock() {
+        State::Output(writer) => Ok(f(&mut **writer)?),
+        State::StringOutput(buffer) => Ok(f(buffer)?),
+        State::Input { .. } | State::Closed { input: true } => Err(PortError::NotAnOutputPort),
+        State::Closed { input: false } => Err(PortError::Closed),
+    }
+}
+
+pub fn write_string(p: Scm, s: &str) -> Result<(), PortError> {
+    with_writer(p, |w| w.write_all(s.as_bytes()))
+}
+
+pub fn write_char(p: Scm, c: char) -> Result<(), PortError> {
+    write_string(p, c.encode_utf8(&mut [0; 4]))
+}
+
+// The external representation of `x`, as the printer produces it.
+pub fn write(p: Scm, x: Scm) -> Result<(), PortError> {
+    with_writer(p, |w| std::io::Write::write_fmt(w, format_args!("{}", x)))
+}
+
+pub fn display(p: Scm, x: Scm) -> Result<(), PortError> {
+    with_writer(p, |w| std::io::Write::write_fmt(w, format_args!("{}", x.display())))
+}
+
+pub fn flush(p: Scm) -> Result<(), PortError> {
+    with_writer(p, |w| w.flush())
+}
+
+// Flushes and drops the underlying reader or writer. Closing a closed port
+// does nothing.
+pub fn close(p: Scm) -> Result<(), PortError> {
+    let mut state = port(p)?.0.lock();
+    let input = match &mut *state {
+        State::Input { .. } => true,
+        State::Output(writer) => {
+            writer.flush()?;
+            false
+        }
+        State::StringOutput(_) => false,
+        State::Closed { .. } => return Ok(()),
+    };
+    *state = State::Closed { input };
+    Ok(())
+}
+
+#[test]
+fn ports_read_and_write_characters() {
+    use std::sync::{Arc, Mutex};
+
+    let input = open_input("héllo".as_bytes());
+    assert!(is_port(input) && is_input_port(input) && !is_output_port(input));
+    assert_eq!(read_char(input).unwrap(), Scm::from_char('h'));
+    assert_eq!(peek_char(input).unwrap(), Scm::from_char('é'));
+    assert_eq!(read_char(input).unwrap(), Scm::from_char('é'));
+    for c in "llo".chars() {
+        assert_eq!(read_char(input).unwrap(), Scm::from_char(c));
+    }
+    assert_eq!(peek_char(input).unwrap(), Scm::EOF);
+    assert_eq!(read_char(input).unwrap(), Scm::EOF);
+    assert!(matches!(write_string(input, "x"), Err(PortError::NotAnOutputPort)));
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buffer = Shared::default();
+    let output = open_output(buffer.clone());
+    write_string(output, "λ = ").unwrap();
+    write_char(output, '1').unwrap();
+    assert_eq!(output.to_string(), "#<output-port>");
+    close(output).unwrap();
+    close(output).unwrap();
+    assert_eq!(output.to_string(), "#<closed-port>");
+    assert!(matches!(write_char(output, '!'), Err(PortError::Closed)));
+    assert_eq!(String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap(), "λ = 1");
+
+    assert!(matches!(read_char(Scm::NIL), Err(PortError::Type(_))));
+    let invalid = open_input(&[0xff][..]);
+    assert!(matches!(read_char(invalid), Err(PortError::Io(_))));
+}
+
+#[test]
+fn string_ports() {
+    let p = open_input_string("(a \"b\") 42");
+    assert_eq!(crate::reader::read(p).unwrap().to_string(), "(a \"b\")");
+    assert_eq!(read_char(p).unwrap(), Scm::from_char(' '));
+    assert_eq!(crate::reader::read(p).unwrap(), Scm::from_int(42));
+    assert_eq!(crate::reader::read(p).unwrap(), Scm::EOF);
+
+    let s = with_output_to_string(|out| {
+        write(out, Scm::string("hi"))?;
+        write_char(out, ' ')?;
+        display(out, Scm::string("hi"))
+    });
+    assert_eq!(s.unwrap(), "\"hi\" hi");
+
+    let out = open_output_string();
+    write_string(out, "abc").unwrap();
+    assert_eq!(get_output_string(out).unwrap().as_str(), Some("abc"));
+    write_string(out, "def").unwrap();
+    assert_eq!(get_output_string(out).unwrap().as_str(), Some("abcdef"));
+}
@@ -0,0 +1,200 @@
+//! External representation of values, as `write` and `display` produce it.
+
+use std::fmt::{self, Write};
+use crate::{Scm, ScmView};
+
+impl fmt::Display for Scm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        print(*self, false, f)
+    }
+}
+
+// The human-readable representation that `display` produces: like `write`,
+// but strings and characters are printed as their contents.
+pub struct Displayed(Scm);
+
+impl Scm {
+    pub fn display(&self) -> Displayed {
+        Displayed(*self)
+    }
+}
+
+impl fmt::Display for Displayed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        print(self.0, true, f)
+    }
+}
+
+struct Printed(Scm, bool);
+
+impl fmt::Display for Printed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        print(self.0, self.1, f)
+    }
+}
+
+fn print(x: Scm, display: bool, f: &mut fmt::Formatter) -> fmt::Result {
+    let p = |x| Printed(x, display);
+    match x.classify() {
+        ScmView::Nil => f.write_str("()"),
+        ScmView::Boolean(true) => f.write_str("#t"),
+        ScmView::Boolean(false) => f.write_str("#f"),
+        ScmView::Eof => f.write_str("#<eof>"),
+        ScmView::Char(c) if display => f.write_char(c),
+        ScmView::Char(c) => write_char(c, f),
+        ScmView::Integer(i) => write!(f, "{}", i),
+        ScmView::Bignum(i) => write!(f, "{}", i),
+        ScmView::Rational(n, d) => write!(f, "{}/{}", n, d),
+        ScmView::Flonum(x) => write_flonum(x, f),
+        ScmView::Symbol(name) if display || !needs_bars(name) => f.write_str(name),
+        ScmView::Symbol(name) => write_escaped(name, '|', f),
+        ScmView::Box(x) => write!(f, "#&{}", p(x)),
+        ScmView::Promise => f.write_str("#<promise>"),
+        ScmView::Port if crate::port::is_input_port(x) => f.write_str("#<input-port>"),
+        ScmView::Port if crate::port::is_output_port(x) => f.write_str("#<output-port>"),
+        ScmView::Port => f.write_str("#<closed-port>"),
+        ScmView::Environment => f.write_str("#<environment>"),
+        ScmView::CharSet => f.write_str("#<char-set>"),
+        ScmView::Regex => f.write_str("#<regex>"),
+        ScmView::RandomState => f.write_str("#<random-state>"),
+        ScmView::Duration(d) => write!(f, "#<duration {:?}>", d),
+        ScmView::Time(t) => match t.duration_since(std::time::UNIX_EPOCH) {
+            Ok(d) => write!(f, "#<time {}.{:09}>", d.as_secs(), d.subsec_nanos()),
+            Err(e) => write!(f, "#<time -{}.{:09}>", e.duration().as_secs(), e.duration().subsec_nanos()),
+        },
+        ScmView::Receiver => f.write_str("#<channel-receiver>"),
+        ScmView::Sender => f.write_str("#<channel-sender>"),
+        ScmView::Thread => f.write_str("#<thread>"),
+        ScmView::CondVar => f.write_str("#<condition-variable>"),
+        ScmView::Mutex => f.write_str("#<mutex>"),
+        ScmView::Parameter => f.write_str("#<parameter>"),
+        ScmView::Continuation => f.write_str("#<continuation>"),
+        ScmView::Code => match crate::code::code_name(x).unwrap() {
+            name if name.as_symbol().is_some() => write!(f, "#<code {}>", p(name)),
+            _ => f.write_str("#<code>"),
+        },
+        ScmView::Bitvector => {
+            f.write_str("#*")?;
+            for i in 0..crate::bitvector::bitvector_length(x).unwrap() {
+                f.write_char(if crate::bitvector::bit_ref(x, i).unwrap() { '1' } else { '0' })?;
+            }
+            Ok(())
+        }
+        ScmView::Syntax(_, location) => write!(f, "#<syntax {} {}>", location, p(crate::syntax::strip(x))),
+        ScmView::Foreign(name) => match crate::foreign::print_hook(name) {
+            Some(hook) => hook(x, f),
+            None => write!(f, "#<{}>", name),
+        },
+        ScmView::Map => {
+            f.write_str("#<map")?;
+            for (k, v) in crate::hamt::map_entries(x).unwrap() {
+                write!(f, " ({} . {})", p(k), p(v))?;
+            }
+            f.write_char('>')
+        }
+        ScmView::Deque => {
+            f.write_str("#<deque")?;
+            for x in crate::deque::deque_items(x).unwrap() {
+                write!(f, " {}", p(x))?;
+            }
+            f.write_char('>')
+        }
+        ScmView::Values(items) => {
+            f.write_str("#<values")?;
+            for &x in items {
+                write!(f, " {}", p(x))?;
+            }
+            f.write_char('>')
+        }
+        ScmView::String(s) if display => f.write_str(s),
+        ScmView::String(s) => write_escaped(s, '"', f),
+        ScmView::Vector(items) => {
+            f.write_str("#(")?;
+            for (i, &x) in items.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(' ')?;
+                }
+                write!(f, "{}", p(x))?;
+            }
+            f.write_char(')')
+        }
+        ScmView::Pair(&(car, mut cdr)) => {
+            write!(f, "({}", p(car))?;
+            while let Some(&(a, d)) = cdr.as_pair() {
+                write!(f, " {}", p(a))?;
+                cdr = d;
+            }
+            if !cdr.is_nil() {
+                write!(f, " . {}", p(cdr))?;
+            }
+            f.write_char(')')
+        }
+    }
+}
+
+// Symbols that the reader would not read back as the same symbol.
+fn needs_bars(name: &str) -> bool {
+    name.is_empty()
+        || name == "."
+        || name.starts_with('#')
+        || name.chars().any(|c| c.is_whitespace() || c.is_control() || "()[]\"';`,|".contains(c))
+        || crate::num::parse(name, 10).is_some()
+}
+
+fn write_flonum(x: f64, f: &mut fmt::Formatter) -> fmt::Result {
+    if x.is_nan() {
+        f.write_str("+nan.0")
+    } else if x.is_infinite() {
+        f.write_str(if x > 0.0 { "+inf.0" } else { "-inf.0" })
+    } else {
+        // Debug always includes a decimal point or exponent: 1.0, 1e100
+        write!(f, "{:?}", x)
+    }
+}
+
+fn write_char(c: char, f: &mut fmt::Formatter) -> fmt::Result {
+    match c {
+        '\x07' => f.write_str("#\\alarm"),
+        '\x08' => f.write_str("#\\backspace"),
+        '\x7f' => f.write_str("#\\delete"),
+        '\x1b' => f.write_str("#\\escape"),
+        '\n' => f.write_str("#\\newline"),
+        '\0' => f.write_str("#\\null"),
+        '\r' => f.write_str("#\\return"),
+        ' ' => f.write_str("#\\space"),
+        '\t' => f.write_str("#\\tab"),
+        c if c.is_control() || c.is_whitespace() => write!(f, "#\\x{:x}", c as u32),
+        c => write!(f, "#\\{}", c),
+    }
+}
+
+fn write_escaped(s: &str, quote: char, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_char(quote)?;
+    for ch in s.chars() {
+        match ch {
+            c if c == quote => write!(f, "\\{}", c)?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            c if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char(quote)
+}
+
+#[test]
+fn print_values() {
+    use crate::cons;
+
+    let list = cons(Scm::from_int(1), cons(Scm::symbol("two"), cons(Scm::string("th\"ree\n"), Scm::NIL)));
+    assert_eq!(list.to_string(), "(1 two \"th\\\"ree\\n\")");
+    assert_eq!(cons(Scm::TRUE, Scm::FALSE).to_string(), "(#t . #f)");
+    assert_eq!(Scm::vector(vec![Scm::NIL, Scm::EOF, Scm::from_int(-5)]).to_string(), "#(() #<eof> -5)");
+    assert_eq!(Scm::from_f64(2.0).to_string(), "2.0");
+    assert_eq!(Scm::from_f64(f64::NEG_INFINITY).to_string(), "-inf.0");
+    assert_eq!(Scm::symbol("hello world").to_string(), "|hello world|");
+    assert_eq!(list.display().to_string(), "(1 two th\"ree\n)");
+    assert_eq!(Scm::vector(vec![Scm::from_char('a'), Scm::from_char(' '), Scm::from_char('\u{a0}')]).to_string(), "#(#\\a #\\space #\\xa0)");
+}
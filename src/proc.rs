@@ -0,0 +1,129 @@
+//! Procedures, and calls to them with one, two or three arguments, with the
+//! `interp` feature.
+//!
+//! `eval::apply` takes the arguments as a slice, which the caller usually
+//! has to collect first. `apply_1`, `apply_2` and `apply_3` take them
+//! directly, and hand them on directly to primitives made with the matching
+//! `make_primitive_n`, so an interpreter's inner loop can call those without
+//! allocating. Every procedure carries its arity, so each call is checked
+//! once, up front, and never again while the arguments are bound.
+
+use std::ops::RangeInclusive;
+use crate::eval::{self, Primitive};
+use crate::foreign::make_foreign;
+use crate::{EvalError, Scm};
+
+pub type Primitive1 = fn(Scm) -> Result<Scm, EvalError>;
+pub type Primitive2 = fn(Scm, Scm) -> Result<Scm, EvalError>;
+pub type Primitive3 = fn(Scm, Scm, Scm) -> Result<Scm, EvalError>;
+
+// The upper end of the arity of procedures that take any number of
+// arguments.
+pub const VARIADIC: usize = usize::MAX;
+
+#[derive(Copy, Clone)]
+pub(crate) enum Procedure {
+    Primitive(Primitive, usize, usize),
+    Primitive1(Primitive1),
+    Primitive2(Primitive2),
+    Primitive3(Primitive3),
+    Closure { params: Scm, body: Scm, env: Scm, required: usize, rest: bool },
+}
+
+impl Procedure {
+    fn arity(&self) -> RangeInclusive<usize> {
+        match *self {
+            Procedure::Primitive(_, min, max) => min..=max,
+            Procedure::Primitive1(_) => 1..=1,
+            Procedure::Primitive2(_) => 2..=2,
+            Procedure::Primitive3(_) => 3..=3,
+            Procedure::Closure { required, rest: true, .. } => required..=VARIADIC,
+            Procedure::Closure { required, rest: false, .. } => required..=required,
+        }
+    }
+}
+
+impl Scm {
+    pub fn is_procedure(&self) -> bool {
+        self.downcast_ref::<Procedure>().is_some()
+    }
+}
+
+pub(crate) fn procedure(f: Scm) -> Result<Procedure, EvalError> {
+    f.downcast_ref::<Procedure>().copied().ok_or(EvalError::NotAProcedure(f))
+}
+
+// The procedure `f`, if it can be called with `n` arguments.
+pub(crate) fn checked(f: Scm, n: usize) -> Result<Procedure, EvalError> {
+    let p = procedure(f)?;
+    if p.arity().contains(&n) { Ok(p) } else { Err(EvalError::Arity(f, n)) }
+}
+
+// Calls a procedure that `checked` returned for this number of arguments.
+pub(crate) fn call(p: Procedure, args: &[Scm]) -> Result<Scm, EvalError> {
+    match (p, args) {
+        (Procedure::Primitive(prim, _, _), _) => prim(args),
+        (Procedure::Primitive1(prim), &[a]) => prim(a),
+        (Procedure::Primitive2(prim), &[a, b]) => prim(a, b),
+        (Procedure::Primitive3(prim), &[a, b, c]) => prim(a, b, c),
+        (Procedure::Closure { params, body, env, .. }, _) => eval::call_closure(params, body, env, args),
+        _ => unreachable!("arity is checked before the call"),
+    }
+}
+
+// The numbers of arguments `f` can be called with, up to `VARIADIC`.
+pub fn arity(f: Scm) -> Result<RangeInclusive<usize>, EvalError> {
+    procedure(f).map(|p| p.arity())
+}
+
+pub fn make_primitive_1(f: Primitive1) -> Scm {
+    make_foreign("procedure", Procedure::Primitive1(f))
+}
+
+pub fn make_primitive_2(f: Primitive2) -> Scm {
+    make_foreign("procedure", Procedure::Primitive2(f))
+}
+
+pub fn make_primitive_3(f: Primitive3) -> Scm {
+    make_foreign("procedure", Procedure::Primitive3(f))
+}
+
+pub fn apply_1(f: Scm, a: Scm) -> Result<Scm, EvalError> {
+    match checked(f, 1)? {
+        Procedure::Primitive1(prim) => prim(a),
+        p => call(p, &[a]),
+    }
+}
+
+pub fn apply_2(f: Scm, a: Scm, b: Scm) -> Result<Scm, EvalError> {
+    match checked(f, 2)? {
+        Procedure::Primitive2(prim) => prim(a, b),
+        p => call(p, &[a, b]),
+    }
+}
+
+pub fn apply_3(f: Scm, a: Scm, b: Scm, c: Scm) -> Result<Scm, EvalError> {
+    match checked(f, 3)? {
+        Procedure::Primitive3(prim) => prim(a, b, c),
+        p => call(p, &[a, b, c]),
+    }
+}
+
+#[test]
+fn fixed_arity_calls() {
+    let env = eval::standard_environment();
+    let run = |src: &str| eval::eval(crate::reader::read_str(src).unwrap(), env).unwrap();
+    let (car, cons, plus) = (run("car"), run("cons"), run("+"));
+    let pair = apply_2(cons, Scm::from_int(1), Scm::from_int(2)).unwrap();
+    assert_eq!(apply_1(car, pair).unwrap(), Scm::from_int(1));
+    assert_eq!(apply_3(plus, Scm::from_int(1), Scm::from_int(2), Scm::from_int(3)).unwrap(), Scm::from_int(6));
+    assert_eq!(eval::apply(cons, &[Scm::NIL, Scm::NIL]).unwrap().to_string(), "(())");
+
+    let add3 = run("(lambda (a b . more) (+ a b (car more)))");
+    assert_eq!(arity(add3).unwrap(), 2..=VARIADIC);
+    assert_eq!(apply_3(add3, Scm::from_int(1), Scm::from_int(2), Scm::from_int(3)).unwrap(), Scm::from_int(6));
+    assert_eq!(arity(car).unwrap(), 1..=1);
+    assert_eq!(apply_2(car, pair, pair).unwrap_err().to_string(), "#<procedure> called with 2 arguments");
+    assert_eq!(apply_1(add3, pair).unwrap_err().to_string(), "#<procedure> called with 1 arguments");
+    assert!(arity(Scm::from_int(1)).is_err());
+}
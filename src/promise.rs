@@ -0,0 +1,161 @@
+//! Promises for `delay`, `delay-force` and `force`, following SRFI 45 so
+//! that iterative lazy algorithms run in constant space.
+//!
+//! There are no procedure objects yet, so the thunk is a Rust closure.
+
+use std::sync::Arc;
+use crate::heap::{self, HeapObject, Kind, Object};
+use crate::lock::Lock;
+use crate::Scm;
+
+#[cfg(not(feature = "sync"))]
+pub trait Thunk: Fn() -> Scm + 'static {}
+#[cfg(not(feature = "sync"))]
+impl<F: Fn() -> Scm + 'static> Thunk for F {}
+
+#[cfg(feature = "sync")]
+pub trait Thunk: Fn() -> Scm + Send + Sync + 'static {}
+#[cfg(feature = "sync")]
+impl<F: Fn() -> Scm + Send + Sync + 'static> Thunk for F {}
+
+#[derive(Clone)]
+enum State {
+    Done(Scm),
+    // the thunk returns the value
+    Delay(Arc<dyn Thunk>),
+    // the thunk returns another promise, which this one becomes
+    DelayForce(Arc<dyn Thunk>),
+    // this promise was merged into another one by `force`
+    Forward(Scm),
+}
+
+pub(crate) struct Promise(Lock<State>);
+
+impl Promise {
+    fn state(&self) -> impl std::ops::DerefMut<Target = State> + '_ {
+        self.0.lock()
+    }
+}
+
+impl HeapObject for Promise {
+    const KIND: Kind = Kind::Promise;
+}
+
+fn alloc(state: State) -> Scm {
+    Scm::from_object(heap::leak(Promise(Lock::new(state))))
+}
+
+impl Scm {
+    pub fn is_promise(&self) -> bool {
+        self.as_object::<Promise>().is_some()
+    }
+}
+
+pub fn delay(thunk: impl Thunk) -> Scm {
+    alloc(State::Delay(Arc::new(thunk)))
+}
+
+pub fn delay_force(thunk: impl Thunk) -> Scm {
+    alloc(State::DelayForce(Arc::new(thunk)))
+}
+
+// An already forced promise. Promises are returned as they are.
+pub fn make_promise(value: Scm) -> Scm {
+    if value.is_promise() { value } else { alloc(State::Done(value)) }
+}
+
+fn resolve(mut p: Scm) -> (Scm, &'static Object<Promise>) {
+    loop {
+        let obj = p.as_object::<Promise>().unwrap();
+        let next = match &*obj.state() {
+            State::Forward(q) => *q,
+            _ => return (p, obj),
+        };
+        p = next;
+    }
+}
+
+// Forcing anything that is not a promise returns it unchanged. If the thunk
+// forces the same promise again, the value computed first wins.
+pub fn force(p: Scm) -> Scm {
+    if !p.is_promise() {
+        return p
+    }
+    loop {
+        let (p, obj) = resolve(p);
+        // the lock must not be held while running the thunk, which may force
+        // this very promise
+        let state = obj.state().clone();
+        match state {
+            State::Done(value) => return value,
+            State::Delay(thunk) => {
+                let value = thunk();
+                let mut state = obj.state();
+                if !matches!(*state, State::Done(_)) {
+                    *state = State::Done(value);
+                }
+            }
+            State::DelayForce(thunk) => {
+                let next = thunk();
+                if !next.is_promise() {
+                    let mut state = obj.state();
+                    if !matches!(*state, State::Done(_)) {
+                        *state = State::Done(next);
+                    }
+                    continue
+                }
+                let (next, other) = resolve(next);
+                if next == p || matches!(*obj.state(), State::Done(_)) {
+                    continue
+                }
+                // take over the other promise's state and leave a forwarding
+                // pointer behind, so chains of promises don't pile up
+                let taken = std::mem::replace(&mut *other.state(), State::Forward(p));
+                *obj.state() = taken;
+            }
+            State::Forward(_) => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn force_memoizes_and_runs_in_constant_space() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let p = delay(|| {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        Scm::from_int(42)
+    });
+    assert_eq!(force(p), Scm::from_int(42));
+    assert_eq!(force(p), Scm::from_int(42));
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    assert_eq!(force(Scm::TRUE), Scm::TRUE);
+    assert_eq!(force(make_promise(Scm::NIL)), Scm::NIL);
+
+    // (define (loop n) (delay-force (if (= n 0) (delay 'done) (loop (- n 1)))))
+    fn countdown(n: i64) -> Scm {
+        delay_force(move || if n == 0 { delay(|| Scm::symbol("done")) } else { countdown(n - 1) })
+    }
+    assert_eq!(force(countdown(100_000)), Scm::symbol("done"));
+}
+
+#[test]
+fn reentrant_force_keeps_the_first_value() {
+    // (define count 0)
+    // (define p (delay (begin (set! count (+ count 1))
+    //                         (if (> count x) count (force p)))))
+    // (define x 5)
+    // (force p) => 6
+    use crate::boxes::{make_box, set_box, unbox};
+    let count = make_box(Scm::from_int(0));
+    let this = make_box(Scm::NIL);
+    let p = delay(move || {
+        let n = unbox(count).unwrap().as_integer().unwrap() + 1;
+        set_box(count, Scm::from_int(n)).unwrap();
+        if n > 5 { Scm::from_int(n) } else { force(unbox(this).unwrap()) }
+    });
+    set_box(this, p).unwrap();
+    assert_eq!(force(p), Scm::from_int(6));
+    assert_eq!(unbox(count).unwrap(), Scm::from_int(6));
+}
@@ -0,0 +1,126 @@
+//! Property lists: metadata attached to heap objects, such as the types or
+//! source locations a compiler records for the nodes of a tree, without
+//! changing their structure.
+//!
+//! The properties live in a side table keyed by the object's address, which
+//! is stored hidden (complemented), so that the table doesn't keep the object
+//! alive for a conservative collector. An object has properties if and only
+//! if it has an entry there. When the object is dropped, its entry goes with
+//! it.
+//!
+//! Symbols are shared by all threads, so their headers are never written: a
+//! dropped symbol always looks for its entry, while other objects set
+//! `FLAG_PROPERTIES` to ask for that.
+
+use std::collections::BTreeMap;
+use crate::heap::{Header, Kind, FLAG_PROPERTIES};
+use crate::{Scm, ScmKind, TypeError};
+
+type Table = BTreeMap<usize, Vec<(Scm, Scm)>>;
+
+// Without the `sync` feature values never leave the thread that made them,
+// so each thread has its own properties, even those of symbols.
+#[cfg(not(feature = "sync"))]
+thread_local! {
+    static PROPERTIES: std::cell::RefCell<Table> = const { std::cell::RefCell::new(BTreeMap::new()) };
+}
+
+#[cfg(feature = "sync")]
+static PROPERTIES: std::sync::Mutex<Table> = std::sync::Mutex::new(BTreeMap::new());
+
+#[cfg(not(feature = "sync"))]
+fn with_properties<R: Default>(f: impl FnOnce(&mut Table) -> R) -> R {
+    // objects may be dropped while the thread shuts down
+    PROPERTIES.try_with(|table| f(&mut table.borrow_mut())).unwrap_or_default()
+}
+
+#[cfg(feature = "sync")]
+fn with_properties<R: Default>(f: impl FnOnce(&mut Table) -> R) -> R {
+    f(&mut PROPERTIES.lock().unwrap())
+}
+
+fn hidden(header: &Header) -> usize {
+    !(header as *const Header as usize)
+}
+
+// Called when an object with properties is dropped.
+pub(crate) fn forget(header: &Header) {
+    with_properties(|table| table.remove(&hidden(header)));
+}
+
+// Sets the property `key`, a symbol, of the object `x`. Immediate values,
+// such as characters, fixnums and short strings, have no identity to attach
+// properties to, so they are rejected like any other type error.
+pub fn putprop(x: Scm, key: Scm, value: Scm) -> Result<(), TypeError> {
+    if key.as_symbol().is_none() {
+        return Err(TypeError::new(ScmKind::Symbol, key))
+    }
+    let header = x.header().ok_or_else(|| TypeError::new(ScmKind::Pair, x))?;
+    if header.kind() != Kind::Symbol {
+        header.set_flag(FLAG_PROPERTIES, true);
+    }
+    with_properties(|table| {
+        let properties = table.entry(hidden(header)).or_default();
+        match properties.iter_mut().find(|(k, _)| *k == key) {
+            Some(property) => property.1 = value,
+            None => properties.push((key, value)),
+        }
+    });
+    Ok(())
+}
+
+pub fn getprop(x: Scm, key: Scm) -> Option<Scm> {
+    let header = x.header()?;
+    with_properties(|table| {
+        let properties = table.get(&hidden(header))?;
+        properties.iter().find(|(k, _)| *k == key).map(|&(_, value)| value)
+    })
+}
+
+pub fn remprop(x: Scm, key: Scm) {
+    if let Some(header) = x.header() {
+        with_properties(|table| {
+            if let Some(properties) = table.get_mut(&hidden(header)) {
+                properties.retain(|(k, _)| *k != key);
+                if properties.is_empty() {
+                    table.remove(&hidden(header));
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn properties_die_with_their_object() {
+    let (ty, line) = (Scm::symbol("type"), Scm::symbol("line"));
+    let node = crate::cons(Scm::symbol("+"), Scm::NIL);
+    putprop(node, ty, Scm::symbol("integer")).unwrap();
+    putprop(node, line, Scm::from_int(3)).unwrap();
+    putprop(node, line, Scm::from_int(4)).unwrap();
+    assert_eq!(getprop(node, ty), Some(Scm::symbol("integer")));
+    assert_eq!(getprop(node, line), Some(Scm::from_int(4)));
+    remprop(node, ty);
+    assert_eq!(getprop(node, ty), None);
+    assert_eq!(getprop(crate::cons(Scm::NIL, Scm::NIL), line), None);
+    assert_eq!(putprop(node, Scm::from_int(1), Scm::NIL).unwrap_err().to_string(), "expected symbol, got integer");
+    assert_eq!(putprop(Scm::string("short"), line, Scm::NIL).unwrap_err().to_string(), "expected pair, got string");
+    assert!(putprop(Scm::from_char('x'), line, Scm::NIL).is_err());
+    assert_eq!(getprop(Scm::string("short"), line), None);
+
+    let key = unsafe {
+        crate::heap::scoped(|_| {
+            let temporary = crate::cons(Scm::NIL, Scm::NIL);
+            putprop(temporary, line, Scm::from_int(5)).unwrap();
+            hidden(temporary.header().unwrap())
+        })
+    };
+    assert!(!with_properties(|table| table.contains_key(&key)));
+
+    let symbol = Scm::symbol("symbol-with-properties");
+    #[cfg(feature = "sync")]
+    std::thread::spawn(move || putprop(symbol, line, Scm::from_int(6)).unwrap()).join().unwrap();
+    #[cfg(not(feature = "sync"))]
+    putprop(symbol, line, Scm::from_int(6)).unwrap();
+    assert_eq!(getprop(symbol, line), Some(Scm::from_int(6)));
+    assert_eq!(symbol.header().unwrap().flags() & FLAG_PROPERTIES, 0);
+}
@@ -0,0 +1,188 @@
+//! Python bindings, with the `python` feature.
+//!
+//! Build the extension module with maturin, or with
+//! `cargo build --release --features python,pyo3/extension-module` and
+//! renaming the library to `scm_repr.so`.
+//!
+//! Python values convert to Scheme values and back as follows:
+//!   - `bool`, `int` (of any size), `float` and `str` as themselves
+//!   - `list` as a proper list, `tuple` as a vector
+//!   - `Scm` objects as the value they wrap
+//!
+//! Anything else (symbols, characters, improper lists, ...) comes back to
+//! Python as an `Scm` object, so round trips never lose information.
+//!
+//! ```python
+//! import scm_repr
+//! x = scm_repr.read("(1 (2 3) #(4 5) \"six\")")
+//! scm_repr.to_python(x)            # [1, [2, 3], (4, 5), 'six']
+//! scm_repr.write([1, (2.5,), "x"]) # '(1 #(2.5) "x")'
+//! ```
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyFloat, PyInt, PyList, PyString, PyTuple};
+use crate::{num, order, reader, Scm, ScmView};
+
+#[pyclass(name = "Scm", module = "scm_repr", unsendable)]
+#[derive(Debug, Copy, Clone)]
+pub struct PyScm(Scm);
+
+#[pymethods]
+impl PyScm {
+    // None for anything but a pair
+    fn car(&self) -> Option<PyScm> {
+        crate::car(self.0).map(PyScm)
+    }
+
+    fn cdr(&self) -> Option<PyScm> {
+        crate::cdr(self.0).map(PyScm)
+    }
+
+    fn kind(&self) -> String {
+        self.0.kind().to_string()
+    }
+
+    fn to_python(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("scm_repr.read({:?})", self.0.to_string())
+    }
+
+    // `equal?`, so that values can be compared and used as dict keys
+    fn __eq__(&self, other: &PyScm) -> bool {
+        order::equal(self.0, other.0)
+    }
+
+    fn __hash__(&self) -> u64 {
+        order::equal_hash(self.0)
+    }
+}
+
+pub fn to_scm(obj: &Bound<'_, PyAny>) -> PyResult<Scm> {
+    if let Ok(x) = obj.extract::<PyScm>() {
+        return Ok(x.0)
+    }
+    // before int, which bool is a subclass of
+    if let Ok(b) = obj.downcast::<PyBool>() {
+        return Ok(Scm::from_bool(b.is_true()))
+    }
+    if obj.is_instance_of::<PyInt>() {
+        return match obj.extract::<i64>() {
+            Ok(i) => Ok(num::integer(i)),
+            Err(_) => num::parse(&obj.str()?.to_cow()?, 10)
+                .ok_or_else(|| PyValueError::new_err("integer out of range")),
+        }
+    }
+    if let Ok(f) = obj.downcast::<PyFloat>() {
+        return Ok(Scm::from_f64(f.value()))
+    }
+    if let Ok(s) = obj.downcast::<PyString>() {
+        return Ok(Scm::string(&s.to_cow()?))
+    }
+    if let Ok(list) = obj.downcast::<PyList>() {
+        let items = list.iter().map(|x| to_scm(&x)).collect::<PyResult<Vec<_>>>()?;
+        return Ok(items.into_iter().rev().fold(Scm::NIL, |list, x| crate::cons(x, list)))
+    }
+    if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        let items = tuple.iter().map(|x| to_scm(&x)).collect::<PyResult<Vec<_>>>()?;
+        return Ok(Scm::vector(items))
+    }
+    Err(PyTypeError::new_err(format!("cannot convert {} to a Scheme value", obj.get_type().name()?)))
+}
+
+pub fn to_python(py: Python<'_>, x: Scm) -> PyResult<PyObject> {
+    if let Some(items) = proper_list(x) {
+        let items = items.into_iter().map(|x| to_python(py, x)).collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new_bound(py, items).into_py(py))
+    }
+    Ok(match x.classify() {
+        ScmView::Boolean(b) => b.into_py(py),
+        ScmView::Integer(i) => i.into_py(py),
+        // Python parses the digits into an int of any size
+        ScmView::Bignum(_) => py.get_type_bound::<PyInt>().call1((x.to_string(),))?.unbind(),
+        ScmView::Flonum(f) => f.into_py(py),
+        ScmView::String(s) => s.into_py(py),
+        ScmView::Vector(items) => {
+            let items = items.iter().map(|&x| to_python(py, x)).collect::<PyResult<Vec<_>>>()?;
+            PyTuple::new_bound(py, items).into_py(py)
+        }
+        _ => PyScm(x).into_py(py),
+    })
+}
+
+fn proper_list(mut x: Scm) -> Option<Vec<Scm>> {
+    let mut items = vec![];
+    while let Some(&(car, cdr)) = x.as_pair() {
+        items.push(car);
+        x = cdr;
+    }
+    if x.is_nil() { Some(items) } else { None }
+}
+
+// The first datum in `source`.
+#[pyfunction]
+fn read(source: &str) -> PyResult<PyScm> {
+    reader::read_str(source).map(PyScm).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+// What `write` prints for a Python value.
+#[pyfunction]
+fn write(obj: &Bound<'_, PyAny>) -> PyResult<String> {
+    Ok(to_scm(obj)?.to_string())
+}
+
+#[pyfunction]
+fn cons(car: &Bound<'_, PyAny>, cdr: &Bound<'_, PyAny>) -> PyResult<PyScm> {
+    Ok(PyScm(crate::cons(to_scm(car)?, to_scm(cdr)?)))
+}
+
+#[pyfunction]
+fn symbol(name: &str) -> PyScm {
+    PyScm(Scm::symbol(name))
+}
+
+#[pyfunction]
+#[pyo3(name = "from_python")]
+fn from_python_py(obj: &Bound<'_, PyAny>) -> PyResult<PyScm> {
+    to_scm(obj).map(PyScm)
+}
+
+#[pyfunction]
+#[pyo3(name = "to_python")]
+fn to_python_py(py: Python<'_>, x: PyScm) -> PyResult<PyObject> {
+    to_python(py, x.0)
+}
+
+#[pymodule]
+fn scm_repr(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyScm>()?;
+    m.add_function(wrap_pyfunction!(read, m)?)?;
+    m.add_function(wrap_pyfunction!(write, m)?)?;
+    m.add_function(wrap_pyfunction!(cons, m)?)?;
+    m.add_function(wrap_pyfunction!(symbol, m)?)?;
+    m.add_function(wrap_pyfunction!(from_python_py, m)?)?;
+    m.add_function(wrap_pyfunction!(to_python_py, m)?)?;
+    Ok(())
+}
+
+#[test]
+fn lists_round_trip_through_python() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let x = reader::read_str("(1 (2 3) #(4 5) \"six\" 100000000000000000000 foo)").unwrap();
+        let obj = to_python(py, x).unwrap();
+        let obj = obj.bind(py);
+        assert_eq!(obj.len().unwrap(), 6);
+        assert_eq!(obj.get_item(2).unwrap().downcast::<PyTuple>().unwrap().len(), 2);
+        assert_eq!(obj.get_item(4).unwrap().str().unwrap().to_cow().unwrap(), "100000000000000000000");
+        assert!(obj.get_item(5).unwrap().extract::<PyScm>().is_ok());
+        assert!(order::equal(to_scm(obj).unwrap(), x));
+    });
+}
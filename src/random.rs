@@ -0,0 +1,130 @@
+//! Random state objects (after SRFI 27): pseudo-random number generators as
+//! values, so that a program can pass its own around instead of sharing
+//! global state, and get the same numbers again from the same seed.
+//!
+//! The generator is xoshiro256**, seeded through SplitMix64. It is fast and
+//! statistically good, but not suitable for cryptography.
+
+use crate::bigint::BigInt;
+use crate::heap::{self, HeapObject, Kind};
+use crate::lock::Lock;
+use crate::{num, NumError, Scm, ScmKind, TypeError};
+
+#[derive(Copy, Clone)]
+struct Xoshiro256([u64; 4]);
+
+impl Xoshiro256 {
+    fn seed(mut seed: u64) -> Self {
+        let mut splitmix = || {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Xoshiro256([splitmix(), splitmix(), splitmix(), splitmix()])
+    }
+
+    fn next(&mut self) -> u64 {
+        let s = &mut self.0;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    // Uniform in 0..n, by rejecting the incomplete last interval.
+    fn below(&mut self, n: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next();
+            if x < limit {
+                return x % n
+            }
+        }
+    }
+}
+
+pub(crate) struct RandomState(Lock<Xoshiro256>);
+
+impl HeapObject for RandomState {
+    const KIND: Kind = Kind::RandomState;
+}
+
+impl Scm {
+    pub fn is_random_state(&self) -> bool {
+        self.as_object::<RandomState>().is_some()
+    }
+}
+
+fn expect_random_state(s: Scm) -> Result<&'static RandomState, TypeError> {
+    s.as_object::<RandomState>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::RandomState, s))
+}
+
+pub fn make_random_state(seed: u64) -> Scm {
+    Scm::from_object(heap::leak(RandomState(Lock::new(Xoshiro256::seed(seed)))))
+}
+
+// A new state that produces the same numbers as `s` from now on.
+pub fn random_state_copy(s: Scm) -> Result<Scm, TypeError> {
+    let rng = *expect_random_state(s)?.0.lock();
+    Ok(Scm::from_object(heap::leak(RandomState(Lock::new(rng)))))
+}
+
+// A uniformly distributed exact integer in `0..n`, for any positive exact
+// integer `n`.
+pub fn random_integer(s: Scm, n: Scm) -> Result<Scm, NumError> {
+    let mut rng = expect_random_state(s)?.0.lock();
+    if let Some(n) = n.as_integer() {
+        return if n > 0 { Ok(num::integer(rng.below(n as u64) as i64)) } else { Err(NumError::OutOfRange) }
+    }
+    let n = n.as_bignum().ok_or_else(|| TypeError::new(ScmKind::Integer, n))?;
+    if n.is_negative() {
+        return Err(NumError::OutOfRange)
+    }
+    // 64 more bits than `n` has make the bias of the remainder negligible
+    let mut x = BigInt::zero();
+    for _ in 0..n.bit_length() / 64 + 2 {
+        x = &x.shl(64) + &BigInt::from_i128(rng.next() as i128);
+    }
+    Ok(num::make_integer(x.div_rem(n).1))
+}
+
+// A uniformly distributed flonum strictly between 0 and 1.
+pub fn random_real(s: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::from_f64(unit_interval(expect_random_state(s)?.0.lock().next())))
+}
+
+// The midpoint of one of 2^52 equal steps of the unit interval. With 53 bits,
+// the largest midpoint would round up to 1.0.
+fn unit_interval(bits: u64) -> f64 {
+    ((bits >> 12) as f64 + 0.5) / (1u64 << 52) as f64
+}
+
+#[test]
+fn random_states_are_reproducible() {
+    let a = make_random_state(42);
+    let b = make_random_state(42);
+    let draws = |s| (0..5).map(|_| random_integer(s, Scm::from_int(6)).unwrap()).collect::<Vec<_>>();
+    let first = draws(a);
+    assert_eq!(first, draws(b));
+    assert!(first.iter().all(|x| (0..6).contains(&x.as_integer().unwrap())));
+    assert_ne!(draws(a), draws(make_random_state(43)));
+
+    let c = random_state_copy(a).unwrap();
+    assert_eq!(random_real(a).unwrap().as_f64(), random_real(c).unwrap().as_f64());
+    assert!((0..100).all(|_| matches!(random_real(a).unwrap().as_f64(), Some(x) if 0.0 < x && x < 1.0)));
+    assert!(0.0 < unit_interval(0) && unit_interval(u64::MAX) < 1.0);
+
+    let big = crate::reader::read_str("100000000000000000000000000000").unwrap();
+    let x = random_integer(a, big).unwrap();
+    assert!(num::lt(x, big).unwrap() && !num::lt(x, Scm::from_int(0)).unwrap());
+    assert_eq!(random_integer(a, Scm::from_int(0)), Err(NumError::OutOfRange));
+    assert_eq!(random_integer(a, Scm::from_f64(2.0)).unwrap_err().to_string(), "expected integer, got flonum");
+    assert_eq!(a.to_string(), "#<random-state>");
+}
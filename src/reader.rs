@@ -0,0 +1,573 @@
+//! The reader: parses the external representation of data from a port, the
+//! inverse of the printer.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use crate::port::{open_input_string, peek_char, position, read_char};
+use crate::syntax::{make_syntax, SourceLocation};
+use crate::heap::{Header, FLAG_SPAN};
+use crate::{boxes, cons, num, ReadError, Scm};
+
+pub type DispatchMacro = Arc<dyn Fn(Scm) -> Result<Scm, ReadError> + Send + Sync>;
+
+static DISPATCH_MACROS: OnceLock<RwLock<HashMap<char, DispatchMacro>>> = OnceLock::new();
+
+// Characters after `#` that standard syntax already uses
+const RESERVED_DISPATCH: &str = "(&\\|;tfeixobd";
+
+fn dispatch_macros() -> &'static RwLock<HashMap<char, DispatchMacro>> {
+    DISPATCH_MACROS.get_or_init(Default::default)
+}
+
+// Makes the reader call `handler` whenever it sees `#c`. The handler gets the
+// port positioned right after the `c` and returns the datum it read. Panics
+// if `c` is already used by the standard syntax.
+pub fn set_dispatch_macro(c: char, handler: impl Fn(Scm) -> Result<Scm, ReadError> + Send + Sync + 'static) {
+    assert!(!RESERVED_DISPATCH.contains(c.to_ascii_lowercase()), "#{} is reserved", c);
+    dispatch_macros().write().unwrap().insert(c, Arc::new(handler));
+}
+
+fn dispatch_macro(c: char) -> Option<DispatchMacro> {
+    dispatch_macros().read().unwrap().get(&c).cloned()
+}
+
+fn peek(p: Scm) -> Result<Option<char>, ReadError> {
+    Ok(peek_char(p)?.as_char())
+}
+
+fn next(p: Scm) -> Result<Option<char>, ReadError> {
+    Ok(read_char(p)?.as_char())
+}
+
+fn expect_next(p: Scm) -> Result<char, ReadError> {
+    next(p)?.ok_or(ReadError::UnexpectedEof)
+}
+
+fn syntax<T>(msg: impl Into<String>) -> Result<T, ReadError> {
+    Err(ReadError::Syntax(msg.into()))
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "()[]\";|".contains(c)
+}
+
+// Skips whitespace and comments, and returns the next character without
+// consuming it.
+fn skip_atmosphere(p: Scm) -> Result<Option<char>, ReadError> {
+    loop {
+        match peek(p)? {
+            Some(c) if c.is_whitespace() => {
+                next(p)?;
+            }
+            Some(';') => {
+                while !matches!(next(p)?, Some('\n') | None) {}
+            }
+            c => return Ok(c),
+        }
+    }
+}
+
+// Deep enough for hand-written programs, shallow enough for the reader's
+// recursion to fit the 2 MiB stack of a spawned thread, even in debug builds.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    syntax_objects: bool,
+    record_spans: bool,
+    frozen: bool,
+    file: Option<Arc<str>>,
+    max_depth: Option<usize>,
+}
+
+impl ReadOptions {
+    // Wrap every datum in a syntax object with its location in `file`.
+    pub fn syntax_objects(mut self, file: Option<Arc<str>>) -> Self {
+        self.syntax_objects = true;
+        self.file = file;
+        self
+    }
+
+    // Leave the data alone, but remember where each list came from, for
+    // `span_of`.
+    pub fn record_spans(mut self, file: Option<Arc<str>>) -> Self {
+        self.record_spans = true;
+        self.file = file;
+        self
+    }
+
+    // Freeze every datum, as is right for the literal constants of a program.
+    pub fn frozen(mut self) -> Self {
+        self.frozen = true;
+        self
+    }
+
+    // Fail with `ReadError::TooDeep` on data nested deeper than `depth`,
+    // instead of overflowing the stack. `(a b)` is nested two deep, and so
+    // is `'a`. The default is 256.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+}
+
+// Where a datum starts and ends in the source, as (line, column) with both
+// counting from 1. The end is just past the last character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub file: Option<Arc<str>>,
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+}
+
+// Keyed by the header address of the first pair of a list. Pairs are never
+// moved, but freeing one lets its address be reused, so pairs with a span
+// set `FLAG_SPAN` and drop their entry with them.
+static SPANS: OnceLock<RwLock<HashMap<usize, Span>>> = OnceLock::new();
+
+fn spans() -> &'static RwLock<HashMap<usize, Span>> {
+    SPANS.get_or_init(Default::default)
+}
+
+// The span of a list read with `record_spans` on.
+pub fn span_of(x: Scm) -> Option<Span> {
+    x.as_pair()?;
+    spans().read().unwrap().get(&span_key(x.header()?)).cloned()
+}
+
+fn span_key(header: &Header) -> usize {
+    header as *const Header as usize
+}
+
+// Called when a pair with a span is dropped.
+pub(crate) fn forget_span(header: &Header) {
+    // objects may be dropped while another thread panicked holding the lock
+    if let Ok(mut spans) = spans().write() {
+        spans.remove(&span_key(header));
+    }
+}
+
+// Drops all recorded spans, e.g. after compiling a file.
+pub fn clear_spans() {
+    spans().write().unwrap().clear();
+}
+
+pub struct Reader {
+    port: Scm,
+    options: ReadOptions,
+    // how many data are being read, each inside the one before
+    depth: usize,
+}
+
+impl Reader {
+    pub fn new(port: Scm) -> Self {
+        Reader::with_options(port, ReadOptions::default())
+    }
+
+    pub fn with_options(port: Scm, options: ReadOptions) -> Self {
+        Reader { port, options, depth: 0 }
+    }
+
+    // The next datum, or the eof object if there is none.
+    pub fn read(&mut self) -> Result<Scm, ReadError> {
+        Ok(self.read_datum()?.unwrap_or(Scm::EOF))
+    }
+
+    fn read_required(&mut self) -> Result<Scm, ReadError> {
+        self.read_datum()?.ok_or(ReadError::UnexpectedEof)
+    }
+
+    fn read_datum(&mut self) -> Result<Option<Scm>, ReadError> {
+        if self.depth >= self.options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH) {
+            return Err(ReadError::TooDeep)
+        }
+        self.depth += 1;
+        let result = self.read_nested();
+        self.depth -= 1;
+        result
+    }
+
+    fn read_nested(&mut self) -> Result<Option<Scm>, ReadError> {
+        loop {
+            let c = match skip_atmosphere(self.port)? {
+                None => return Ok(None),
+                Some(c) => c,
+            };
+            let (line, column) = position(self.port)?;
+            if let Some(x) = self.read_unwrapped(c)? {
+                if self.options.record_spans && x.as_pair().is_some() {
+                    let span = Span { file: self.options.file.clone(), start: (line, column), end: position(self.port)? };
+                    let header = x.header().unwrap();
+                    header.set_flag(FLAG_SPAN, true);
+                    spans().write().unwrap().insert(span_key(header), span);
+                }
+                let x = self.wrap(x, line, column);
+                return Ok(Some(if self.options.frozen { x.freeze() } else { x }))
+            }
+        }
+    }
+
+    fn wrap(&self, x: Scm, line: u32, column: u32) -> Scm {
+        if self.options.syntax_objects {
+            make_syntax(x, SourceLocation { file: self.options.file.clone(), line, column })
+        } else {
+            x
+        }
+    }
+
+    // None if all that was read was a datum comment
+    fn read_unwrapped(&mut self, c: char) -> Result<Option<Scm>, ReadError> {
+        let p = self.port;
+        match c {
+            '(' | '[' => {
+                next(p)?;
+                self.read_list(if c == '(' { ')' } else { ']' }).map(Some)
+            }
+            ')' | ']' => syntax(format!("unexpected '{}'", c)),
+            '"' => {
+                next(p)?;
+                Ok(Some(Scm::string(&read_escaped(p, '"')?)))
+            }
+            '|' => {
+                next(p)?;
+                Ok(Some(Scm::symbol(&read_escaped(p, '|')?)))
+            }
+            '\'' | '`' | ',' => {
+                next(p)?;
+                let name = match c {
+                    '\'' => "quote",
+                    '`' => "quasiquote",
+                    _ if peek(p)? == Some('@') => {
+                        next(p)?;
+                        "unquote-splicing"
+                    }
+                    _ => "unquote",
+                };
+                Ok(Some(cons(Scm::symbol(name), cons(self.read_required()?, Scm::NIL))))
+            }
+            '#' => {
+                next(p)?;
+                self.read_hash()
+            }
+            _ => {
+                let token = read_token(p)?;
+                Ok(Some(parse_atom(&token)?))
+            }
+        }
+    }
+
+    fn read_list(&mut self, close: char) -> Result<Scm, ReadError> {
+        let p = self.port;
+        let mut items = vec![];
+        let mut tail = Scm::NIL;
+        loop {
+            match skip_atmosphere(p)? {
+                None => return Err(ReadError::UnexpectedEof),
+                Some(c) if c == close => {
+                    next(p)?;
+                    break
+                }
+                Some(')') | Some(']') => return syntax("mismatched closing bracket"),
+                Some('.') => {
+                    let (line, column) = position(p)?;
+                    let token = read_token(p)?;
+                    if token != "." {
+                        items.push(self.wrap(parse_atom(&token)?, line, column));
+                        continue
+                    }
+                    if items.is_empty() {
+                        return syntax("nothing before '.' in list")
+                    }
+                    tail = self.read_required()?;
+                    if skip_atmosphere(p)? != Some(close) {
+                        return syntax("expected end of list after dotted tail")
+                    }
+                    next(p)?;
+                    break
+                }
+                Some(_) => match self.read_datum()? {
+                    Some(x) => items.push(x),
+                    None => return Err(ReadError::UnexpectedEof),
+                },
+            }
+        }
+        Ok(items.into_iter().rev().fold(tail, |acc, x| cons(x, acc)))
+    }
+
+    fn read_hash(&mut self) -> Result<Option<Scm>, ReadError> {
+        let p = self.port;
+        match expect_next(p)? {
+            '(' => {
+                let list = self.read_list(')')?;
+                let mut items = vec![];
+                let mut rest = list;
+                while let Some(&(x, d)) = rest.as_pair() {
+                    items.push(x);
+                    rest = d;
+                }
+                if !rest.is_nil() {
+                    return syntax("unexpected '.' in vector")
+                }
+                Ok(Some(Scm::vector(items)))
+            }
+            '&' => Ok(Some(boxes::make_box(self.read_required()?))),
+            '\\' => read_char_literal(p).map(Some),
+            '|' => {
+                skip_block_comment(p)?;
+                Ok(None)
+            }
+            ';' => {
+                self.read_required()?;
+                Ok(None)
+            }
+            c if !RESERVED_DISPATCH.contains(c.to_ascii_lowercase()) => match dispatch_macro(c) {
+                Some(handler) => handler(p).map(Some),
+                None => syntax(format!("unknown syntax #{}", c)),
+            },
+            c => {
+                let token = format!("#{}{}", c, read_token(p)?);
+                match token.as_str() {
+                    "#t" | "#true" => Ok(Some(Scm::TRUE)),
+                    "#f" | "#false" => Ok(Some(Scm::FALSE)),
+                    _ => match num::parse(&token, 10) {
+                        Some(x) => Ok(Some(x)),
+                        None => syntax(format!("unknown syntax {}", token)),
+                    },
+                }
+            }
+        }
+    }
+}
+
+// The next datum, or the eof object if there is none.
+pub fn read(p: Scm) -> Result<Scm, ReadError> {
+    Reader::new(p).read()
+}
+
+// Reads the first datum of a string.
+pub fn read_str(s: &str) -> Result<Scm, ReadError> {
+    read(open_input_string(s))
+}
+
+// All data in a string.
+pub fn read_all(s: &str) -> Result<Vec<Scm>, ReadError> {
+    let mut reader = Reader::new(open_input_string(s));
+    let mut data = vec![];
+    while let Some(x) = reader.read_datum()? {
+        data.push(x);
+    }
+    Ok(data)
+}
+
+fn read_token(p: Scm) -> Result<String, ReadError> {
+    let mut token = String::new();
+    while let Some(c) = peek(p)? {
+        if is_delimiter(c) {
+            break
+        }
+        token.push(c);
+        next(p)?;
+    }
+    Ok(token)
+}
+
+fn parse_atom(token: &str) -> Result<Scm, ReadError> {
+    if token == "." {
+        return syntax("unexpected '.'")
+    }
+    Ok(num::parse(token, 10).unwrap_or_else(|| Scm::symbol(token)))
+}
+
+// `#| ... |#`, which may nest
+fn skip_block_comment(p: Scm) -> Result<(), ReadError> {
+    let mut depth = 1;
+    let mut prev = '\0';
+    while depth > 0 {
+        let c = expect_next(p)?;
+        match (prev, c) {
+            ('|', '#') => {
+                depth -= 1;
+                prev = '\0';
+            }
+            ('#', '|') => {
+                depth += 1;
+                prev = '\0';
+            }
+            _ => prev = c,
+        }
+    }
+    Ok(())
+}
+
+const CHAR_NAMES: [(&str, char); 9] = [
+    ("alarm", '\x07'),
+    ("backspace", '\x08'),
+    ("delete", '\x7f'),
+    ("escape", '\x1b'),
+    ("newline", '\n'),
+    ("null", '\0'),
+    ("return", '\r'),
+    ("space", ' '),
+    ("tab", '\t'),
+];
+
+fn read_char_literal(p: Scm) -> Result<Scm, ReadError> {
+    // the first character is taken even if it is a delimiter, as in #\(
+    let first = expect_next(p)?;
+    let name = format!("{}{}", first, read_token(p)?);
+    if name.chars().count() == 1 {
+        return Ok(Scm::from_char(first))
+    }
+    if let Some(&(_, c)) = CHAR_NAMES.iter().find(|(n, _)| *n == name) {
+        return Ok(Scm::from_char(c))
+    }
+    if let Some(hex) = name.strip_prefix('x') {
+        if let Some(c) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+            return Ok(Scm::from_char(c))
+        }
+    }
+    syntax(format!("unknown character name #\\{}", name))
+}
+
+fn read_escaped(p: Scm, quote: char) -> Result<String, ReadError> {
+    let mut s = String::new();
+    loop {
+        match expect_next(p)? {
+            c if c == quote => return Ok(s),
+            '\\' => match expect_next(p)? {
+                'a' => s.push('\x07'),
+                'b' => s.push('\x08'),
+                't' => s.push('\t'),
+                'n' => s.push('\n'),
+                'r' => s.push('\r'),
+                'x' => {
+                    let mut hex = String::new();
+                    loop {
+                        match expect_next(p)? {
+                            ';' => break,
+                            c => hex.push(c),
+                        }
+                    }
+                    match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        Some(c) => s.push(c),
+                        None => return syntax(format!("invalid escape \\x{};", hex)),
+                    }
+                }
+                c if c == ' ' || c == '\t' || c == '\n' => {
+                    // line continuation: skip to the first non-blank of the next line
+                    let mut seen_newline = c == '\n';
+                    while let Some(c) = peek(p)? {
+                        if c == '\n' && !seen_newline {
+                            seen_newline = true;
+                        } else if c != ' ' && c != '\t' {
+                            break
+                        }
+                        next(p)?;
+                    }
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+#[test]
+fn read_what_the_printer_writes() {
+    let text = r#"(define (f x) `(,x ,@(g "a\x41;\n" #\space #\x3bb)))
+                  #(1 2.5 -3/4 #t #false) #&sym |two words| (a . b) [c d]
+                  ; comment
+                  #| nested #| block |# comment |# #;(ignored datum) end"#;
+    let data = read_all(text).unwrap();
+    let printed: Vec<String> = data.iter().map(|x| x.to_string()).collect();
+    assert_eq!(
+        printed,
+        [
+            "(define (f x) (quasiquote ((unquote x) (unquote-splicing (g \"aA\\n\" #\\space #\\λ)))))",
+            "#(1 2.5 -3/4 #t #f)",
+            "#&sym",
+            "|two words|",
+            "(a . b)",
+            "(c d)",
+            "end",
+        ]
+    );
+    for s in &printed {
+        assert_eq!(&read_str(s).unwrap().to_string(), s);
+    }
+
+    assert!(matches!(read_str("(1 2"), Err(ReadError::UnexpectedEof)));
+    assert!(matches!(read_str(")"), Err(ReadError::Syntax(_))));
+    assert!(matches!(read_str("(1 . 2 3)"), Err(ReadError::Syntax(_))));
+    assert_eq!(read_str("#(1 . 2)").unwrap_err().to_string(), "unexpected '.' in vector");
+    assert!(matches!(read_str("#e1.5e-9223372036854775808"), Err(ReadError::Syntax(_))));
+    assert!(matches!(read_str("#e1e9223372036854775807"), Err(ReadError::Syntax(_))));
+    assert_eq!(read_str("  ").unwrap(), Scm::EOF);
+}
+
+#[test]
+fn custom_syntax_round_trips() {
+    use crate::foreign::{make_foreign, set_print_hook};
+
+    #[derive(Debug, PartialEq)]
+    struct Point(i64, i64);
+
+    set_print_hook("point", |x, f| {
+        let p = x.downcast_ref::<Point>().unwrap();
+        write!(f, "#p({} {})", p.0, p.1)
+    });
+    set_dispatch_macro('p', |port| {
+        let coords = read(port)?;
+        match (crate::car(coords), crate::cdr(coords).and_then(crate::car)) {
+            (Some(x), Some(y)) => Ok(make_foreign("point", Point(x.as_integer().unwrap(), y.as_integer().unwrap()))),
+            _ => syntax("expected #p(x y)"),
+        }
+    });
+
+    let p = make_foreign("point", Point(3, -4));
+    let printed = Scm::vector(vec![p]).to_string();
+    assert_eq!(printed, "#(#p(3 -4))");
+    let back = read_str(&printed).unwrap();
+    assert_eq!(back.as_vector().unwrap()[0].downcast_ref::<Point>(), Some(&Point(3, -4)));
+    assert!(matches!(read_str("#q"), Err(ReadError::Syntax(_))));
+}
+
+#[test]
+fn spans_are_recorded_on_the_side() {
+    let options = ReadOptions::default().record_spans(Some("spans.scm".into()));
+    let mut reader = Reader::with_options(open_input_string("(define x\n  '(1 2))"), options.clone());
+    let form = reader.read().unwrap();
+    assert_eq!(form.to_string(), "(define x (quote (1 2)))");
+
+    let span = span_of(form).unwrap();
+    assert_eq!((span.file.as_deref(), span.start, span.end), (Some("spans.scm"), (1, 1), (2, 10)));
+    let quoted = crate::car(crate::cdr(crate::cdr(form).unwrap()).unwrap()).unwrap();
+    assert_eq!(span_of(quoted).map(|s| (s.start, s.end)), Some(((2, 3), (2, 9))));
+    let inner = crate::car(crate::cdr(quoted).unwrap()).unwrap();
+    assert_eq!(span_of(inner).map(|s| (s.start, s.end)), Some(((2, 4), (2, 9))));
+
+    assert_eq!(span_of(read_str("(not recorded)").unwrap()), None);
+    assert_eq!(span_of(Scm::from_int(1)), None);
+
+    let key = unsafe {
+        crate::heap::scoped(|_| {
+            let temporary = Reader::with_options(open_input_string("(temporary)"), options.clone()).read().unwrap();
+            assert!(span_of(temporary).is_some());
+            span_key(temporary.header().unwrap())
+        })
+    };
+    assert!(!spans().read().unwrap().contains_key(&key));
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    assert!(matches!(read_str(&"(".repeat(100_000)), Err(ReadError::TooDeep)));
+    assert_eq!(read_str(&"'#(#&".repeat(100_000)).unwrap_err().to_string(), "data nested too deeply");
+    let deep = format!("{}{}", "(".repeat(DEFAULT_MAX_DEPTH), ")".repeat(DEFAULT_MAX_DEPTH));
+    assert_eq!(read_str(&deep).unwrap().to_string(), deep);
+
+    let options = ReadOptions::default().max_depth(2);
+    let read = |text| Reader::with_options(open_input_string(text), options.clone()).read();
+    assert_eq!(read("(a ())").unwrap().to_string(), "(a ())");
+    assert!(matches!(read("(a (b))"), Err(ReadError::TooDeep)));
+}
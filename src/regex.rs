@@ -0,0 +1,106 @@
+//! Compiled regular expressions as values, with the `regex` feature, using
+//! the syntax of the `regex` crate.
+//!
+//! Positions are character indices, like everywhere else strings are
+//! indexed. A successful match returns its match data: a vector with an
+//! entry for the whole match and then one for each group, in order. An entry
+//! is a list of the matched substring, its start and its end, or #f for a
+//! group that didn't take part in the match. A failed match returns #f.
+
+use ::regex::{Captures, Regex};
+use crate::heap::{self, HeapObject, Kind};
+use crate::strings::substring;
+use crate::{cons, Scm, ScmKind, TypeError};
+
+pub(crate) struct ScmRegex {
+    search: Regex,
+    // the same pattern, anchored at both ends
+    whole: Regex,
+}
+
+impl HeapObject for ScmRegex {
+    const KIND: Kind = Kind::Regex;
+}
+
+impl Scm {
+    pub fn is_regex(&self) -> bool {
+        self.as_object::<ScmRegex>().is_some()
+    }
+}
+
+fn expect_regex(re: Scm) -> Result<&'static ScmRegex, TypeError> {
+    re.as_object::<ScmRegex>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Regex, re))
+}
+
+pub fn make_regex(pattern: &str) -> Result<Scm, ::regex::Error> {
+    let search = Regex::new(pattern)?;
+    let whole = Regex::new(&format!("^(?:{})$", pattern))?;
+    Ok(Scm::from_object(heap::leak(ScmRegex { search, whole })))
+}
+
+pub fn regex_pattern(re: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::string(expect_regex(re)?.search.as_str()))
+}
+
+fn char_index(text: &str, byte: usize) -> usize {
+    text[..byte].chars().count()
+}
+
+fn match_data(s: Scm, text: &str, captures: &Captures) -> Result<Scm, TypeError> {
+    let groups = captures.iter().map(|group| match group {
+        Some(m) => {
+            let (start, end) = (char_index(text, m.start()), char_index(text, m.end()));
+            let (first, last) = (Scm::from_int(start as i64), Scm::from_int(end as i64));
+            Ok(cons(substring(s, start, end)?, cons(first, cons(last, Scm::NIL))))
+        }
+        None => Ok(Scm::FALSE),
+    });
+    Ok(Scm::vector(groups.collect::<Result<_, TypeError>>()?))
+}
+
+// Match data if all of `s` matches, or #f.
+pub fn regex_match(re: Scm, s: Scm) -> Result<Scm, TypeError> {
+    let (re, text) = (expect_regex(re)?, s.expect_str()?);
+    match re.whole.captures(text) {
+        Some(captures) => match_data(s, text, &captures),
+        None => Ok(Scm::FALSE),
+    }
+}
+
+// Match data for the first match that starts at character `start` or later,
+// or #f.
+//
+// Panics if `start` is past the end of `s`.
+pub fn regex_search(re: Scm, s: Scm, start: usize) -> Result<Scm, TypeError> {
+    let (re, text) = (expect_regex(re)?, s.expect_str()?);
+    let offset = text.char_indices().map(|(pos, _)| pos).chain(Some(text.len())).nth(start);
+    let offset = offset.unwrap_or_else(|| panic!("start {} out of range for string of length {}", start, text.chars().count()));
+    match re.search.captures_at(text, offset) {
+        Some(captures) => match_data(s, text, &captures),
+        None => Ok(Scm::FALSE),
+    }
+}
+
+// A new string with every match replaced. `$1` or `${name}` in the
+// replacement stands for what a group matched.
+pub fn regex_replace(re: Scm, s: Scm, replacement: Scm) -> Result<Scm, TypeError> {
+    let (re, text) = (expect_regex(re)?, s.expect_str()?);
+    Ok(Scm::string(&re.search.replace_all(text, replacement.expect_str()?)))
+}
+
+#[test]
+fn regexes_return_match_data() {
+    let re = make_regex(r"(\d+)-(\d+)?").unwrap();
+    let s = Scm::string("pages 12-, née 3-45");
+    assert_eq!(regex_search(re, s, 0).unwrap().to_string(), r#"#(("12-" 6 9) ("12" 6 8) #f)"#);
+    assert_eq!(regex_search(re, s, 9).unwrap().to_string(), r#"#(("3-45" 15 19) ("3" 15 16) ("45" 17 19))"#);
+    assert_eq!(regex_search(re, s, 19).unwrap(), Scm::FALSE);
+
+    assert_eq!(regex_match(re, s).unwrap(), Scm::FALSE);
+    assert_eq!(regex_match(make_regex("a|ab").unwrap(), Scm::string("ab")).unwrap().to_string(), r#"#(("ab" 0 2))"#);
+    let swapped = regex_replace(re, s, Scm::string("$2:$1")).unwrap();
+    assert_eq!(swapped.as_str(), Some("pages :12, née 45:3"));
+    assert!(make_regex("(").is_err());
+    assert_eq!(regex_pattern(re).unwrap().as_str(), Some(r"(\d+)-(\d+)?"));
+    assert_eq!(regex_match(Scm::NIL, s).unwrap_err().to_string(), "expected regex, got empty list");
+}
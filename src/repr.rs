@@ -0,0 +1,84 @@
+//! The interface that every representation variant provides: the `Scm` of
+//! this crate as well as the experimental ones in the benchmarks, which run
+//! the same code on all of them through it.
+//!
+//! Constants (nil, booleans, eof) and characters carry no data worth
+//! allocating, so every variant must return the same value for the same
+//! constant, either as an immediate or as an interned singleton.
+//! `shares_singletons` checks that.
+
+pub trait Representation: Copy {
+    fn nil() -> Self;
+    fn eof() -> Self;
+    fn from_bool(b: bool) -> Self;
+    fn from_char(c: char) -> Self;
+    fn from_int(i: i64) -> Self;
+    fn cons(car: Self, cdr: Self) -> Self;
+    // identity, as in `eq?`
+    fn is_eq(self, other: Self) -> bool;
+    fn as_integer(self) -> Option<i64>;
+    fn car(self) -> Option<Self>;
+    fn cdr(self) -> Option<Self>;
+    fn is_null(self) -> bool;
+}
+
+impl Representation for crate::Scm {
+    fn nil() -> Self {
+        crate::Scm::NIL
+    }
+
+    fn eof() -> Self {
+        crate::Scm::EOF
+    }
+
+    fn from_bool(b: bool) -> Self {
+        crate::Scm::from_bool(b)
+    }
+
+    fn from_char(c: char) -> Self {
+        crate::Scm::from_char(c)
+    }
+
+    fn from_int(i: i64) -> Self {
+        crate::Scm::from_int(i)
+    }
+
+    fn cons(car: Self, cdr: Self) -> Self {
+        crate::cons(car, cdr)
+    }
+
+    fn is_eq(self, other: Self) -> bool {
+        self == other
+    }
+
+    fn as_integer(self) -> Option<i64> {
+        crate::Scm::as_integer(&self)
+    }
+
+    fn car(self) -> Option<Self> {
+        crate::car(self)
+    }
+
+    fn cdr(self) -> Option<Self> {
+        crate::cdr(self)
+    }
+
+    fn is_null(self) -> bool {
+        crate::is_null(self)
+    }
+}
+
+pub fn shares_singletons<R: Representation>() -> bool {
+    R::nil().is_eq(R::nil())
+        && R::eof().is_eq(R::eof())
+        && R::from_bool(true).is_eq(R::from_bool(true))
+        && R::from_bool(false).is_eq(R::from_bool(false))
+        && !R::from_bool(true).is_eq(R::from_bool(false))
+        && ['a', '\0', 'λ', '\u{10FFFF}'].iter().all(|&c| R::from_char(c).is_eq(R::from_char(c)))
+        && !R::from_char('a').is_eq(R::from_char('b'))
+}
+
+#[test]
+fn scm_shares_singletons() {
+    assert!(shares_singletons::<crate::Scm>());
+}
@@ -0,0 +1,68 @@
+//! Fast string concatenation.
+//!
+//! Appending to a flat string copies it, so building a string piece by piece
+//! takes quadratic time. `string_append` instead makes a rope node that just
+//! points at both halves. To the rest of the string API a rope is an ordinary
+//! string: the first call to `as_str` flattens it (in linear time, without
+//! recursion, however unbalanced the rope) and keeps the result.
+//!
+//! Building a rope and then reading it is fast; alternating between appending
+//! and reading is as slow as it always was.
+
+use std::cell::Cell;
+use crate::heap;
+use crate::{Scm, Str, TypeError};
+
+// Below this many bytes, copying is cheaper than a rope node and flattening it later.
+const FLAT_LIMIT: usize = 32;
+
+pub fn string_append(a: Scm, b: Scm) -> Result<Scm, TypeError> {
+    let len = string_byte_length(a)? + string_byte_length(b)?;
+    if len <= FLAT_LIMIT {
+        return Ok(Scm::string(&[a.expect_str()?, b.expect_str()?].concat()))
+    }
+    Ok(Scm::from_object(heap::leak(Str { flat: Default::default(), parts: Some((a, b)), len: Cell::new(len) })))
+}
+
+// Length in bytes of the (flattened) contents, without flattening.
+pub fn string_byte_length(s: Scm) -> Result<usize, TypeError> {
+    match s.as_object::<Str>() {
+        Some(obj) => Ok(obj.len.get()),
+        // short strings are immediates
+        None => s.expect_str().map(str::len),
+    }
+}
+
+pub(crate) fn flatten(s: &Str) -> String {
+    let mut out = String::with_capacity(s.len.get());
+    let (a, b) = s.parts.expect("string without contents");
+    let mut todo = vec![b, a];
+    while let Some(x) = todo.pop() {
+        match x.as_object::<Str>().map(|obj| (obj.flat().get(), obj.parts)) {
+            Some((Some(flat), _)) => out.push_str(flat),
+            Some((None, Some((a, b)))) => {
+                todo.push(b);
+                todo.push(a);
+            }
+            Some((None, None)) => unreachable!("string without contents"),
+            None => out.push_str(x.as_str().unwrap()),
+        }
+    }
+    out
+}
+
+#[test]
+fn ropes_read_like_strings() {
+    let mut s = Scm::string("");
+    for i in 0..10_000 {
+        s = string_append(s, Scm::string(&format!("{},", i % 10))).unwrap();
+    }
+    assert_eq!(string_byte_length(s).unwrap(), 20_000);
+    assert!(s.is_string());
+    assert_eq!(&s.as_str().unwrap()[..8], "0,1,2,3,");
+    assert_eq!(s.as_str().unwrap().len(), 20_000);
+
+    let short = string_append(Scm::string("a"), Scm::string("b")).unwrap();
+    assert_eq!(short.to_string(), "\"ab\"");
+    assert_eq!(string_append(short, Scm::NIL).unwrap_err().to_string(), "expected string, got empty list");
+}
@@ -0,0 +1,345 @@
+//! Snapshots: everything reachable from a set of roots, written to a file
+//! and loaded into the heap of a later run, so that an expensive setup (like
+//! loading and compiling a large program) can be checkpointed.
+//!
+//! A snapshot holds pairs, vectors, multiple values, boxes, strings, symbols
+//! and numbers besides the immediates. Sharing survives the round trip: an
+//! object that is reachable along several paths is written once and loaded as
+//! one object, and cycles through boxes are loaded as cycles. Symbols are
+//! interned again. Objects that wrap Rust state, like ports, can't be saved.
+//!
+//! The format is a magic number, then the objects, each after the objects it
+//! refers to (except for the contents of boxes, which are filled in at the
+//! end), and then the roots. References to objects are their position in the
+//! snapshot. Numbers are little-endian.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use crate::{num, Scm, ScmView, SnapshotError};
+
+const MAGIC: &[u8; 8] = b"SCMSNAP1";
+
+// how a reference starts
+const REF_OBJECT: u8 = 0;
+const REF_INTEGER: u8 = 1;
+const REF_CHAR: u8 = 2;
+const REF_NIL: u8 = 3;
+const REF_FALSE: u8 = 4;
+const REF_TRUE: u8 = 5;
+const REF_EOF: u8 = 6;
+const REF_SHORT_STRING: u8 = 7;
+
+// how an object starts
+const OBJ_PAIR: u8 = 0;
+const OBJ_VECTOR: u8 = 1;
+const OBJ_VALUES: u8 = 2;
+const OBJ_BOX: u8 = 3;
+const OBJ_STRING: u8 = 4;
+const OBJ_SYMBOL: u8 = 5;
+const OBJ_FLONUM: u8 = 6;
+// bignums and rationals, in decimal
+const OBJ_EXACT: u8 = 7;
+
+// The objects `x` refers to, or an error if it can't be saved.
+fn children(x: Scm) -> Result<Vec<Scm>, SnapshotError> {
+    match x.classify() {
+        ScmView::Pair(&(car, cdr)) => Ok(vec![car, cdr]),
+        ScmView::Vector(items) | ScmView::Values(items) => Ok(items.to_vec()),
+        ScmView::Box(x) => Ok(vec![x]),
+        ScmView::String(_) | ScmView::Symbol(_) | ScmView::Flonum(_) | ScmView::Bignum(_) | ScmView::Rational(..) => {
+            Ok(vec![])
+        }
+        _ => Err(SnapshotError::Unsupported(x)),
+    }
+}
+
+// The heap objects reachable from the roots, each after its children. A box
+// is placed before its contents instead, which is what allows cycles. Other
+// cycles, made with `lists::set_cdr` or `vectors::vector_set`, can't be
+// loaded, so they are unsupported.
+fn objects_in_order(roots: &[Scm]) -> Result<Vec<Scm>, SnapshotError> {
+    let mut objects = vec![];
+    let mut seen = HashSet::new();
+    // The objects whose children are being ordered, which are the ancestors
+    // of the current one, with the number of boxes on the path to each. An
+    // ancestor reached again without passing another box is a cycle.
+    let mut open = HashMap::new();
+    let mut todo: Vec<_> = roots.iter().rev().map(|&x| (x, false, 0)).collect();
+    while let Some((x, children_done, boxes)) = todo.pop() {
+        if children_done {
+            open.remove(&x);
+            objects.push(x);
+            continue
+        }
+        if open.get(&x) == Some(&boxes) {
+            return Err(SnapshotError::Unsupported(x))
+        }
+        if x.is_immediate() || !seen.insert(x) {
+            continue
+        }
+        let children = children(x)?;
+        let boxes = if x.is_box() {
+            objects.push(x);
+            boxes + 1
+        } else {
+            open.insert(x, boxes);
+            todo.push((x, true, boxes));
+            boxes
+        };
+        todo.extend(children.into_iter().rev().map(|child| (child, false, boxes)));
+    }
+    Ok(objects)
+}
+
+struct Writer<W> {
+    w: W,
+    index: HashMap<Scm, u32>,
+}
+
+impl<W: Write> Writer<W> {
+    fn u8(&mut self, n: u8) -> Result<(), SnapshotError> {
+        Ok(self.w.write_all(&[n])?)
+    }
+
+    fn u32(&mut self, n: usize) -> Result<(), SnapshotError> {
+        Ok(self.w.write_all(&(n as u32).to_le_bytes())?)
+    }
+
+    fn u64(&mut self, n: u64) -> Result<(), SnapshotError> {
+        Ok(self.w.write_all(&n.to_le_bytes())?)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        self.u32(bytes.len())?;
+        Ok(self.w.write_all(bytes)?)
+    }
+
+    fn reference(&mut self, x: Scm) -> Result<(), SnapshotError> {
+        if let Some(&i) = self.index.get(&x) {
+            self.u8(REF_OBJECT)?;
+            return self.u32(i as usize)
+        }
+        match x.classify() {
+            ScmView::Integer(i) => {
+                self.u8(REF_INTEGER)?;
+                self.u64(i as u64)
+            }
+            ScmView::Char(c) => {
+                self.u8(REF_CHAR)?;
+                self.u32(c as usize)
+            }
+            ScmView::Nil => self.u8(REF_NIL),
+            ScmView::Boolean(false) => self.u8(REF_FALSE),
+            ScmView::Boolean(true) => self.u8(REF_TRUE),
+            ScmView::Eof => self.u8(REF_EOF),
+            ScmView::String(s) => {
+                self.u8(REF_SHORT_STRING)?;
+                self.bytes(s.as_bytes())
+            }
+            _ => unreachable!("{} is neither immediate nor in the snapshot", x),
+        }
+    }
+
+    fn references(&mut self, items: &[Scm]) -> Result<(), SnapshotError> {
+        self.u32(items.len())?;
+        items.iter().try_for_each(|&x| self.reference(x))
+    }
+
+    fn object(&mut self, x: Scm) -> Result<(), SnapshotError> {
+        match x.classify() {
+            ScmView::Pair(&(car, cdr)) => {
+                self.u8(OBJ_PAIR)?;
+                self.reference(car)?;
+                self.reference(cdr)
+            }
+            ScmView::Vector(items) => {
+                self.u8(OBJ_VECTOR)?;
+                self.references(items)
+            }
+            ScmView::Values(items) => {
+                self.u8(OBJ_VALUES)?;
+                self.references(items)
+            }
+            ScmView::Box(x) => {
+                self.u8(OBJ_BOX)?;
+                self.reference(x)
+            }
+            ScmView::String(s) => {
+                self.u8(OBJ_STRING)?;
+                self.bytes(s.as_bytes())
+            }
+            ScmView::Symbol(name) => {
+                self.u8(OBJ_SYMBOL)?;
+                self.bytes(name.as_bytes())
+            }
+            ScmView::Flonum(f) => {
+                self.u8(OBJ_FLONUM)?;
+                self.u64(f.to_bits())
+            }
+            _ => {
+                self.u8(OBJ_EXACT)?;
+                self.bytes(num::to_string(x, 10).unwrap().as_bytes())
+            }
+        }
+    }
+}
+
+// Writes everything reachable from the roots.
+pub fn save_snapshot(roots: &[Scm], w: impl Write) -> Result<(), SnapshotError> {
+    let objects = objects_in_order(roots)?;
+    let index = objects.iter().enumerate().map(|(i, &x)| (x, i as u32)).collect();
+    let mut w = Writer { w, index };
+    w.w.write_all(MAGIC)?;
+    w.u32(objects.len())?;
+    for &x in &objects {
+        w.object(x)?;
+    }
+    w.references(roots)?;
+    Ok(w.w.flush()?)
+}
+
+// A reference as read, before the object it refers to exists.
+enum Ref {
+    Object(usize),
+    Value(Scm),
+}
+
+struct Reader<R> {
+    r: R,
+    objects: Vec<Scm>,
+}
+
+impl<R: Read> Reader<R> {
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        let mut buf = [0; 1];
+        self.r.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, SnapshotError> {
+        let mut buf = [0; 4];
+        self.r.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf) as usize)
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let mut buf = [0; 8];
+        self.r.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn string(&mut self) -> Result<String, SnapshotError> {
+        let mut buf = vec![];
+        let len = self.u32()?;
+        (&mut self.r).take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() < len {
+            return Err(SnapshotError::Corrupt)
+        }
+        String::from_utf8(buf).map_err(|_| SnapshotError::Corrupt)
+    }
+
+    fn raw_reference(&mut self) -> Result<Ref, SnapshotError> {
+        Ok(Ref::Value(match self.u8()? {
+            REF_OBJECT => return Ok(Ref::Object(self.u32()?)),
+            REF_INTEGER => num::integer(self.u64()? as i64),
+            REF_CHAR => Scm::from_char(std::char::from_u32(self.u32()? as u32).ok_or(SnapshotError::Corrupt)?),
+            REF_NIL => Scm::NIL,
+            REF_FALSE => Scm::FALSE,
+            REF_TRUE => Scm::TRUE,
+            REF_EOF => Scm::EOF,
+            REF_SHORT_STRING => Scm::string(&self.string()?),
+            _ => return Err(SnapshotError::Corrupt),
+        }))
+    }
+
+    fn resolve(&self, r: Ref) -> Result<Scm, SnapshotError> {
+        match r {
+            Ref::Object(i) => self.objects.get(i).copied().ok_or(SnapshotError::Corrupt),
+            Ref::Value(x) => Ok(x),
+        }
+    }
+
+    fn reference(&mut self) -> Result<Scm, SnapshotError> {
+        let r = self.raw_reference()?;
+        self.resolve(r)
+    }
+
+    fn references(&mut self) -> Result<Vec<Scm>, SnapshotError> {
+        (0..self.u32()?).map(|_| self.reference()).collect()
+    }
+}
+
+// The roots of a snapshot, with everything reachable from them allocated
+// anew.
+pub fn load_snapshot(r: impl Read) -> Result<Vec<Scm>, SnapshotError> {
+    let mut r = Reader { r, objects: vec![] };
+    let mut magic = [0; 8];
+    r.r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(SnapshotError::Corrupt)
+    }
+    let mut boxes = vec![];
+    for _ in 0..r.u32()? {
+        let x = match r.u8()? {
+            OBJ_PAIR => {
+                let car = r.reference()?;
+                crate::cons(car, r.reference()?)
+            }
+            OBJ_VECTOR => Scm::vector(r.references()?),
+            OBJ_VALUES => crate::values::values(r.references()?),
+            OBJ_BOX => {
+                let b = crate::boxes::make_box(Scm::NIL);
+                boxes.push((b, r.raw_reference()?));
+                b
+            }
+            OBJ_STRING => Scm::string(&r.string()?),
+            OBJ_SYMBOL => Scm::symbol(&r.string()?),
+            OBJ_FLONUM => Scm::from_f64(f64::from_bits(r.u64()?)),
+            OBJ_EXACT => num::parse(&r.string()?, 10).ok_or(SnapshotError::Corrupt)?,
+            _ => return Err(SnapshotError::Corrupt),
+        };
+        r.objects.push(x);
+    }
+    for (b, contents) in boxes {
+        crate::boxes::set_box(b, r.resolve(contents)?).unwrap();
+    }
+    r.references()
+}
+
+#[test]
+fn snapshots_preserve_sharing() {
+    use crate::boxes::{make_box, set_box, unbox};
+    use crate::reader::read_str;
+
+    let tail = read_str("(\"a shared tail\" 1/3 -0.5 123456789012345678901234567890)").unwrap();
+    let list = crate::cons(Scm::symbol("head"), tail);
+    let b = make_box(Scm::NIL);
+    let cycle = crate::cons(b, Scm::from_char('λ'));
+    set_box(b, cycle).unwrap();
+    let roots = [list, Scm::vector(vec![tail, Scm::string("short"), Scm::EOF]), cycle, Scm::from_int(-7)];
+
+    let mut file = vec![];
+    save_snapshot(&roots, &mut file).unwrap();
+    let loaded = load_snapshot(&file[..]).unwrap();
+    assert_eq!(loaded.len(), 4);
+    assert!(crate::order::equal(loaded[0], list) && loaded[0] != list);
+    assert_eq!(loaded[1].to_string(), "#((\"a shared tail\" 1/3 -0.5 123456789012345678901234567890) \"short\" #<eof>)");
+    assert_eq!(crate::cdr(loaded[0]), Some(loaded[1].as_vector().unwrap()[0]));
+    let b = crate::car(loaded[2]).unwrap();
+    assert_eq!(unbox(b), Ok(loaded[2]));
+    assert_eq!(loaded[3], Scm::from_int(-7));
+
+    assert!(matches!(load_snapshot(&file[..file.len() - 1]), Err(SnapshotError::Corrupt)));
+    let port = crate::port::open_input_string("");
+    assert_eq!(save_snapshot(&[list, port], vec![]).unwrap_err().to_string(), "can't put #<input-port> into a snapshot");
+
+    // Without a box in the cycle, nothing could be loaded first.
+    let circular = read_str("(1 2 3)").unwrap();
+    unsafe { crate::lists::set_cdr(crate::lists::last_pair(circular).unwrap(), circular).unwrap() };
+    let mut file = vec![];
+    assert!(matches!(save_snapshot(&[circular], &mut file), Err(SnapshotError::Unsupported(_))));
+    assert!(matches!(load_snapshot(&file[..]), Err(SnapshotError::Corrupt)));
+    let shared = read_str("(1 2)").unwrap();
+    let dag = Scm::vector(vec![shared, crate::cons(shared, shared)]);
+    save_snapshot(&[dag], vec![]).unwrap();
+}
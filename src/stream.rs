@@ -0,0 +1,90 @@
+//! Lazy streams built from promises, in the spirit of SRFI 41 (and SICP).
+//!
+//! A stream is either the empty list or a pair whose car is the first
+//! element and whose cdr is a promise of the rest of the stream.
+
+use std::sync::Arc;
+use crate::promise::{self, Thunk};
+use crate::{cdr, cons, Scm, ScmKind, TypeError};
+
+#[cfg(not(feature = "sync"))]
+pub trait StreamFn: Fn(Scm) -> Scm + 'static {}
+#[cfg(not(feature = "sync"))]
+impl<F: Fn(Scm) -> Scm + 'static> StreamFn for F {}
+
+#[cfg(feature = "sync")]
+pub trait StreamFn: Fn(Scm) -> Scm + Send + Sync + 'static {}
+#[cfg(feature = "sync")]
+impl<F: Fn(Scm) -> Scm + Send + Sync + 'static> StreamFn for F {}
+
+pub fn stream_cons(first: Scm, rest: impl Thunk) -> Scm {
+    cons(first, promise::delay(rest))
+}
+
+pub fn is_stream_pair(s: Scm) -> bool {
+    matches!(cdr(s), Some(rest) if rest.is_promise())
+}
+
+fn expect_stream_pair(s: Scm) -> Result<(Scm, Scm), TypeError> {
+    match s.as_pair() {
+        Some(&(first, rest)) if rest.is_promise() => Ok((first, rest)),
+        _ => Err(TypeError::new(ScmKind::Pair, s)),
+    }
+}
+
+pub fn stream_car(s: Scm) -> Result<Scm, TypeError> {
+    expect_stream_pair(s).map(|(first, _)| first)
+}
+
+pub fn stream_cdr(s: Scm) -> Result<Scm, TypeError> {
+    expect_stream_pair(s).map(|(_, rest)| promise::force(rest))
+}
+
+// The infinite stream seed, f(seed), f(f(seed)), ...
+pub fn stream_iterate(f: impl StreamFn, seed: Scm) -> Scm {
+    iterate(Arc::new(f), seed)
+}
+
+fn iterate(f: Arc<dyn StreamFn>, seed: Scm) -> Scm {
+    stream_cons(seed, move || iterate(f.clone(), f(seed)))
+}
+
+pub fn stream_map(f: impl StreamFn, s: Scm) -> Scm {
+    map(Arc::new(f), s)
+}
+
+fn map(f: Arc<dyn StreamFn>, s: Scm) -> Scm {
+    match expect_stream_pair(s) {
+        Ok((first, rest)) => stream_cons(f(first), move || map(f.clone(), promise::force(rest))),
+        Err(_) => Scm::NIL,
+    }
+}
+
+// The first n elements as a list, forcing no more of the stream than needed.
+pub fn stream_take(s: Scm, n: usize) -> Result<Scm, TypeError> {
+    let mut items = Vec::with_capacity(n);
+    let mut s = s;
+    while items.len() < n && !s.is_nil() {
+        items.push(stream_car(s)?);
+        if items.len() < n {
+            s = stream_cdr(s)?;
+        }
+    }
+    Ok(items.into_iter().rev().fold(Scm::NIL, |acc, x| cons(x, acc)))
+}
+
+#[test]
+fn infinite_streams_are_forced_on_demand() {
+    let naturals = stream_iterate(|n| Scm::from_int(n.as_integer().unwrap() + 1), Scm::from_int(0));
+    let squares = stream_map(|n| crate::num::mul(n, n).unwrap(), naturals);
+    assert_eq!(stream_take(squares, 5).unwrap().to_string(), "(0 1 4 9 16)");
+    assert_eq!(stream_take(squares, 0).unwrap(), Scm::NIL);
+
+    // the promises are memoized, so the same cells come back
+    let second = stream_cdr(squares).unwrap();
+    assert_eq!(stream_cdr(squares).unwrap(), second);
+    assert!(is_stream_pair(second) && !is_stream_pair(crate::car(second).unwrap()));
+
+    let finite = stream_cons(Scm::TRUE, || Scm::NIL);
+    assert_eq!(stream_take(finite, 10).unwrap().to_string(), "(#t)");
+}
@@ -0,0 +1,164 @@
+//! String case mapping, and (with the `unicode` feature) grapheme clusters
+//! and normalization forms.
+//!
+//! Unlike the per-character mappings in `chars`, these map whole strings, so
+//! they get context-dependent cases right: "STRASSE" is the upper case of
+//! "Straße", and a final Σ becomes ς.
+//!
+//! A substring borrows the UTF-8 text of the string it was taken from, so
+//! it costs one small heap object however long it is; substrings short
+//! enough to be immediate values are copied instead. `string_copy` makes a
+//! private copy for code that wants to hold on to a short substring without
+//! keeping a large parent alive.
+//!
+//! `string_set` changes a string in place, which no other operation does. A
+//! substring copies its text first, and a rope is flattened first, so the
+//! strings they were made from never change. Immediate strings can't be set.
+
+#[cfg(feature = "unicode")]
+use unicode_normalization::UnicodeNormalization;
+#[cfg(feature = "unicode")]
+use unicode_segmentation::UnicodeSegmentation;
+use crate::heap;
+use crate::{MutationError, Scm, ScmKind, Str, TypeError};
+
+// The characters `start..end` of `s`, without copying.
+//
+// Panics if the range is out of bounds, like slicing a `str` does.
+pub fn substring(s: Scm, start: usize, end: usize) -> Result<Scm, TypeError> {
+    let text = s.expect_str()?;
+    let offset = |i| text.char_indices().map(|(pos, _)| pos).chain(Some(text.len())).nth(i);
+    let (a, b) = match (offset(start), offset(end)) {
+        (Some(a), Some(b)) if a <= b => (a, b),
+        _ => panic!("substring {}..{} out of range for string of length {}", start, end, text.chars().count()),
+    };
+    if b - a <= crate::SHORT_STRING_MAX {
+        // as cheap to copy as to point to, if it is short enough to be immediate
+        return Ok(Scm::string(&text[a..b]))
+    }
+    // `s` isn't short, so the text is borrowed from the heap object and lives as long as it does
+    let text = s.as_object::<Str>().unwrap().body.as_str();
+    Ok(Scm::from_object(heap::leak(Str::borrowed(&text[a..b]))))
+}
+
+/// Sets character `k` of the string `s`, like `string-set!`. The new
+/// character may be encoded in more or fewer bytes than the old one.
+/// Containers of `s` keep their cached hashes, see `order::forget_hash`.
+///
+/// Panics if `k` is out of bounds. Fails without changing anything if `s` is
+/// frozen or short enough to be an immediate value.
+///
+/// # Safety
+/// `s` must be private to the caller: not hash-consed, not part of a rope,
+/// nothing may be borrowing its text, including references returned by
+/// `as_str` and substrings taken with `substring`, and no other thread may
+/// be using it.
+pub unsafe fn string_set(s: Scm, k: usize, c: char) -> Result<(), MutationError> {
+    let obj = match s.as_object::<Str>() {
+        Some(obj) => obj,
+        None if s.is_string() => return Err(MutationError::Immutable(s)),
+        None => return Err(TypeError::new(ScmKind::String, s).into()),
+    };
+    if s.is_frozen() {
+        return Err(MutationError::Immutable(s))
+    }
+    obj.body.as_str();
+    let text = (*obj.body.flat.get()).get_mut().expect("flattened").to_mut();
+    let (pos, old) = text.char_indices().nth(k).unwrap_or_else(|| {
+        panic!("index {} out of range for string of length {}", k, text.chars().count())
+    });
+    text.replace_range(pos..pos + old.len_utf8(), c.encode_utf8(&mut [0; 4]));
+    obj.body.len.set(text.len());
+    // a cached hash covers the old character
+    crate::order::forget_hash(s);
+    Ok(())
+}
+
+pub fn string_copy(s: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::string(s.expect_str()?))
+}
+
+pub fn string_upcase(s: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::string(&s.expect_str()?.to_uppercase()))
+}
+
+pub fn string_downcase(s: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::string(&s.expect_str()?.to_lowercase()))
+}
+
+// Full case folding for comparing strings without regard to case. Lower case
+// is the fold of nearly every character; the exceptions that matter in
+// practice are the ones where folding expands or merges characters.
+pub fn string_foldcase(s: Scm) -> Result<Scm, TypeError> {
+    let mut out = String::new();
+    for c in s.expect_str()?.chars() {
+        match c {
+            'ß' | 'ẞ' => out.push_str("ss"),
+            'ς' => out.push('σ'),
+            'ſ' => out.push('s'),
+            'İ' | 'ı' => out.push(c),
+            c => out.extend(c.to_lowercase()),
+        }
+    }
+    Ok(Scm::string(&out))
+}
+
+// What a user perceives as characters: "e\u{301}" is one grapheme but two
+// code points.
+#[cfg(feature = "unicode")]
+pub fn string_grapheme_length(s: Scm) -> Result<usize, TypeError> {
+    Ok(s.expect_str()?.graphemes(true).count())
+}
+
+#[cfg(feature = "unicode")]
+pub fn string_nfc(s: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::string(&s.expect_str()?.nfc().collect::<String>()))
+}
+
+#[cfg(feature = "unicode")]
+pub fn string_nfd(s: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::string(&s.expect_str()?.nfd().collect::<String>()))
+}
+
+#[test]
+fn case_mapping_whole_strings() {
+    let s = Scm::string;
+    assert_eq!(string_upcase(s("Straße")).unwrap().as_str(), Some("STRASSE"));
+    assert_eq!(string_downcase(s("ΟΔΟΣ")).unwrap().as_str(), Some("οδος"));
+    assert_eq!(string_foldcase(s("Straße ΟΔΟΣ")).unwrap().as_str(), Some("strasse οδοσ"));
+    assert_eq!(string_upcase(Scm::NIL).unwrap_err().to_string(), "expected string, got empty list");
+}
+
+#[test]
+fn substrings_share_storage() {
+    let s = Scm::string("(define λ 42)");
+    let body = substring(s, 1, 12).unwrap();
+    assert_eq!(body.as_str(), Some("define λ 42"));
+    assert_eq!(body.as_str().unwrap().as_ptr(), s.as_str().unwrap()[1..].as_ptr());
+    assert_eq!(substring(s, 8, 9).unwrap().as_str(), Some("λ"));
+    assert_eq!(substring(s, 13, 13).unwrap().as_str(), Some(""));
+
+    let copy = string_copy(body).unwrap();
+    assert_eq!(copy.as_str(), body.as_str());
+    assert_ne!(copy.as_str().unwrap().as_ptr(), body.as_str().unwrap().as_ptr());
+
+    let name = substring(s, 1, 12).unwrap();
+    unsafe { string_set(name, 7, 'x').unwrap() };
+    assert_eq!(name.as_str(), Some("define x 42"));
+    assert_eq!(crate::rope::string_byte_length(name).unwrap(), 11);
+    assert_eq!(s.as_str(), Some("(define λ 42)"));
+    let short = Scm::string("abc");
+    assert_eq!(unsafe { string_set(short, 0, 'x') }, Err(MutationError::Immutable(short)));
+    assert!(unsafe { string_set(Scm::NIL, 0, 'x') }.is_err());
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn graphemes_and_normalization() {
+    let composed = Scm::string("caf\u{e9}");
+    let decomposed = string_nfd(composed).unwrap();
+    assert_eq!(decomposed.as_str(), Some("cafe\u{301}"));
+    assert_eq!(string_nfc(decomposed).unwrap().as_str(), Some("caf\u{e9}"));
+    assert_eq!(decomposed.as_str().unwrap().chars().count(), 5);
+    assert_eq!(string_grapheme_length(decomposed).unwrap(), 4);
+}
@@ -0,0 +1,208 @@
+//! Symbol interning.
+//!
+//! There is one table for the whole process, so the same name is the same
+//! symbol on every thread. The table is split into shards with a lock each,
+//! and every thread keeps a cache of the symbols it has seen in front of it,
+//! so interning a known name takes no lock at all.
+//!
+//! The table does not keep symbols alive. After a collector has set
+//! `FLAG_MARK` on every reachable symbol, `sweep_unmarked` frees the rest, so
+//! programs that read lots of throwaway names don't grow the table forever.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{OnceLock, RwLock};
+use crate::heap::{self, HeapObject, Kind, Object, FLAG_MARK};
+use crate::Scm;
+
+const N_SHARDS: usize = 16;
+
+type Shard = RwLock<HashMap<&'static str, SymbolRef>>;
+
+pub(crate) struct Symbol(&'static str);
+
+impl Symbol {
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl HeapObject for Symbol {
+    const KIND: Kind = Kind::Symbol;
+}
+
+// Symbols are fully initialized (hash included) before they are published in
+// the table, and their headers are only written again by the collector while
+// no other thread touches them, so sharing them between threads is fine even
+// if `Header` uses a `Cell` (without the `sync` feature).
+#[derive(Copy, Clone)]
+struct SymbolRef {
+    obj: &'static Object<Symbol>,
+    // false if the name was borrowed by `intern_static`
+    owns_name: bool,
+}
+
+unsafe impl Send for SymbolRef {}
+unsafe impl Sync for SymbolRef {}
+
+pub struct SymbolTable {
+    shards: Vec<Shard>,
+    // bumped by every sweep, so threads know to drop their caches
+    epoch: AtomicUsize,
+}
+
+static SYMBOLS: OnceLock<SymbolTable> = OnceLock::new();
+
+thread_local! {
+    static CACHE: RefCell<(usize, HashMap<&'static str, Scm>)> = RefCell::new((0, HashMap::new()));
+}
+
+// FNV-1a, so the hash is the same in every run and on every thread
+pub(crate) fn hash_name(name: &str) -> u32 {
+    name.bytes().fold(0x811c_9dc5, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable {
+            shards: (0..N_SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            epoch: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn intern(&self, name: &str) -> Scm {
+        Scm::from_object(self.intern_with(name, |name| (Box::leak(name.into()), true)))
+    }
+
+    // Like `intern`, but a new symbol uses the given string as its name
+    // instead of copying it.
+    pub fn intern_static(&self, name: &'static str) -> Scm {
+        Scm::from_object(self.intern_with(name, |name| (name, false)))
+    }
+
+    fn intern_with<'a>(&self, name: &'a str, to_static: impl FnOnce(&'a str) -> (&'static str, bool)) -> &'static Object<Symbol> {
+        let hash = hash_name(name);
+        let shard = &self.shards[hash as usize % N_SHARDS];
+        if let Some(sym) = shard.read().unwrap().get(name) {
+            return sym.obj;
+        }
+        let mut table = shard.write().unwrap();
+        // another thread may have added it while we waited for the lock
+        if let Some(sym) = table.get(name) {
+            return sym.obj;
+        }
+        let (name, owns_name) = to_static(name);
+        let obj = heap::leak(Symbol(name));
+        obj.header.set_hash(hash);
+        table.insert(name, SymbolRef { obj, owns_name });
+        obj
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Frees every symbol without `FLAG_MARK` and clears the mark on the
+    /// others. Returns the number of symbols freed.
+    ///
+    /// # Safety
+    /// Every symbol that is still reachable must be marked, and no other
+    /// thread may use symbols from this table until the sweep is done.
+    pub unsafe fn sweep_unmarked(&self) -> usize {
+        let mut n_freed = 0;
+        for shard in &self.shards {
+            shard.write().unwrap().retain(|_, sym| {
+                let marked = sym.obj.header.flags() & FLAG_MARK != 0;
+                if marked {
+                    sym.obj.header.set_flag(FLAG_MARK, false);
+                } else {
+                    let obj = sym.obj as *const Object<Symbol> as *mut Object<Symbol>;
+                    let name = sym.obj.name() as *const str as *mut str;
+                    drop(Box::from_raw(obj));
+                    if sym.owns_name {
+                        drop(Box::from_raw(name));
+                    }
+                    n_freed += 1;
+                }
+                marked
+            });
+        }
+        self.epoch.fetch_add(1, Ordering::Release);
+        n_freed
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
+pub fn global() -> &'static SymbolTable {
+    SYMBOLS.get_or_init(SymbolTable::new)
+}
+
+fn cached(name: &str, intern: impl FnOnce(&'static SymbolTable) -> Scm) -> Scm {
+    let table = global();
+    let epoch = table.epoch.load(Ordering::Acquire);
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.0 != epoch {
+            *cache = (epoch, HashMap::new());
+        }
+        if let Some(&sym) = cache.1.get(name) {
+            return sym;
+        }
+        let sym = intern(table);
+        cache.1.insert(sym.as_object::<Symbol>().unwrap().name(), sym);
+        sym
+    })
+}
+
+pub fn intern(name: &str) -> Scm {
+    cached(name, |table| table.intern(name))
+}
+
+pub fn intern_static(name: &'static str) -> Scm {
+    cached(name, |table| table.intern_static(name))
+}
+
+#[test]
+fn interned_symbols_are_identical() {
+    let a = intern("lambda");
+    let b = intern(&String::from("lambda"));
+    let c = intern("define");
+    assert_eq!(a.as_symbol(), Some("lambda"));
+    assert!(std::ptr::eq(a.header().unwrap(), b.header().unwrap()));
+    assert!(!std::ptr::eq(a.header().unwrap(), c.header().unwrap()));
+
+    let name: &'static str = "static-name";
+    let s = intern_static(name);
+    assert!(std::ptr::eq(s.as_symbol().unwrap(), name));
+    assert_eq!(s.header().unwrap().hash(), Some(hash_name(name)));
+
+    let from_thread = std::thread::spawn(|| intern("lambda").to_raw()).join().unwrap();
+    assert_eq!(from_thread, a.to_raw());
+}
+
+#[test]
+fn unmarked_symbols_are_swept() {
+    let table = SymbolTable::new();
+    let keep = table.intern("keep");
+    for i in 0..100 {
+        table.intern(&format!("temp-{}", i));
+    }
+    table.intern_static("temp-static");
+    assert_eq!(table.len(), 102);
+
+    keep.header().unwrap().set_flag(FLAG_MARK, true);
+    assert_eq!(unsafe { table.sweep_unmarked() }, 101);
+    assert_eq!(table.len(), 1);
+    assert_eq!(keep.header().unwrap().flags() & FLAG_MARK, 0);
+    assert_eq!(table.intern("keep"), keep);
+}
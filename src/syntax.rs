@@ -0,0 +1,90 @@
+//! Syntax objects: a datum together with the place in the source it was read
+//! from, for macro expanders and error messages that point at the source.
+//!
+//! The reader produces them when asked to (see `ReadOptions`). Inside a
+//! wrapped list or vector every element is wrapped too, so `strip` is needed
+//! to get back to plain data.
+
+use std::fmt;
+use std::sync::Arc;
+use crate::heap::{self, HeapObject, Kind};
+use crate::{cons, Scm};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: Option<Arc<str>>,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.as_deref().unwrap_or("?"), self.line, self.column)
+    }
+}
+
+pub(crate) struct Syntax {
+    datum: Scm,
+    location: SourceLocation,
+}
+
+impl HeapObject for Syntax {
+    const KIND: Kind = Kind::Syntax;
+}
+
+pub fn make_syntax(datum: Scm, location: SourceLocation) -> Scm {
+    Scm::from_object(heap::leak(Syntax { datum, location }))
+}
+
+impl Scm {
+    pub fn is_syntax(&self) -> bool {
+        self.as_object::<Syntax>().is_some()
+    }
+}
+
+// The wrapped datum; anything else is returned as it is.
+pub fn datum(x: Scm) -> Scm {
+    x.as_object::<Syntax>().map(|obj| obj.datum).unwrap_or(x)
+}
+
+pub fn location(x: Scm) -> Option<&'static SourceLocation> {
+    x.as_object::<Syntax>().map(|obj| &obj.body.location)
+}
+
+// Removes all syntax wrappers, also from inside pairs and vectors.
+pub fn strip(x: Scm) -> Scm {
+    let x = datum(x);
+    if let Some(&(a, d)) = x.as_pair() {
+        // the spine may be long, so it is rebuilt in a loop
+        let mut items = vec![strip(a)];
+        let mut rest = datum(d);
+        while let Some(&(a, d)) = rest.as_pair() {
+            items.push(strip(a));
+            rest = datum(d);
+        }
+        return items.into_iter().rev().fold(strip(rest), |acc, x| cons(x, acc))
+    }
+    if let Some(items) = x.as_vector() {
+        if items.iter().any(|x| x.is_syntax()) {
+            return Scm::vector(items.iter().map(|&x| strip(x)).collect())
+        }
+    }
+    x
+}
+
+#[test]
+fn the_reader_can_wrap_data() {
+    use crate::port::open_input_string;
+    use crate::reader::{ReadOptions, Reader};
+
+    let options = ReadOptions::default().syntax_objects(Some("test.scm".into()));
+    let mut reader = Reader::with_options(open_input_string("(a\n  (b . 1) #(c))"), options);
+    let form = reader.read().unwrap();
+    assert_eq!(location(form).unwrap().to_string(), "test.scm:1:1");
+    assert_eq!(strip(form).to_string(), "(a (b . 1) #(c))");
+
+    let second = crate::car(crate::cdr(datum(form)).unwrap()).unwrap();
+    assert_eq!(location(second).unwrap().to_string(), "test.scm:2:3");
+    assert_eq!(second.to_string(), "#<syntax test.scm:2:3 (b . 1)>");
+    assert_eq!(strip(Scm::from_int(1)), Scm::from_int(1));
+}
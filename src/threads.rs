@@ -0,0 +1,216 @@
+//! Threads, mutexes and condition variables (after SRFI 18), with the `sync`
+//! feature, for building a multithreaded Scheme on this representation.
+//!
+//! A Scheme mutex is not tied to a Rust scope: a program may lock it in one
+//! procedure and unlock it in another. So it records which thread owns it
+//! instead of holding a `MutexGuard`, and locking waits until it is free.
+//! Mistakes a Scheme program can make, like unlocking a mutex it doesn't
+//! hold, are returned as errors instead of panicking or deadlocking.
+
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, JoinHandle, ThreadId};
+use crate::heap::{self, HeapObject, Kind};
+use crate::{Scm, ScmKind, ThreadError, TypeError};
+
+pub(crate) struct ScmMutex {
+    owner: Mutex<Option<ThreadId>>,
+    released: Condvar,
+}
+
+pub(crate) struct ScmCondVar(Condvar);
+
+pub(crate) struct ScmThread {
+    // taken by the first join
+    handle: Mutex<Option<JoinHandle<Scm>>>,
+}
+
+impl HeapObject for ScmMutex {
+    const KIND: Kind = Kind::Mutex;
+}
+
+impl HeapObject for ScmCondVar {
+    const KIND: Kind = Kind::CondVar;
+}
+
+impl HeapObject for ScmThread {
+    const KIND: Kind = Kind::Thread;
+}
+
+impl Scm {
+    pub fn is_mutex(&self) -> bool {
+        self.as_object::<ScmMutex>().is_some()
+    }
+
+    pub fn is_condition_variable(&self) -> bool {
+        self.as_object::<ScmCondVar>().is_some()
+    }
+
+    pub fn is_thread(&self) -> bool {
+        self.as_object::<ScmThread>().is_some()
+    }
+}
+
+fn expect_mutex(m: Scm) -> Result<&'static ScmMutex, TypeError> {
+    m.as_object::<ScmMutex>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Mutex, m))
+}
+
+fn expect_condvar(cv: Scm) -> Result<&'static ScmCondVar, TypeError> {
+    cv.as_object::<ScmCondVar>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::CondVar, cv))
+}
+
+fn expect_thread(t: Scm) -> Result<&'static ScmThread, TypeError> {
+    t.as_object::<ScmThread>().map(|obj| &obj.body).ok_or_else(|| TypeError::new(ScmKind::Thread, t))
+}
+
+pub fn make_mutex() -> Scm {
+    Scm::from_object(heap::leak(ScmMutex { owner: Mutex::new(None), released: Condvar::new() }))
+}
+
+// Blocks until no other thread holds `m`.
+pub fn mutex_lock(m: Scm) -> Result<(), ThreadError> {
+    let me = thread::current().id();
+    let mutex = expect_mutex(m)?;
+    let mut owner = mutex.owner.lock().unwrap();
+    if *owner == Some(me) {
+        return Err(ThreadError::AlreadyLocked(m))
+    }
+    while owner.is_some() {
+        owner = mutex.released.wait(owner).unwrap();
+    }
+    *owner = Some(me);
+    Ok(())
+}
+
+pub fn mutex_unlock(m: Scm) -> Result<(), ThreadError> {
+    let mutex = expect_mutex(m)?;
+    let mut owner = mutex.owner.lock().unwrap();
+    if *owner != Some(thread::current().id()) {
+        return Err(ThreadError::NotLocked(m))
+    }
+    *owner = None;
+    mutex.released.notify_one();
+    Ok(())
+}
+
+// Unlocks the mutex when dropped, also when unwinding.
+struct Unlock(Scm);
+
+impl Drop for Unlock {
+    fn drop(&mut self) {
+        // can't fail, since nobody else could have unlocked it
+        let _ = mutex_unlock(self.0);
+    }
+}
+
+// Calls `f` with `m` locked.
+pub fn with_mutex<R>(m: Scm, f: impl FnOnce() -> R) -> Result<R, ThreadError> {
+    mutex_lock(m)?;
+    let _unlock = Unlock(m);
+    Ok(f())
+}
+
+pub fn make_condition_variable() -> Scm {
+    Scm::from_object(heap::leak(ScmCondVar(Condvar::new())))
+}
+
+// Unlocks `m`, waits until `cv` is signaled and locks `m` again, like
+// `pthread_cond_wait`. Wakeups may be spurious, so wait in a loop that
+// checks the condition.
+//
+// Panics if `cv` is waited on with different mutexes, as `std::sync::Condvar`
+// may.
+pub fn condition_wait(cv: Scm, m: Scm) -> Result<(), ThreadError> {
+    let me = thread::current().id();
+    let condvar = expect_condvar(cv)?;
+    let mutex = expect_mutex(m)?;
+    let mut owner = mutex.owner.lock().unwrap();
+    if *owner != Some(me) {
+        return Err(ThreadError::NotLocked(m))
+    }
+    *owner = None;
+    mutex.released.notify_one();
+    owner = condvar.0.wait(owner).unwrap();
+    while owner.is_some() {
+        owner = mutex.released.wait(owner).unwrap();
+    }
+    *owner = Some(me);
+    Ok(())
+}
+
+// Wakes one thread waiting on `cv`.
+pub fn condition_signal(cv: Scm) -> Result<(), TypeError> {
+    expect_condvar(cv).map(|condvar| condvar.0.notify_one())
+}
+
+// Wakes all threads waiting on `cv`.
+pub fn condition_broadcast(cv: Scm) -> Result<(), TypeError> {
+    expect_condvar(cv).map(|condvar| condvar.0.notify_all())
+}
+
+// Runs `f` on a new thread, whose result `thread_join` returns.
+pub fn spawn(f: impl FnOnce() -> Scm + Send + 'static) -> Scm {
+    let handle = thread::spawn(f);
+    Scm::from_object(heap::leak(ScmThread { handle: Mutex::new(Some(handle)) }))
+}
+
+// Waits for `t` to finish and returns its result. Only the first join gets
+// the result.
+pub fn thread_join(t: Scm) -> Result<Scm, ThreadError> {
+    let handle = expect_thread(t)?.handle.lock().unwrap().take();
+    match handle {
+        Some(handle) => handle.join().map_err(|_| ThreadError::Panicked(t)),
+        None => Err(ThreadError::AlreadyJoined(t)),
+    }
+}
+
+#[test]
+fn threads_share_state_under_a_mutex() {
+    use crate::boxes::{make_box, set_box, unbox};
+
+    let m = make_mutex();
+    let counter = make_box(Scm::from_int(0));
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            spawn(move || {
+                for _ in 0..1000 {
+                    with_mutex(m, || {
+                        let n = unbox(counter).unwrap().as_integer().unwrap();
+                        set_box(counter, Scm::from_int(n + 1)).unwrap();
+                    })
+                    .unwrap();
+                }
+                Scm::TRUE
+            })
+        })
+        .collect();
+    for t in threads {
+        assert_eq!(thread_join(t), Ok(Scm::TRUE));
+    }
+    assert_eq!(unbox(counter), Ok(Scm::from_int(4000)));
+
+    let cv = make_condition_variable();
+    let ready = make_box(Scm::FALSE);
+    let waiter = spawn(move || {
+        mutex_lock(m).unwrap();
+        while !unbox(ready).unwrap().is_true() {
+            condition_wait(cv, m).unwrap();
+        }
+        mutex_unlock(m).unwrap();
+        Scm::symbol("woken")
+    });
+    with_mutex(m, || {
+        set_box(ready, Scm::TRUE).unwrap();
+        condition_broadcast(cv).unwrap();
+    })
+    .unwrap();
+    assert_eq!(thread_join(waiter), Ok(Scm::symbol("woken")));
+    assert_eq!(thread_join(waiter), Err(ThreadError::AlreadyJoined(waiter)));
+    assert!(m.is_mutex() && cv.is_condition_variable() && waiter.is_thread());
+    assert_eq!(cv.to_string(), "#<condition-variable>");
+
+    assert_eq!(mutex_unlock(m), Err(ThreadError::NotLocked(m)));
+    mutex_lock(m).unwrap();
+    assert_eq!(mutex_lock(m).unwrap_err().to_string(), "#<mutex> is already locked by this thread");
+    mutex_unlock(m).unwrap();
+    assert_eq!(thread_join(m).unwrap_err().to_string(), "expected thread, got mutex");
+}
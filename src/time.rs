@@ -0,0 +1,163 @@
+//! Points in time and durations as values, after SRFI 19, so that date
+//! libraries and benchmark harnesses written in Scheme share one
+//! representation.
+//!
+//! Times wrap `SystemTime` and durations wrap `Duration`, so both have
+//! nanosecond resolution and durations can't be negative. Converted to
+//! seconds they are exact numbers; seconds given as any other number are
+//! rounded to the nearest nanosecond.
+
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::bigint::BigInt;
+use crate::heap::{self, HeapObject, Kind};
+use crate::num::{self, Rounding};
+use crate::{NumError, Scm, ScmKind, TypeError};
+
+pub(crate) struct Time(SystemTime);
+
+pub(crate) struct ScmDuration(Duration);
+
+impl HeapObject for Time {
+    const KIND: Kind = Kind::Time;
+}
+
+impl HeapObject for ScmDuration {
+    const KIND: Kind = Kind::Duration;
+}
+
+impl Scm {
+    pub fn is_time(&self) -> bool {
+        self.as_object::<Time>().is_some()
+    }
+
+    pub fn is_duration(&self) -> bool {
+        self.as_object::<ScmDuration>().is_some()
+    }
+}
+
+pub fn make_time(t: SystemTime) -> Scm {
+    Scm::from_object(heap::leak(Time(t)))
+}
+
+pub fn make_duration(d: Duration) -> Scm {
+    Scm::from_object(heap::leak(ScmDuration(d)))
+}
+
+pub fn current_time() -> Scm {
+    make_time(SystemTime::now())
+}
+
+pub fn time_value(t: Scm) -> Result<SystemTime, TypeError> {
+    t.as_object::<Time>().map(|obj| obj.0).ok_or_else(|| TypeError::new(ScmKind::Time, t))
+}
+
+pub fn duration_value(d: Scm) -> Result<Duration, TypeError> {
+    d.as_object::<ScmDuration>().map(|obj| obj.0).ok_or_else(|| TypeError::new(ScmKind::Duration, d))
+}
+
+pub fn time_add(t: Scm, d: Scm) -> Result<Scm, NumError> {
+    let t = time_value(t)?.checked_add(duration_value(d)?).ok_or(NumError::OutOfRange)?;
+    Ok(make_time(t))
+}
+
+pub fn time_subtract(t: Scm, d: Scm) -> Result<Scm, NumError> {
+    let t = time_value(t)?.checked_sub(duration_value(d)?).ok_or(NumError::OutOfRange)?;
+    Ok(make_time(t))
+}
+
+// The duration from `b` to `a`. Fails if `a` is earlier than `b`.
+pub fn time_difference(a: Scm, b: Scm) -> Result<Scm, NumError> {
+    let d = time_value(a)?.duration_since(time_value(b)?).map_err(|_| NumError::OutOfRange)?;
+    Ok(make_duration(d))
+}
+
+pub fn duration_add(a: Scm, b: Scm) -> Result<Scm, NumError> {
+    let d = duration_value(a)?.checked_add(duration_value(b)?).ok_or(NumError::OutOfRange)?;
+    Ok(make_duration(d))
+}
+
+// Fails if `b` is longer than `a`.
+pub fn duration_subtract(a: Scm, b: Scm) -> Result<Scm, NumError> {
+    let d = duration_value(a)?.checked_sub(duration_value(b)?).ok_or(NumError::OutOfRange)?;
+    Ok(make_duration(d))
+}
+
+pub fn time_compare(a: Scm, b: Scm) -> Result<Ordering, TypeError> {
+    Ok(time_value(a)?.cmp(&time_value(b)?))
+}
+
+pub fn duration_compare(a: Scm, b: Scm) -> Result<Ordering, TypeError> {
+    Ok(duration_value(a)?.cmp(&duration_value(b)?))
+}
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+fn nanos_to_seconds(nanos: i128) -> Scm {
+    num::make_rational(BigInt::from_i128(nanos), BigInt::from_i64(NANOS_PER_SECOND)).unwrap()
+}
+
+// The nearest whole number of nanoseconds in `seconds`, which is any real
+// number. That is about 292 years at most.
+fn seconds_to_nanos(seconds: Scm) -> Result<i64, NumError> {
+    let nanos = num::mul(seconds, Scm::from_int(NANOS_PER_SECOND))?;
+    let nanos = num::inexact_to_exact(num::round(nanos, Rounding::Round)?)?;
+    match nanos.as_integer() {
+        Some(i) => Ok(i),
+        None => nanos.as_bignum().and_then(BigInt::to_i64).ok_or(NumError::OutOfRange),
+    }
+}
+
+// The length of `d` in seconds, as an exact number.
+pub fn duration_to_seconds(d: Scm) -> Result<Scm, TypeError> {
+    Ok(nanos_to_seconds(duration_value(d)?.as_nanos() as i128))
+}
+
+pub fn seconds_to_duration(seconds: Scm) -> Result<Scm, NumError> {
+    match seconds_to_nanos(seconds)? {
+        nanos if nanos < 0 => Err(NumError::OutOfRange),
+        nanos => Ok(make_duration(Duration::from_nanos(nanos as u64))),
+    }
+}
+
+// Seconds since the Unix epoch, negative for earlier times, as an exact
+// number.
+pub fn time_to_seconds(t: Scm) -> Result<Scm, TypeError> {
+    let nanos = match time_value(t)?.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    };
+    Ok(nanos_to_seconds(nanos))
+}
+
+pub fn seconds_to_time(seconds: Scm) -> Result<Scm, NumError> {
+    let nanos = seconds_to_nanos(seconds)?;
+    let d = Duration::from_nanos(nanos.unsigned_abs());
+    let t = if nanos < 0 { UNIX_EPOCH.checked_sub(d) } else { UNIX_EPOCH.checked_add(d) };
+    t.map(make_time).ok_or(NumError::OutOfRange)
+}
+
+#[test]
+fn times_convert_to_seconds() {
+    let start = seconds_to_time(Scm::from_int(1_700_000_000)).unwrap();
+    let step = seconds_to_duration(Scm::from_f64(1.5)).unwrap();
+    let end = time_add(start, step).unwrap();
+    assert_eq!(end.to_string(), "#<time 1700000001.500000000>");
+    assert_eq!(time_to_seconds(end).unwrap().to_string(), "3400000003/2");
+    assert_eq!(duration_to_seconds(time_difference(end, start).unwrap()).unwrap().to_string(), "3/2");
+    assert_eq!(time_difference(start, end), Err(NumError::OutOfRange));
+    assert_eq!(time_compare(start, end), Ok(Ordering::Less));
+    assert_eq!(time_subtract(end, step).map(|t| crate::order::equal(t, start)), Ok(true));
+
+    let before_epoch = seconds_to_time(crate::reader::read_str("-1/4").unwrap()).unwrap();
+    assert_eq!(before_epoch.to_string(), "#<time -0.250000000>");
+    assert_eq!(time_to_seconds(before_epoch).unwrap().to_string(), "-1/4");
+
+    let tick = seconds_to_duration(Scm::from_f64(1e-9)).unwrap();
+    assert_eq!(tick.to_string(), "#<duration 1ns>");
+    assert_eq!(duration_add(step, tick).map(|d| duration_value(d).unwrap().subsec_nanos()), Ok(500_000_001));
+    assert_eq!(duration_subtract(tick, step), Err(NumError::OutOfRange));
+    assert_eq!(seconds_to_duration(Scm::from_int(-1)), Err(NumError::OutOfRange));
+    assert!(current_time().is_time() && step.is_duration());
+    assert_eq!(duration_compare(step, start).unwrap_err().to_string(), "expected duration, got time");
+}
@@ -0,0 +1,195 @@
+//! Tracing: enumerating the values an object refers to, which is what a
+//! precise collector needs to know about every kind of heap object.
+//!
+//! Foreign values take part by implementing `Trace` and being wrapped with
+//! `foreign::make_traced_foreign`. With the `derive` feature, `Trace` can be
+//! derived for structs and enums whose fields all implement it; fields marked
+//! `#[trace(skip)]` are left out.
+//!
+//! ```ignore
+//! #[derive(Trace)]
+//! struct Closure {
+//!     code: Scm,
+//!     captured: Vec<Scm>,
+//!     #[trace(skip)]
+//!     calls: AtomicUsize,
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+use crate::{Scm, ScmView};
+
+#[cfg(feature = "derive")]
+pub use scm_repr_derive::Trace;
+
+pub trait Trace {
+    // Calls `visit` with every value this refers to directly.
+    fn trace(&self, visit: &mut dyn FnMut(Scm));
+}
+
+impl Trace for Scm {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        visit(*self)
+    }
+}
+
+macro_rules! no_values {
+    ($($t:ty),*) => {
+        $(impl Trace for $t {
+            fn trace(&self, _: &mut dyn FnMut(Scm)) {}
+        })*
+    };
+}
+
+no_values!((), bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, String, str);
+
+impl<T: Trace + ?Sized> Trace for &T {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        (**self).trace(visit)
+    }
+}
+
+impl<T: Trace + ?Sized> Trace for Box<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        (**self).trace(visit)
+    }
+}
+
+impl<T: Trace + ?Sized> Trace for Rc<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        (**self).trace(visit)
+    }
+}
+
+impl<T: Trace + ?Sized> Trace for Arc<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        (**self).trace(visit)
+    }
+}
+
+impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|x| x.trace(visit))
+    }
+}
+
+impl<T: Trace> Trace for [T] {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|x| x.trace(visit))
+    }
+}
+
+impl<T: Trace, const N: usize> Trace for [T; N] {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|x| x.trace(visit))
+    }
+}
+
+impl<T: Trace> Trace for Vec<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|x| x.trace(visit))
+    }
+}
+
+impl<T: Trace> Trace for VecDeque<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|x| x.trace(visit))
+    }
+}
+
+impl<K: Trace, V: Trace, S> Trace for HashMap<K, V, S> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|(k, v)| {
+            k.trace(visit);
+            v.trace(visit);
+        })
+    }
+}
+
+impl<K: Trace, V: Trace> Trace for BTreeMap<K, V> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.iter().for_each(|(k, v)| {
+            k.trace(visit);
+            v.trace(visit);
+        })
+    }
+}
+
+impl<A: Trace, B: Trace> Trace for (A, B) {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.0.trace(visit);
+        self.1.trace(visit);
+    }
+}
+
+impl<A: Trace, B: Trace, C: Trace> Trace for (A, B, C) {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.0.trace(visit);
+        self.1.trace(visit);
+        self.2.trace(visit);
+    }
+}
+
+impl<T: Trace + Copy> Trace for std::cell::Cell<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.get().trace(visit)
+    }
+}
+
+impl<T: Trace> Trace for std::cell::RefCell<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.borrow().trace(visit)
+    }
+}
+
+impl<T: Trace> Trace for Mutex<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.lock().unwrap().trace(visit)
+    }
+}
+
+impl<T: Trace> Trace for RwLock<T> {
+    fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+        self.read().unwrap().trace(visit)
+    }
+}
+
+// The values `x` refers to directly. Only pairs, vectors, multiple values,
+// boxes, syntax objects and traced foreign objects are looked into so far.
+pub fn children(x: Scm) -> Vec<Scm> {
+    let mut found = vec![];
+    match x.classify() {
+        ScmView::Pair(&(car, cdr)) => found.extend([car, cdr]),
+        ScmView::Vector(items) | ScmView::Values(items) => found.extend_from_slice(items),
+        ScmView::Box(x) | ScmView::Syntax(x, _) => found.push(x),
+        ScmView::Foreign(_) => crate::foreign::trace(x, &mut |child| found.push(child)),
+        _ => {}
+    }
+    found
+}
+
+#[test]
+fn traced_foreign_objects_have_children() {
+    struct Node {
+        label: String,
+        edges: Vec<(Scm, u32)>,
+        parent: Option<Scm>,
+    }
+
+    impl Trace for Node {
+        fn trace(&self, visit: &mut dyn FnMut(Scm)) {
+            self.label.trace(visit);
+            self.edges.trace(visit);
+            self.parent.trace(visit);
+        }
+    }
+
+    let (a, b) = (Scm::symbol("a"), crate::cons(Scm::NIL, Scm::NIL));
+    let node = Node { label: "n".into(), edges: vec![(a, 1), (b, 2)], parent: Some(Scm::from_int(7)) };
+    let x = crate::foreign::make_traced_foreign("node", node);
+    assert_eq!(children(x), [a, b, Scm::from_int(7)]);
+    assert_eq!(children(crate::foreign::make_foreign("opaque", 5)), []);
+    assert_eq!(children(b), [Scm::NIL, Scm::NIL]);
+}
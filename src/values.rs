@@ -0,0 +1,56 @@
+//! Multiple return values, as produced by `values` and consumed by
+//! `call-with-values` or `receive`.
+//!
+//! A separate heap kind, so that returning several values can't be mistaken
+//! for returning a list. As in most implementations, exactly one value is just
+//! that value and needs no allocation.
+
+use crate::heap::{self, HeapObject, Kind};
+use crate::Scm;
+
+pub(crate) struct Values(Box<[Scm]>);
+
+impl Values {
+    pub fn items(&self) -> &[Scm] {
+        &self.0
+    }
+}
+
+impl HeapObject for Values {
+    const KIND: Kind = Kind::Values;
+}
+
+pub fn values(items: Vec<Scm>) -> Scm {
+    if items.len() == 1 {
+        items[0]
+    } else {
+        Scm::from_object(heap::leak(Values(items.into_boxed_slice())))
+    }
+}
+
+impl Scm {
+    pub fn is_values(&self) -> bool {
+        self.as_object::<Values>().is_some()
+    }
+
+    // What a consumer in `call-with-values` gets called with: the values of a
+    // multiple-values object, or any other value on its own.
+    pub fn as_values(&self) -> &[Scm] {
+        match self.as_object::<Values>() {
+            Some(obj) => obj.items(),
+            None => std::slice::from_ref(self),
+        }
+    }
+}
+
+#[test]
+fn values_are_not_lists() {
+    let none = values(vec![]);
+    let two = values(vec![Scm::from_int(1), Scm::NIL]);
+    assert!(none.is_values() && none.as_values().is_empty());
+    assert_eq!(two.as_values(), &[Scm::from_int(1), Scm::NIL]);
+    assert!(two.as_pair().is_none());
+    assert_eq!(values(vec![Scm::TRUE]), Scm::TRUE);
+    assert_eq!(Scm::TRUE.as_values(), &[Scm::TRUE]);
+    assert_eq!(two.to_string(), "#<values 1 ()>");
+}
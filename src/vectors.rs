@@ -0,0 +1,78 @@
+//! Slicing vectors without copying.
+//!
+//! Vectors are immutable, so a slice can share the items of the vector it was
+//! taken from for as long as it lives, and subvector-heavy algorithms (like
+//! merge sort on vectors) take no O(n) copies. `vector_copy` makes a private
+//! copy for code that keeps a small slice of a large vector around.
+//!
+//! `vector_set` is the exception, for code that builds a vector in place.
+//! Setting an item of a slice copies the slice first, so the vector it was
+//! taken from never changes.
+
+use std::borrow::Cow;
+use crate::heap;
+use crate::{MutationError, Scm, ScmKind, TypeError, Vector};
+
+// The items `start..end` of `v`, without copying.
+//
+// Panics if the range is out of bounds, like slicing a `[T]` does.
+pub fn vector_slice(v: Scm, start: usize, end: usize) -> Result<Scm, TypeError> {
+    // borrowed from the heap object, so it lives as long as `v` does
+    let items: &'static [Scm] = match v.as_object::<Vector>() {
+        Some(obj) => obj.body.items(),
+        None => return Err(TypeError::new(ScmKind::Vector, v)),
+    };
+    Ok(Scm::from_object(heap::leak(Vector::new(Cow::Borrowed(&items[start..end])))))
+}
+
+/// Sets item `k` of the vector `v`, like `vector-set!`. A slice gets a copy
+/// of its items first. Fails if `v` is not a vector or is frozen. Containers
+/// of `v` keep their cached hashes, see `order::forget_hash`.
+///
+/// Panics if `k` is out of bounds, like indexing a `[T]` does.
+///
+/// # Safety
+/// `v` must be private to the caller: nothing may be borrowing its items,
+/// including references returned by `as_vector` and slices taken with
+/// `vector_slice`, and no other thread may be using it.
+pub unsafe fn vector_set(v: Scm, k: usize, value: Scm) -> Result<(), MutationError> {
+    let obj = v.as_object::<Vector>().ok_or_else(|| TypeError::new(ScmKind::Vector, v))?;
+    if v.is_frozen() {
+        return Err(MutationError::Immutable(v))
+    }
+    (*obj.body.0.get()).to_mut()[k] = value;
+    // a cached hash covers the old item
+    crate::order::forget_hash(v);
+    Ok(())
+}
+
+pub fn vector_copy(v: Scm) -> Result<Scm, TypeError> {
+    Ok(Scm::vector(v.expect_vector()?.to_vec()))
+}
+
+// The items of `v`, copied out of it.
+pub fn vector_to_vec(v: Scm) -> Result<Vec<Scm>, TypeError> {
+    Ok(v.expect_vector()?.to_vec())
+}
+
+#[test]
+fn slices_share_items() {
+    let v = Scm::vector((0..10).map(Scm::from_int).collect());
+    let middle = vector_slice(v, 3, 7).unwrap();
+    assert_eq!(middle.to_string(), "#(3 4 5 6)");
+    assert_eq!(middle.as_vector().unwrap().as_ptr(), v.as_vector().unwrap()[3..].as_ptr());
+    let inner = vector_slice(middle, 1, 2).unwrap();
+    assert_eq!(inner.as_vector(), Some(&[Scm::from_int(4)][..]));
+
+    let copy = vector_copy(middle).unwrap();
+    assert_eq!(copy.as_vector(), middle.as_vector());
+    assert_ne!(copy.as_vector().unwrap().as_ptr(), middle.as_vector().unwrap().as_ptr());
+    assert!(vector_slice(Scm::NIL, 0, 0).is_err());
+
+    let slice = vector_slice(v, 3, 7).unwrap();
+    unsafe { vector_set(slice, 0, Scm::symbol("new")).unwrap() };
+    assert_eq!(slice.to_string(), "#(new 4 5 6)");
+    assert_eq!(v.to_string(), "#(0 1 2 3 4 5 6 7 8 9)");
+    assert_ne!(slice.as_vector().unwrap().as_ptr(), v.as_vector().unwrap()[3..].as_ptr());
+    assert!(unsafe { vector_set(Scm::NIL, 0, Scm::NIL) }.is_err());
+}
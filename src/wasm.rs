@@ -0,0 +1,116 @@
+//! JavaScript bindings, with the `wasm` feature.
+//!
+//! `Scm` is exported as a class of the same name that wraps one value. The
+//! wrapper owns nothing but the word itself, so freeing it on the JS side
+//! (explicitly or by its finalizer) never frees the Scheme object.
+//!
+//! ```js
+//! const list = Scm.cons(Scm.fromNumber(1), Scm.cons(Scm.fromString("two"), Scm.nil()));
+//! list.toString();              // '(1 "two")'
+//! list.cdr().car().asString();  // 'two'
+//! ```
+
+use wasm_bindgen::prelude::*;
+use crate::{num, reader, Scm};
+
+#[wasm_bindgen(js_name = Scm)]
+#[derive(Debug, Copy, Clone)]
+pub struct JsScm(Scm);
+
+// Integers are exact only up to 2^53 as JS numbers.
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+#[wasm_bindgen(js_class = Scm)]
+impl JsScm {
+    pub fn nil() -> JsScm {
+        JsScm(Scm::NIL)
+    }
+
+    pub fn cons(car: &JsScm, cdr: &JsScm) -> JsScm {
+        JsScm(crate::cons(car.0, cdr.0))
+    }
+
+    // undefined for anything but a pair
+    pub fn car(&self) -> Option<JsScm> {
+        crate::car(self.0).map(JsScm)
+    }
+
+    pub fn cdr(&self) -> Option<JsScm> {
+        crate::cdr(self.0).map(JsScm)
+    }
+
+    #[wasm_bindgen(js_name = isPair)]
+    pub fn is_pair(&self) -> bool {
+        crate::is_pair(self.0)
+    }
+
+    #[wasm_bindgen(js_name = isNull)]
+    pub fn is_null(&self) -> bool {
+        self.0.is_nil()
+    }
+
+    // Whole numbers in the safe integer range become exact integers, all
+    // others flonums.
+    #[wasm_bindgen(js_name = fromNumber)]
+    pub fn from_number(x: f64) -> JsScm {
+        if x.fract() == 0.0 && x.abs() <= MAX_SAFE_INTEGER {
+            JsScm(num::integer(x as i64))
+        } else {
+            JsScm(Scm::from_f64(x))
+        }
+    }
+
+    // The nearest float for any real number, undefined for non-numbers.
+    #[wasm_bindgen(js_name = toNumber)]
+    pub fn to_number(&self) -> Option<f64> {
+        num::exact_to_inexact(self.0).ok().and_then(|x| x.as_f64())
+    }
+
+    #[wasm_bindgen(js_name = fromBool)]
+    pub fn from_bool(b: bool) -> JsScm {
+        JsScm(Scm::from_bool(b))
+    }
+
+    #[wasm_bindgen(js_name = fromString)]
+    pub fn from_string(s: &str) -> JsScm {
+        JsScm(Scm::string(s))
+    }
+
+    #[wasm_bindgen(js_name = asString)]
+    pub fn as_string(&self) -> Option<String> {
+        self.0.as_str().map(String::from)
+    }
+
+    pub fn symbol(name: &str) -> JsScm {
+        JsScm(Scm::symbol(name))
+    }
+
+    // The first datum in `source`.
+    pub fn read(source: &str) -> Result<JsScm, JsError> {
+        reader::read_str(source).map(JsScm).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    // Identity, as in `eq?`.
+    #[wasm_bindgen(js_name = isEq)]
+    pub fn is_eq(&self, other: &JsScm) -> bool {
+        self.0 == other.0
+    }
+
+    // What `write` prints.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn display(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[test]
+fn lists_from_javascript() {
+    let list = JsScm::cons(&JsScm::from_number(1.0), &JsScm::cons(&JsScm::from_string("two"), &JsScm::nil()));
+    assert_eq!(list.display(), "(1 \"two\")");
+    assert_eq!(list.cdr().unwrap().car().unwrap().as_string().as_deref(), Some("two"));
+    assert!(list.cdr().unwrap().cdr().unwrap().is_null());
+    assert_eq!(JsScm::from_number(0.5).display(), "0.5");
+    assert_eq!(JsScm::read("(/ 1 4)").unwrap().cdr().unwrap().car().unwrap().to_number(), Some(1.0));
+    assert_eq!(JsScm(Scm::from_int(1) / Scm::from_int(4)).to_number(), Some(0.25));
+    assert!(JsScm::nil().car().is_none());
+}
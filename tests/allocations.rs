@@ -0,0 +1,65 @@
+//* Counts heap allocations to check that constants, characters and short
+//* strings are immediates and never allocate, however often they are made.
+//*
+//* The counter is global, so every test in this file must run under the
+//* same lock.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use scm_repr::repr::Representation;
+use scm_repr::Scm;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static SERIAL: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations_in(f: impl FnOnce()) -> usize {
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    f();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+fn make_constants<R: Representation>() {
+    for i in 0..1000 {
+        std::hint::black_box(R::nil());
+        std::hint::black_box(R::eof());
+        std::hint::black_box(R::from_bool(i % 2 == 0));
+        std::hint::black_box(R::from_char(std::char::from_u32(i).unwrap()));
+    }
+}
+
+#[test]
+fn constants_do_not_allocate() {
+    assert_eq!(allocations_in(make_constants::<Scm>), 0);
+    assert_eq!(allocations_in(|| {
+        for _ in 0..1000 {
+            std::hint::black_box(Scm::string("abc"));
+        }
+    }), 0);
+}
+
+#[test]
+fn pairs_do_allocate() {
+    let n = allocations_in(|| {
+        std::hint::black_box(Scm::cons(Scm::nil(), Scm::nil()));
+    });
+    assert!(n > 0);
+}
@@ -0,0 +1,37 @@
+//* The census counts live objects for the whole process, so it gets a test
+//* binary of its own where nothing else allocates behind its back.
+
+use std::mem::size_of;
+
+use scm_repr::heap::{alloc, census, Header, HeapObject, Kind};
+use scm_repr::{cons, Scm};
+
+struct Probe([u8; 20]);
+
+impl HeapObject for Probe {
+    const KIND: Kind = Kind::Foreign;
+}
+
+#[test]
+fn census_counts_live_objects() {
+    let before = census();
+    let mut list = Scm::NIL;
+    for i in 0..100 {
+        list = cons(Scm::from_int(i), list);
+    }
+    std::hint::black_box(list);
+    let probes: Vec<_> = (0..10).map(|_| alloc(Probe([0; 20]))).collect();
+
+    let during = census();
+    let pairs = during.get(Kind::Pair);
+    assert_eq!(pairs.count, before.get(Kind::Pair).count + 100);
+    assert_eq!(pairs.bytes, before.get(Kind::Pair).bytes + 100 * (size_of::<Header>() + 2 * size_of::<Scm>()));
+    assert_eq!(during.get(Kind::Foreign).count, before.get(Kind::Foreign).count + 10);
+    assert_eq!(during.get(Kind::Foreign).bytes, before.get(Kind::Foreign).bytes + 10 * 32);
+    assert_eq!(during.total().count, before.total().count + 110);
+    assert!(during.to_string().lines().any(|line| line.starts_with("Pair")));
+
+    assert!(probes.iter().all(|probe| probe.body.0 == [0; 20]));
+    drop(probes);
+    assert_eq!(census().get(Kind::Foreign), before.get(Kind::Foreign));
+}
@@ -0,0 +1,60 @@
+//* `#[derive(ToScm, FromScm)]` on configuration-like types, with the
+//* `derive` feature:
+//*
+//*     cargo test --features derive --test derive_convert
+
+#![cfg(feature = "derive")]
+
+use scm_repr::convert::{FromScm, ToScm};
+use scm_repr::reader::read_str;
+use scm_repr::ConversionError;
+
+#[derive(Debug, PartialEq, ToScm, FromScm)]
+struct Config {
+    name: String,
+    #[scm(rename = "depth")]
+    max_depth: u32,
+    modes: Vec<Mode>,
+    origin: Point,
+    scale: Scale,
+}
+
+#[derive(Debug, PartialEq, ToScm, FromScm)]
+enum Mode {
+    Fast,
+    WithRetries(u8, bool),
+    Careful { retries: u8, log_file: Option<String> },
+}
+
+#[derive(Debug, PartialEq, ToScm, FromScm)]
+#[scm(record)]
+struct Point(i64, i64);
+
+#[derive(Debug, PartialEq, ToScm, FromScm)]
+struct Scale(f64);
+
+#[test]
+fn derived_conversions_use_alists_records_and_tagged_lists() {
+    let config = Config {
+        name: "test".into(),
+        max_depth: 3,
+        modes: vec![Mode::Fast, Mode::WithRetries(2, true), Mode::Careful { retries: 1, log_file: None }],
+        origin: Point(-1, 2),
+        scale: Scale(0.5),
+    };
+    let x = config.to_scm();
+    assert_eq!(
+        x.to_string(),
+        r#"((name . "test") (depth . 3) (modes (fast) (with-retries 2 #t) (careful (retries . 1) (log-file . #f))) (origin . #(point -1 2)) (scale . 0.5))"#
+    );
+    assert_eq!(Config::from_scm(x), Ok(config));
+
+    let x = read_str("((scale . 2) (origin . #(point 0 0)) (modes) (depth . 1) (name . \"x\") (comment . \"ignored\"))").unwrap();
+    assert_eq!(Config::from_scm(x).unwrap().scale, Scale(2.0));
+    let x = read_str("((name . \"x\"))").unwrap();
+    assert_eq!(Config::from_scm(x), Err(ConversionError::MissingField("depth")));
+    let x = read_str("(slow)").unwrap();
+    assert!(matches!(Mode::from_scm(x), Err(ConversionError::UnknownTag(_))));
+    let x = read_str("(with-retries 2)").unwrap();
+    assert!(matches!(Mode::from_scm(x), Err(ConversionError::Length(_))));
+}
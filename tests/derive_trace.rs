@@ -0,0 +1,52 @@
+//* `#[derive(Trace)]` on the kinds of types embedders wrap as foreign
+//* values, with the `derive` feature:
+//*
+//*     cargo test --features derive --test derive_trace
+
+#![cfg(feature = "derive")]
+
+use std::sync::atomic::AtomicUsize;
+
+use scm_repr::foreign::make_traced_foreign;
+use scm_repr::trace::{children, Trace};
+use scm_repr::{cons, Scm};
+
+#[derive(Trace)]
+struct Closure {
+    code: Scm,
+    captured: Vec<Scm>,
+    #[trace(skip)]
+    calls: AtomicUsize,
+}
+
+#[derive(Trace)]
+struct Pair<T>(T, Option<T>);
+
+#[derive(Trace)]
+enum Node {
+    Leaf,
+    Constant(Scm),
+    Call {
+        procedure: Scm,
+        arguments: Vec<Node>,
+        #[trace(skip)]
+        line: u32,
+    },
+}
+
+#[test]
+fn derived_impls_visit_every_field_in_order() {
+    let (a, b, c) = (Scm::symbol("a"), cons(Scm::NIL, Scm::NIL), Scm::string("a string long enough for the heap"));
+    let closure = Closure { code: a, captured: vec![b, c], calls: AtomicUsize::new(0) };
+    let x = make_traced_foreign("closure", closure);
+    assert_eq!(children(x), [a, b, c]);
+
+    let mut found = vec![];
+    Pair(a, Some(b)).trace(&mut |x| found.push(x));
+    assert_eq!(found, [a, b]);
+
+    let tree = Node::Call { procedure: a, arguments: vec![Node::Constant(b), Node::Leaf, Node::Constant(c)], line: 3 };
+    let mut found = vec![];
+    tree.trace(&mut |x| found.push(x));
+    assert_eq!(found, [a, b, c]);
+}
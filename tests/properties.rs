@@ -0,0 +1,146 @@
+//* Property tests for the invariants every value keeps: what the
+//* constructors put in the accessors give back, exactly one type predicate
+//* holds, printing and reading again gives an equal value, and equal values
+//* hash alike.
+//*
+//* Values are generated as `Datum` trees and built from those, so that the
+//* result can be checked against what it was built from. With the
+//* `arbitrary` feature, values from `Scm`'s `Arbitrary` impl are checked as
+//* well, which covers the kinds that the trees don't.
+
+use proptest::prelude::*;
+use scm_repr::order::{equal, equal_hash};
+use scm_repr::reader::read_str;
+use scm_repr::{cons, num, Scm};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Datum {
+    Nil,
+    Bool(bool),
+    Char(char),
+    Int(i64),
+    Real(f64),
+    Str(String),
+    Sym(String),
+    Pair(Box<Datum>, Box<Datum>),
+    Vector(Vec<Datum>),
+}
+
+fn datum() -> impl Strategy<Value = Datum> {
+    let leaf = prop_oneof![
+        Just(Datum::Nil),
+        any::<bool>().prop_map(Datum::Bool),
+        any::<char>().prop_map(Datum::Char),
+        any::<i64>().prop_map(Datum::Int),
+        (-1e9..1e9).prop_map(Datum::Real),
+        any::<String>().prop_map(Datum::Str),
+        "[a-z][a-z0-9!?*<>=/+-]{0,8}".prop_map(Datum::Sym),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone()).prop_map(|(car, cdr)| Datum::Pair(Box::new(car), Box::new(cdr))),
+            prop::collection::vec(inner, 0..8).prop_map(Datum::Vector),
+        ]
+    })
+}
+
+fn build(d: &Datum) -> Scm {
+    match d {
+        Datum::Nil => Scm::NIL,
+        Datum::Bool(b) => Scm::from_bool(*b),
+        Datum::Char(c) => Scm::from_char(*c),
+        Datum::Int(i) => num::integer(*i),
+        Datum::Real(f) => Scm::from_f64(*f),
+        Datum::Str(s) => Scm::string(s),
+        Datum::Sym(name) => Scm::symbol(name),
+        Datum::Pair(car, cdr) => cons(build(car), build(cdr)),
+        Datum::Vector(items) => Scm::vector(items.iter().map(build).collect()),
+    }
+}
+
+// Reads a datum back through the accessors, or None for anything else.
+fn unbuild(x: Scm) -> Option<Datum> {
+    if x.is_nil() {
+        Some(Datum::Nil)
+    } else if let Some(b) = x.as_bool() {
+        Some(Datum::Bool(b))
+    } else if let Some(c) = x.as_char() {
+        Some(Datum::Char(c))
+    } else if let Some(i) = x.as_integer().or_else(|| x.as_bignum()?.to_i64()) {
+        Some(Datum::Int(i))
+    } else if let Some(f) = x.as_f64() {
+        Some(Datum::Real(f))
+    } else if let Some(s) = x.as_str() {
+        Some(Datum::Str(s.to_string()))
+    } else if let Some(name) = x.as_symbol() {
+        Some(Datum::Sym(name.to_string()))
+    } else if let Some(&(car, cdr)) = x.as_pair() {
+        Some(Datum::Pair(Box::new(unbuild(car)?), Box::new(unbuild(cdr)?)))
+    } else {
+        Some(Datum::Vector(x.as_vector()?.iter().map(|&item| unbuild(item)).collect::<Option<_>>()?))
+    }
+}
+
+fn exactly_one_predicate(x: Scm) -> bool {
+    let predicates = [
+        x.is_nil(),
+        x.is_eof(),
+        x.as_bool().is_some(),
+        x.is_char(),
+        num::is_number(x),
+        x.is_string(),
+        x.is_symbol(),
+        x.as_pair().is_some(),
+        x.is_vector(),
+    ];
+    predicates.iter().filter(|&&holds| holds).count() == 1
+}
+
+proptest! {
+    #[test]
+    fn accessors_return_what_was_built(d in datum()) {
+        prop_assert_eq!(unbuild(build(&d)), Some(d));
+    }
+
+    #[test]
+    fn exactly_one_predicate_holds(d in datum()) {
+        prop_assert!(exactly_one_predicate(build(&d)));
+    }
+
+    #[test]
+    fn printed_values_read_back_equal(d in datum()) {
+        let x = build(&d);
+        let text = x.to_string();
+        let y = read_str(&text).map_err(|e| TestCaseError::fail(format!("{}: {}", text, e)))?;
+        prop_assert!(equal(x, y), "{} read back as {}", text, y);
+    }
+
+    #[test]
+    fn equal_values_hash_alike(a in datum(), b in datum()) {
+        let (x, y) = (build(&a), build(&b));
+        prop_assert!(equal(x, build(&a)));
+        prop_assert_eq!(equal_hash(x), equal_hash(build(&a)));
+        prop_assert_eq!(equal(x, y), a == b);
+        if equal(x, y) {
+            prop_assert_eq!(equal_hash(x), equal_hash(y));
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+proptest! {
+    #[test]
+    fn arbitrary_values_keep_the_invariants(bytes in prop::collection::vec(any::<u8>(), 0..1024)) {
+        use arbitrary::{Arbitrary, Unstructured};
+        use scm_repr::snapshot::{load_snapshot, save_snapshot};
+
+        let x = Scm::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        prop_assert!(exactly_one_predicate(x), "{:?}", x);
+        // an equal copy that shares nothing with `x`
+        let mut snapshot = vec![];
+        save_snapshot(&[x], &mut snapshot).unwrap();
+        let copy = load_snapshot(&snapshot[..]).unwrap()[0];
+        prop_assert!(equal(x, copy), "{} loaded as {}", x, copy);
+        prop_assert_eq!(equal_hash(x), equal_hash(copy));
+    }
+}
@@ -0,0 +1,108 @@
+//* These tests are meant to be run under Miri with strict provenance checks:
+//*
+//*     MIRIFLAGS="-Zmiri-strict-provenance -Zmiri-ignore-leaks" cargo +nightly miri test --test provenance
+//*
+//* Leaks must be ignored because `cons` deliberately leaks its pairs (the GC
+//* is expected to clean up after us).
+//*
+//* Besides tagging, they cover the references that accessors like `as_pair`
+//* hand out: those live on while the header of the same object is written
+//* (when its hash is cached) and while boxes are mutated through other
+//* references, and Miri's aliasing model has to agree that this is fine.
+
+use scm_repr::boxes::{make_box, set_box, unbox};
+use scm_repr::branded::{self, Heap};
+use scm_repr::heap::{AllocationCount, Kind};
+use scm_repr::order::equal_hash32;
+use scm_repr::{car, cdr, cons, is_null, Scm};
+
+#[test]
+fn cons_car_cdr() {
+    let p = cons(Scm::from_int(1), Scm::from_int(2));
+    assert_eq!(car(p).unwrap().as_integer(), Some(1));
+    assert_eq!(cdr(p).unwrap().as_integer(), Some(2));
+}
+
+#[test]
+fn nested_list_traversal() {
+    let mut list = Scm::nil();
+    for i in 0..100 {
+        list = cons(Scm::from_int(i), list);
+    }
+
+    let mut sum = 0;
+    while !is_null(list) {
+        sum += car(list).unwrap().as_integer().unwrap();
+        list = cdr(list).unwrap();
+    }
+    assert_eq!(sum, 4950);
+}
+
+#[test]
+fn pointer_values() {
+    let v = Scm::vector(vec![Scm::from_int(7), Scm::nil()]);
+    let items = v.as_vector().unwrap();
+    assert_eq!(items[0].as_integer(), Some(7));
+    assert!(items[1].is_nil());
+    assert_eq!(v.header().unwrap().kind(), Kind::Vector);
+    assert!(v.as_pair().is_none());
+
+    let s = Scm::string("hello, world");
+    assert_eq!(s.as_str(), Some("hello, world"));
+    assert_eq!(s.header().unwrap().kind(), Kind::String);
+}
+
+// Leaks are ignored under Miri, so this counts the frees itself.
+#[test]
+fn branded_heap_frees_its_objects() {
+    let count = AllocationCount::start();
+    let heap = Heap::new();
+    let p = heap.cons(branded::Scm::from_int(1), branded::Scm::nil());
+    let q = heap.cons(p, p);
+    assert_eq!(q.car().unwrap().car().unwrap().as_integer(), Some(1));
+    assert!(q.cdr().unwrap().cdr().unwrap().is_nil());
+    assert_eq!(count.outstanding(), [(Kind::Pair, 2)]);
+    drop(heap);
+    count.assert_none_outstanding();
+}
+
+#[test]
+fn references_survive_header_writes() {
+    let p = cons(Scm::from_int(1), Scm::string("too long to be an immediate"));
+    let (first, second) = (p.as_pair().unwrap(), p.as_pair().unwrap());
+    let v = Scm::vector(vec![p, p]);
+    let items = v.as_vector().unwrap();
+
+    // caches the hashes in the headers of `v` and `p`
+    let hash = equal_hash32(v);
+    assert_eq!(equal_hash32(p), p.header().unwrap().hash().unwrap());
+    assert_eq!(v.header().unwrap().hash(), Some(hash));
+
+    assert!(std::ptr::eq(first, second));
+    assert_eq!(first.0.as_integer(), Some(1));
+    assert_eq!(second.1.as_str(), Some("too long to be an immediate"));
+    assert_eq!(items[1].as_pair().map(|pair| pair as *const _), Some(first as *const _));
+}
+
+#[test]
+fn boxes_change_under_shared_references() {
+    let b = make_box(Scm::NIL);
+    let p = cons(b, b);
+    let pair = p.as_pair().unwrap();
+    set_box(pair.1, Scm::from_int(3)).unwrap();
+    assert_eq!(unbox(pair.0), Ok(Scm::from_int(3)));
+}
+
+#[test]
+fn kind_checks_read_only_the_header() {
+    // A flonum is smaller than most objects that share its tag, so looking at
+    // it as one of those would reach past its end.
+    let x = Scm::from_f64(0.5);
+    assert!(!x.is_continuation() && !x.is_time() && !x.is_box() && !x.is_values());
+    assert_eq!(x.as_f64(), Some(0.5));
+
+    let s = Scm::string("short");
+    let text = s.as_str().unwrap();
+    let copy = s;
+    assert_eq!(copy.as_str(), Some(text));
+}